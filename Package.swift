@@ -33,7 +33,8 @@ let package = Package(
                     "src/alloc.c",
                     "src/subtree.c",
                     "src/tree.c",
-                    "src/query.c"
+                    "src/query.c",
+                    "src/serialization.c"
                 ],
                 sources: ["src/lib.c"]),
     ],
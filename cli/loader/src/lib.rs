@@ -22,7 +22,10 @@ use once_cell::unsync::OnceCell;
 use regex::{Regex, RegexBuilder};
 use serde::{Deserialize, Deserializer, Serialize};
 use tree_sitter::{Language, QueryError, QueryErrorKind};
+use tree_sitter_folds::{Error as FoldsError, FoldsConfiguration};
+use tree_sitter_format::{Error as FormatError, FormattingConfiguration};
 use tree_sitter_highlight::HighlightConfiguration;
+use tree_sitter_indent::{Error as IndentError, IndentConfiguration};
 use tree_sitter_tags::{Error as TagsError, TagsConfiguration};
 
 pub const EMSCRIPTEN_TAG: &str = concat!("docker.io/emscripten/emsdk:", env!("EMSCRIPTEN_VERSION"));
@@ -96,10 +99,16 @@ pub struct LanguageConfiguration<'a> {
     pub injections_filenames: Option<Vec<String>>,
     pub locals_filenames: Option<Vec<String>>,
     pub tags_filenames: Option<Vec<String>>,
+    pub formatting_filenames: Option<Vec<String>>,
+    pub indents_filenames: Option<Vec<String>>,
+    pub folds_filenames: Option<Vec<String>>,
     pub language_name: String,
     language_id: usize,
     highlight_config: OnceCell<Option<HighlightConfiguration>>,
     tags_config: OnceCell<Option<TagsConfiguration>>,
+    formatting_config: OnceCell<Option<FormattingConfiguration>>,
+    indent_config: OnceCell<Option<IndentConfiguration>>,
+    folds_config: OnceCell<Option<FoldsConfiguration>>,
     highlight_names: &'a Mutex<Vec<String>>,
     use_all_highlight_names: bool,
 }
@@ -874,24 +883,51 @@ impl Loader {
         &'a self,
         string: &str,
     ) -> Option<&'a HighlightConfiguration> {
+        self.config_for_injection_string(string, "property sheet", |configuration, language| {
+            configuration.highlight_config(language, None)
+        })
+    }
+
+    pub fn indent_config_for_injection_string<'a>(
+        &'a self,
+        string: &str,
+    ) -> Option<&'a IndentConfiguration> {
+        self.config_for_injection_string(string, "indent config", |configuration, language| {
+            configuration.indent_config(language)
+        })
+    }
+
+    pub fn folds_config_for_injection_string<'a>(
+        &'a self,
+        string: &str,
+    ) -> Option<&'a FoldsConfiguration> {
+        self.config_for_injection_string(string, "folds config", |configuration, language| {
+            configuration.folds_config(language)
+        })
+    }
+
+    // Find a configuration of the language with the given injection string,
+    // reporting any errors instead of returning them.
+    fn config_for_injection_string<'a, T>(
+        &'a self,
+        string: &str,
+        config_name: &str,
+        config: impl FnOnce(&'a LanguageConfiguration<'a>, Language) -> Result<Option<&'a T>>,
+    ) -> Option<&'a T> {
         match self.language_configuration_for_injection_string(string) {
             Err(e) => {
                 eprintln!("Failed to load language for injection string '{string}': {e}",);
                 None
             }
             Ok(None) => None,
-            Ok(Some((language, configuration))) => {
-                match configuration.highlight_config(language, None) {
-                    Err(e) => {
-                        eprintln!(
-                            "Failed to load property sheet for injection string '{string}': {e}",
-                        );
-                        None
-                    }
-                    Ok(None) => None,
-                    Ok(Some(config)) => Some(config),
+            Ok(Some((language, configuration))) => match config(configuration, language) {
+                Err(e) => {
+                    eprintln!("Failed to load {config_name} for injection string '{string}': {e}",);
+                    None
                 }
-            }
+                Ok(None) => None,
+                Ok(Some(config)) => Some(config),
+            },
         }
     }
 
@@ -940,6 +976,12 @@ impl Loader {
             locals: PathsJSON,
             #[serde(default)]
             tags: PathsJSON,
+            #[serde(default)]
+            formatting: PathsJSON,
+            #[serde(default)]
+            indents: PathsJSON,
+            #[serde(default)]
+            folds: PathsJSON,
             #[serde(default, rename = "external-files")]
             external_files: PathsJSON,
         }
@@ -1022,8 +1064,14 @@ impl Loader {
                         locals_filenames: config_json.locals.into_vec(),
                         tags_filenames: config_json.tags.into_vec(),
                         highlights_filenames: config_json.highlights.into_vec(),
+                        formatting_filenames: config_json.formatting.into_vec(),
+                        indents_filenames: config_json.indents.into_vec(),
+                        folds_filenames: config_json.folds.into_vec(),
                         highlight_config: OnceCell::new(),
                         tags_config: OnceCell::new(),
+                        formatting_config: OnceCell::new(),
+                        indent_config: OnceCell::new(),
+                        folds_config: OnceCell::new(),
                         highlight_names: &self.highlight_names,
                         use_all_highlight_names: self.use_all_highlight_names,
                     };
@@ -1079,8 +1127,14 @@ impl Loader {
                 locals_filenames: None,
                 highlights_filenames: None,
                 tags_filenames: None,
+                formatting_filenames: None,
+                indents_filenames: None,
+                folds_filenames: None,
                 highlight_config: OnceCell::new(),
                 tags_config: OnceCell::new(),
+                formatting_config: OnceCell::new(),
+                indent_config: OnceCell::new(),
+                folds_config: OnceCell::new(),
                 highlight_names: &self.highlight_names,
                 use_all_highlight_names: self.use_all_highlight_names,
             };
@@ -1313,6 +1367,119 @@ impl<'a> LanguageConfiguration<'a> {
             .map(Option::as_ref)
     }
 
+    pub fn formatting_config(
+        &self,
+        language: Language,
+    ) -> Result<Option<&FormattingConfiguration>> {
+        self.formatting_config
+            .get_or_try_init(|| {
+                let (formatting_query, formatting_ranges) =
+                    self.read_queries(self.formatting_filenames.as_deref(), "formatting.scm")?;
+                if formatting_query.is_empty() {
+                    Ok(None)
+                } else {
+                    FormattingConfiguration::new(language, &formatting_query)
+                        .map(Some)
+                        .map_err(|error| {
+                            if let FormatError::Query(error) = error {
+                                Self::include_path_in_query_error(
+                                    error,
+                                    &formatting_ranges,
+                                    &formatting_query,
+                                    0,
+                                )
+                            } else {
+                                error.into()
+                            }
+                        })
+                }
+            })
+            .map(Option::as_ref)
+    }
+
+    pub fn indent_config(&self, language: Language) -> Result<Option<&IndentConfiguration>> {
+        self.indent_config
+            .get_or_try_init(|| {
+                self.config_with_injections(
+                    self.indents_filenames.as_deref(),
+                    "indents.scm",
+                    |query, injections_query| {
+                        IndentConfiguration::new(
+                            language,
+                            &self.language_name,
+                            query,
+                            injections_query,
+                        )
+                    },
+                    |error| match error {
+                        IndentError::Query(error) => Ok(error),
+                        error => Err(error.into()),
+                    },
+                )
+            })
+            .map(Option::as_ref)
+    }
+
+    pub fn folds_config(&self, language: Language) -> Result<Option<&FoldsConfiguration>> {
+        self.folds_config
+            .get_or_try_init(|| {
+                self.config_with_injections(
+                    self.folds_filenames.as_deref(),
+                    "folds.scm",
+                    |query, injections_query| {
+                        FoldsConfiguration::new(
+                            language,
+                            &self.language_name,
+                            query,
+                            injections_query,
+                        )
+                    },
+                    |error| match error {
+                        FoldsError::Query(error) => Ok(error),
+                        error => Err(error.into()),
+                    },
+                )
+            })
+            .map(Option::as_ref)
+    }
+
+    // Create a configuration from a query that is combined with the language's
+    // injection query, unless the query is empty. Errors in either query are
+    // reported with the path of the file that contains them.
+    fn config_with_injections<T, E>(
+        &self,
+        paths: Option<&[String]>,
+        default_path: &str,
+        new_config: impl FnOnce(&str, &str) -> std::result::Result<T, E>,
+        into_query_error: impl FnOnce(E) -> std::result::Result<QueryError, Error>,
+    ) -> Result<Option<T>> {
+        let (query, ranges) = self.read_queries(paths, default_path)?;
+        let (injections_query, injections_ranges) =
+            self.read_queries(self.injections_filenames.as_deref(), "injections.scm")?;
+        if query.is_empty() {
+            return Ok(None);
+        }
+        new_config(&query, &injections_query)
+            .map(Some)
+            .map_err(|error| match into_query_error(error) {
+                Ok(error) if error.offset < injections_query.len() => {
+                    Self::include_path_in_query_error(
+                        error,
+                        &injections_ranges,
+                        &injections_query,
+                        0,
+                    )
+                }
+                Ok(error) => Self::include_path_in_query_error(
+                    error,
+                    &ranges,
+                    &query,
+                    injections_query.len(),
+                ),
+                Err(error) => error,
+            })
+    }
+
     fn include_path_in_query_error(
         mut error: QueryError,
         ranges: &[(String, Range<usize>)],
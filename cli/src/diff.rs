@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use tree_sitter::{DiffAction, Language, Node, Parser, TreeDiff};
+
+pub fn diff_files(language: &Language, old_path: &Path, new_path: &Path) -> Result<()> {
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+
+    let old_source =
+        fs::read(old_path).with_context(|| format!("Error reading source file {old_path:?}"))?;
+    let new_source =
+        fs::read(new_path).with_context(|| format!("Error reading source file {new_path:?}"))?;
+    let old_tree = parser.parse(&old_source, None).unwrap();
+    let new_tree = parser.parse(&new_source, None).unwrap();
+
+    let diff = TreeDiff::new(&old_tree, &old_source, &new_tree, &new_source);
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for action in diff.actions() {
+        match action {
+            DiffAction::Insert { node } => {
+                writeln!(&mut stdout, "insert {}", describe(*node, &new_source))?;
+            }
+            DiffAction::Delete { node } => {
+                writeln!(&mut stdout, "delete {}", describe(*node, &old_source))?;
+            }
+            DiffAction::Update { old, new } => {
+                writeln!(
+                    &mut stdout,
+                    "update {} -> {:?}",
+                    describe(*old, &old_source),
+                    text(*new, &new_source)
+                )?;
+            }
+            DiffAction::Move { old, new } => {
+                writeln!(
+                    &mut stdout,
+                    "move {} -> {}",
+                    describe(*old, &old_source),
+                    describe(*new, &new_source)
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn describe(node: Node, source: &[u8]) -> String {
+    let start = node.start_position();
+    let end = node.end_position();
+    let mut result = format!(
+        "({} [{}, {}] - [{}, {}])",
+        node.kind(),
+        start.row,
+        start.column,
+        end.row,
+        end.column
+    );
+    if node.child_count() == 0 {
+        result += &format!(" {:?}", text(node, source));
+    }
+    result
+}
+
+fn text(node: Node, source: &[u8]) -> String {
+    String::from_utf8_lossy(&source[node.byte_range()]).to_string()
+}
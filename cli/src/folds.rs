@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::json;
+use tree_sitter_folds::FoldsContext;
+use tree_sitter_loader::{Config, Loader};
+
+use super::util;
+
+pub fn print_folds(
+    loader: &Loader,
+    loader_config: &Config,
+    scope: Option<&str>,
+    paths: &[String],
+) -> Result<()> {
+    let mut lang = None;
+    if let Some(scope) = scope {
+        lang = loader.language_configuration_for_scope(scope)?;
+        if lang.is_none() {
+            return Err(anyhow!("Unknown scope '{scope}'"));
+        }
+    }
+
+    let mut context = FoldsContext::new();
+    let cancellation_flag = util::cancel_on_signal();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for path in paths {
+        let path = Path::new(&path);
+        let (language, language_config) = match lang.clone() {
+            Some(v) => v,
+            None => {
+                if let Some(v) = loader.language_configuration_for_file_name(path)? {
+                    v
+                } else {
+                    eprintln!("{}", util::lang_not_found_for_path(path, loader_config));
+                    continue;
+                }
+            }
+        };
+
+        if let Some(folds_config) = language_config.folds_config(language)? {
+            let source = fs::read(path)?;
+            context.parser.set_language(&folds_config.language)?;
+            let tree = context.parser.parse(&source, None).unwrap();
+            let folds = context
+                .folds(
+                    folds_config,
+                    &tree,
+                    &source,
+                    Some(&cancellation_flag),
+                    |string| loader.folds_config_for_injection_string(string),
+                )
+                .with_context(|| format!("Error computing folds for {}", path.display()))?;
+
+            let folds = folds
+                .iter()
+                .map(|fold| {
+                    json!({
+                        "start_row": fold.start_row,
+                        "end_row": fold.end_row,
+                        "kind": fold.kind,
+                    })
+                })
+                .collect::<Vec<_>>();
+            serde_json::to_writer(
+                &mut stdout,
+                &json!({
+                    "path": path.to_string_lossy(),
+                    "folds": folds,
+                }),
+            )?;
+            writeln!(&mut stdout)?;
+        } else {
+            eprintln!("No folds config found for path {path:?}");
+        }
+    }
+
+    Ok(())
+}
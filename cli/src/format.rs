@@ -0,0 +1,76 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+use tree_sitter_format::Formatter;
+use tree_sitter_loader::{Config, Loader};
+
+use super::util;
+
+pub struct FormatOptions {
+    pub line_width: usize,
+    pub indent_width: usize,
+    pub write: bool,
+}
+
+pub fn format_files(
+    loader: &Loader,
+    loader_config: &Config,
+    scope: Option<&str>,
+    paths: &[String],
+    options: &FormatOptions,
+) -> Result<()> {
+    let mut lang = None;
+    if let Some(scope) = scope {
+        lang = loader.language_configuration_for_scope(scope)?;
+        if lang.is_none() {
+            return Err(anyhow!("Unknown scope '{scope}'"));
+        }
+    }
+
+    let mut formatter = Formatter::new();
+    formatter.line_width = options.line_width;
+    formatter.indent = " ".repeat(options.indent_width);
+    let cancellation_flag = util::cancel_on_signal();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for path in paths {
+        let path = Path::new(&path);
+        let (language, language_config) = match lang.clone() {
+            Some(v) => v,
+            None => {
+                if let Some(v) = loader.language_configuration_for_file_name(path)? {
+                    v
+                } else {
+                    eprintln!("{}", util::lang_not_found_for_path(path, loader_config));
+                    continue;
+                }
+            }
+        };
+
+        if let Some(formatting_config) = language_config.formatting_config(language)? {
+            let source = fs::read(path)?;
+            let formatted = formatter
+                .format(formatting_config, &source, Some(&cancellation_flag))
+                .with_context(|| format!("Error formatting {}", path.display()))?;
+            if options.write {
+                if formatted.as_bytes() != source {
+                    fs::write(path, formatted)?;
+                }
+            } else {
+                if paths.len() > 1 {
+                    writeln!(&mut stdout, "{}", path.to_string_lossy())?;
+                }
+                write!(&mut stdout, "{formatted}")?;
+            }
+        } else {
+            eprintln!("No formatting config found for path {path:?}");
+        }
+    }
+
+    Ok(())
+}
@@ -5,19 +5,23 @@ use std::{
     hash::BuildHasherDefault,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use indexmap::{map::Entry, IndexMap};
 use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
 
 use super::{
+    counterexample::{render_derivation, CounterexampleFinder},
     item::{ParseItem, ParseItemSet, ParseItemSetCore},
     item_set_builder::ParseItemSetBuilder,
 };
 use crate::generate::{
+    diagnostics::{Diagnostic, DiagnosticKind, RulePrecedence, Suggestion, SuggestionKind},
     grammars::{
         InlinedProductionMap, LexicalGrammar, PrecedenceEntry, SyntaxGrammar, VariableType,
     },
     node_types::VariableInfo,
+    parallel::parallel_map,
     rules::{Associativity, Precedence, Symbol, SymbolType, TokenSet},
     tables::{
         FieldLocation, GotoAction, ParseAction, ParseState, ParseStateId, ParseTable,
@@ -32,6 +36,18 @@ type SymbolSequence = Vec<Symbol>;
 type AuxiliarySymbolSequence = Vec<AuxiliarySymbolInfo>;
 pub type ParseStateInfo<'a> = (SymbolSequence, ParseItemSet<'a>);
 
+/// Information about how the grammar's conflict-resolution declarations were
+/// used while building the parse table.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConflictResolutionInfo {
+    /// The entries of the grammar's `conflicts` list that did not correspond
+    /// to any actual conflict.
+    pub unnecessary_conflicts: Vec<Vec<Symbol>>,
+    /// The non-terminals whose precedence or associativity was consulted in
+    /// order to choose between conflicting actions.
+    pub symbols_with_used_precedence: HashSet<Symbol>,
+}
+
 #[derive(Clone)]
 struct AuxiliarySymbolInfo {
     auxiliary_symbol: Symbol,
@@ -60,24 +76,27 @@ struct ParseTableBuilder<'a> {
     core_ids_by_core: HashMap<ParseItemSetCore<'a>, usize>,
     state_ids_by_item_set: IndexMap<ParseItemSet<'a>, ParseStateId, BuildHasherDefault<FxHasher>>,
     parse_state_info_by_id: Vec<ParseStateInfo<'a>>,
+    parse_state_predecessors: Vec<Option<ParseStateId>>,
     parse_state_queue: VecDeque<ParseStateQueueEntry>,
     non_terminal_extra_states: Vec<(Symbol, usize)>,
     actual_conflicts: HashSet<Vec<Symbol>>,
+    symbols_with_used_precedence: HashSet<Symbol>,
     parse_table: ParseTable,
 }
 
 impl<'a> ParseTableBuilder<'a> {
-    fn build(mut self) -> Result<(ParseTable, Vec<ParseStateInfo<'a>>)> {
+    fn build(mut self) -> Result<(ParseTable, Vec<ParseStateInfo<'a>>, ConflictResolutionInfo)> {
         // Ensure that the empty alias sequence has index 0.
         self.parse_table
             .production_infos
             .push(ProductionInfo::default());
 
         // Add the error state at index 0.
-        self.add_parse_state(&Vec::new(), &Vec::new(), ParseItemSet::default());
+        self.add_parse_state(None, &Vec::new(), &Vec::new(), ParseItemSet::default());
 
         // Add the starting state at index 1.
         self.add_parse_state(
+            None,
             &Vec::new(),
             &Vec::new(),
             ParseItemSet::with(std::iter::once((
@@ -117,41 +136,50 @@ impl<'a> ParseTableBuilder<'a> {
         for (terminal, item_set) in non_terminal_extra_item_sets_by_first_terminal {
             self.non_terminal_extra_states
                 .push((terminal, self.parse_table.states.len()));
-            self.add_parse_state(&Vec::new(), &Vec::new(), item_set);
+            self.add_parse_state(None, &Vec::new(), &Vec::new(), item_set);
         }
 
-        while let Some(entry) = self.parse_state_queue.pop_front() {
-            let item_set = self
-                .item_set_builder
-                .transitive_closure(&self.parse_state_info_by_id[entry.state_id].1);
-
-            self.add_actions(
-                self.parse_state_info_by_id[entry.state_id].0.clone(),
-                entry.preceding_auxiliary_symbols,
-                entry.state_id,
-                &item_set,
-            )?;
-        }
+        while !self.parse_state_queue.is_empty() {
+            // The closures of the queued item sets don't depend on each other, so they
+            // are computed in parallel. The states' actions are then added in the same
+            // order as they would be if the queue were processed one state at a time.
+            let entries = self.parse_state_queue.drain(..).collect::<Vec<_>>();
+            let item_sets = parallel_map(
+                &entries,
+                8,
+                || (),
+                |(), entry| {
+                    self.item_set_builder
+                        .transitive_closure(&self.parse_state_info_by_id[entry.state_id].1)
+                },
+            );
 
-        if !self.actual_conflicts.is_empty() {
-            println!("Warning: unnecessary conflicts");
-            for conflict in &self.actual_conflicts {
-                println!(
-                    "  {}",
-                    conflict
-                        .iter()
-                        .map(|symbol| format!("`{}`", self.symbol_name(symbol)))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                );
+            for (entry, item_set) in entries.into_iter().zip(item_sets) {
+                self.add_actions(
+                    self.parse_state_info_by_id[entry.state_id].0.clone(),
+                    entry.preceding_auxiliary_symbols,
+                    entry.state_id,
+                    &item_set,
+                )?;
             }
         }
 
-        Ok((self.parse_table, self.parse_state_info_by_id))
+        let mut unnecessary_conflicts = self.actual_conflicts.into_iter().collect::<Vec<_>>();
+        unnecessary_conflicts.sort_unstable();
+
+        Ok((
+            self.parse_table,
+            self.parse_state_info_by_id,
+            ConflictResolutionInfo {
+                unnecessary_conflicts,
+                symbols_with_used_precedence: self.symbols_with_used_precedence,
+            },
+        ))
     }
 
     fn add_parse_state(
         &mut self,
+        predecessor: Option<ParseStateId>,
         preceding_symbols: &SymbolSequence,
         preceding_auxiliary_symbols: &AuxiliarySymbolSequence,
         item_set: ParseItemSet<'a>,
@@ -171,6 +199,7 @@ impl<'a> ParseTableBuilder<'a> {
                 let state_id = self.parse_table.states.len();
                 self.parse_state_info_by_id
                     .push((preceding_symbols.clone(), v.key().clone()));
+                self.parse_state_predecessors.push(predecessor);
 
                 self.parse_table.states.push(ParseState {
                     id: state_id,
@@ -277,6 +306,9 @@ impl<'a> ParseTableBuilder<'a> {
                     if table_entry.actions.is_empty() {
                         table_entry.actions.push(action);
                     } else {
+                        self.symbols_with_used_precedence.insert(symbol);
+                        self.symbols_with_used_precedence
+                            .extend(reduction_info.symbols.iter().copied());
                         match Self::compare_precedence(
                             self.syntax_grammar,
                             precedence,
@@ -317,6 +349,7 @@ impl<'a> ParseTableBuilder<'a> {
         for (symbol, next_item_set) in terminal_successors {
             preceding_symbols.push(symbol);
             let next_state_id = self.add_parse_state(
+                Some(state_id),
                 &preceding_symbols,
                 &preceding_auxiliary_symbols,
                 next_item_set,
@@ -344,6 +377,7 @@ impl<'a> ParseTableBuilder<'a> {
         for (symbol, next_item_set) in non_terminal_successors {
             preceding_symbols.push(symbol);
             let next_state_id = self.add_parse_state(
+                Some(state_id),
                 &preceding_symbols,
                 &preceding_auxiliary_symbols,
                 next_item_set,
@@ -398,7 +432,13 @@ impl<'a> ParseTableBuilder<'a> {
                     }
                     message += &self.syntax_grammar.variables[*variable_index as usize].name;
                 }
-                return Err(anyhow!(message));
+                return Err(Diagnostic::new(DiagnosticKind::ExtraRuleConflict, message)
+                    .with_rules(
+                        parent_symbols
+                            .iter()
+                            .map(|i| &self.syntax_grammar.variables[*i as usize].name),
+                    )
+                    .into());
             }
         }
         // Add actions for the start tokens of each non-terminal extra rule.
@@ -441,7 +481,7 @@ impl<'a> ParseTableBuilder<'a> {
 
     fn handle_conflict(
         &mut self,
-        item_set: &ParseItemSet,
+        item_set: &ParseItemSet<'a>,
         state_id: ParseStateId,
         preceding_symbols: &SymbolSequence,
         preceding_auxiliary_symbols: &[AuxiliarySymbolInfo],
@@ -509,6 +549,9 @@ impl<'a> ParseTableBuilder<'a> {
             let mut shift_is_less = false;
             let mut shift_is_more = false;
             for p in shift_precedence {
+                self.symbols_with_used_precedence.insert(p.1);
+                self.symbols_with_used_precedence
+                    .extend(reduction_info.symbols.iter().copied());
                 match Self::compare_precedence(
                     self.syntax_grammar,
                     p.0,
@@ -661,7 +704,7 @@ impl<'a> ParseTableBuilder<'a> {
                     None
                 };
 
-                (line, prec_line)
+                (line, prec_line, **item)
             })
             .collect::<Vec<_>>();
 
@@ -670,8 +713,12 @@ impl<'a> ParseTableBuilder<'a> {
             .map(|i| i.0.chars().count())
             .max()
             .unwrap();
-        interpretations.sort_unstable();
-        for (i, (line, prec_suffix)) in interpretations.into_iter().enumerate() {
+        interpretations.sort_unstable_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        let interpreted_items = interpretations
+            .iter()
+            .map(|(_, _, item)| *item)
+            .collect::<Vec<_>>();
+        for (i, (line, prec_suffix, _)) in interpretations.into_iter().enumerate() {
             write!(&mut msg, "  {}:", i + 1).unwrap();
             msg += &line;
             if let Some(prec_suffix) = prec_suffix {
@@ -684,7 +731,14 @@ impl<'a> ParseTableBuilder<'a> {
             msg.push('\n');
         }
 
-        let mut resolution_count = 0;
+        self.write_counterexample(
+            &mut msg,
+            state_id,
+            preceding_symbols,
+            conflicting_lookahead,
+            &interpreted_items,
+        );
+
         writeln!(&mut msg, "\nPossible resolutions:\n").unwrap();
         let mut shift_items = Vec::new();
         let mut reduce_items = Vec::new();
@@ -698,76 +752,161 @@ impl<'a> ParseTableBuilder<'a> {
         shift_items.sort_unstable();
         reduce_items.sort_unstable();
 
-        let list_rule_names = |mut msg: &mut String, items: &[&ParseItem]| {
+        let rule_names = |items: &[&ParseItem]| {
+            let mut names = Vec::new();
             let mut last_rule_id = None;
             for item in items {
-                if last_rule_id == Some(item.variable_index) {
-                    continue;
-                }
-
-                if last_rule_id.is_some() {
-                    write!(&mut msg, " and").unwrap();
+                if last_rule_id != Some(item.variable_index) {
+                    last_rule_id = Some(item.variable_index);
+                    names.push(
+                        self.symbol_name(&Symbol::non_terminal(item.variable_index as usize)),
+                    );
                 }
-
-                last_rule_id = Some(item.variable_index);
-                write!(
-                    msg,
-                    " `{}`",
-                    self.symbol_name(&Symbol::non_terminal(item.variable_index as usize))
-                )
-                .unwrap();
             }
+            names
+        };
+        let quote_names = |names: &[String], separator: &str| {
+            names
+                .iter()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(separator)
         };
 
+        let mut suggestions = Vec::new();
         if actual_conflict.len() > 1 {
             if !shift_items.is_empty() {
-                resolution_count += 1;
-                write!(
-                    &mut msg,
-                    "  {resolution_count}:  Specify a higher precedence in",
-                )
-                .unwrap();
-                list_rule_names(&mut msg, &shift_items);
-                writeln!(&mut msg, " than in the other rules.").unwrap();
+                let rules = rule_names(&shift_items);
+                let message = format!(
+                    "Specify a higher precedence in {} than in the other rules.",
+                    quote_names(&rules, " and ")
+                );
+                suggestions.push((SuggestionKind::HigherPrecedence, rules, message));
             }
 
             for item in &reduce_items {
-                resolution_count += 1;
-                writeln!(
-                    &mut msg,
-                    "  {resolution_count}:  Specify a higher precedence in `{}` than in the other rules.",
-                    self.symbol_name(&Symbol::non_terminal(item.variable_index as usize))
-                )
-                .unwrap();
+                let rules = rule_names(&[item]);
+                let message = format!(
+                    "Specify a higher precedence in {} than in the other rules.",
+                    quote_names(&rules, " and ")
+                );
+                suggestions.push((SuggestionKind::HigherPrecedence, rules, message));
             }
         }
 
         if considered_associativity {
-            resolution_count += 1;
-            write!(
-                &mut msg,
-                "  {resolution_count}:  Specify a left or right associativity in",
+            let rules = rule_names(&reduce_items);
+            let message = format!(
+                "Specify a left or right associativity in {}",
+                quote_names(&rules, " and ")
+            );
+            suggestions.push((SuggestionKind::Associativity, rules, message));
+        }
+
+        let rules = actual_conflict
+            .iter()
+            .map(|symbol| self.symbol_name(symbol))
+            .collect::<Vec<_>>();
+        let message = format!(
+            "Add a conflict for these rules: {}",
+            quote_names(&rules, ", ")
+        );
+        suggestions.push((SuggestionKind::AddConflict, rules, message));
+
+        for (i, (_, _, message)) in suggestions.iter().enumerate() {
+            writeln!(&mut msg, "  {}:  {message}", i + 1).unwrap();
+        }
+
+        let mut diagnostic = Diagnostic::new(DiagnosticKind::Conflict, msg)
+            .with_rules(
+                actual_conflict
+                    .iter()
+                    .map(|symbol| self.symbol_name(symbol)),
             )
-            .unwrap();
-            list_rule_names(&mut msg, &reduce_items);
-            writeln!(&mut msg).unwrap();
+            .with_symbols(
+                preceding_symbols
+                    .iter()
+                    .map(|symbol| self.symbol_name(symbol)),
+            );
+        diagnostic.lookahead = Some(self.symbol_name(&conflicting_lookahead));
+        diagnostic.precedences = interpreted_items
+            .iter()
+            .map(|item| {
+                RulePrecedence::new(
+                    self.symbol_name(&Symbol::non_terminal(item.variable_index as usize)),
+                    item.precedence(),
+                    item.associativity(),
+                )
+            })
+            .collect();
+        diagnostic.suggestions = suggestions
+            .into_iter()
+            .map(|(kind, rules, message)| Suggestion {
+                kind,
+                rules,
+                message,
+            })
+            .collect();
+        Err(diagnostic.into())
+    }
+
+    // Describe a concrete example of the conflict: a sequence of tokens that leads to
+    // the conflicting state, and a derivation tree for each of the conflicting items.
+    fn write_counterexample(
+        &mut self,
+        msg: &mut String,
+        state_id: ParseStateId,
+        preceding_symbols: &SymbolSequence,
+        conflicting_lookahead: Symbol,
+        items: &[ParseItem<'a>],
+    ) {
+        let mut state_ids = vec![state_id];
+        while let Some(predecessor) = self.parse_state_predecessors[*state_ids.last().unwrap()] {
+            state_ids.push(predecessor);
+        }
+        let item_sets = state_ids
+            .iter()
+            .rev()
+            .map(|id| {
+                self.item_set_builder
+                    .transitive_closure(&self.parse_state_info_by_id[*id].1)
+            })
+            .collect::<Vec<_>>();
+        if item_sets.len() != preceding_symbols.len() + 1 {
+            return;
         }
 
-        resolution_count += 1;
-        write!(
-            &mut msg,
-            "  {resolution_count}:  Add a conflict for these rules: ",
-        )
-        .unwrap();
-        for (i, symbol) in actual_conflict.iter().enumerate() {
-            if i > 0 {
-                write!(&mut msg, ", ").unwrap();
-            }
-            write!(&mut msg, "`{}`", self.symbol_name(symbol)).unwrap();
+        let finder = CounterexampleFinder {
+            syntax_grammar: self.syntax_grammar,
+            item_set_builder: &self.item_set_builder,
+            item_sets: &item_sets,
+            preceding_symbols,
+            lookahead: conflicting_lookahead,
+        };
+
+        writeln!(msg, "\nCounterexample:\n").unwrap();
+        for symbol in finder.example_tokens() {
+            write!(msg, "  {}", self.symbol_name(&symbol)).unwrap();
         }
-        writeln!(&mut msg).unwrap();
+        writeln!(msg, "  •  {}  …", self.symbol_name(&conflicting_lookahead)).unwrap();
 
-        Err(anyhow!(msg))
+        let symbol_name = |symbol: &Symbol| self.symbol_name(symbol);
+        for (i, derivation) in finder.derivations(items).iter().enumerate() {
+            let label = format!("  {}:  ", i + 1);
+            msg.push('\n');
+            for (j, line) in render_derivation(derivation, &symbol_name)
+                .iter()
+                .enumerate()
+            {
+                if j == 0 {
+                    msg.push_str(&label);
+                } else {
+                    msg.extend(std::iter::repeat(' ').take(label.len()));
+                }
+                msg.push_str(line);
+                msg.push('\n');
+            }
+        }
     }
 
     fn compare_precedence(
@@ -913,19 +1052,25 @@ impl<'a> ParseTableBuilder<'a> {
     }
 
     fn symbol_name(&self, symbol: &Symbol) -> String {
-        match symbol.kind {
-            SymbolType::End | SymbolType::EndOfNonTerminalExtra => "EOF".to_string(),
-            SymbolType::External => self.syntax_grammar.external_tokens[symbol.index]
-                .name
-                .clone(),
-            SymbolType::NonTerminal => self.syntax_grammar.variables[symbol.index].name.clone(),
-            SymbolType::Terminal => {
-                let variable = &self.lexical_grammar.variables[symbol.index];
-                if variable.kind == VariableType::Named {
-                    variable.name.clone()
-                } else {
-                    format!("'{}'", &variable.name)
-                }
+        symbol_name(self.syntax_grammar, self.lexical_grammar, symbol)
+    }
+}
+
+pub fn symbol_name(
+    syntax_grammar: &SyntaxGrammar,
+    lexical_grammar: &LexicalGrammar,
+    symbol: &Symbol,
+) -> String {
+    match symbol.kind {
+        SymbolType::End | SymbolType::EndOfNonTerminalExtra => "EOF".to_string(),
+        SymbolType::External => syntax_grammar.external_tokens[symbol.index].name.clone(),
+        SymbolType::NonTerminal => syntax_grammar.variables[symbol.index].name.clone(),
+        SymbolType::Terminal => {
+            let variable = &lexical_grammar.variables[symbol.index];
+            if variable.kind == VariableType::Named {
+                variable.name.clone()
+            } else {
+                format!("'{}'", &variable.name)
             }
         }
     }
@@ -971,7 +1116,12 @@ pub fn build_parse_table<'a>(
     lexical_grammar: &'a LexicalGrammar,
     inlines: &'a InlinedProductionMap,
     variable_info: &'a [VariableInfo],
-) -> Result<(ParseTable, Vec<TokenSet>, Vec<ParseStateInfo<'a>>)> {
+) -> Result<(
+    ParseTable,
+    Vec<TokenSet>,
+    Vec<ParseStateInfo<'a>>,
+    ConflictResolutionInfo,
+)> {
     let actual_conflicts = syntax_grammar.expected_conflicts.iter().cloned().collect();
     let item_set_builder = ParseItemSetBuilder::new(syntax_grammar, lexical_grammar, inlines);
     let mut following_tokens = vec![TokenSet::new(); lexical_grammar.variables.len()];
@@ -982,16 +1132,18 @@ pub fn build_parse_table<'a>(
         &item_set_builder,
     );
 
-    let (table, item_sets) = ParseTableBuilder {
+    let (table, item_sets, conflict_resolution_info) = ParseTableBuilder {
         syntax_grammar,
         lexical_grammar,
         item_set_builder,
         variable_info,
         non_terminal_extra_states: Vec::new(),
         actual_conflicts,
+        symbols_with_used_precedence: HashSet::new(),
         state_ids_by_item_set: IndexMap::default(),
         core_ids_by_core: HashMap::new(),
         parse_state_info_by_id: Vec::new(),
+        parse_state_predecessors: Vec::new(),
         parse_state_queue: VecDeque::new(),
         parse_table: ParseTable {
             states: Vec::new(),
@@ -1003,5 +1155,5 @@ pub fn build_parse_table<'a>(
     }
     .build()?;
 
-    Ok((table, following_tokens, item_sets))
+    Ok((table, following_tokens, item_sets, conflict_resolution_info))
 }
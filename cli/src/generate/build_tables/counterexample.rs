@@ -0,0 +1,394 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::{
+    item::{ParseItem, ParseItemSet},
+    item_set_builder::ParseItemSetBuilder,
+};
+use crate::generate::{grammars::SyntaxGrammar, rules::Symbol};
+
+/// A node in a derivation tree that explains one side of a parse conflict.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Derivation {
+    Leaf(Symbol),
+    /// The position of the parser when the conflict is encountered.
+    Dot,
+    Node(Symbol, Vec<Derivation>),
+}
+
+/// Searches the parse states leading up to a conflict for concrete examples of
+/// the conflict, in the style of Bison's counterexamples.
+///
+/// The `item_sets` are the closed item sets of the parse states that are
+/// visited while consuming the `preceding_symbols`: `item_sets[i]` is the
+/// state that is reached after the first `i` symbols.
+pub struct CounterexampleFinder<'a, 'b> {
+    pub syntax_grammar: &'a SyntaxGrammar,
+    pub item_set_builder: &'b ParseItemSetBuilder<'a>,
+    pub item_sets: &'b [ParseItemSet<'a>],
+    pub preceding_symbols: &'b [Symbol],
+    pub lookahead: Symbol,
+}
+
+// A step in the search for the ancestors of a conflicting item. Each node is a
+// non-terminal that starts after `position` symbols, and was reached from
+// `child` through an item whose next symbol is the child's symbol.
+struct SearchNode<'a> {
+    position: usize,
+    symbol: Symbol,
+    child: Option<(usize, ParseItem<'a>)>,
+}
+
+impl<'a, 'b> CounterexampleFinder<'a, 'b> {
+    /// Expand the symbols preceding the conflict into a shortest possible
+    /// sequence of tokens.
+    pub fn example_tokens(&self) -> Vec<Symbol> {
+        let yields = self.shortest_yields();
+        let mut result = Vec::new();
+        for symbol in self.preceding_symbols {
+            if symbol.is_non_terminal() {
+                if let Some(tokens) = &yields[symbol.index] {
+                    result.extend_from_slice(tokens);
+                    continue;
+                }
+            }
+            result.push(*symbol);
+        }
+        result
+    }
+
+    /// Build a derivation for each of the given conflicting items. Each derivation
+    /// is a sequence of top-level nodes covering all of the preceding symbols. The
+    /// trees are rooted at a common position, so that they can be compared.
+    pub fn derivations(&self, items: &[ParseItem<'a>]) -> Vec<Vec<Derivation>> {
+        let roots = items
+            .iter()
+            .map(|item| {
+                if item.is_done() {
+                    self.reduce_derivation(item)
+                } else {
+                    self.shift_derivation(item)
+                }
+            })
+            .collect::<Vec<_>>();
+        let common_position = roots.iter().map(|r| r.0).min().unwrap_or(0);
+        roots
+            .into_iter()
+            .map(|(position, tree)| {
+                let (position, tree) = self.climb(position, tree, common_position);
+                self.preceding_symbols[..position]
+                    .iter()
+                    .map(|symbol| Derivation::Leaf(*symbol))
+                    .chain(std::iter::once(tree))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // For an item that can shift the lookahead, the derivation is just the
+    // item's own production, with the next symbol expanded down to the lookahead.
+    fn shift_derivation(&self, item: &ParseItem<'a>) -> (usize, Derivation) {
+        let step_index = item.step_index as usize;
+        let steps = &item.production.steps;
+        let mut children = steps[..step_index]
+            .iter()
+            .map(|step| Derivation::Leaf(step.symbol))
+            .collect::<Vec<_>>();
+        children.push(Derivation::Dot);
+        children.push(self.expand_to_lookahead(steps[step_index].symbol));
+        children.extend(
+            steps[step_index + 1..]
+                .iter()
+                .map(|step| Derivation::Leaf(step.symbol)),
+        );
+        (
+            self.preceding_symbols.len().saturating_sub(step_index),
+            Derivation::Node(Symbol::non_terminal(item.variable_index as usize), children),
+        )
+    }
+
+    // For a finished item, search outward through the enclosing rules for one that
+    // can consume the lookahead token after the item's rule has been reduced.
+    fn reduce_derivation(&self, item: &ParseItem<'a>) -> (usize, Derivation) {
+        let mut children = item
+            .production
+            .steps
+            .iter()
+            .map(|step| Derivation::Leaf(step.symbol))
+            .collect::<Vec<_>>();
+        children.push(Derivation::Dot);
+        let start = self
+            .preceding_symbols
+            .len()
+            .saturating_sub(item.step_index as usize);
+        let tree = Derivation::Node(Symbol::non_terminal(item.variable_index as usize), children);
+
+        let mut nodes = vec![SearchNode {
+            position: start,
+            symbol: Symbol::non_terminal(item.variable_index as usize),
+            child: None,
+        }];
+        let mut visited = HashSet::new();
+        visited.insert((start, nodes[0].symbol));
+        let mut queue = VecDeque::from([0]);
+        while let Some(node_index) = queue.pop_front() {
+            let (position, symbol) = (nodes[node_index].position, nodes[node_index].symbol);
+            for (parent, lookaheads) in &self.item_sets[position].entries {
+                if parent.symbol() != Some(symbol) || parent.step_index as usize > position {
+                    continue;
+                }
+                let following_steps = &parent.production.steps[parent.step_index as usize + 1..];
+                if let Some(next_step) = following_steps.first() {
+                    if self
+                        .item_set_builder
+                        .first_set(&next_step.symbol)
+                        .contains(&self.lookahead)
+                    {
+                        let tree = self.build_chain(&nodes, node_index, tree);
+                        return (
+                            position - parent.step_index as usize,
+                            self.wrap(parent, tree, true),
+                        );
+                    }
+                } else if parent.is_augmented() {
+                    if self.lookahead.is_eof() {
+                        return (position, self.build_chain(&nodes, node_index, tree));
+                    }
+                } else if lookaheads.contains(&self.lookahead) {
+                    let parent_position = position - parent.step_index as usize;
+                    let parent_symbol = Symbol::non_terminal(parent.variable_index as usize);
+                    if visited.insert((parent_position, parent_symbol)) {
+                        queue.push_back(nodes.len());
+                        nodes.push(SearchNode {
+                            position: parent_position,
+                            symbol: parent_symbol,
+                            child: Some((node_index, *parent)),
+                        });
+                    }
+                }
+            }
+        }
+
+        (start, tree)
+    }
+
+    // Wrap the given tree in the shortest chain of enclosing rules that starts at or
+    // before the given target position.
+    fn climb(&self, position: usize, tree: Derivation, target: usize) -> (usize, Derivation) {
+        let symbol = match &tree {
+            Derivation::Node(symbol, _) => *symbol,
+            _ => return (position, tree),
+        };
+        if position <= target {
+            return (position, tree);
+        }
+
+        let mut nodes = vec![SearchNode {
+            position,
+            symbol,
+            child: None,
+        }];
+        let mut visited = HashSet::new();
+        visited.insert((position, symbol));
+        let mut queue = VecDeque::from([0]);
+        while let Some(node_index) = queue.pop_front() {
+            let (position, symbol) = (nodes[node_index].position, nodes[node_index].symbol);
+            for (parent, _) in &self.item_sets[position].entries {
+                if parent.symbol() != Some(symbol)
+                    || parent.is_augmented()
+                    || parent.step_index as usize > position
+                {
+                    continue;
+                }
+                let parent_position = position - parent.step_index as usize;
+                let parent_symbol = Symbol::non_terminal(parent.variable_index as usize);
+                if !visited.insert((parent_position, parent_symbol)) {
+                    continue;
+                }
+                nodes.push(SearchNode {
+                    position: parent_position,
+                    symbol: parent_symbol,
+                    child: Some((node_index, *parent)),
+                });
+                if parent_position <= target {
+                    let root_index = nodes.len() - 1;
+                    return (parent_position, self.build_chain(&nodes, root_index, tree));
+                }
+                queue.push_back(nodes.len() - 1);
+            }
+        }
+
+        (position, tree)
+    }
+
+    // Build the tree for a search node by wrapping the original tree in each of the
+    // items that were traversed to reach the node.
+    fn build_chain(
+        &self,
+        nodes: &[SearchNode<'a>],
+        node_index: usize,
+        tree: Derivation,
+    ) -> Derivation {
+        let mut items = Vec::new();
+        let mut index = node_index;
+        while let Some((child_index, item)) = &nodes[index].child {
+            items.push(item);
+            index = *child_index;
+        }
+        items
+            .into_iter()
+            .rev()
+            .fold(tree, |tree, item| self.wrap(item, tree, false))
+    }
+
+    // Create a node for the given item's rule, with the given tree in place of the
+    // item's next symbol. If `consumes_lookahead` is true, then the symbol following
+    // the tree is expanded down to the lookahead token.
+    fn wrap(&self, item: &ParseItem<'a>, tree: Derivation, consumes_lookahead: bool) -> Derivation {
+        let step_index = item.step_index as usize;
+        let steps = &item.production.steps;
+        let mut children = steps[..step_index]
+            .iter()
+            .map(|step| Derivation::Leaf(step.symbol))
+            .collect::<Vec<_>>();
+        children.push(tree);
+        for (i, step) in steps[step_index + 1..].iter().enumerate() {
+            if i == 0 && consumes_lookahead {
+                children.push(self.expand_to_lookahead(step.symbol));
+            } else {
+                children.push(Derivation::Leaf(step.symbol));
+            }
+        }
+        Derivation::Node(Symbol::non_terminal(item.variable_index as usize), children)
+    }
+
+    // Expand a symbol through the shortest chain of leftmost derivations that
+    // begins with the lookahead token.
+    fn expand_to_lookahead(&self, symbol: Symbol) -> Derivation {
+        if !symbol.is_non_terminal() {
+            return Derivation::Leaf(symbol);
+        }
+
+        let mut parents = vec![None];
+        let mut symbols = vec![symbol];
+        let mut visited = HashSet::from([symbol]);
+        let mut queue = VecDeque::from([0]);
+        while let Some(index) = queue.pop_front() {
+            let variable = &self.syntax_grammar.variables[symbols[index].index];
+            for production in &variable.productions {
+                let Some(first_symbol) = production.first_symbol() else {
+                    continue;
+                };
+                let is_match = first_symbol == self.lookahead;
+                if !is_match
+                    && (!first_symbol.is_non_terminal()
+                        || !self
+                            .item_set_builder
+                            .first_set(&first_symbol)
+                            .contains(&self.lookahead)
+                        || !visited.insert(first_symbol))
+                {
+                    continue;
+                }
+
+                let mut node = if is_match {
+                    Derivation::Leaf(first_symbol)
+                } else {
+                    symbols.push(first_symbol);
+                    parents.push(Some((index, production)));
+                    queue.push_back(symbols.len() - 1);
+                    continue;
+                };
+
+                // Rebuild the chain of productions from the expanded symbol down to
+                // the lookahead token.
+                let mut current = Some((index, production));
+                while let Some((index, production)) = current {
+                    let mut children = vec![node];
+                    children.extend(
+                        production.steps[1..]
+                            .iter()
+                            .map(|step| Derivation::Leaf(step.symbol)),
+                    );
+                    node = Derivation::Node(symbols[index], children);
+                    current = parents[index];
+                }
+                return node;
+            }
+        }
+
+        Derivation::Leaf(symbol)
+    }
+
+    // Compute a shortest sequence of tokens that can be derived from each
+    // non-terminal.
+    fn shortest_yields(&self) -> Vec<Option<Vec<Symbol>>> {
+        let mut result: Vec<Option<Vec<Symbol>>> = vec![None; self.syntax_grammar.variables.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (i, variable) in self.syntax_grammar.variables.iter().enumerate() {
+                for production in &variable.productions {
+                    let mut tokens = Vec::new();
+                    let is_complete = production.steps.iter().all(|step| {
+                        if step.symbol.is_non_terminal() {
+                            if let Some(expansion) = &result[step.symbol.index] {
+                                tokens.extend_from_slice(expansion);
+                                true
+                            } else {
+                                false
+                            }
+                        } else {
+                            tokens.push(step.symbol);
+                            true
+                        }
+                    });
+                    if is_complete
+                        && !matches!(&result[i], Some(existing) if existing.len() <= tokens.len())
+                    {
+                        result[i] = Some(tokens);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Render a derivation as a tree, with each node's children listed on the line
+/// below the node, and aligned with the columns of their subtrees.
+pub fn render_derivation(
+    nodes: &[Derivation],
+    symbol_name: &impl Fn(&Symbol) -> String,
+) -> Vec<String> {
+    let mut lines = Vec::<String>::new();
+    let mut column = 0;
+    for node in nodes {
+        let block = match node {
+            Derivation::Leaf(symbol) => vec![symbol_name(symbol)],
+            Derivation::Dot => vec!["•".to_string()],
+            Derivation::Node(symbol, children) => {
+                let mut block = vec![symbol_name(symbol)];
+                for (i, line) in render_derivation(children, symbol_name)
+                    .into_iter()
+                    .enumerate()
+                {
+                    block.push(format!("{}{line}", if i == 0 { "↳ " } else { "  " }));
+                }
+                block
+            }
+        };
+
+        for (row, text) in block.iter().enumerate() {
+            if lines.len() <= row {
+                lines.push(String::new());
+            }
+            let line = &mut lines[row];
+            let padding = column - line.chars().count().min(column);
+            line.extend(std::iter::repeat(' ').take(padding));
+            line.push_str(text);
+        }
+        column += block.iter().map(|l| l.chars().count()).max().unwrap_or(0) + 2;
+    }
+    lines
+}
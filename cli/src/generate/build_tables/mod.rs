@@ -1,20 +1,23 @@
 mod build_lex_table;
 mod build_parse_table;
 mod coincident_tokens;
+mod counterexample;
 mod item;
 mod item_set_builder;
 mod minimize_parse_table;
+mod parse_table_cache;
 mod token_conflicts;
 
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use anyhow::Result;
 pub use build_lex_table::LARGE_CHARACTER_RANGE_COUNT;
 use log::info;
+pub use parse_table_cache::ParseTableCache;
 
 use self::{
     build_lex_table::build_lex_table,
-    build_parse_table::{build_parse_table, ParseStateInfo},
+    build_parse_table::{build_parse_table, symbol_name, ParseStateInfo},
     coincident_tokens::CoincidentTokenIndex,
     minimize_parse_table::minimize_parse_table,
     token_conflicts::TokenConflictMap,
@@ -42,9 +45,46 @@ pub fn build_tables(
     variable_info: &[VariableInfo],
     inlines: &InlinedProductionMap,
     report_symbol_name: Option<&str>,
+    cache: Option<&ParseTableCache>,
 ) -> Result<Tables> {
-    let (mut parse_table, following_tokens, parse_state_info) =
-        build_parse_table(syntax_grammar, lexical_grammar, inlines, variable_info)?;
+    // The parse item sets are not cached, so the cache can't be used when
+    // they need to be reported.
+    let cache = cache.filter(|_| report_symbol_name.is_none());
+    let cache_key = ParseTableCache::key(syntax_grammar, lexical_grammar, variable_info);
+    let (mut parse_table, following_tokens, parse_state_info, conflict_resolution_info) =
+        if let Some(cached) = cache.and_then(|cache| cache.load(&cache_key)) {
+            (
+                cached.parse_table,
+                cached.following_tokens,
+                Vec::new(),
+                cached.conflict_resolution_info,
+            )
+        } else {
+            let result =
+                build_parse_table(syntax_grammar, lexical_grammar, inlines, variable_info)?;
+            if let Some(cache) = cache {
+                cache.store(&cache_key, &result.0, &result.1, &result.3);
+            }
+            result
+        };
+    let unnecessary_conflicts = conflict_names(
+        syntax_grammar,
+        lexical_grammar,
+        &conflict_resolution_info.unnecessary_conflicts,
+    );
+    if !unnecessary_conflicts.is_empty() {
+        println!("Warning: unnecessary conflicts");
+        for conflict in &unnecessary_conflicts {
+            println!(
+                "  {}",
+                conflict
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    }
     let token_conflict_map = TokenConflictMap::new(lexical_grammar, following_tokens);
     let coincident_token_index = CoincidentTokenIndex::new(&parse_table, lexical_grammar);
     let keywords = identify_keywords(
@@ -101,6 +141,81 @@ pub fn build_tables(
     })
 }
 
+/// Facts about a grammar that are discovered while building its parse table, and
+/// which indicate declarations in the grammar that have no effect.
+pub struct GrammarAnalysis {
+    /// The entries of the grammar's `conflicts` list that did not correspond to
+    /// any actual conflict.
+    pub unnecessary_conflicts: Vec<Vec<String>>,
+    /// The non-terminals whose precedence or associativity was consulted in order
+    /// to resolve a conflict.
+    pub symbols_with_used_precedence: HashSet<Symbol>,
+    /// Tokens that are not keywords, but which can match the same string as the
+    /// word token in a state where the word token is also valid, and is preferred.
+    pub tokens_shadowed_by_word_token: Vec<Symbol>,
+}
+
+pub fn analyze_grammar(
+    syntax_grammar: &SyntaxGrammar,
+    lexical_grammar: &LexicalGrammar,
+    variable_info: &[VariableInfo],
+    inlines: &InlinedProductionMap,
+) -> Result<GrammarAnalysis> {
+    let (parse_table, following_tokens, _, conflict_resolution_info) =
+        build_parse_table(syntax_grammar, lexical_grammar, inlines, variable_info)?;
+    let token_conflict_map = TokenConflictMap::new(lexical_grammar, following_tokens);
+    let coincident_token_index = CoincidentTokenIndex::new(&parse_table, lexical_grammar);
+    let keywords = identify_keywords(
+        lexical_grammar,
+        &parse_table,
+        syntax_grammar.word_token,
+        &token_conflict_map,
+        &coincident_token_index,
+    );
+
+    let mut tokens_shadowed_by_word_token = Vec::new();
+    if let Some(word_token) = syntax_grammar.word_token.filter(Symbol::is_terminal) {
+        for i in 0..lexical_grammar.variables.len() {
+            let token = Symbol::terminal(i);
+            if token != word_token
+                && !keywords.contains(&token)
+                && token_conflict_map.does_match_same_string(word_token.index, i)
+                && coincident_token_index.contains(word_token, token)
+            {
+                tokens_shadowed_by_word_token.push(token);
+            }
+        }
+    }
+
+    Ok(GrammarAnalysis {
+        unnecessary_conflicts: conflict_names(
+            syntax_grammar,
+            lexical_grammar,
+            &conflict_resolution_info.unnecessary_conflicts,
+        ),
+        symbols_with_used_precedence: conflict_resolution_info.symbols_with_used_precedence,
+        tokens_shadowed_by_word_token,
+    })
+}
+
+fn conflict_names(
+    syntax_grammar: &SyntaxGrammar,
+    lexical_grammar: &LexicalGrammar,
+    conflicts: &[Vec<Symbol>],
+) -> Vec<Vec<String>> {
+    let mut result = conflicts
+        .iter()
+        .map(|conflict| {
+            conflict
+                .iter()
+                .map(|symbol| symbol_name(syntax_grammar, lexical_grammar, symbol))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    result.sort_unstable();
+    result
+}
+
 fn populate_error_state(
     parse_table: &mut ParseTable,
     syntax_grammar: &SyntaxGrammar,
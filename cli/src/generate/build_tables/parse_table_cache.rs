@@ -0,0 +1,225 @@
+use std::{
+    fmt::Write,
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+};
+
+use log::{info, warn};
+use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
+
+use super::build_parse_table::ConflictResolutionInfo;
+use crate::generate::{
+    grammars::{LexicalGrammar, SyntaxGrammar},
+    node_types::VariableInfo,
+    rules::TokenSet,
+    tables::ParseTable,
+};
+
+/// An on-disk cache of the results of building a grammar's LR(1) parse table,
+/// which is by far the most expensive step of parser generation.
+///
+/// The parse table only depends on the structure of the syntax grammar, and not
+/// on the contents of the tokens, so it can be reused when a grammar is edited
+/// in a way that only affects its lexical rules, for example when a keyword is
+/// changed. All of the subsequent steps, which do depend on the tokens, are
+/// always re-run. This includes preparing the grammars, building the lexical
+/// NFA and computing the token conflicts, which are cheap compared to building
+/// the parse table, and which would not be reused when a token is edited.
+///
+/// Each grammar has a single cache entry, which is replaced whenever the parse
+/// table needs to be rebuilt. Entries are named after both the grammar's name
+/// and its path, so that grammars with the same name, such as a fork and its
+/// upstream, don't replace each other's entries.
+pub struct ParseTableCache {
+    path: PathBuf,
+}
+
+pub struct CachedParseTable {
+    pub parse_table: ParseTable,
+    pub following_tokens: Vec<TokenSet>,
+    pub conflict_resolution_info: ConflictResolutionInfo,
+}
+
+#[derive(Deserialize)]
+struct CacheEntry {
+    key: String,
+    parse_table: ParseTable,
+    following_tokens: Vec<TokenSet>,
+    conflict_resolution_info: ConflictResolutionInfo,
+}
+
+#[derive(Serialize)]
+struct CacheEntryRef<'a> {
+    key: &'a str,
+    parse_table: &'a ParseTable,
+    following_tokens: &'a [TokenSet],
+    conflict_resolution_info: &'a ConflictResolutionInfo,
+}
+
+impl ParseTableCache {
+    pub fn new(cache_dir: &Path, grammar_name: &str, grammar_path: &Path) -> Self {
+        let grammar_path = fs::canonicalize(grammar_path).unwrap_or_else(|_| grammar_path.into());
+        let mut hasher = FxHasher::default();
+        hasher.write(grammar_path.as_os_str().as_encoded_bytes());
+        Self {
+            path: cache_dir.join(format!("{grammar_name}-{:016x}.json", hasher.finish())),
+        }
+    }
+
+    /// Compute the key under which the parse table for the given grammar is stored.
+    ///
+    /// The key is a full description of the inputs of the parse table, rather
+    /// than a hash of them, so that an entry can't be loaded for a different
+    /// grammar whose description happens to have the same hash.
+    pub fn key(
+        syntax_grammar: &SyntaxGrammar,
+        lexical_grammar: &LexicalGrammar,
+        variable_info: &[VariableInfo],
+    ) -> String {
+        let mut description = format!(
+            "{}\n{}\n{syntax_grammar:?}\n",
+            env!("CARGO_PKG_VERSION"),
+            lexical_grammar.variables.len()
+        );
+        for info in variable_info {
+            let mut field_names = info.fields.keys().collect::<Vec<_>>();
+            field_names.sort_unstable();
+            writeln!(&mut description, "{field_names:?}").unwrap();
+        }
+        description
+    }
+
+    pub fn load(&self, key: &str) -> Option<CachedParseTable> {
+        let contents = fs::read(&self.path).ok()?;
+        match serde_json::from_slice::<CacheEntry>(&contents) {
+            Ok(entry) if entry.key == key => {
+                info!("reusing cached parse table from {:?}", self.path);
+                Some(CachedParseTable {
+                    parse_table: entry.parse_table,
+                    following_tokens: entry.following_tokens,
+                    conflict_resolution_info: entry.conflict_resolution_info,
+                })
+            }
+            Ok(_) => None,
+            Err(e) => {
+                warn!("failed to read parse table cache {:?}: {e}", self.path);
+                None
+            }
+        }
+    }
+
+    pub fn store(
+        &self,
+        key: &str,
+        parse_table: &ParseTable,
+        following_tokens: &[TokenSet],
+        conflict_resolution_info: &ConflictResolutionInfo,
+    ) {
+        let entry = CacheEntryRef {
+            key,
+            parse_table,
+            following_tokens,
+            conflict_resolution_info,
+        };
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(&self.path, serde_json::to_vec(&entry)?));
+        if let Err(e) = result {
+            warn!("failed to write parse table cache {:?}: {e}", self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::{generate_parser_for_grammar_with_opts, parse_grammar::parse_grammar};
+
+    const GRAMMAR: &str = r#"{
+        "name": "test_cache",
+        "word": "identifier",
+        "rules": {
+            "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "statement"}},
+            "statement": {
+                "type": "SEQ",
+                "members": [
+                    {"type": "STRING", "value": "KEYWORD"},
+                    {"type": "FIELD", "name": "value", "content": {"type": "SYMBOL", "name": "expression"}},
+                    {"type": "STRING", "value": ";"}
+                ]
+            },
+            "expression": {
+                "type": "CHOICE",
+                "members": [
+                    {"type": "SYMBOL", "name": "identifier"},
+                    {
+                        "type": "PREC_LEFT",
+                        "value": 1,
+                        "content": {
+                            "type": "SEQ",
+                            "members": [
+                                {"type": "SYMBOL", "name": "expression"},
+                                {"type": "STRING", "value": "+"},
+                                {"type": "SYMBOL", "name": "expression"}
+                            ]
+                        }
+                    }
+                ]
+            },
+            "identifier": {"type": "PATTERN", "value": "[a-z]+"}
+        }
+    }"#;
+
+    fn generate(keyword: &str, cache: Option<&ParseTableCache>) -> String {
+        let grammar = parse_grammar(&GRAMMAR.replace("KEYWORD", keyword)).unwrap();
+        generate_parser_for_grammar_with_opts(&grammar, tree_sitter::LANGUAGE_VERSION, None, cache)
+            .unwrap()
+            .c_code
+    }
+
+    #[test]
+    fn test_cached_parse_table_is_reused_after_lexical_change() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = ParseTableCache::new(cache_dir.path(), "test_cache", Path::new("grammar.js"));
+        let cache_path = &cache.path;
+
+        assert_eq!(generate("let", Some(&cache)), generate("let", None));
+        let first_entry = fs::read_to_string(cache_path).unwrap();
+
+        // Changing a keyword does not change the syntax grammar, so the cache
+        // entry is reused, and the output is the same as without a cache.
+        assert_eq!(generate("var", Some(&cache)), generate("var", None));
+        assert_eq!(fs::read_to_string(cache_path).unwrap(), first_entry);
+
+        // Changing the structure of the grammar invalidates the entry.
+        assert_eq!(
+            generate(
+                "let\"}, {\"type\": \"STRING\", \"value\": \"=",
+                Some(&cache)
+            ),
+            generate("let\"}, {\"type\": \"STRING\", \"value\": \"=", None)
+        );
+        assert_ne!(fs::read_to_string(cache_path).unwrap(), first_entry);
+    }
+    #[test]
+    fn test_grammars_with_the_same_name_have_separate_entries() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let fork = ParseTableCache::new(cache_dir.path(), "test_cache", Path::new("a/grammar.js"));
+        let upstream =
+            ParseTableCache::new(cache_dir.path(), "test_cache", Path::new("b/grammar.js"));
+        assert_ne!(fork.path, upstream.path);
+
+        generate("let", Some(&fork));
+        let fork_entry = fs::read_to_string(&fork.path).unwrap();
+        generate(
+            "let\"}, {\"type\": \"STRING\", \"value\": \"=",
+            Some(&upstream),
+        );
+        assert_eq!(fs::read_to_string(&fork.path).unwrap(), fork_entry);
+        assert!(upstream.path.exists());
+    }
+}
@@ -4,6 +4,7 @@ use crate::generate::{
     build_tables::item::TokenSetDisplay,
     grammars::{LexicalGrammar, SyntaxGrammar},
     nfa::{CharacterSet, NfaCursor, NfaTransition},
+    parallel::parallel_map,
     rules::TokenSet,
 };
 
@@ -38,11 +39,23 @@ impl<'a> TokenConflictMap<'a> {
         let starting_chars = get_starting_chars(&mut cursor, grammar);
         let following_chars = get_following_chars(&starting_chars, &following_tokens);
 
+        // Each row of the matrix is computed independently, on a separate thread
+        // if there are enough tokens.
         let n = grammar.variables.len();
+        let rows = parallel_map(
+            &(0..n).collect::<Vec<_>>(),
+            16,
+            || NfaCursor::new(&grammar.nfa, Vec::new()),
+            |cursor, &i| {
+                (0..i)
+                    .map(|j| compute_conflict_status(cursor, grammar, &following_chars, i, j))
+                    .collect::<Vec<_>>()
+            },
+        );
+
         let mut status_matrix = vec![TokenConflictStatus::default(); n * n];
-        for i in 0..grammar.variables.len() {
-            for j in 0..i {
-                let status = compute_conflict_status(&mut cursor, grammar, &following_chars, i, j);
+        for (i, row) in rows.into_iter().enumerate() {
+            for (j, status) in row.into_iter().enumerate() {
                 status_matrix[matrix_index(n, i, j)] = status.0;
                 status_matrix[matrix_index(n, j, i)] = status.1;
             }
@@ -0,0 +1,445 @@
+//! A typed API for constructing grammars in Rust, without writing a `grammar.js` file or
+//! round-tripping through JSON. The rule functions mirror those of the JavaScript DSL:
+//!
+//! ```
+//! use tree_sitter_cli::{
+//!     choice,
+//!     generate::builder::{field, pattern, prec_left, repeat, sym, GrammarBuilder},
+//!     seq,
+//! };
+//!
+//! let grammar = GrammarBuilder::new("arithmetic")
+//!     .rule("program", repeat(sym("_expression")))
+//!     .rule("_expression", choice![sym("number"), sym("sum")])
+//!     .rule(
+//!         "sum",
+//!         prec_left(
+//!             1,
+//!             seq![
+//!                 field("left", sym("_expression")),
+//!                 "+",
+//!                 field("right", sym("_expression")),
+//!             ],
+//!         ),
+//!     )
+//!     .rule("number", pattern(r"\d+"))
+//!     .build()
+//!     .unwrap();
+//!
+//! let parser = tree_sitter_cli::generate::generate_parser_for_input_grammar(&grammar).unwrap();
+//! assert!(parser.c_code.contains("tree_sitter_arithmetic"));
+//! ```
+
+use anyhow::{anyhow, Result};
+
+use super::parse_grammar::parse_pattern_flags;
+pub use super::{
+    grammars::{InputGrammar, PrecedenceEntry, Variable, VariableType},
+    rules::{Precedence, Rule},
+};
+
+/// Build a sequence rule from any number of rules, converting each argument with
+/// [`Rule::from`]. String literals become anonymous tokens.
+#[macro_export]
+macro_rules! seq {
+    ($($rule:expr),* $(,)?) => {
+        $crate::generate::builder::seq(vec![$($crate::generate::builder::Rule::from($rule)),*])
+    };
+}
+
+/// Build a choice rule from any number of rules, converting each argument with
+/// [`Rule::from`]. String literals become anonymous tokens.
+#[macro_export]
+macro_rules! choice {
+    ($($rule:expr),* $(,)?) => {
+        $crate::generate::builder::choice(vec![$($crate::generate::builder::Rule::from($rule)),*])
+    };
+}
+
+/// Incrementally builds an [`InputGrammar`].
+pub struct GrammarBuilder {
+    grammar: InputGrammar,
+    precedences: Vec<Vec<Rule>>,
+}
+
+impl GrammarBuilder {
+    /// Create a builder for a grammar with the given name. As with `grammar.js` files, the
+    /// grammar's extras default to whitespace.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            grammar: InputGrammar {
+                name: name.into(),
+                extra_symbols: vec![pattern(r"\s")],
+                ..Default::default()
+            },
+            precedences: Vec::new(),
+        }
+    }
+
+    /// Add a rule to the grammar. The first rule added is the start rule. Adding a rule with
+    /// the same name as an existing rule replaces that rule's definition.
+    #[must_use]
+    pub fn rule(mut self, name: impl Into<String>, rule: impl Into<Rule>) -> Self {
+        let name = name.into();
+        let rule = rule.into();
+        if let Some(variable) = self.grammar.variables.iter_mut().find(|v| v.name == name) {
+            variable.rule = rule;
+        } else {
+            self.grammar.variables.push(Variable {
+                name,
+                kind: VariableType::Named,
+                rule,
+            });
+        }
+        self
+    }
+
+    /// Replace the tokens that may appear anywhere in the language.
+    #[must_use]
+    pub fn extras<R: Into<Rule>>(mut self, extras: impl IntoIterator<Item = R>) -> Self {
+        self.grammar.extra_symbols = extras.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Add a token that is produced by the grammar's external scanner.
+    #[must_use]
+    pub fn external(mut self, rule: impl Into<Rule>) -> Self {
+        self.grammar.external_tokens.push(rule.into());
+        self
+    }
+
+    /// Declare a set of rules that are expected to conflict with each other.
+    #[must_use]
+    pub fn conflict<S: Into<String>>(mut self, rule_names: impl IntoIterator<Item = S>) -> Self {
+        self.grammar
+            .expected_conflicts
+            .push(rule_names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add an ordering of precedence names and symbols, from highest to lowest precedence.
+    /// Strings are treated as precedence names, and symbols as rule names.
+    #[must_use]
+    pub fn precedences<R: Into<Rule>>(mut self, ordering: impl IntoIterator<Item = R>) -> Self {
+        self.precedences
+            .push(ordering.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Mark a rule to be inlined at all of its usage sites.
+    #[must_use]
+    pub fn inline(mut self, rule_name: impl Into<String>) -> Self {
+        self.grammar.variables_to_inline.push(rule_name.into());
+        self
+    }
+
+    /// Mark a hidden rule as a supertype of the rules that it's composed of.
+    #[must_use]
+    pub fn supertype(mut self, rule_name: impl Into<String>) -> Self {
+        self.grammar.supertype_symbols.push(rule_name.into());
+        self
+    }
+
+    /// Set the token used for keyword extraction.
+    #[must_use]
+    pub fn word(mut self, rule_name: impl Into<String>) -> Self {
+        self.grammar.word_token = Some(rule_name.into());
+        self
+    }
+
+    /// Finish building the grammar, applying the same validation as grammars loaded from JSON.
+    pub fn build(mut self) -> Result<InputGrammar> {
+        if self.grammar.variables.is_empty() {
+            return Err(anyhow!("Grammar must have at least one rule."));
+        }
+
+        if self
+            .grammar
+            .extra_symbols
+            .iter()
+            .any(|rule| matches!(rule, Rule::String(value) if value.is_empty()))
+        {
+            return Err(anyhow!(
+                "Rules in the `extras` array must not contain empty strings"
+            ));
+        }
+
+        for list in self.precedences {
+            let ordering = list
+                .into_iter()
+                .map(|entry| match entry {
+                    Rule::String(value) => Ok(PrecedenceEntry::Name(value)),
+                    Rule::NamedSymbol(name) => Ok(PrecedenceEntry::Symbol(name)),
+                    _ => Err(anyhow!(
+                        "Invalid rule in precedences array. Only strings and symbols are allowed"
+                    )),
+                })
+                .collect::<Result<_>>()?;
+            self.grammar.precedence_orderings.push(ordering);
+        }
+
+        Ok(self.grammar)
+    }
+}
+
+/// A rule that matches the empty string.
+#[must_use]
+pub const fn blank() -> Rule {
+    Rule::Blank
+}
+
+/// An anonymous token that matches a literal string.
+#[must_use]
+pub fn string(value: impl Into<String>) -> Rule {
+    Rule::String(value.into())
+}
+
+/// A token that matches a regular expression.
+#[must_use]
+pub fn pattern(value: impl Into<String>) -> Rule {
+    Rule::Pattern(value.into(), String::new())
+}
+
+/// A token that matches a regular expression with the given flags. As in `grammar.js` files,
+/// only the `i` flag affects the generated lexer.
+#[must_use]
+pub fn pattern_with_flags(value: impl Into<String>, flags: &str) -> Rule {
+    Rule::Pattern(value.into(), parse_pattern_flags(flags))
+}
+
+/// A reference to another rule, by name.
+#[must_use]
+pub fn sym(name: impl Into<String>) -> Rule {
+    Rule::NamedSymbol(name.into())
+}
+
+#[must_use]
+pub fn seq(rules: Vec<Rule>) -> Rule {
+    Rule::seq(rules)
+}
+
+#[must_use]
+pub fn choice(rules: Vec<Rule>) -> Rule {
+    Rule::choice(rules)
+}
+
+#[must_use]
+pub fn optional(rule: impl Into<Rule>) -> Rule {
+    Rule::choice(vec![rule.into(), Rule::Blank])
+}
+
+/// Zero or more repetitions of a rule.
+#[must_use]
+pub fn repeat(rule: impl Into<Rule>) -> Rule {
+    Rule::choice(vec![Rule::repeat(rule.into()), Rule::Blank])
+}
+
+/// One or more repetitions of a rule.
+#[must_use]
+pub fn repeat1(rule: impl Into<Rule>) -> Rule {
+    Rule::repeat(rule.into())
+}
+
+/// Assign a field name to the node matched by a rule.
+#[must_use]
+pub fn field(name: impl Into<String>, rule: impl Into<Rule>) -> Rule {
+    Rule::field(name.into(), rule.into())
+}
+
+/// Make a rule appear in the syntax tree as a named node with a different name.
+#[must_use]
+pub fn alias(rule: impl Into<Rule>, name: impl Into<String>) -> Rule {
+    Rule::alias(rule.into(), name.into(), true)
+}
+
+/// Make a rule appear in the syntax tree as an anonymous node with the given text.
+#[must_use]
+pub fn anonymous_alias(rule: impl Into<Rule>, value: impl Into<String>) -> Rule {
+    Rule::alias(rule.into(), value.into(), false)
+}
+
+#[must_use]
+pub fn prec(precedence: impl Into<Precedence>, rule: impl Into<Rule>) -> Rule {
+    Rule::prec(precedence.into(), rule.into())
+}
+
+#[must_use]
+pub fn prec_left(precedence: impl Into<Precedence>, rule: impl Into<Rule>) -> Rule {
+    Rule::prec_left(precedence.into(), rule.into())
+}
+
+#[must_use]
+pub fn prec_right(precedence: impl Into<Precedence>, rule: impl Into<Rule>) -> Rule {
+    Rule::prec_right(precedence.into(), rule.into())
+}
+
+#[must_use]
+pub fn prec_dynamic(precedence: i32, rule: impl Into<Rule>) -> Rule {
+    Rule::prec_dynamic(precedence, rule.into())
+}
+
+/// Combine a rule into a single token.
+#[must_use]
+pub fn token(rule: impl Into<Rule>) -> Rule {
+    Rule::token(rule.into())
+}
+
+/// Combine a rule into a single token that cannot be preceded by extras.
+#[must_use]
+pub fn token_immediate(rule: impl Into<Rule>) -> Rule {
+    Rule::immediate_token(rule.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::parse_grammar::parse_grammar;
+
+    #[test]
+    fn test_builder_matches_parsed_grammar() {
+        let built = GrammarBuilder::new("my_lang")
+            .rule("program", repeat(sym("_statement")))
+            .rule(
+                "_statement",
+                choice![sym("assignment"), sym("call"), sym("heredoc")],
+            )
+            .rule(
+                "assignment",
+                prec_right(
+                    "assign",
+                    seq![
+                        field("left", sym("identifier")),
+                        "=",
+                        field("right", sym("_statement")),
+                        optional(";"),
+                    ],
+                ),
+            )
+            .rule(
+                "call",
+                prec_dynamic(
+                    1,
+                    seq![
+                        alias(sym("identifier"), "function"),
+                        token_immediate("("),
+                        repeat1(anonymous_alias(sym("identifier"), "arg")),
+                        ")",
+                    ],
+                ),
+            )
+            .rule("identifier", token(pattern_with_flags("[a-z]+", "iu")))
+            .extras([pattern(r"\s"), sym("comment")])
+            .rule("comment", prec(-1, pattern("#.*")))
+            .external(sym("heredoc"))
+            .conflict(["assignment", "call"])
+            .precedences([string("assign"), sym("call")])
+            .inline("_statement")
+            .supertype("_statement")
+            .word("identifier")
+            .build()
+            .unwrap();
+
+        let parsed = parse_grammar(
+            r##"{
+                "name": "my_lang",
+                "word": "identifier",
+                "rules": {
+                    "program": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "_statement"}},
+                    "_statement": {
+                        "type": "CHOICE",
+                        "members": [
+                            {"type": "SYMBOL", "name": "assignment"},
+                            {"type": "SYMBOL", "name": "call"},
+                            {"type": "SYMBOL", "name": "heredoc"}
+                        ]
+                    },
+                    "assignment": {
+                        "type": "PREC_RIGHT",
+                        "value": "assign",
+                        "content": {
+                            "type": "SEQ",
+                            "members": [
+                                {"type": "FIELD", "name": "left", "content": {"type": "SYMBOL", "name": "identifier"}},
+                                {"type": "STRING", "value": "="},
+                                {"type": "FIELD", "name": "right", "content": {"type": "SYMBOL", "name": "_statement"}},
+                                {"type": "CHOICE", "members": [{"type": "STRING", "value": ";"}, {"type": "BLANK"}]}
+                            ]
+                        }
+                    },
+                    "call": {
+                        "type": "PREC_DYNAMIC",
+                        "value": 1,
+                        "content": {
+                            "type": "SEQ",
+                            "members": [
+                                {"type": "ALIAS", "named": true, "value": "function", "content": {"type": "SYMBOL", "name": "identifier"}},
+                                {"type": "IMMEDIATE_TOKEN", "content": {"type": "STRING", "value": "("}},
+                                {
+                                    "type": "REPEAT1",
+                                    "content": {"type": "ALIAS", "named": false, "value": "arg", "content": {"type": "SYMBOL", "name": "identifier"}}
+                                },
+                                {"type": "STRING", "value": ")"}
+                            ]
+                        }
+                    },
+                    "identifier": {"type": "TOKEN", "content": {"type": "PATTERN", "value": "[a-z]+", "flags": "iu"}},
+                    "comment": {"type": "PREC", "value": -1, "content": {"type": "PATTERN", "value": "#.*"}}
+                },
+                "extras": [{"type": "PATTERN", "value": "\\s"}, {"type": "SYMBOL", "name": "comment"}],
+                "externals": [{"type": "SYMBOL", "name": "heredoc"}],
+                "conflicts": [["assignment", "call"]],
+                "precedences": [[{"type": "STRING", "value": "assign"}, {"type": "SYMBOL", "name": "call"}]],
+                "inline": ["_statement"],
+                "supertypes": ["_statement"]
+            }"##,
+        )
+        .unwrap();
+
+        assert_eq!(built, parsed);
+    }
+
+    #[test]
+    fn test_builder_replaces_rules_with_the_same_name() {
+        let grammar = GrammarBuilder::new("test")
+            .rule("a", sym("b"))
+            .rule("b", "x")
+            .rule("a", "y")
+            .build()
+            .unwrap();
+        assert_eq!(
+            grammar
+                .variables
+                .iter()
+                .map(|v| (v.name.as_str(), &v.rule))
+                .collect::<Vec<_>>(),
+            [("a", &Rule::string("y")), ("b", &Rule::string("x"))]
+        );
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        let error = GrammarBuilder::new("test").build().unwrap_err();
+        assert_eq!(error.to_string(), "Grammar must have at least one rule.");
+
+        let error = GrammarBuilder::new("test")
+            .rule("a", "b")
+            .extras([""])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Rules in the `extras` array must not contain empty strings"
+        );
+
+        let error = GrammarBuilder::new("test")
+            .rule("a", "b")
+            .precedences([seq!["b"]])
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid rule in precedences array. Only strings and symbols are allowed"
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use super::rules::{Associativity, Precedence};
+
+/// A structured description of an error that prevented a grammar from being
+/// generated. Its `Display` implementation produces the same human-readable
+/// message that is printed by `tree-sitter generate`, and it can also be
+/// serialized to JSON for consumption by other tools.
+///
+/// Errors returned by the generator can be inspected with
+/// [`anyhow::Error::downcast_ref`].
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    /// The names of the grammar rules that are responsible for the error.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<String>,
+    /// The names of the symbols involved in the error. For conflicts, this is the
+    /// sequence of symbols that leads to the conflicting state.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<String>,
+    /// For conflicts, the token for which the parser has multiple possible actions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lookahead: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub precedences: Vec<RulePrecedence>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub suggestions: Vec<Suggestion>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticKind {
+    Conflict,
+    ExtraRuleConflict,
+    UndefinedSymbol,
+    InvisibleStartRule,
+    InvalidExternalToken,
+    InvalidWordToken,
+    InvalidInlineRule,
+    /// Any other error, which is only described by its message.
+    Other,
+}
+
+/// The precedence and associativity that apply to one of the rules in a conflict.
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct RulePrecedence {
+    pub rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub precedence: Option<PrecedenceValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub associativity: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum PrecedenceValue {
+    Integer(i32),
+    Name(String),
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Suggestion {
+    pub kind: SuggestionKind,
+    pub rules: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionKind {
+    HigherPrecedence,
+    Associativity,
+    AddConflict,
+}
+
+impl Diagnostic {
+    pub fn new(kind: DiagnosticKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            rules: Vec::new(),
+            symbols: Vec::new(),
+            lookahead: None,
+            precedences: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// Create a diagnostic for an arbitrary error. If the error was produced by the
+    /// generator with a structured diagnostic, that diagnostic is returned.
+    #[must_use]
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        error.downcast_ref::<Self>().cloned().unwrap_or_else(|| {
+            let message = error
+                .chain()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(": ");
+            Self::new(DiagnosticKind::Other, message)
+        })
+    }
+
+    #[must_use]
+    pub fn with_rules(mut self, rules: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.rules.extend(rules.into_iter().map(Into::into));
+        self
+    }
+
+    #[must_use]
+    pub fn with_symbols(mut self, symbols: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.symbols.extend(symbols.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl RulePrecedence {
+    pub fn new(
+        rule: impl Into<String>,
+        precedence: &Precedence,
+        associativity: Option<Associativity>,
+    ) -> Self {
+        Self {
+            rule: rule.into(),
+            precedence: match precedence {
+                Precedence::None => None,
+                Precedence::Integer(i) => Some(PrecedenceValue::Integer(*i)),
+                Precedence::Name(name) => Some(PrecedenceValue::Name(name.clone())),
+            },
+            associativity: associativity.map(|a| format!("{a:?}").to_lowercase()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::generate_parser_for_grammar;
+
+    #[test]
+    fn test_conflict_diagnostic() {
+        let error = generate_parser_for_grammar(
+            r#"{
+                "name": "test",
+                "rules": {
+                    "expression": {
+                        "type": "CHOICE",
+                        "members": [
+                            {"type": "SYMBOL", "name": "sum"},
+                            {"type": "SYMBOL", "name": "number"}
+                        ]
+                    },
+                    "sum": {
+                        "type": "SEQ",
+                        "members": [
+                            {"type": "SYMBOL", "name": "expression"},
+                            {"type": "STRING", "value": "+"},
+                            {"type": "SYMBOL", "name": "expression"}
+                        ]
+                    },
+                    "number": {"type": "PATTERN", "value": "\\d+"}
+                }
+            }"#,
+        )
+        .unwrap_err();
+
+        let diagnostic = Diagnostic::from_error(&error);
+        assert_eq!(diagnostic.message, error.to_string());
+        assert_eq!(diagnostic.kind, DiagnosticKind::Conflict);
+        assert_eq!(diagnostic.rules, ["sum"]);
+        assert_eq!(diagnostic.symbols, ["expression", "'+'", "expression"]);
+        assert_eq!(diagnostic.lookahead.as_deref(), Some("'+'"));
+        assert_eq!(
+            diagnostic
+                .suggestions
+                .iter()
+                .map(|s| s.kind)
+                .collect::<Vec<_>>(),
+            [SuggestionKind::Associativity, SuggestionKind::AddConflict],
+        );
+
+        let json = serde_json::to_value(&diagnostic).unwrap();
+        assert_eq!(json["kind"], "conflict");
+        assert_eq!(json["suggestions"][1]["rules"], serde_json::json!(["sum"]));
+    }
+
+    #[test]
+    fn test_undefined_symbol_diagnostic() {
+        let error = generate_parser_for_grammar(
+            r#"{
+                "name": "test",
+                "rules": {
+                    "program": {"type": "SYMBOL", "name": "statement"},
+                    "statement": {"type": "SYMBOL", "name": "missing"}
+                }
+            }"#,
+        )
+        .unwrap_err();
+
+        let diagnostic = Diagnostic::from_error(&error);
+        assert_eq!(diagnostic.kind, DiagnosticKind::UndefinedSymbol);
+        assert_eq!(diagnostic.message, "Undefined symbol `missing`");
+        assert_eq!(diagnostic.rules, ["statement"]);
+        assert_eq!(diagnostic.symbols, ["missing"]);
+    }
+}
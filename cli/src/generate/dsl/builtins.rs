@@ -0,0 +1,1843 @@
+use std::{cmp::Ordering, env, path::Path, rc::Rc};
+
+use indexmap::IndexMap;
+use regex::{Regex, RegexBuilder};
+use semver::Version;
+
+use super::{
+    grammar,
+    interpreter::{to_int32, Interpreter, JsResult},
+    value::{number_to_string, Callable, NativeFunction, ObjectKind, Value},
+};
+
+pub fn install_globals(interpreter: &mut Interpreter) {
+    grammar::install(interpreter);
+
+    let cli_version = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+    interpreter.set_global(
+        "TREE_SITTER_CLI_VERSION_MAJOR",
+        Value::Number(cli_version.major as f64),
+    );
+    interpreter.set_global(
+        "TREE_SITTER_CLI_VERSION_MINOR",
+        Value::Number(cli_version.minor as f64),
+    );
+    interpreter.set_global(
+        "TREE_SITTER_CLI_VERSION_PATCH",
+        Value::Number(cli_version.patch as f64),
+    );
+
+    interpreter.set_global(
+        "Object",
+        with_methods(
+            Value::new_native("Object", object_constructor),
+            &[
+                ("keys", object_keys),
+                ("getOwnPropertyNames", object_keys),
+                ("values", object_values),
+                ("entries", object_entries),
+                ("assign", object_assign),
+                ("fromEntries", object_from_entries),
+                ("freeze", identity),
+                ("seal", identity),
+                ("create", object_create),
+            ],
+        ),
+    );
+    interpreter.set_global(
+        "Array",
+        with_methods(
+            Value::new_native("Array", array_constructor),
+            &[
+                ("isArray", array_is_array),
+                ("from", array_from),
+                ("of", array_of),
+            ],
+        ),
+    );
+    interpreter.set_global(
+        "String",
+        with_methods(
+            Value::new_native("String", string_constructor),
+            &[("fromCharCode", string_from_char_code)],
+        ),
+    );
+    let number = with_methods(
+        Value::new_native("Number", number_constructor),
+        &[
+            ("isInteger", number_is_integer),
+            ("isNaN", number_is_nan),
+            ("isFinite", number_is_finite),
+            ("parseInt", parse_int),
+            ("parseFloat", parse_float),
+        ],
+    );
+    if let Value::Object(object) = &number {
+        let properties = &mut object.borrow_mut().properties;
+        properties.insert(
+            "MAX_SAFE_INTEGER".into(),
+            Value::Number(9_007_199_254_740_991.0),
+        );
+        properties.insert(
+            "MIN_SAFE_INTEGER".into(),
+            Value::Number(-9_007_199_254_740_991.0),
+        );
+    }
+    interpreter.set_global("Number", number);
+    interpreter.set_global("Boolean", Value::new_native("Boolean", boolean_constructor));
+    interpreter.set_global("RegExp", Value::new_native("RegExp", regexp_constructor));
+    interpreter.set_global(
+        "Function",
+        Value::new_native("Function", function_constructor),
+    );
+    for name in [
+        "Error",
+        "TypeError",
+        "ReferenceError",
+        "RangeError",
+        "SyntaxError",
+    ] {
+        interpreter.set_global(
+            name,
+            bound(
+                Value::new_native(name, error_constructor),
+                Value::string(name),
+            ),
+        );
+    }
+
+    let math = with_methods(
+        Value::new_object(IndexMap::new()),
+        &[
+            ("max", math_max),
+            ("min", math_min),
+            ("abs", math_abs),
+            ("floor", math_floor),
+            ("ceil", math_ceil),
+            ("round", math_round),
+            ("trunc", math_trunc),
+            ("sign", math_sign),
+            ("sqrt", math_sqrt),
+            ("pow", math_pow),
+        ],
+    );
+    if let Value::Object(object) = &math {
+        let properties = &mut object.borrow_mut().properties;
+        properties.insert("PI".into(), Value::Number(std::f64::consts::PI));
+        properties.insert("E".into(), Value::Number(std::f64::consts::E));
+    }
+    interpreter.set_global("Math", math);
+
+    interpreter.set_global(
+        "JSON",
+        with_methods(
+            Value::new_object(IndexMap::new()),
+            &[("stringify", json_stringify), ("parse", json_parse)],
+        ),
+    );
+    interpreter.set_global(
+        "console",
+        with_methods(
+            Value::new_object(IndexMap::new()),
+            &[
+                ("log", console_log),
+                ("info", console_log),
+                ("debug", console_log),
+                ("warn", console_warn),
+                ("error", console_warn),
+            ],
+        ),
+    );
+
+    let environment = env::vars()
+        .map(|(k, v)| (k.as_str().into(), Value::string(&v)))
+        .collect();
+    interpreter.set_global(
+        "process",
+        Value::object_from_pairs([
+            ("env", Value::new_object(environment)),
+            ("platform", Value::string(env::consts::OS)),
+            ("argv", Value::new_array(Vec::new())),
+        ]),
+    );
+
+    interpreter.set_global("parseInt", Value::new_native("parseInt", parse_int));
+    interpreter.set_global("parseFloat", Value::new_native("parseFloat", parse_float));
+    interpreter.set_global("isNaN", Value::new_native("isNaN", global_is_nan));
+    interpreter.set_global("isFinite", Value::new_native("isFinite", global_is_finite));
+}
+
+pub fn with_methods(target: Value, methods: &[(&'static str, NativeFunction)]) -> Value {
+    if let Value::Object(object) = &target {
+        let properties = &mut object.borrow_mut().properties;
+        for (name, function) in methods {
+            properties.insert((*name).into(), Value::new_native(name, *function));
+        }
+    }
+    target
+}
+
+pub fn bound(target: Value, this: Value) -> Value {
+    Value::from_kind(
+        ObjectKind::Function(Callable::Bound(target, this)),
+        IndexMap::new(),
+    )
+}
+
+pub fn argument(arguments: &[Value], index: usize) -> Value {
+    arguments.get(index).cloned().unwrap_or(Value::Undefined)
+}
+
+pub fn new_regexp(source: &str, flags: &str) -> Value {
+    Value::from_kind(
+        ObjectKind::RegExp {
+            source: source.to_string(),
+            flags: flags.to_string(),
+        },
+        IndexMap::new(),
+    )
+}
+
+/// The keys that `Object.keys` would return for a value.
+pub fn own_keys(value: &Value) -> Vec<Rc<str>> {
+    match value {
+        Value::String(s) => (0..s.chars().count())
+            .map(|i| i.to_string().into())
+            .collect(),
+        Value::Object(object) => {
+            let object = object.borrow();
+            let mut keys = match &object.kind {
+                ObjectKind::Array(elements) => {
+                    (0..elements.len()).map(|i| i.to_string().into()).collect()
+                }
+                _ => Vec::new(),
+            };
+            keys.extend(object.properties.keys().cloned());
+            keys
+        }
+        _ => Vec::new(),
+    }
+}
+
+pub fn instance_of(value: &Value, constructor: &Value) -> bool {
+    let Value::Object(reference) = value else {
+        return false;
+    };
+    let name = match constructor {
+        Value::Object(constructor) => match &constructor.borrow().kind {
+            ObjectKind::Function(Callable::Native(name, _)) => (*name).to_string(),
+            ObjectKind::Function(Callable::Bound(_, Value::String(name))) => name.to_string(),
+            _ => return false,
+        },
+        _ => return false,
+    };
+    let object = reference.borrow();
+    match (name.as_str(), &object.kind) {
+        ("Object", _)
+        | ("Array", ObjectKind::Array(_))
+        | ("RegExp", ObjectKind::RegExp { .. })
+        | ("Function", ObjectKind::Function(_))
+        | ("Error", ObjectKind::Error) => true,
+        (_, ObjectKind::Error) => object
+            .properties
+            .get("name")
+            .and_then(Value::as_str)
+            .is_some_and(|n| n == name),
+        _ => false,
+    }
+}
+
+pub fn require(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let specifier = argument(&arguments, 0).to_js_string();
+    let current_path = this.to_js_string();
+    let directory = Path::new(&current_path).parent().unwrap_or(Path::new("."));
+
+    let resolved = if specifier.starts_with("./")
+        || specifier.starts_with("../")
+        || specifier.starts_with('/')
+    {
+        resolve_file(&directory.join(&specifier))
+    } else {
+        directory.ancestors().find_map(|ancestor| {
+            let package_path = ancestor.join("node_modules").join(&specifier);
+            resolve_file(&package_path).or_else(|| resolve_package(&package_path))
+        })
+    };
+
+    match resolved {
+        Some(path) => interpreter.require(&path),
+        None => Err(interpreter.error(
+            "Error",
+            &format!("Cannot find module '{specifier}' from '{current_path}'"),
+        )),
+    }
+}
+
+fn resolve_file(path: &Path) -> Option<std::path::PathBuf> {
+    if path.is_file() {
+        return path.canonicalize().ok();
+    }
+    for extension in ["js", "cjs", "json"] {
+        let candidate = path.with_extension(extension);
+        if candidate.is_file() {
+            return candidate.canonicalize().ok();
+        }
+    }
+    let index = path.join("index.js");
+    if index.is_file() {
+        return index.canonicalize().ok();
+    }
+    None
+}
+
+fn resolve_package(path: &Path) -> Option<std::path::PathBuf> {
+    let package_json = std::fs::read_to_string(path.join("package.json")).ok()?;
+    let package_json = serde_json::from_str::<serde_json::Value>(&package_json).ok()?;
+    let main = package_json.get("main")?.as_str()?;
+    resolve_file(&path.join(main))
+}
+
+// Properties of primitive and built-in values
+
+pub fn object_property(key: &str) -> Value {
+    match key {
+        "hasOwnProperty" => Value::new_native("hasOwnProperty", object_has_own_property),
+        "toString" => Value::new_native("toString", to_string),
+        _ => Value::Undefined,
+    }
+}
+
+pub fn function_property(key: &str) -> Value {
+    match key {
+        "call" => Value::new_native("call", function_call),
+        "apply" => Value::new_native("apply", function_apply),
+        "bind" => Value::new_native("bind", function_bind),
+        "toString" => Value::new_native("toString", to_string),
+        "hasOwnProperty" => Value::new_native("hasOwnProperty", object_has_own_property),
+        _ => Value::Undefined,
+    }
+}
+
+pub fn number_property(key: &str) -> Value {
+    match key {
+        "toString" => Value::new_native("toString", number_to_string_method),
+        "toFixed" => Value::new_native("toFixed", number_to_fixed),
+        _ => Value::Undefined,
+    }
+}
+
+pub fn regexp_property(source: &str, flags: &str, key: &str) -> Value {
+    match key {
+        "source" => Value::string(source),
+        "flags" => Value::string(flags),
+        "global" => Value::Bool(flags.contains('g')),
+        "ignoreCase" => Value::Bool(flags.contains('i')),
+        "multiline" => Value::Bool(flags.contains('m')),
+        "unicode" => Value::Bool(flags.contains('u')),
+        "test" => Value::new_native("test", regexp_test),
+        "exec" => Value::new_native("exec", regexp_exec),
+        "toString" => Value::new_native("toString", to_string),
+        _ => Value::Undefined,
+    }
+}
+
+pub fn array_property(elements: &[Value], key: &str) -> Value {
+    if let Ok(index) = key.parse::<usize>() {
+        return elements.get(index).cloned().unwrap_or(Value::Undefined);
+    }
+    let function: NativeFunction = match key {
+        "length" => return Value::Number(elements.len() as f64),
+        "push" => array_push,
+        "pop" => array_pop,
+        "shift" => array_shift,
+        "unshift" => array_unshift,
+        "slice" => array_slice,
+        "splice" => array_splice,
+        "concat" => array_concat,
+        "join" => array_join,
+        "map" => array_map,
+        "filter" => array_filter,
+        "forEach" => array_for_each,
+        "reduce" => array_reduce,
+        "reduceRight" => array_reduce_right,
+        "some" => array_some,
+        "every" => array_every,
+        "find" => array_find,
+        "findIndex" => array_find_index,
+        "findLast" => array_find_last,
+        "indexOf" => array_index_of,
+        "lastIndexOf" => array_last_index_of,
+        "includes" => array_includes,
+        "reverse" => array_reverse,
+        "sort" => array_sort,
+        "flat" => array_flat,
+        "flatMap" => array_flat_map,
+        "fill" => array_fill,
+        "at" => array_at,
+        "keys" => array_keys,
+        "entries" => array_entries,
+        "toString" => to_string,
+        _ => return Value::Undefined,
+    };
+    Value::new_native("", function)
+}
+
+pub fn string_property(string: &str, key: &str) -> Value {
+    if let Ok(index) = key.parse::<usize>() {
+        return string
+            .chars()
+            .nth(index)
+            .map_or(Value::Undefined, |c| Value::String(c.to_string().into()));
+    }
+    let function: NativeFunction = match key {
+        "length" => return Value::Number(string.encode_utf16().count() as f64),
+        "charAt" => string_char_at,
+        "charCodeAt" => string_char_code_at,
+        "codePointAt" => string_char_code_at,
+        "at" => string_at,
+        "indexOf" => string_index_of,
+        "lastIndexOf" => string_last_index_of,
+        "includes" => string_includes,
+        "startsWith" => string_starts_with,
+        "endsWith" => string_ends_with,
+        "slice" => string_slice,
+        "substring" => string_substring,
+        "substr" => string_substr,
+        "toUpperCase" => string_to_upper_case,
+        "toLowerCase" => string_to_lower_case,
+        "trim" => string_trim,
+        "trimStart" => string_trim_start,
+        "trimEnd" => string_trim_end,
+        "split" => string_split,
+        "replace" => string_replace,
+        "replaceAll" => string_replace_all,
+        "repeat" => string_repeat,
+        "padStart" => string_pad_start,
+        "padEnd" => string_pad_end,
+        "concat" => string_concat,
+        "localeCompare" => string_locale_compare,
+        "match" => string_match,
+        "toString" | "valueOf" => to_string,
+        _ => return Value::Undefined,
+    };
+    Value::new_native("", function)
+}
+
+fn to_string(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::String(this.to_js_string().into()))
+}
+
+fn identity(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(argument(&arguments, 0))
+}
+
+// Object
+
+fn object_constructor(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let value = argument(&arguments, 0);
+    Ok(if matches!(value, Value::Object(_)) {
+        value
+    } else {
+        Value::new_object(IndexMap::new())
+    })
+}
+
+fn object_create(_: &mut Interpreter, _: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::new_object(IndexMap::new()))
+}
+
+fn object_keys(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let keys = own_keys(&argument(&arguments, 0));
+    Ok(Value::new_array(
+        keys.into_iter().map(Value::String).collect(),
+    ))
+}
+
+fn object_values(
+    interpreter: &mut Interpreter,
+    _: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let object = argument(&arguments, 0);
+    let mut values = Vec::new();
+    for key in own_keys(&object) {
+        values.push(interpreter.get_property(&object, &key)?);
+    }
+    Ok(Value::new_array(values))
+}
+
+fn object_entries(
+    interpreter: &mut Interpreter,
+    _: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let object = argument(&arguments, 0);
+    let mut entries = Vec::new();
+    for key in own_keys(&object) {
+        let value = interpreter.get_property(&object, &key)?;
+        entries.push(Value::new_array(vec![Value::String(key), value]));
+    }
+    Ok(Value::new_array(entries))
+}
+
+fn object_assign(
+    interpreter: &mut Interpreter,
+    _: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let target = argument(&arguments, 0);
+    for source in arguments.iter().skip(1) {
+        for key in own_keys(source) {
+            let value = interpreter.get_property(source, &key)?;
+            interpreter.set_property(&target, &key, value)?;
+        }
+    }
+    Ok(target)
+}
+
+fn object_from_entries(
+    interpreter: &mut Interpreter,
+    _: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let object = Value::new_object(IndexMap::new());
+    for entry in interpreter.iterate(&argument(&arguments, 0))? {
+        let key = interpreter.get_property(&entry, "0")?.to_property_key();
+        let value = interpreter.get_property(&entry, "1")?;
+        interpreter.set_property(&object, &key, value)?;
+    }
+    Ok(object)
+}
+
+fn object_has_own_property(
+    _: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let key = argument(&arguments, 0).to_property_key();
+    Ok(Value::Bool(own_keys(&this).contains(&key)))
+}
+
+// Function
+
+fn function_constructor(interpreter: &mut Interpreter, _: Value, _: Vec<Value>) -> JsResult<Value> {
+    Err(interpreter.error(
+        "Error",
+        "The Function constructor is not supported by the native grammar evaluator",
+    ))
+}
+
+fn function_call(
+    interpreter: &mut Interpreter,
+    this: Value,
+    mut arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let receiver = if arguments.is_empty() {
+        Value::Undefined
+    } else {
+        arguments.remove(0)
+    };
+    interpreter.call(&this, receiver, arguments)
+}
+
+fn function_apply(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let receiver = argument(&arguments, 0);
+    let list = argument(&arguments, 1);
+    let list = if list.is_nullish() {
+        Vec::new()
+    } else {
+        interpreter.iterate(&list)?
+    };
+    interpreter.call(&this, receiver, list)
+}
+
+fn function_bind(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    if arguments.len() > 1 {
+        let bound_arguments = Value::new_array(arguments[1..].to_vec());
+        let target = Value::object_from_pairs([("target", this), ("arguments", bound_arguments)]);
+        return Ok(bound(
+            Value::new_native("bound", call_with_bound_arguments),
+            Value::new_array(vec![target, argument(&arguments, 0)]),
+        ));
+    }
+    Ok(bound(this, argument(&arguments, 0)))
+}
+
+fn call_with_bound_arguments(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let target = interpreter.get_property(&this, "0")?;
+    let receiver = interpreter.get_property(&this, "1")?;
+    let function = interpreter.get_property(&target, "target")?;
+    let mut all_arguments = interpreter
+        .get_property(&target, "arguments")?
+        .array_elements()
+        .unwrap_or_default();
+    all_arguments.extend(arguments);
+    interpreter.call(&function, receiver, all_arguments)
+}
+
+// Errors and regular expressions
+
+// The error constructors share this function, with the error's name bound as `this`.
+fn error_constructor(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let message = match argument(&arguments, 0) {
+        Value::Undefined => String::new(),
+        message => message.to_js_string(),
+    };
+    Ok(interpreter.new_error(&this.to_js_string(), &message))
+}
+
+fn regexp_constructor(
+    interpreter: &mut Interpreter,
+    _: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let pattern = argument(&arguments, 0);
+    let flags = argument(&arguments, 1);
+    if let Value::Object(object) = &pattern {
+        if let ObjectKind::RegExp { source, flags: f } = &object.borrow().kind {
+            let flags = if flags.is_nullish() {
+                f.clone()
+            } else {
+                flags.to_js_string()
+            };
+            return Ok(new_regexp(source, &flags));
+        }
+    }
+    let source = if matches!(pattern, Value::Undefined) {
+        String::new()
+    } else {
+        pattern.to_js_string()
+    };
+    let flags = if flags.is_nullish() {
+        String::new()
+    } else {
+        flags.to_js_string()
+    };
+    if let Some(invalid) = flags.chars().find(|c| !"dgimsuvy".contains(*c)) {
+        return Err(interpreter.error(
+            "SyntaxError",
+            &format!("Invalid flags supplied to RegExp constructor '{invalid}'"),
+        ));
+    }
+    Ok(new_regexp(&escape_regexp_source(&source), &flags))
+}
+
+// Mirror the way `RegExp.prototype.source` escapes forward slashes and line terminators.
+fn escape_regexp_source(source: &str) -> String {
+    if source.is_empty() {
+        return "(?:)".to_string();
+    }
+    let mut result = String::with_capacity(source.len());
+    let mut in_class = false;
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                result.push(c);
+                if let Some(next) = chars.next() {
+                    result.push(next);
+                }
+                continue;
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                result.push_str("\\/");
+                continue;
+            }
+            '\n' => {
+                result.push_str("\\n");
+                continue;
+            }
+            '\r' => {
+                result.push_str("\\r");
+                continue;
+            }
+            _ => {}
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn compile_regexp(interpreter: &Interpreter, value: &Value) -> JsResult<Option<(Regex, bool)>> {
+    let Value::Object(object) = value else {
+        return Ok(None);
+    };
+    let object = object.borrow();
+    let ObjectKind::RegExp { source, flags } = &object.kind else {
+        return Ok(None);
+    };
+    let regex = RegexBuilder::new(&source.replace("\\/", "/"))
+        .case_insensitive(flags.contains('i'))
+        .multi_line(flags.contains('m'))
+        .dot_matches_new_line(flags.contains('s'))
+        .build()
+        .map_err(|e| {
+            interpreter.error("SyntaxError", &format!("Invalid regular expression: {e}"))
+        })?;
+    Ok(Some((regex, flags.contains('g'))))
+}
+
+fn regexp_test(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let Some((regex, _)) = compile_regexp(interpreter, &this)? else {
+        return Err(interpreter.error(
+            "TypeError",
+            "RegExp.prototype.test requires that 'this' be a RegExp",
+        ));
+    };
+    Ok(Value::Bool(
+        regex.is_match(&argument(&arguments, 0).to_js_string()),
+    ))
+}
+
+fn regexp_exec(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let Some((regex, _)) = compile_regexp(interpreter, &this)? else {
+        return Err(interpreter.error(
+            "TypeError",
+            "RegExp.prototype.exec requires that 'this' be a RegExp",
+        ));
+    };
+    let haystack = argument(&arguments, 0).to_js_string();
+    Ok(regex.captures(&haystack).map_or(Value::Null, |captures| {
+        captures_to_array(&captures, &haystack)
+    }))
+}
+
+fn captures_to_array(captures: &regex::Captures, haystack: &str) -> Value {
+    let groups = captures
+        .iter()
+        .map(|m| m.map_or(Value::Undefined, |m| Value::string(m.as_str())))
+        .collect();
+    let array = Value::new_array(groups);
+    if let Value::Object(object) = &array {
+        let start = captures.get(0).map_or(0, |m| m.start());
+        let properties = &mut object.borrow_mut().properties;
+        properties.insert(
+            "index".into(),
+            Value::Number(haystack[..start].chars().count() as f64),
+        );
+        properties.insert("input".into(), Value::string(haystack));
+    }
+    array
+}
+
+// Arrays
+
+fn with_elements<T>(this: &Value, f: impl FnOnce(&mut Vec<Value>) -> T) -> T {
+    match this {
+        Value::Object(object) => match &mut object.borrow_mut().kind {
+            ObjectKind::Array(elements) => f(elements),
+            _ => f(&mut Vec::new()),
+        },
+        _ => f(&mut Vec::new()),
+    }
+}
+
+fn relative_index(index: &Value, length: usize, default: usize) -> usize {
+    if matches!(index, Value::Undefined) {
+        return default;
+    }
+    let index = index.to_number();
+    let index = if index.is_nan() { 0.0 } else { index.trunc() };
+    if index < 0.0 {
+        (length as f64 + index).max(0.0) as usize
+    } else {
+        (index as usize).min(length)
+    }
+}
+
+fn array_constructor(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    if let [Value::Number(length)] = arguments.as_slice() {
+        return Ok(Value::new_array(vec![Value::Undefined; *length as usize]));
+    }
+    Ok(Value::new_array(arguments))
+}
+
+fn array_is_array(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(argument(&arguments, 0).is_array()))
+}
+
+fn array_of(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::new_array(arguments))
+}
+
+fn array_from(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let source = argument(&arguments, 0);
+    let items = if source.is_array() || matches!(source, Value::String(_)) {
+        interpreter.iterate(&source)?
+    } else if source.is_nullish() {
+        return Err(interpreter.error("TypeError", "Array.from requires an array-like object"));
+    } else {
+        let length = interpreter.get_property(&source, "length")?.to_number();
+        let length = if length.is_nan() { 0 } else { length as usize };
+        let mut items = Vec::with_capacity(length);
+        for i in 0..length {
+            items.push(interpreter.get_property(&source, &i.to_string())?);
+        }
+        items
+    };
+    let map = argument(&arguments, 1);
+    if map.is_nullish() {
+        return Ok(Value::new_array(items));
+    }
+    let mut result = Vec::with_capacity(items.len());
+    for (i, item) in items.into_iter().enumerate() {
+        result.push(interpreter.call(
+            &map,
+            Value::Undefined,
+            vec![item, Value::Number(i as f64)],
+        )?);
+    }
+    Ok(Value::new_array(result))
+}
+
+fn array_push(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(with_elements(&this, |elements| {
+        elements.extend(arguments);
+        elements.len() as f64
+    })))
+}
+
+fn array_pop(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(with_elements(&this, Vec::pop).unwrap_or(Value::Undefined))
+}
+
+fn array_shift(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(with_elements(&this, |elements| {
+        if elements.is_empty() {
+            Value::Undefined
+        } else {
+            elements.remove(0)
+        }
+    }))
+}
+
+fn array_unshift(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(with_elements(&this, |elements| {
+        elements.splice(0..0, arguments);
+        elements.len() as f64
+    })))
+}
+
+fn array_slice(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let elements = this.array_elements().unwrap_or_default();
+    let start = relative_index(&argument(&arguments, 0), elements.len(), 0);
+    let end = relative_index(&argument(&arguments, 1), elements.len(), elements.len());
+    Ok(Value::new_array(if start < end {
+        elements[start..end].to_vec()
+    } else {
+        Vec::new()
+    }))
+}
+
+fn array_splice(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let removed = with_elements(&this, |elements| {
+        let start = relative_index(&argument(&arguments, 0), elements.len(), 0);
+        let count = match arguments.get(1) {
+            Some(count) => (count.to_number().max(0.0) as usize).min(elements.len() - start),
+            None => elements.len() - start,
+        };
+        let insertion = arguments.iter().skip(2).cloned();
+        elements.splice(start..start + count, insertion).collect()
+    });
+    Ok(Value::new_array(removed))
+}
+
+fn array_concat(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let mut elements = this.array_elements().unwrap_or_default();
+    for argument in arguments {
+        match argument.array_elements() {
+            Some(items) => elements.extend(items),
+            None => elements.push(argument),
+        }
+    }
+    Ok(Value::new_array(elements))
+}
+
+fn array_join(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let separator = match argument(&arguments, 0) {
+        Value::Undefined => ",".to_string(),
+        separator => separator.to_js_string(),
+    };
+    let elements = this.array_elements().unwrap_or_default();
+    let strings = elements
+        .iter()
+        .map(|e| {
+            if e.is_nullish() {
+                String::new()
+            } else {
+                e.to_js_string()
+            }
+        })
+        .collect::<Vec<_>>();
+    Ok(Value::String(strings.join(&separator).into()))
+}
+
+// Invoke a callback for each element of an array, passing the element, its index, and the
+// array, as `Array.prototype` methods do.
+fn each_with_callback(
+    interpreter: &mut Interpreter,
+    this: &Value,
+    arguments: &[Value],
+    mut f: impl FnMut(usize, Value, Value) -> Option<Value>,
+) -> JsResult<Option<Value>> {
+    let callback = argument(arguments, 0);
+    if !callback.is_function() {
+        return Err(interpreter.error("TypeError", &format!("{callback:?} is not a function")));
+    }
+    let this_argument = argument(arguments, 1);
+    let elements = this.array_elements().unwrap_or_default();
+    for (i, element) in elements.into_iter().enumerate() {
+        let result = interpreter.call(
+            &callback,
+            this_argument.clone(),
+            vec![element.clone(), Value::Number(i as f64), this.clone()],
+        )?;
+        if let Some(value) = f(i, element, result) {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+fn array_map(interpreter: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let mut result = Vec::new();
+    each_with_callback(interpreter, &this, &arguments, |_, _, value| {
+        result.push(value);
+        None
+    })?;
+    Ok(Value::new_array(result))
+}
+
+fn array_filter(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let mut result = Vec::new();
+    each_with_callback(interpreter, &this, &arguments, |_, element, value| {
+        if value.truthy() {
+            result.push(element);
+        }
+        None
+    })?;
+    Ok(Value::new_array(result))
+}
+
+fn array_for_each(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    each_with_callback(interpreter, &this, &arguments, |_, _, _| None)?;
+    Ok(Value::Undefined)
+}
+
+fn array_some(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let found = each_with_callback(interpreter, &this, &arguments, |_, _, value| {
+        value.truthy().then_some(Value::Bool(true))
+    })?;
+    Ok(Value::Bool(found.is_some()))
+}
+
+fn array_every(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let failed = each_with_callback(interpreter, &this, &arguments, |_, _, value| {
+        (!value.truthy()).then_some(Value::Bool(false))
+    })?;
+    Ok(Value::Bool(failed.is_none()))
+}
+
+fn array_find(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let found = each_with_callback(interpreter, &this, &arguments, |_, element, value| {
+        value.truthy().then_some(element)
+    })?;
+    Ok(found.unwrap_or(Value::Undefined))
+}
+
+fn array_find_index(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let found = each_with_callback(interpreter, &this, &arguments, |i, _, value| {
+        value.truthy().then_some(Value::Number(i as f64))
+    })?;
+    Ok(found.unwrap_or(Value::Number(-1.0)))
+}
+
+fn array_find_last(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let mut last = Value::Undefined;
+    each_with_callback(interpreter, &this, &arguments, |_, element, value| {
+        if value.truthy() {
+            last = element;
+        }
+        None
+    })?;
+    Ok(last)
+}
+
+fn array_reduce(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let elements = this.array_elements().unwrap_or_default();
+    reduce(
+        interpreter,
+        &this,
+        elements.into_iter().enumerate().collect(),
+        &arguments,
+    )
+}
+
+fn array_reduce_right(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let elements = this.array_elements().unwrap_or_default();
+    reduce(
+        interpreter,
+        &this,
+        elements.into_iter().enumerate().rev().collect(),
+        &arguments,
+    )
+}
+
+fn reduce(
+    interpreter: &mut Interpreter,
+    this: &Value,
+    elements: Vec<(usize, Value)>,
+    arguments: &[Value],
+) -> JsResult<Value> {
+    let callback = argument(arguments, 0);
+    let mut elements = elements.into_iter();
+    let mut accumulator = if arguments.len() > 1 {
+        arguments[1].clone()
+    } else if let Some((_, first)) = elements.next() {
+        first
+    } else {
+        return Err(interpreter.error("TypeError", "Reduce of empty array with no initial value"));
+    };
+    for (i, element) in elements {
+        accumulator = interpreter.call(
+            &callback,
+            Value::Undefined,
+            vec![accumulator, element, Value::Number(i as f64), this.clone()],
+        )?;
+    }
+    Ok(accumulator)
+}
+
+fn array_index_of(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let needle = argument(&arguments, 0);
+    let elements = this.array_elements().unwrap_or_default();
+    let index = elements.iter().position(|e| e.strict_equals(&needle));
+    Ok(Value::Number(index.map_or(-1.0, |i| i as f64)))
+}
+
+fn array_last_index_of(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let needle = argument(&arguments, 0);
+    let elements = this.array_elements().unwrap_or_default();
+    let index = elements.iter().rposition(|e| e.strict_equals(&needle));
+    Ok(Value::Number(index.map_or(-1.0, |i| i as f64)))
+}
+
+fn array_includes(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let needle = argument(&arguments, 0);
+    let elements = this.array_elements().unwrap_or_default();
+    Ok(Value::Bool(elements.iter().any(|e| {
+        e.strict_equals(&needle)
+            || matches!(
+                (e, &needle),
+                (Value::Number(a), Value::Number(b)) if a.is_nan() && b.is_nan()
+            )
+    })))
+}
+
+fn array_reverse(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    with_elements(&this, |elements| elements.reverse());
+    Ok(this)
+}
+
+fn array_sort(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let comparator = argument(&arguments, 0);
+    let mut elements = this.array_elements().unwrap_or_default();
+    let mut error = None;
+    // Undefined values always sort to the end, without being passed to the comparator.
+    elements.sort_by(|a, b| match (a, b) {
+        (Value::Undefined, Value::Undefined) => Ordering::Equal,
+        (Value::Undefined, _) => Ordering::Greater,
+        (_, Value::Undefined) => Ordering::Less,
+        _ if error.is_some() => Ordering::Equal,
+        _ if comparator.is_function() => {
+            match interpreter.call(&comparator, Value::Undefined, vec![a.clone(), b.clone()]) {
+                Ok(result) => result
+                    .to_number()
+                    .partial_cmp(&0.0)
+                    .unwrap_or(Ordering::Equal),
+                Err(e) => {
+                    error = Some(e);
+                    Ordering::Equal
+                }
+            }
+        }
+        _ => a.to_js_string().cmp(&b.to_js_string()),
+    });
+    if let Some(error) = error {
+        return Err(error);
+    }
+    with_elements(&this, |existing| *existing = elements);
+    Ok(this)
+}
+
+fn flatten_into(result: &mut Vec<Value>, elements: Vec<Value>, depth: f64) {
+    for element in elements {
+        match element.array_elements() {
+            Some(items) if depth >= 1.0 => flatten_into(result, items, depth - 1.0),
+            _ => result.push(element),
+        }
+    }
+}
+
+fn array_flat(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let depth = match argument(&arguments, 0) {
+        Value::Undefined => 1.0,
+        depth => depth.to_number(),
+    };
+    let mut result = Vec::new();
+    flatten_into(
+        &mut result,
+        this.array_elements().unwrap_or_default(),
+        depth,
+    );
+    Ok(Value::new_array(result))
+}
+
+fn array_flat_map(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let mapped = array_map(interpreter, this, arguments)?;
+    let mut result = Vec::new();
+    flatten_into(
+        &mut result,
+        mapped.array_elements().unwrap_or_default(),
+        1.0,
+    );
+    Ok(Value::new_array(result))
+}
+
+fn array_fill(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let value = argument(&arguments, 0);
+    with_elements(&this, |elements| {
+        let start = relative_index(&argument(&arguments, 1), elements.len(), 0);
+        let end = relative_index(&argument(&arguments, 2), elements.len(), elements.len());
+        for element in elements.iter_mut().take(end).skip(start) {
+            *element = value.clone();
+        }
+    });
+    Ok(this)
+}
+
+fn array_at(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let elements = this.array_elements().unwrap_or_default();
+    let index = argument(&arguments, 0).to_number().trunc();
+    let index = if index < 0.0 {
+        elements.len() as f64 + index
+    } else {
+        index
+    };
+    Ok(if index >= 0.0 {
+        elements
+            .get(index as usize)
+            .cloned()
+            .unwrap_or(Value::Undefined)
+    } else {
+        Value::Undefined
+    })
+}
+
+fn array_keys(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    let length = this.array_elements().unwrap_or_default().len();
+    Ok(Value::new_array(
+        (0..length).map(|i| Value::Number(i as f64)).collect(),
+    ))
+}
+
+fn array_entries(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    let elements = this.array_elements().unwrap_or_default();
+    Ok(Value::new_array(
+        elements
+            .into_iter()
+            .enumerate()
+            .map(|(i, e)| Value::new_array(vec![Value::Number(i as f64), e]))
+            .collect(),
+    ))
+}
+
+// Strings
+
+fn this_string(this: &Value) -> String {
+    this.to_js_string()
+}
+
+fn char_slice(s: &str, start: usize, end: usize) -> Value {
+    Value::String(
+        s.chars()
+            .skip(start)
+            .take(end.saturating_sub(start))
+            .collect::<String>()
+            .into(),
+    )
+}
+
+fn char_index_of(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+    let byte_start = haystack
+        .char_indices()
+        .nth(from)
+        .map_or(haystack.len(), |(i, _)| i);
+    haystack[byte_start..]
+        .find(needle)
+        .map(|i| from + haystack[byte_start..byte_start + i].chars().count())
+}
+
+fn string_constructor(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(match arguments.first() {
+        Some(value) => Value::String(value.to_js_string().into()),
+        None => Value::string(""),
+    })
+}
+
+fn string_from_char_code(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let units = arguments
+        .iter()
+        .map(|a| a.to_number() as u16)
+        .collect::<Vec<_>>();
+    Ok(Value::String(String::from_utf16_lossy(&units).into()))
+}
+
+fn string_char_at(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let index = argument(&arguments, 0).to_number() as usize;
+    Ok(Value::String(
+        s.chars()
+            .nth(index)
+            .map(String::from)
+            .unwrap_or_default()
+            .into(),
+    ))
+}
+
+fn string_char_code_at(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let index = argument(&arguments, 0).to_number() as usize;
+    Ok(s.chars().nth(index).map_or(Value::Number(f64::NAN), |c| {
+        Value::Number(f64::from(c as u32))
+    }))
+}
+
+fn string_at(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let chars = this_string(&this).chars().collect::<Vec<_>>();
+    let index = argument(&arguments, 0).to_number().trunc();
+    let index = if index < 0.0 {
+        chars.len() as f64 + index
+    } else {
+        index
+    };
+    Ok(if index >= 0.0 {
+        chars
+            .get(index as usize)
+            .map_or(Value::Undefined, |c| Value::String(c.to_string().into()))
+    } else {
+        Value::Undefined
+    })
+}
+
+fn string_index_of(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let needle = argument(&arguments, 0).to_js_string();
+    let from = relative_index(&argument(&arguments, 1), s.chars().count(), 0);
+    Ok(Value::Number(
+        char_index_of(&s, &needle, from).map_or(-1.0, |i| i as f64),
+    ))
+}
+
+fn string_last_index_of(
+    _: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let s = this_string(&this);
+    let needle = argument(&arguments, 0).to_js_string();
+    Ok(Value::Number(
+        s.rfind(&needle)
+            .map_or(-1.0, |i| s[..i].chars().count() as f64),
+    ))
+}
+
+fn string_includes(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let needle = argument(&arguments, 0).to_js_string();
+    Ok(Value::Bool(this_string(&this).contains(&needle)))
+}
+
+fn string_starts_with(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let needle = argument(&arguments, 0).to_js_string();
+    let from = relative_index(&argument(&arguments, 1), s.chars().count(), 0);
+    let rest = s.chars().skip(from).collect::<String>();
+    Ok(Value::Bool(rest.starts_with(&needle)))
+}
+
+fn string_ends_with(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let needle = argument(&arguments, 0).to_js_string();
+    Ok(Value::Bool(this_string(&this).ends_with(&needle)))
+}
+
+fn string_slice(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let length = s.chars().count();
+    let start = relative_index(&argument(&arguments, 0), length, 0);
+    let end = relative_index(&argument(&arguments, 1), length, length);
+    Ok(char_slice(&s, start, end))
+}
+
+fn string_substring(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let length = s.chars().count();
+    let clamp = |value: Value, default: usize| match value {
+        Value::Undefined => default,
+        value => (value.to_number().max(0.0) as usize).min(length),
+    };
+    let start = clamp(argument(&arguments, 0), 0);
+    let end = clamp(argument(&arguments, 1), length);
+    Ok(char_slice(&s, start.min(end), start.max(end)))
+}
+
+fn string_substr(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = this_string(&this);
+    let length = s.chars().count();
+    let start = relative_index(&argument(&arguments, 0), length, 0);
+    let count = match argument(&arguments, 1) {
+        Value::Undefined => length,
+        count => count.to_number().max(0.0) as usize,
+    };
+    Ok(char_slice(&s, start, start.saturating_add(count)))
+}
+
+fn string_to_upper_case(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::String(this_string(&this).to_uppercase().into()))
+}
+
+fn string_to_lower_case(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::String(this_string(&this).to_lowercase().into()))
+}
+
+fn string_trim(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::string(this_string(&this).trim()))
+}
+
+fn string_trim_start(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::string(this_string(&this).trim_start()))
+}
+
+fn string_trim_end(_: &mut Interpreter, this: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::string(this_string(&this).trim_end()))
+}
+
+fn string_split(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let s = this_string(&this);
+    let separator = argument(&arguments, 0);
+    let parts: Vec<Value> = if let Some((regex, _)) = compile_regexp(interpreter, &separator)? {
+        regex.split(&s).map(Value::string).collect()
+    } else if matches!(separator, Value::Undefined) {
+        vec![Value::String(s.into())]
+    } else {
+        let separator = separator.to_js_string();
+        if separator.is_empty() {
+            s.chars()
+                .map(|c| Value::String(c.to_string().into()))
+                .collect()
+        } else {
+            s.split(separator.as_str()).map(Value::string).collect()
+        }
+    };
+    Ok(Value::new_array(parts))
+}
+
+fn string_replace(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    replace(interpreter, &this, &arguments, false)
+}
+
+fn string_replace_all(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    replace(interpreter, &this, &arguments, true)
+}
+
+fn replace(
+    interpreter: &mut Interpreter,
+    this: &Value,
+    arguments: &[Value],
+    all: bool,
+) -> JsResult<Value> {
+    let s = this_string(this);
+    let pattern = argument(arguments, 0);
+    let replacement = argument(arguments, 1);
+
+    let (regex, global) = match compile_regexp(interpreter, &pattern)? {
+        Some((regex, global)) => (regex, global || all),
+        None => (
+            Regex::new(&regex::escape(&pattern.to_js_string())).unwrap(),
+            all,
+        ),
+    };
+
+    let mut result = String::new();
+    let mut last_end = 0;
+    for captures in regex.captures_iter(&s) {
+        let whole = captures.get(0).unwrap();
+        result.push_str(&s[last_end..whole.start()]);
+        if replacement.is_function() {
+            let mut callback_arguments = captures
+                .iter()
+                .map(|m| m.map_or(Value::Undefined, |m| Value::string(m.as_str())))
+                .collect::<Vec<_>>();
+            callback_arguments.push(Value::Number(s[..whole.start()].chars().count() as f64));
+            callback_arguments.push(Value::string(&s));
+            let value = interpreter.call(&replacement, Value::Undefined, callback_arguments)?;
+            result.push_str(&value.to_js_string());
+        } else {
+            expand_replacement(&mut result, &replacement.to_js_string(), &captures);
+        }
+        last_end = whole.end();
+        if !global {
+            break;
+        }
+    }
+    result.push_str(&s[last_end..]);
+    Ok(Value::String(result.into()))
+}
+
+// Expand the `$&` and `$n` substitution patterns in a replacement string.
+fn expand_replacement(result: &mut String, replacement: &str, captures: &regex::Captures) {
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            result.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('$') => {
+                chars.next();
+                result.push('$');
+            }
+            Some('&') => {
+                chars.next();
+                result.push_str(captures.get(0).map_or("", |m| m.as_str()));
+            }
+            Some(d) if d.is_ascii_digit() => {
+                chars.next();
+                let index = d.to_digit(10).unwrap() as usize;
+                result.push_str(captures.get(index).map_or("", |m| m.as_str()));
+            }
+            _ => result.push('$'),
+        }
+    }
+}
+
+fn string_match(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let s = this_string(&this);
+    let pattern = argument(&arguments, 0);
+    let (regex, global) = match compile_regexp(interpreter, &pattern)? {
+        Some(compiled) => compiled,
+        None => (
+            Regex::new(&pattern.to_js_string()).map_err(|e| {
+                interpreter.error("SyntaxError", &format!("Invalid regular expression: {e}"))
+            })?,
+            false,
+        ),
+    };
+    if global {
+        let matches = regex
+            .find_iter(&s)
+            .map(|m| Value::string(m.as_str()))
+            .collect::<Vec<_>>();
+        return Ok(if matches.is_empty() {
+            Value::Null
+        } else {
+            Value::new_array(matches)
+        });
+    }
+    Ok(regex
+        .captures(&s)
+        .map_or(Value::Null, |captures| captures_to_array(&captures, &s)))
+}
+
+fn string_repeat(
+    interpreter: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let count = argument(&arguments, 0).to_number();
+    if count < 0.0 || count.is_infinite() {
+        return Err(interpreter.error("RangeError", &format!("Invalid count value: {count}")));
+    }
+    Ok(Value::String(
+        this_string(&this).repeat(count as usize).into(),
+    ))
+}
+
+fn pad(this: &Value, arguments: &[Value], at_start: bool) -> Value {
+    let s = this_string(this);
+    let target = argument(arguments, 0).to_number().max(0.0) as usize;
+    let filler = match argument(arguments, 1) {
+        Value::Undefined => " ".to_string(),
+        filler => filler.to_js_string(),
+    };
+    let length = s.chars().count();
+    if target <= length || filler.is_empty() {
+        return Value::String(s.into());
+    }
+    let padding = filler
+        .chars()
+        .cycle()
+        .take(target - length)
+        .collect::<String>();
+    Value::String(if at_start { padding + &s } else { s + &padding }.into())
+}
+
+fn string_pad_start(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(pad(&this, &arguments, true))
+}
+
+fn string_pad_end(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(pad(&this, &arguments, false))
+}
+
+fn string_concat(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let mut s = this_string(&this);
+    for argument in arguments {
+        s += &argument.to_js_string();
+    }
+    Ok(Value::String(s.into()))
+}
+
+fn string_locale_compare(
+    _: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let other = argument(&arguments, 0).to_js_string();
+    Ok(Value::Number(match this_string(&this).cmp(&other) {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    }))
+}
+
+// Numbers, booleans and math
+
+fn number_constructor(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(
+        arguments.first().map_or(0.0, Value::to_number),
+    ))
+}
+
+fn boolean_constructor(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(argument(&arguments, 0).truthy()))
+}
+
+fn number_is_integer(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(
+        matches!(argument(&arguments, 0), Value::Number(n) if n.is_finite() && n.fract() == 0.0),
+    ))
+}
+
+fn number_is_nan(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(
+        matches!(argument(&arguments, 0), Value::Number(n) if n.is_nan()),
+    ))
+}
+
+fn number_is_finite(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(
+        matches!(argument(&arguments, 0), Value::Number(n) if n.is_finite()),
+    ))
+}
+
+fn global_is_nan(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(argument(&arguments, 0).to_number().is_nan()))
+}
+
+fn global_is_finite(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Bool(argument(&arguments, 0).to_number().is_finite()))
+}
+
+fn parse_int(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = argument(&arguments, 0).to_js_string();
+    let s = s.trim();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let mut radix = match argument(&arguments, 1) {
+        Value::Undefined => 10,
+        radix => to_int32(radix.to_number()) as u32,
+    };
+    let mut digits = s;
+    if radix == 16 || radix == 0 {
+        if let Some(rest) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+            digits = rest;
+            radix = 16;
+        }
+    }
+    if radix == 0 {
+        radix = 10;
+    }
+    if !(2..=36).contains(&radix) {
+        return Ok(Value::Number(f64::NAN));
+    }
+    let digits = digits
+        .chars()
+        .take_while(|c| c.is_digit(radix))
+        .collect::<String>();
+    if digits.is_empty() {
+        return Ok(Value::Number(f64::NAN));
+    }
+    let value = digits.chars().fold(0.0, |acc, c| {
+        acc * f64::from(radix) + f64::from(c.to_digit(radix).unwrap())
+    });
+    Ok(Value::Number(if negative { -value } else { value }))
+}
+
+fn parse_float(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let s = argument(&arguments, 0).to_js_string();
+    let s = s.trim_start();
+    let mut end = 0;
+    for (i, _) in s.char_indices().skip(1).chain([(s.len(), ' ')]) {
+        if s[..i].parse::<f64>().is_ok() {
+            end = i;
+        }
+    }
+    Ok(Value::Number(s[..end].parse().unwrap_or(f64::NAN)))
+}
+
+fn number_to_string_method(
+    _: &mut Interpreter,
+    this: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    let n = this.to_number();
+    let radix = match argument(&arguments, 0) {
+        Value::Undefined => 10,
+        radix => radix.to_number() as u32,
+    };
+    if radix == 10 || !(2..=36).contains(&radix) || n.fract() != 0.0 || n < 0.0 {
+        return Ok(Value::String(number_to_string(n).into()));
+    }
+    let mut value = n as u64;
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((value % u64::from(radix)) as u32, radix).unwrap());
+        value /= u64::from(radix);
+        if value == 0 {
+            break;
+        }
+    }
+    Ok(Value::String(
+        digits.into_iter().rev().collect::<String>().into(),
+    ))
+}
+
+fn number_to_fixed(_: &mut Interpreter, this: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let digits = argument(&arguments, 0).to_number().max(0.0) as usize;
+    Ok(Value::String(
+        format!("{:.*}", digits, this.to_number()).into(),
+    ))
+}
+
+fn numbers(arguments: &[Value]) -> impl Iterator<Item = f64> + '_ {
+    arguments.iter().map(Value::to_number)
+}
+
+fn math_max(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(numbers(&arguments).fold(
+        f64::NEG_INFINITY,
+        |a, b| {
+            if a.is_nan() || b.is_nan() {
+                f64::NAN
+            } else {
+                a.max(b)
+            }
+        },
+    )))
+}
+
+fn math_min(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(numbers(&arguments).fold(
+        f64::INFINITY,
+        |a, b| {
+            if a.is_nan() || b.is_nan() {
+                f64::NAN
+            } else {
+                a.min(b)
+            }
+        },
+    )))
+}
+
+fn math_abs(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(argument(&arguments, 0).to_number().abs()))
+}
+
+fn math_floor(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(argument(&arguments, 0).to_number().floor()))
+}
+
+fn math_ceil(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(argument(&arguments, 0).to_number().ceil()))
+}
+
+fn math_round(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(
+        (argument(&arguments, 0).to_number() + 0.5).floor(),
+    ))
+}
+
+fn math_trunc(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(argument(&arguments, 0).to_number().trunc()))
+}
+
+fn math_sign(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let n = argument(&arguments, 0).to_number();
+    Ok(Value::Number(if n == 0.0 || n.is_nan() {
+        n
+    } else {
+        n.signum()
+    }))
+}
+
+fn math_sqrt(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(Value::Number(argument(&arguments, 0).to_number().sqrt()))
+}
+
+fn math_pow(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let base = argument(&arguments, 0).to_number();
+    Ok(Value::Number(
+        base.powf(argument(&arguments, 1).to_number()),
+    ))
+}
+
+// JSON and console
+
+fn json_stringify(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let Some(json) = argument(&arguments, 0).to_json() else {
+        return Ok(Value::Undefined);
+    };
+    let indent = argument(&arguments, 2);
+    let pretty = match &indent {
+        Value::Number(n) if *n > 0.0 => Some(" ".repeat(*n as usize)),
+        Value::String(s) if !s.is_empty() => Some(s.to_string()),
+        _ => None,
+    };
+    let text = match pretty {
+        None => serde_json::to_string(&json).unwrap(),
+        Some(indent) => {
+            let mut buffer = Vec::new();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut serializer = serde_json::Serializer::with_formatter(&mut buffer, formatter);
+            serde::Serialize::serialize(&json, &mut serializer).unwrap();
+            String::from_utf8(buffer).unwrap()
+        }
+    };
+    Ok(Value::String(text.into()))
+}
+
+fn json_parse(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let text = argument(&arguments, 0).to_js_string();
+    let json = serde_json::from_str(&text)
+        .map_err(|e| interpreter.error("SyntaxError", &format!("JSON.parse: {e}")))?;
+    Ok(Value::from_json(&json))
+}
+
+fn format_console_arguments(arguments: &[Value]) -> String {
+    arguments
+        .iter()
+        .map(|a| match a {
+            Value::String(s) => s.to_string(),
+            _ => format!("{a:?}"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn console_log(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    println!("{}", format_console_arguments(&arguments));
+    Ok(Value::Undefined)
+}
+
+fn console_warn(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    eprintln!("{}", format_console_arguments(&arguments));
+    Ok(Value::Undefined)
+}
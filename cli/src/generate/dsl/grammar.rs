@@ -0,0 +1,703 @@
+use std::{collections::HashSet, rc::Rc};
+
+use indexmap::IndexMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    builtins::{argument, own_keys, with_methods},
+    interpreter::{Exception, Interpreter, JsResult},
+    value::{ObjectKind, Value},
+};
+
+lazy_static! {
+    static ref GRAMMAR_NAME_REGEX: Regex = Regex::new(r"^[a-zA-Z_]\w*$").unwrap();
+}
+
+// The rule functions of the grammar DSL, ported from `dsl.js`.
+
+pub fn install(interpreter: &mut Interpreter) {
+    interpreter.set_global("alias", Value::new_native("alias", alias));
+    interpreter.set_global("blank", Value::new_native("blank", blank));
+    interpreter.set_global("choice", Value::new_native("choice", choice));
+    interpreter.set_global("field", Value::new_native("field", field));
+    interpreter.set_global("optional", Value::new_native("optional", optional));
+    interpreter.set_global(
+        "prec",
+        with_methods(
+            Value::new_native("prec", prec),
+            &[
+                ("left", prec_left),
+                ("right", prec_right),
+                ("dynamic", prec_dynamic),
+            ],
+        ),
+    );
+    interpreter.set_global("repeat", Value::new_native("repeat", repeat));
+    interpreter.set_global("repeat1", Value::new_native("repeat1", repeat1));
+    interpreter.set_global("seq", Value::new_native("seq", seq));
+    interpreter.set_global("sym", Value::new_native("sym", sym));
+    interpreter.set_global(
+        "token",
+        with_methods(
+            Value::new_native("token", token),
+            &[("immediate", token_immediate)],
+        ),
+    );
+    interpreter.set_global("grammar", Value::new_native("grammar", grammar));
+}
+
+/// Look up a property of a rule builder (the `$` argument of rule functions). Every name is
+/// a symbol, but names that are missing from the rule map produce a `ReferenceError` that is
+/// reported when the rule is normalized.
+pub fn rule_builder_property(
+    interpreter: &Interpreter,
+    rule_names: Option<&HashSet<String>>,
+    key: &str,
+) -> Value {
+    let symbol = symbol(key);
+    if rule_names.map_or(true, |names| names.contains(key)) {
+        symbol
+    } else {
+        let error = interpreter.new_error("ReferenceError", &format!("Undefined symbol '{key}'"));
+        if let Value::Object(object) = &error {
+            object
+                .borrow_mut()
+                .properties
+                .insert("symbol".into(), symbol);
+        }
+        error
+    }
+}
+
+fn rule<const N: usize>(rule_type: &str, properties: [(&str, Value); N]) -> Value {
+    Value::object_from_pairs(
+        [("type", Value::string(rule_type))]
+            .into_iter()
+            .chain(properties),
+    )
+}
+
+fn symbol(name: &str) -> Value {
+    rule("SYMBOL", [("name", Value::string(name))])
+}
+
+fn is_reference_error(value: &Value) -> bool {
+    let Value::Object(object) = value else {
+        return false;
+    };
+    let object = object.borrow();
+    matches!(object.kind, ObjectKind::Error)
+        && object.properties.get("name").and_then(Value::as_str) == Some("ReferenceError")
+}
+
+fn string_property(
+    interpreter: &Interpreter,
+    value: &Value,
+    key: &str,
+) -> JsResult<Option<Rc<str>>> {
+    if value.is_nullish() {
+        return Ok(None);
+    }
+    Ok(match interpreter.get_property(value, key)? {
+        Value::String(s) => Some(s),
+        _ => None,
+    })
+}
+
+fn normalize(interpreter: &Interpreter, value: &Value) -> JsResult<Value> {
+    match value {
+        Value::Undefined => Err(interpreter.error("Error", "Undefined symbol")),
+        Value::String(_) => Ok(rule("STRING", [("value", value.clone())])),
+        Value::Object(object) => {
+            if let ObjectKind::RegExp { source, flags } = &object.borrow().kind {
+                let mut properties = vec![("value", Value::string(source))];
+                if !flags.is_empty() {
+                    properties.push(("flags", Value::string(flags)));
+                }
+                return Ok(Value::object_from_pairs(
+                    [("type", Value::string("PATTERN"))]
+                        .into_iter()
+                        .chain(properties),
+                ));
+            }
+            if is_reference_error(value) {
+                return Err(Exception(value.clone()));
+            }
+            if string_property(interpreter, value, "type")?.is_some() {
+                Ok(value.clone())
+            } else {
+                Err(interpreter.error(
+                    "TypeError",
+                    &format!("Invalid rule: {}", value.to_js_string()),
+                ))
+            }
+        }
+        _ => Err(interpreter.error(
+            "TypeError",
+            &format!("Invalid rule: {}", value.to_js_string()),
+        )),
+    }
+}
+
+fn normalize_all(interpreter: &Interpreter, values: &[Value]) -> JsResult<Value> {
+    let members = values
+        .iter()
+        .map(|value| normalize(interpreter, value))
+        .collect::<JsResult<Vec<_>>>()?;
+    Ok(Value::new_array(members))
+}
+
+fn check_arguments(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    rule_count: usize,
+    caller_name: &str,
+    suffix: &str,
+    argument_type: &str,
+) -> JsResult<()> {
+    // Allow for `.map()` usage where additional arguments are index and the entire array.
+    let is_map_call = rule_count == 3
+        && matches!(argument(arguments, 1), Value::Number(_))
+        && argument(arguments, 2).is_array();
+    if rule_count > 1 && !is_map_call {
+        return Err(interpreter.error(
+            "Error",
+            &format!(
+                "The `{caller_name}` function only takes one {argument_type} argument{suffix}.\n\
+                 You passed in multiple {argument_type}s. Did you mean to call `seq`?\n"
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn check_precedence(interpreter: &Interpreter, value: &Value) -> JsResult<()> {
+    if value.is_nullish() {
+        return Err(interpreter.error("Error", "Missing precedence value"));
+    }
+    Ok(())
+}
+
+fn alias(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let content = normalize(interpreter, &argument(&arguments, 0))?;
+    let value = argument(&arguments, 1);
+    let (named, value) = if let Value::String(_) = value {
+        (false, value)
+    } else if is_reference_error(&value) {
+        let symbol = interpreter.get_property(&value, "symbol")?;
+        (true, interpreter.get_property(&symbol, "name")?)
+    } else if matches!(&value, Value::Object(o) if matches!(o.borrow().kind, ObjectKind::Plain))
+        && string_property(interpreter, &value, "type")?.as_deref() == Some("SYMBOL")
+    {
+        (true, interpreter.get_property(&value, "name")?)
+    } else {
+        return Err(interpreter.error(
+            "Error",
+            &format!("Invalid alias value {}", value.to_js_string()),
+        ));
+    };
+    Ok(rule(
+        "ALIAS",
+        [
+            ("content", content),
+            ("named", Value::Bool(named)),
+            ("value", value),
+        ],
+    ))
+}
+
+fn blank(_: &mut Interpreter, _: Value, _: Vec<Value>) -> JsResult<Value> {
+    Ok(rule("BLANK", []))
+}
+
+fn field(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let content = normalize(interpreter, &argument(&arguments, 1))?;
+    Ok(rule(
+        "FIELD",
+        [("name", argument(&arguments, 0)), ("content", content)],
+    ))
+}
+
+fn choice(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(rule(
+        "CHOICE",
+        [("members", normalize_all(interpreter, &arguments)?)],
+    ))
+}
+
+fn optional(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    check_arguments(
+        interpreter,
+        &arguments,
+        arguments.len(),
+        "optional",
+        "",
+        "rule",
+    )?;
+    let members = vec![argument(&arguments, 0), rule("BLANK", [])];
+    Ok(rule(
+        "CHOICE",
+        [("members", normalize_all(interpreter, &members)?)],
+    ))
+}
+
+fn precedence_rule(
+    interpreter: &Interpreter,
+    rule_type: &str,
+    caller_name: &str,
+    suffix: &str,
+    arguments: &[Value],
+    value_is_optional: bool,
+) -> JsResult<Value> {
+    let (mut number, mut content) = (argument(arguments, 0), argument(arguments, 1));
+    if value_is_optional && content.is_nullish() {
+        content = number;
+        number = Value::Number(0.0);
+    }
+    check_precedence(interpreter, &number)?;
+    check_arguments(
+        interpreter,
+        arguments,
+        arguments.len().saturating_sub(1),
+        caller_name,
+        suffix,
+        "rule",
+    )?;
+    let content = normalize(interpreter, &content)?;
+    Ok(rule(rule_type, [("value", number), ("content", content)]))
+}
+
+fn prec(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    precedence_rule(
+        interpreter,
+        "PREC",
+        "prec",
+        " and a precedence argument",
+        &arguments,
+        false,
+    )
+}
+
+fn prec_left(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    precedence_rule(
+        interpreter,
+        "PREC_LEFT",
+        "prec.left",
+        " and an optional precedence argument",
+        &arguments,
+        true,
+    )
+}
+
+fn prec_right(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    precedence_rule(
+        interpreter,
+        "PREC_RIGHT",
+        "prec.right",
+        " and an optional precedence argument",
+        &arguments,
+        true,
+    )
+}
+
+fn prec_dynamic(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    precedence_rule(
+        interpreter,
+        "PREC_DYNAMIC",
+        "prec.dynamic",
+        " and a precedence argument",
+        &arguments,
+        false,
+    )
+}
+
+fn repeat(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    check_arguments(
+        interpreter,
+        &arguments,
+        arguments.len(),
+        "repeat",
+        "",
+        "rule",
+    )?;
+    let content = normalize(interpreter, &argument(&arguments, 0))?;
+    Ok(rule("REPEAT", [("content", content)]))
+}
+
+fn repeat1(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    check_arguments(
+        interpreter,
+        &arguments,
+        arguments.len(),
+        "repeat1",
+        "",
+        "rule",
+    )?;
+    let content = normalize(interpreter, &argument(&arguments, 0))?;
+    Ok(rule("REPEAT1", [("content", content)]))
+}
+
+fn seq(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(rule(
+        "SEQ",
+        [("members", normalize_all(interpreter, &arguments)?)],
+    ))
+}
+
+fn sym(_: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    Ok(rule("SYMBOL", [("name", argument(&arguments, 0))]))
+}
+
+fn token(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    check_arguments(
+        interpreter,
+        &arguments,
+        arguments.len(),
+        "token",
+        "",
+        "literal",
+    )?;
+    let content = normalize(interpreter, &argument(&arguments, 0))?;
+    Ok(rule("TOKEN", [("content", content)]))
+}
+
+fn token_immediate(
+    interpreter: &mut Interpreter,
+    _: Value,
+    arguments: Vec<Value>,
+) -> JsResult<Value> {
+    check_arguments(
+        interpreter,
+        &arguments,
+        arguments.len(),
+        "token.immediate",
+        "",
+        "literal",
+    )?;
+    let content = normalize(interpreter, &argument(&arguments, 0))?;
+    Ok(rule("IMMEDIATE_TOKEN", [("content", content)]))
+}
+
+fn rule_builder(rule_names: Option<HashSet<String>>) -> Value {
+    Value::from_kind(
+        ObjectKind::RuleBuilder(rule_names.map(Rc::new)),
+        IndexMap::new(),
+    )
+}
+
+// Look up an optional function-valued property of the grammar options, verifying its type.
+fn option_function(
+    interpreter: &Interpreter,
+    options: &Value,
+    key: &str,
+    message: &str,
+) -> JsResult<Option<Value>> {
+    let value = interpreter.get_property(options, key)?;
+    if !value.truthy() {
+        return Ok(None);
+    }
+    if !value.is_function() {
+        return Err(interpreter.error("Error", message));
+    }
+    Ok(Some(value))
+}
+
+fn array_result(interpreter: &Interpreter, value: &Value, message: &str) -> JsResult<Vec<Value>> {
+    value
+        .array_elements()
+        .ok_or_else(|| interpreter.error("Error", message))
+}
+
+fn grammar(interpreter: &mut Interpreter, _: Value, arguments: Vec<Value>) -> JsResult<Value> {
+    let (base_grammar, options, inherits) = if argument(&arguments, 1).truthy() {
+        let base_grammar = interpreter.get_property(&arguments[0], "grammar")?;
+        let inherits = interpreter.get_property(&base_grammar, "name")?;
+        (base_grammar, arguments[1].clone(), inherits)
+    } else {
+        let base_grammar = Value::object_from_pairs([
+            ("name", Value::Null),
+            ("rules", Value::new_object(IndexMap::new())),
+            (
+                "extras",
+                Value::new_array(vec![rule("PATTERN", [("value", Value::string("\\s"))])]),
+            ),
+            ("conflicts", Value::new_array(Vec::new())),
+            ("externals", Value::new_array(Vec::new())),
+            ("inline", Value::new_array(Vec::new())),
+            ("supertypes", Value::new_array(Vec::new())),
+            ("precedences", Value::new_array(Vec::new())),
+        ]);
+        (base_grammar, argument(&arguments, 0), Value::Null)
+    };
+    let base = |key: &str| interpreter.get_property(&base_grammar, key);
+    let base_rules = base("rules")?;
+    let base_extras = base("extras")?;
+    let base_conflicts = base("conflicts")?;
+    let base_externals = base("externals")?;
+    let base_inline = base("inline")?;
+    let base_supertypes = base("supertypes")?;
+    let base_precedences = base("precedences")?;
+    let base_word = base("word")?;
+
+    let mut externals = base_externals.clone();
+    if let Some(function) = option_function(
+        interpreter,
+        &options,
+        "externals",
+        "Grammar's 'externals' property must be a function.",
+    )? {
+        let builder = rule_builder(None);
+        let result = interpreter.call(&function, builder.clone(), vec![builder, base_externals])?;
+        let result = array_result(
+            interpreter,
+            &result,
+            "Grammar's 'externals' property must return an array of rules.",
+        )?;
+        externals = normalize_all(interpreter, &result)?;
+    }
+
+    let option_rules = interpreter.get_property(&options, "rules")?;
+    if option_rules.is_nullish() {
+        return Err(interpreter.error("TypeError", "Cannot convert undefined or null to object"));
+    }
+    let mut rule_names = HashSet::new();
+    rule_names.extend(own_keys(&option_rules).iter().map(ToString::to_string));
+    rule_names.extend(own_keys(&base_rules).iter().map(ToString::to_string));
+    for external in externals.array_elements().unwrap_or_default() {
+        if let Some(name) = string_property(interpreter, &external, "name")? {
+            rule_names.insert(name.to_string());
+        }
+    }
+    let builder = rule_builder(Some(rule_names));
+
+    let name = interpreter.get_property(&options, "name")?;
+    let Value::String(name_string) = &name else {
+        return Err(interpreter.error("Error", "Grammar's 'name' property must be a string."));
+    };
+    if !GRAMMAR_NAME_REGEX.is_match(name_string) {
+        return Err(interpreter.error(
+            "Error",
+            "Grammar's 'name' property must not start with a digit and cannot contain non-word characters.",
+        ));
+    }
+    if inherits.truthy() && !matches!(inherits, Value::String(_)) {
+        return Err(interpreter.error("Error", "Base grammar's 'name' property must be a string."));
+    }
+
+    let rules = Value::new_object(IndexMap::new());
+    for key in own_keys(&base_rules) {
+        let value = interpreter.get_property(&base_rules, &key)?;
+        interpreter.set_property(&rules, &key, value)?;
+    }
+    if option_rules.truthy() {
+        if !matches!(option_rules, Value::Object(_)) || option_rules.is_function() {
+            return Err(interpreter.error("Error", "Grammar's 'rules' property must be an object."));
+        }
+        for rule_name in own_keys(&option_rules) {
+            let function = interpreter.get_property(&option_rules, &rule_name)?;
+            if !function.is_function() {
+                return Err(interpreter.error(
+                    "Error",
+                    &format!("Grammar rules must all be functions. '{rule_name}' rule is not."),
+                ));
+            }
+            let original = interpreter.get_property(&base_rules, &rule_name)?;
+            let result =
+                interpreter.call(&function, builder.clone(), vec![builder.clone(), original])?;
+            let result = normalize(interpreter, &result)?;
+            interpreter.set_property(&rules, &rule_name, result)?;
+        }
+    }
+
+    let mut extras = Value::new_array(base_extras.array_elements().unwrap_or_default());
+    if let Some(function) = option_function(
+        interpreter,
+        &options,
+        "extras",
+        "Grammar's 'extras' property must be a function.",
+    )? {
+        let result = interpreter.call(
+            &function,
+            builder.clone(),
+            vec![builder.clone(), base_extras],
+        )?;
+        let result = array_result(
+            interpreter,
+            &result,
+            "Grammar's 'extras' function must return an array.",
+        )?;
+        extras = normalize_all(interpreter, &result)?;
+    }
+
+    let mut word = base_word;
+    let word_function = interpreter.get_property(&options, "word")?;
+    if word_function.truthy() {
+        let result = interpreter.call(&word_function, builder.clone(), vec![builder.clone()])?;
+        word = interpreter.get_property(&result, "name")?;
+        match word.as_str() {
+            None => {
+                return Err(
+                    interpreter.error("Error", "Grammar's 'word' property must be a named rule.")
+                )
+            }
+            Some("ReferenceError") => {
+                return Err(interpreter.error(
+                    "Error",
+                    "Grammar's 'word' property must be a valid rule name.",
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut conflicts = base_conflicts.clone();
+    if let Some(function) = option_function(
+        interpreter,
+        &options,
+        "conflicts",
+        "Grammar's 'conflicts' property must be a function.",
+    )? {
+        let mut base_conflict_rules = Vec::new();
+        for conflict in interpreter.iterate(&base_conflicts)? {
+            let symbols = interpreter
+                .iterate(&conflict)?
+                .iter()
+                .map(|name| symbol(&name.to_js_string()))
+                .collect();
+            base_conflict_rules.push(Value::new_array(symbols));
+        }
+        let result = interpreter.call(
+            &function,
+            builder.clone(),
+            vec![builder.clone(), Value::new_array(base_conflict_rules)],
+        )?;
+        let message = "Grammar's conflicts must be an array of arrays of rules.";
+        let mut conflict_sets = Vec::new();
+        for conflict_set in array_result(interpreter, &result, message)? {
+            let mut names = Vec::new();
+            for symbol in array_result(interpreter, &conflict_set, message)? {
+                let symbol = normalize(interpreter, &symbol)?;
+                names.push(interpreter.get_property(&symbol, "name")?);
+            }
+            conflict_sets.push(Value::new_array(names));
+        }
+        conflicts = Value::new_array(conflict_sets);
+    }
+
+    let mut inline = base_inline.clone();
+    if let Some(function) = option_function(
+        interpreter,
+        &options,
+        "inline",
+        "Grammar's 'inline' property must be a function.",
+    )? {
+        let base_inline_rules = interpreter
+            .iterate(&base_inline)?
+            .iter()
+            .map(|name| symbol(&name.to_js_string()))
+            .collect();
+        let result = interpreter.call(
+            &function,
+            builder.clone(),
+            vec![builder.clone(), Value::new_array(base_inline_rules)],
+        )?;
+        let result = array_result(
+            interpreter,
+            &result,
+            "Grammar's inline must be an array of rules.",
+        )?;
+        let mut names = Vec::<Value>::new();
+        for symbol in result {
+            let name = interpreter.get_property(&symbol, "name")?;
+            if names.iter().any(|n| n.strict_equals(&name)) {
+                println!("Warning: duplicate inline rule '{}'", name.to_js_string());
+                continue;
+            }
+            if name.as_str() == Some("ReferenceError") {
+                let undefined_symbol = interpreter.get_property(&symbol, "symbol")?;
+                let undefined_name = interpreter.get_property(&undefined_symbol, "name")?;
+                println!(
+                    "Warning: inline rule '{}' is not defined.",
+                    undefined_name.to_js_string()
+                );
+                continue;
+            }
+            names.push(name);
+        }
+        inline = Value::new_array(names);
+    }
+
+    let mut supertypes = base_supertypes.clone();
+    if let Some(function) = option_function(
+        interpreter,
+        &options,
+        "supertypes",
+        "Grammar's 'supertypes' property must be a function.",
+    )? {
+        let base_supertype_rules = interpreter
+            .iterate(&base_supertypes)?
+            .iter()
+            .map(|name| symbol(&name.to_js_string()))
+            .collect();
+        let result = interpreter.call(
+            &function,
+            builder.clone(),
+            vec![builder.clone(), Value::new_array(base_supertype_rules)],
+        )?;
+        let result = array_result(
+            interpreter,
+            &result,
+            "Grammar's supertypes must be an array of rules.",
+        )?;
+        let mut names = Vec::new();
+        for symbol in result {
+            names.push(interpreter.get_property(&symbol, "name")?);
+        }
+        supertypes = Value::new_array(names);
+    }
+
+    let mut precedences = base_precedences.clone();
+    if let Some(function) = option_function(
+        interpreter,
+        &options,
+        "precedences",
+        "Grammar's 'precedences' property must be a function",
+    )? {
+        let result = interpreter.call(
+            &function,
+            builder.clone(),
+            vec![builder.clone(), base_precedences],
+        )?;
+        let message = "Grammar's precedences must be an array of arrays of rules.";
+        let mut lists = Vec::new();
+        for list in array_result(interpreter, &result, message)? {
+            let list = array_result(interpreter, &list, message)?;
+            lists.push(normalize_all(interpreter, &list)?);
+        }
+        precedences = Value::new_array(lists);
+    }
+
+    if own_keys(&rules).is_empty() {
+        return Err(interpreter.error("Error", "Grammar must have at least one rule."));
+    }
+
+    let mut grammar = vec![("name", name.clone())];
+    if inherits.truthy() {
+        grammar.push(("inherits", inherits));
+    }
+    grammar.extend([
+        ("word", word),
+        ("rules", rules),
+        ("extras", extras),
+        ("conflicts", conflicts),
+        ("precedences", precedences),
+        ("externals", externals),
+        ("inline", inline),
+        ("supertypes", supertypes),
+    ]);
+    Ok(Value::object_from_pairs([(
+        "grammar",
+        Value::object_from_pairs(grammar),
+    )]))
+}
@@ -0,0 +1,1173 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+
+use super::{
+    builtins, grammar,
+    lexer::{Lexer, Position},
+    parser::{
+        DeclarationKind, Element, Expression, Function, FunctionBody, Parser, Pattern, Property,
+        PropertyKey, Statement,
+    },
+    value::{Callable, ObjectKind, ObjectRef, Value},
+};
+
+const MAX_CALL_DEPTH: usize = 2000;
+
+/// A JavaScript exception propagating through the interpreter.
+pub struct Exception(pub Value);
+
+pub type JsResult<T> = Result<T, Exception>;
+
+pub struct Scope {
+    bindings: RefCell<HashMap<String, Binding>>,
+    parent: Option<Rc<Scope>>,
+    is_function_scope: bool,
+}
+
+struct Binding {
+    value: Value,
+    mutable: bool,
+}
+
+enum Completion {
+    Normal,
+    Return(Value),
+    Break,
+    Continue,
+}
+
+pub struct Interpreter {
+    pub global: ObjectRef,
+    global_scope: Rc<Scope>,
+    modules: HashMap<PathBuf, Value>,
+    path: Rc<str>,
+    position: Position,
+    call_depth: usize,
+}
+
+impl Scope {
+    fn new(parent: Option<Rc<Self>>, is_function_scope: bool) -> Rc<Self> {
+        Rc::new(Self {
+            bindings: RefCell::new(HashMap::new()),
+            parent,
+            is_function_scope,
+        })
+    }
+
+    fn declare(&self, name: &str, value: Value, mutable: bool) {
+        self.bindings
+            .borrow_mut()
+            .insert(name.to_string(), Binding { value, mutable });
+    }
+
+    fn lookup(&self, name: &str) -> Option<Value> {
+        if let Some(binding) = self.bindings.borrow().get(name) {
+            return Some(binding.value.clone());
+        }
+        self.parent.as_ref()?.lookup(name)
+    }
+
+    // Returns `None` if no binding exists, or `Some(false)` if the binding is constant.
+    fn assign(&self, name: &str, value: Value) -> Option<bool> {
+        if let Some(binding) = self.bindings.borrow_mut().get_mut(name) {
+            if !binding.mutable {
+                return Some(false);
+            }
+            binding.value = value;
+            return Some(true);
+        }
+        self.parent.as_ref()?.assign(name, value)
+    }
+
+    fn function_scope(self: &Rc<Self>) -> Rc<Self> {
+        let mut scope = self.clone();
+        while !scope.is_function_scope {
+            match &scope.parent {
+                Some(parent) => scope = parent.clone(),
+                None => break,
+            }
+        }
+        scope
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let global = match Value::new_object(IndexMap::new()) {
+            Value::Object(object) => object,
+            _ => unreachable!(),
+        };
+        let mut interpreter = Self {
+            global,
+            global_scope: Scope::new(None, true),
+            modules: HashMap::new(),
+            path: "<native>".into(),
+            position: Position::default(),
+            call_depth: 0,
+        };
+        builtins::install_globals(&mut interpreter);
+        interpreter
+    }
+
+    /// Define a global variable, visible to every module.
+    pub fn set_global(&self, name: &str, value: Value) {
+        self.global
+            .borrow_mut()
+            .properties
+            .insert(name.into(), value);
+    }
+
+    /// Evaluate the module at the given path and return its `module.exports`.
+    pub fn run_module(&mut self, path: &Path) -> Result<Value> {
+        self.require(path).map_err(|Exception(error)| {
+            let stack = self.get_property(&error, "stack").ok();
+            match stack {
+                Some(Value::String(stack)) => anyhow!("{stack}"),
+                _ => anyhow!("Uncaught {}", error.to_js_string()),
+            }
+        })
+    }
+
+    pub fn require(&mut self, path: &Path) -> JsResult<Value> {
+        if let Some(exports) = self.modules.get(path) {
+            return Ok(exports.clone());
+        }
+
+        let source = fs::read_to_string(path).map_err(|e| {
+            self.error(
+                "Error",
+                &format!("Cannot read module {}: {e}", path.display()),
+            )
+        })?;
+
+        if path.extension().and_then(|e| e.to_str()) == Some("json") {
+            let json = serde_json::from_str(&source)
+                .map_err(|e| self.error("SyntaxError", &format!("{}: {e}", path.display())))?;
+            let exports = Value::from_json(&json);
+            self.modules.insert(path.to_owned(), exports.clone());
+            return Ok(exports);
+        }
+
+        let path_string = path.to_string_lossy().to_string();
+        let program = Lexer::new(&source, &path_string)
+            .tokenize()
+            .and_then(|tokens| Parser::new(tokens, &path_string).parse_program())
+            .map_err(|e| {
+                // The parser's message already includes the location of the error.
+                let error = self.new_error("SyntaxError", &e.to_string());
+                let stack = Value::String(format!("SyntaxError: {e}").into());
+                self.set_property(&error, "stack", stack).ok();
+                Exception(error)
+            })?;
+
+        let exports = Value::new_object(IndexMap::new());
+        let module = Value::object_from_pairs([("exports", exports.clone())]);
+        // Register the module before evaluating it so that circular requires terminate.
+        self.modules.insert(path.to_owned(), exports.clone());
+
+        let directory = path.parent().unwrap_or(Path::new(".")).to_string_lossy();
+        let scope = Scope::new(Some(self.global_scope.clone()), true);
+        scope.declare("module", module.clone(), true);
+        scope.declare("exports", exports.clone(), true);
+        scope.declare("this", exports, false);
+        scope.declare("__filename", Value::string(&path_string), false);
+        scope.declare("__dirname", Value::string(&directory), false);
+        scope.declare(
+            "require",
+            builtins::bound(
+                Value::new_native("require", builtins::require),
+                Value::string(&path_string),
+            ),
+            false,
+        );
+
+        let previous_path = std::mem::replace(&mut self.path, path_string.into());
+        let previous_position = self.position;
+        let result = self.execute_function_body(&program, &scope);
+        self.path = previous_path;
+        self.position = previous_position;
+        result?;
+
+        let exports = self.get_property(&module, "exports")?;
+        self.modules.insert(path.to_owned(), exports.clone());
+        Ok(exports)
+    }
+
+    /// Construct an error object of the given type, recording the current source position.
+    pub fn error(&self, name: &str, message: &str) -> Exception {
+        Exception(self.new_error(name, message))
+    }
+
+    pub fn new_error(&self, name: &str, message: &str) -> Value {
+        let stack = format!("{name}: {message}\n    at {}:{}", self.path, self.position);
+        Value::from_kind(
+            ObjectKind::Error,
+            [
+                ("name".into(), Value::string(name)),
+                ("message".into(), Value::string(message)),
+                ("stack".into(), Value::String(stack.into())),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    pub fn call(
+        &mut self,
+        function: &Value,
+        this: Value,
+        arguments: Vec<Value>,
+    ) -> JsResult<Value> {
+        let callable = match function {
+            Value::Object(object) => match &object.borrow().kind {
+                ObjectKind::Function(callable) => Some(callable.clone()),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(callable) = callable else {
+            return Err(self.not_a_function(function));
+        };
+
+        if self.call_depth >= MAX_CALL_DEPTH {
+            return Err(self.error("RangeError", "Maximum call stack size exceeded"));
+        }
+        self.call_depth += 1;
+        let result = match callable {
+            Callable::Native(_, native) => native(self, this, arguments),
+            Callable::Bound(target, bound_this) => self.call(&target, bound_this, arguments),
+            Callable::Closure {
+                function,
+                scope,
+                this: lexical_this,
+            } => self.call_closure(function, &scope, lexical_this.unwrap_or(this), arguments),
+        };
+        self.call_depth -= 1;
+        result
+    }
+
+    fn call_closure(
+        &mut self,
+        function: Rc<Function>,
+        parent: &Rc<Scope>,
+        this: Value,
+        arguments: Vec<Value>,
+    ) -> JsResult<Value> {
+        let scope = Scope::new(Some(parent.clone()), true);
+        if !function.is_arrow {
+            scope.declare("this", this, false);
+            scope.declare("arguments", Value::new_array(arguments.clone()), true);
+        }
+
+        let mut arguments = arguments.into_iter();
+        for parameter in &function.parameters {
+            let argument = arguments.next().unwrap_or(Value::Undefined);
+            self.bind_pattern(parameter, argument, &scope, true)?;
+        }
+        if let Some(rest) = &function.rest {
+            self.bind_pattern(rest, Value::new_array(arguments.collect()), &scope, true)?;
+        }
+
+        match &function.body {
+            FunctionBody::Expression(expression) => self.evaluate(expression, &scope),
+            FunctionBody::Block(statements) => self.execute_function_body(statements, &scope),
+        }
+    }
+
+    fn execute_function_body(
+        &mut self,
+        statements: &[Statement],
+        scope: &Rc<Scope>,
+    ) -> JsResult<Value> {
+        hoist_var_declarations(statements, scope);
+        match self.execute_block(statements, scope)? {
+            Completion::Return(value) => Ok(value),
+            _ => Ok(Value::Undefined),
+        }
+    }
+
+    pub fn construct(&mut self, constructor: &Value, arguments: Vec<Value>) -> JsResult<Value> {
+        let is_closure = matches!(
+            constructor,
+            Value::Object(o)
+                if matches!(o.borrow().kind, ObjectKind::Function(Callable::Closure { .. }))
+        );
+        if !is_closure {
+            return self.call(constructor, Value::Undefined, arguments);
+        }
+        let this = Value::new_object(IndexMap::new());
+        let prototype = self.get_property(constructor, "prototype")?;
+        if let (Value::Object(this), Value::Object(prototype)) = (&this, &prototype) {
+            let methods = prototype.borrow().properties.clone();
+            this.borrow_mut().properties.extend(methods);
+        }
+        let result = self.call(constructor, this.clone(), arguments)?;
+        Ok(if matches!(result, Value::Object(_)) {
+            result
+        } else {
+            this
+        })
+    }
+
+    fn not_a_function(&self, value: &Value) -> Exception {
+        self.error("TypeError", &format!("{value:?} is not a function"))
+    }
+
+    fn execute_block(
+        &mut self,
+        statements: &[Statement],
+        scope: &Rc<Scope>,
+    ) -> JsResult<Completion> {
+        // Function declarations are hoisted to the top of their enclosing block.
+        for statement in statements {
+            if let Statement::Positioned(_, statement) = statement {
+                if let Statement::Function(name, function) = statement.as_ref() {
+                    let closure = self.closure(function, scope);
+                    scope.declare(name, closure, true);
+                }
+            }
+        }
+        for statement in statements {
+            match self.execute(statement, scope)? {
+                Completion::Normal => {}
+                completion => return Ok(completion),
+            }
+        }
+        Ok(Completion::Normal)
+    }
+
+    fn execute(&mut self, statement: &Statement, scope: &Rc<Scope>) -> JsResult<Completion> {
+        match statement {
+            Statement::Positioned(position, statement) => {
+                self.position = *position;
+                self.execute(statement, scope)
+            }
+            Statement::Expression(expression) => {
+                self.evaluate(expression, scope)?;
+                Ok(Completion::Normal)
+            }
+            Statement::Declaration(kind, declarators) => {
+                for (pattern, init) in declarators {
+                    let value = match init {
+                        Some(init) => self.evaluate(init, scope)?,
+                        None if *kind == DeclarationKind::Var => continue,
+                        None => Value::Undefined,
+                    };
+                    let target = if *kind == DeclarationKind::Var {
+                        scope.function_scope()
+                    } else {
+                        scope.clone()
+                    };
+                    self.bind_pattern(pattern, value, &target, *kind != DeclarationKind::Const)?;
+                }
+                Ok(Completion::Normal)
+            }
+            Statement::Function(..) | Statement::Empty => Ok(Completion::Normal),
+            Statement::Return(value) => Ok(Completion::Return(match value {
+                Some(value) => self.evaluate(value, scope)?,
+                None => Value::Undefined,
+            })),
+            Statement::If(condition, consequence, alternative) => {
+                if self.evaluate(condition, scope)?.truthy() {
+                    self.execute_nested(consequence, scope)
+                } else if let Some(alternative) = alternative {
+                    self.execute_nested(alternative, scope)
+                } else {
+                    Ok(Completion::Normal)
+                }
+            }
+            Statement::Block(statements) => {
+                let scope = Scope::new(Some(scope.clone()), false);
+                self.execute_block(statements, &scope)
+            }
+            Statement::For {
+                init,
+                test,
+                update,
+                body,
+            } => {
+                let scope = Scope::new(Some(scope.clone()), false);
+                if let Some(init) = init {
+                    self.execute(init, &scope)?;
+                }
+                loop {
+                    if let Some(test) = test {
+                        if !self.evaluate(test, &scope)?.truthy() {
+                            break;
+                        }
+                    }
+                    match self.execute_nested(body, &scope)? {
+                        Completion::Break => break,
+                        Completion::Return(value) => return Ok(Completion::Return(value)),
+                        Completion::Normal | Completion::Continue => {}
+                    }
+                    if let Some(update) = update {
+                        self.evaluate(update, &scope)?;
+                    }
+                }
+                Ok(Completion::Normal)
+            }
+            Statement::ForOf(kind, pattern, iterable, body) => {
+                let iterable = self.evaluate(iterable, scope)?;
+                let items = self.iterate(&iterable)?;
+                self.execute_loop_over(*kind, pattern, items, body, scope)
+            }
+            Statement::ForIn(kind, pattern, object, body) => {
+                let object = self.evaluate(object, scope)?;
+                let keys = builtins::own_keys(&object)
+                    .into_iter()
+                    .map(Value::String)
+                    .collect();
+                self.execute_loop_over(*kind, pattern, keys, body, scope)
+            }
+            Statement::While(condition, body) => {
+                while self.evaluate(condition, scope)?.truthy() {
+                    match self.execute_nested(body, scope)? {
+                        Completion::Break => break,
+                        Completion::Return(value) => return Ok(Completion::Return(value)),
+                        Completion::Normal | Completion::Continue => {}
+                    }
+                }
+                Ok(Completion::Normal)
+            }
+            Statement::DoWhile(body, condition) => {
+                loop {
+                    match self.execute_nested(body, scope)? {
+                        Completion::Break => break,
+                        Completion::Return(value) => return Ok(Completion::Return(value)),
+                        Completion::Normal | Completion::Continue => {}
+                    }
+                    if !self.evaluate(condition, scope)?.truthy() {
+                        break;
+                    }
+                }
+                Ok(Completion::Normal)
+            }
+            Statement::Switch(discriminant, cases) => {
+                let discriminant = self.evaluate(discriminant, scope)?;
+                let scope = Scope::new(Some(scope.clone()), false);
+                let mut matched = None;
+                for (i, (test, _)) in cases.iter().enumerate() {
+                    if let Some(test) = test {
+                        if self.evaluate(test, &scope)?.strict_equals(&discriminant) {
+                            matched = Some(i);
+                            break;
+                        }
+                    }
+                }
+                let matched = matched.or_else(|| cases.iter().position(|(test, _)| test.is_none()));
+                if let Some(start) = matched {
+                    for (_, body) in &cases[start..] {
+                        match self.execute_block(body, &scope)? {
+                            Completion::Normal => {}
+                            Completion::Break => break,
+                            completion => return Ok(completion),
+                        }
+                    }
+                }
+                Ok(Completion::Normal)
+            }
+            Statement::Try {
+                block,
+                parameter,
+                handler,
+                finalizer,
+            } => {
+                let block_scope = Scope::new(Some(scope.clone()), false);
+                let mut result = self.execute_block(block, &block_scope);
+                if let (Err(Exception(error)), Some(handler)) = (&result, handler) {
+                    let handler_scope = Scope::new(Some(scope.clone()), false);
+                    if let Some(parameter) = parameter {
+                        self.bind_pattern(parameter, error.clone(), &handler_scope, true)?;
+                    }
+                    result = self.execute_block(handler, &handler_scope);
+                }
+                if let Some(finalizer) = finalizer {
+                    let finalizer_scope = Scope::new(Some(scope.clone()), false);
+                    match self.execute_block(finalizer, &finalizer_scope)? {
+                        Completion::Normal => {}
+                        completion => return Ok(completion),
+                    }
+                }
+                result
+            }
+            Statement::Throw(value) => {
+                let value = self.evaluate(value, scope)?;
+                Err(Exception(value))
+            }
+            Statement::Break => Ok(Completion::Break),
+            Statement::Continue => Ok(Completion::Continue),
+            Statement::ExportDefault(value) => {
+                let value = self.evaluate(value, scope)?;
+                let module = self.lookup(scope, "module")?;
+                self.set_property(&module, "exports", value)?;
+                Ok(Completion::Normal)
+            }
+        }
+    }
+
+    fn execute_loop_over(
+        &mut self,
+        kind: DeclarationKind,
+        pattern: &Pattern,
+        items: Vec<Value>,
+        body: &Statement,
+        scope: &Rc<Scope>,
+    ) -> JsResult<Completion> {
+        for item in items {
+            let iteration_scope = Scope::new(Some(scope.clone()), false);
+            let target = if kind == DeclarationKind::Var {
+                scope.function_scope()
+            } else {
+                iteration_scope.clone()
+            };
+            self.bind_pattern(pattern, item, &target, kind != DeclarationKind::Const)?;
+            match self.execute_nested(body, &iteration_scope)? {
+                Completion::Break => break,
+                Completion::Return(value) => return Ok(Completion::Return(value)),
+                Completion::Normal | Completion::Continue => {}
+            }
+        }
+        Ok(Completion::Normal)
+    }
+
+    // Execute the body of a compound statement. Blocks reuse the given scope, since the
+    // caller has already created one for this iteration or branch.
+    fn execute_nested(&mut self, statement: &Statement, scope: &Rc<Scope>) -> JsResult<Completion> {
+        let mut inner = statement;
+        while let Statement::Positioned(position, statement) = inner {
+            self.position = *position;
+            inner = statement;
+        }
+        match inner {
+            Statement::Block(statements) => {
+                let scope = Scope::new(Some(scope.clone()), false);
+                self.execute_block(statements, &scope)
+            }
+            _ => self.execute(inner, scope),
+        }
+    }
+
+    fn bind_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: Value,
+        scope: &Rc<Scope>,
+        mutable: bool,
+    ) -> JsResult<()> {
+        match pattern {
+            Pattern::Identifier(name) => {
+                scope.declare(name, value, mutable);
+                Ok(())
+            }
+            Pattern::Default(pattern, default) => {
+                let value = if matches!(value, Value::Undefined) {
+                    self.evaluate(default, scope)?
+                } else {
+                    value
+                };
+                self.bind_pattern(pattern, value, scope, mutable)
+            }
+            Pattern::Array(elements, rest) => {
+                let mut items = self.iterate(&value)?.into_iter();
+                for element in elements {
+                    let item = items.next().unwrap_or(Value::Undefined);
+                    if let Some(element) = element {
+                        self.bind_pattern(element, item, scope, mutable)?;
+                    }
+                }
+                if let Some(rest) = rest {
+                    self.bind_pattern(rest, Value::new_array(items.collect()), scope, mutable)?;
+                }
+                Ok(())
+            }
+            Pattern::Object(properties, rest) => {
+                if value.is_nullish() {
+                    return Err(self.error(
+                        "TypeError",
+                        &format!("Cannot destructure '{value:?}' as it is {value:?}."),
+                    ));
+                }
+                let mut used_keys = Vec::new();
+                for (key, pattern) in properties {
+                    let key = self.property_key(key, scope)?;
+                    let item = self.get_property(&value, &key)?;
+                    used_keys.push(key);
+                    self.bind_pattern(pattern, item, scope, mutable)?;
+                }
+                if let Some(rest) = rest {
+                    let mut remaining = IndexMap::new();
+                    for key in builtins::own_keys(&value) {
+                        if !used_keys.contains(&key) {
+                            let item = self.get_property(&value, &key)?;
+                            remaining.insert(key, item);
+                        }
+                    }
+                    self.bind_pattern(rest, Value::new_object(remaining), scope, mutable)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn closure(&self, function: &Rc<Function>, scope: &Rc<Scope>) -> Value {
+        let this = if function.is_arrow {
+            Some(scope.lookup("this").unwrap_or(Value::Undefined))
+        } else {
+            None
+        };
+        let closure = Value::from_kind(
+            ObjectKind::Function(Callable::Closure {
+                function: function.clone(),
+                scope: scope.clone(),
+                this,
+            }),
+            IndexMap::new(),
+        );
+        if !function.is_arrow {
+            let _ = self.set_property(&closure, "prototype", Value::new_object(IndexMap::new()));
+        }
+        closure
+    }
+
+    fn lookup(&self, scope: &Rc<Scope>, name: &str) -> JsResult<Value> {
+        if let Some(value) = scope.lookup(name) {
+            return Ok(value);
+        }
+        if let Some(value) = self.global.borrow().properties.get(name) {
+            return Ok(value.clone());
+        }
+        match name {
+            "undefined" => Ok(Value::Undefined),
+            "NaN" => Ok(Value::Number(f64::NAN)),
+            "Infinity" => Ok(Value::Number(f64::INFINITY)),
+            "globalThis" | "global" => Ok(Value::Object(self.global.clone())),
+            "null" => Ok(Value::Null),
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(self.error("ReferenceError", &format!("{name} is not defined"))),
+        }
+    }
+
+    fn assign_identifier(&mut self, scope: &Rc<Scope>, name: &str, value: Value) -> JsResult<()> {
+        match scope.assign(name, value.clone()) {
+            Some(true) => Ok(()),
+            Some(false) => Err(self.error("TypeError", "Assignment to constant variable.")),
+            None => {
+                self.set_global(name, value);
+                Ok(())
+            }
+        }
+    }
+
+    fn property_key(&mut self, key: &PropertyKey, scope: &Rc<Scope>) -> JsResult<Rc<str>> {
+        match key {
+            PropertyKey::Static(name) => Ok(name.clone()),
+            PropertyKey::Computed(expression) => {
+                Ok(self.evaluate(expression, scope)?.to_property_key())
+            }
+        }
+    }
+
+    pub fn evaluate(&mut self, expression: &Expression, scope: &Rc<Scope>) -> JsResult<Value> {
+        match expression {
+            Expression::Identifier(name) => self.lookup(scope, name),
+            Expression::Number(n) => Ok(Value::Number(*n)),
+            Expression::String(s) => Ok(Value::String(s.clone())),
+            Expression::This => Ok(scope.lookup("this").unwrap_or(Value::Undefined)),
+            Expression::Template(strings, expressions) => {
+                let mut result = strings[0].clone();
+                for (expression, string) in expressions.iter().zip(&strings[1..]) {
+                    result += &self.evaluate(expression, scope)?.to_js_string();
+                    result += string;
+                }
+                Ok(Value::String(result.into()))
+            }
+            Expression::Regex(pattern, flags) => Ok(builtins::new_regexp(pattern, flags)),
+            Expression::Array(elements) => {
+                let elements = self.evaluate_elements(elements, scope)?;
+                Ok(Value::new_array(elements))
+            }
+            Expression::Object(properties) => {
+                let object = Value::new_object(IndexMap::new());
+                for property in properties {
+                    match property {
+                        Property::KeyValue(key, value) => {
+                            let key = self.property_key(key, scope)?;
+                            let value = self.evaluate(value, scope)?;
+                            self.set_property(&object, &key, value)?;
+                        }
+                        Property::Spread(value) => {
+                            let value = self.evaluate(value, scope)?;
+                            for key in builtins::own_keys(&value) {
+                                let item = self.get_property(&value, &key)?;
+                                self.set_property(&object, &key, item)?;
+                            }
+                        }
+                    }
+                }
+                Ok(object)
+            }
+            Expression::Function(function) => Ok(self.closure(function, scope)),
+            Expression::Unary(operator, argument) => self.evaluate_unary(operator, argument, scope),
+            Expression::Update {
+                operator,
+                prefix,
+                target,
+            } => {
+                let old = self.evaluate(target, scope)?.to_number();
+                let new = if *operator == "++" {
+                    old + 1.0
+                } else {
+                    old - 1.0
+                };
+                self.assign_to(target, Value::Number(new), scope)?;
+                Ok(Value::Number(if *prefix { new } else { old }))
+            }
+            Expression::Binary(operator, left, right) => {
+                let left = self.evaluate(left, scope)?;
+                let right = self.evaluate(right, scope)?;
+                self.binary_operation(operator, &left, &right)
+            }
+            Expression::Logical(operator, left, right) => {
+                let left = self.evaluate(left, scope)?;
+                let short_circuit = match *operator {
+                    "&&" => !left.truthy(),
+                    "||" => left.truthy(),
+                    _ => !left.is_nullish(),
+                };
+                if short_circuit {
+                    Ok(left)
+                } else {
+                    self.evaluate(right, scope)
+                }
+            }
+            Expression::Conditional(condition, consequence, alternative) => {
+                if self.evaluate(condition, scope)?.truthy() {
+                    self.evaluate(consequence, scope)
+                } else {
+                    self.evaluate(alternative, scope)
+                }
+            }
+            Expression::Assign(operator, target, value) => {
+                let value = if *operator == "=" {
+                    self.evaluate(value, scope)?
+                } else {
+                    let current = self.evaluate(target, scope)?;
+                    let operator = &operator[..operator.len() - 1];
+                    match operator {
+                        "&&" if !current.truthy() => return Ok(current),
+                        "||" if current.truthy() => return Ok(current),
+                        "??" if !current.is_nullish() => return Ok(current),
+                        "&&" | "||" | "??" => self.evaluate(value, scope)?,
+                        _ => {
+                            let value = self.evaluate(value, scope)?;
+                            self.binary_operation(operator, &current, &value)?
+                        }
+                    }
+                };
+                self.assign_to(target, value.clone(), scope)?;
+                Ok(value)
+            }
+            Expression::Sequence(expressions) => {
+                let mut result = Value::Undefined;
+                for expression in expressions {
+                    result = self.evaluate(expression, scope)?;
+                }
+                Ok(result)
+            }
+            Expression::Member {
+                object,
+                property,
+                computed,
+                optional,
+            } => {
+                let object = self.evaluate(object, scope)?;
+                if *optional && object.is_nullish() {
+                    return Ok(Value::Undefined);
+                }
+                let key = self.member_key(property, *computed, scope)?;
+                self.get_property(&object, &key)
+            }
+            Expression::Call {
+                callee,
+                arguments,
+                optional,
+                position,
+            } => {
+                self.position = *position;
+                let (this, function) = match callee.as_ref() {
+                    Expression::Member {
+                        object,
+                        property,
+                        computed,
+                        optional,
+                    } => {
+                        let object = self.evaluate(object, scope)?;
+                        if *optional && object.is_nullish() {
+                            return Ok(Value::Undefined);
+                        }
+                        let key = self.member_key(property, *computed, scope)?;
+                        let function = self.get_property(&object, &key)?;
+                        (object, function)
+                    }
+                    callee => (Value::Undefined, self.evaluate(callee, scope)?),
+                };
+                if *optional && function.is_nullish() {
+                    return Ok(Value::Undefined);
+                }
+                let arguments = self.evaluate_elements(arguments, scope)?;
+                self.position = *position;
+                self.call(&function, this, arguments)
+            }
+            Expression::New {
+                callee,
+                arguments,
+                position,
+            } => {
+                let constructor = self.evaluate(callee, scope)?;
+                let arguments = self.evaluate_elements(arguments, scope)?;
+                self.position = *position;
+                self.construct(&constructor, arguments)
+            }
+        }
+    }
+
+    fn member_key(
+        &mut self,
+        property: &Expression,
+        computed: bool,
+        scope: &Rc<Scope>,
+    ) -> JsResult<Rc<str>> {
+        match (property, computed) {
+            (Expression::String(name), false) => Ok(name.clone()),
+            _ => Ok(self.evaluate(property, scope)?.to_property_key()),
+        }
+    }
+
+    fn evaluate_elements(
+        &mut self,
+        elements: &[Element],
+        scope: &Rc<Scope>,
+    ) -> JsResult<Vec<Value>> {
+        let mut result = Vec::with_capacity(elements.len());
+        for element in elements {
+            match element {
+                Element::Expression(expression) => result.push(self.evaluate(expression, scope)?),
+                Element::Spread(expression) => {
+                    let value = self.evaluate(expression, scope)?;
+                    result.extend(self.iterate(&value)?);
+                }
+                Element::Hole => result.push(Value::Undefined),
+            }
+        }
+        Ok(result)
+    }
+
+    fn evaluate_unary(
+        &mut self,
+        operator: &str,
+        argument: &Expression,
+        scope: &Rc<Scope>,
+    ) -> JsResult<Value> {
+        if operator == "typeof" {
+            if let Expression::Identifier(name) = argument {
+                return Ok(Value::string(
+                    self.lookup(scope, name)
+                        .map_or("undefined", |v| v.type_of()),
+                ));
+            }
+        }
+        if operator == "delete" {
+            if let Expression::Member {
+                object,
+                property,
+                computed,
+                ..
+            } = argument
+            {
+                let object = self.evaluate(object, scope)?;
+                let key = self.member_key(property, *computed, scope)?;
+                if let Value::Object(object) = object {
+                    object.borrow_mut().properties.shift_remove(&key);
+                }
+            }
+            return Ok(Value::Bool(true));
+        }
+
+        let value = self.evaluate(argument, scope)?;
+        Ok(match operator {
+            "!" => Value::Bool(!value.truthy()),
+            "-" => Value::Number(-value.to_number()),
+            "+" => Value::Number(value.to_number()),
+            "~" => Value::Number(f64::from(!to_int32(value.to_number()))),
+            "typeof" => Value::string(value.type_of()),
+            _ => Value::Undefined,
+        })
+    }
+
+    fn assign_to(&mut self, target: &Expression, value: Value, scope: &Rc<Scope>) -> JsResult<()> {
+        match target {
+            Expression::Identifier(name) => self.assign_identifier(scope, name, value),
+            Expression::Member {
+                object,
+                property,
+                computed,
+                ..
+            } => {
+                let object = self.evaluate(object, scope)?;
+                let key = self.member_key(property, *computed, scope)?;
+                self.set_property(&object, &key, value)
+            }
+            _ => Err(self.error("SyntaxError", "Invalid assignment target")),
+        }
+    }
+
+    pub fn binary_operation(&self, operator: &str, left: &Value, right: &Value) -> JsResult<Value> {
+        Ok(match operator {
+            "+" => {
+                let left = to_primitive(left);
+                let right = to_primitive(right);
+                if matches!(left, Value::String(_)) || matches!(right, Value::String(_)) {
+                    Value::String((left.to_js_string() + &right.to_js_string()).into())
+                } else {
+                    Value::Number(left.to_number() + right.to_number())
+                }
+            }
+            "-" => Value::Number(left.to_number() - right.to_number()),
+            "*" => Value::Number(left.to_number() * right.to_number()),
+            "/" => Value::Number(left.to_number() / right.to_number()),
+            "%" => Value::Number(left.to_number() % right.to_number()),
+            "**" => Value::Number(left.to_number().powf(right.to_number())),
+            "==" => Value::Bool(left.loose_equals(right)),
+            "!=" => Value::Bool(!left.loose_equals(right)),
+            "===" => Value::Bool(left.strict_equals(right)),
+            "!==" => Value::Bool(!left.strict_equals(right)),
+            "<" | ">" | "<=" | ">=" => {
+                let left = to_primitive(left);
+                let right = to_primitive(right);
+                let ordering = match (&left, &right) {
+                    (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                    _ => left.to_number().partial_cmp(&right.to_number()),
+                };
+                Value::Bool(ordering.is_some_and(|ordering| match operator {
+                    "<" => ordering.is_lt(),
+                    ">" => ordering.is_gt(),
+                    "<=" => ordering.is_le(),
+                    _ => ordering.is_ge(),
+                }))
+            }
+            "&" => Value::Number(f64::from(
+                to_int32(left.to_number()) & to_int32(right.to_number()),
+            )),
+            "|" => Value::Number(f64::from(
+                to_int32(left.to_number()) | to_int32(right.to_number()),
+            )),
+            "^" => Value::Number(f64::from(
+                to_int32(left.to_number()) ^ to_int32(right.to_number()),
+            )),
+            "<<" => Value::Number(f64::from(
+                to_int32(left.to_number()).wrapping_shl(to_int32(right.to_number()) as u32 & 31),
+            )),
+            ">>" => Value::Number(f64::from(
+                to_int32(left.to_number()).wrapping_shr(to_int32(right.to_number()) as u32 & 31),
+            )),
+            ">>>" => Value::Number(f64::from(
+                (to_int32(left.to_number()) as u32)
+                    .wrapping_shr(to_int32(right.to_number()) as u32 & 31),
+            )),
+            "in" => {
+                if !matches!(right, Value::Object(_)) {
+                    return Err(self.error(
+                        "TypeError",
+                        "Cannot use 'in' operator to search for a key in a primitive",
+                    ));
+                }
+                let key = left.to_property_key();
+                Value::Bool(builtins::own_keys(right).contains(&key))
+            }
+            "instanceof" => Value::Bool(builtins::instance_of(left, right)),
+            _ => return Err(self.error("SyntaxError", &format!("Unknown operator {operator}"))),
+        })
+    }
+
+    pub fn iterate(&self, value: &Value) -> JsResult<Vec<Value>> {
+        match value {
+            Value::String(s) => Ok(s
+                .chars()
+                .map(|c| Value::String(c.to_string().into()))
+                .collect()),
+            _ => value
+                .array_elements()
+                .ok_or_else(|| self.error("TypeError", &format!("{value:?} is not iterable"))),
+        }
+    }
+
+    pub fn get_property(&self, object: &Value, key: &str) -> JsResult<Value> {
+        match object {
+            Value::Undefined | Value::Null => Err(self.error(
+                "TypeError",
+                &format!("Cannot read properties of {object:?} (reading '{key}')"),
+            )),
+            Value::String(s) => Ok(builtins::string_property(s, key)),
+            Value::Number(_) => Ok(builtins::number_property(key)),
+            Value::Bool(_) => Ok(Value::Undefined),
+            Value::Object(reference) => {
+                let object = reference.borrow();
+                if let Some(value) = object.properties.get(key) {
+                    return Ok(value.clone());
+                }
+                Ok(match &object.kind {
+                    ObjectKind::Array(elements) => builtins::array_property(elements, key),
+                    ObjectKind::Function(_) => builtins::function_property(key),
+                    ObjectKind::RegExp { source, flags } => {
+                        builtins::regexp_property(source, flags, key)
+                    }
+                    ObjectKind::RuleBuilder(rule_names) => {
+                        grammar::rule_builder_property(self, rule_names.as_deref(), key)
+                    }
+                    ObjectKind::Plain | ObjectKind::Error => builtins::object_property(key),
+                })
+            }
+        }
+    }
+
+    pub fn set_property(&self, object: &Value, key: &str, value: Value) -> JsResult<()> {
+        match object {
+            Value::Undefined | Value::Null => Err(self.error(
+                "TypeError",
+                &format!("Cannot set properties of {object:?} (setting '{key}')"),
+            )),
+            Value::Object(reference) => {
+                let mut object = reference.borrow_mut();
+                if let ObjectKind::Array(elements) = &mut object.kind {
+                    if key == "length" {
+                        let length = value.to_number();
+                        if length >= 0.0 && length.fract() == 0.0 {
+                            elements.resize(length as usize, Value::Undefined);
+                            return Ok(());
+                        }
+                        drop(object);
+                        return Err(self.error("RangeError", "Invalid array length"));
+                    }
+                    if let Ok(index) = key.parse::<usize>() {
+                        if index >= elements.len() {
+                            elements.resize(index + 1, Value::Undefined);
+                        }
+                        elements[index] = value;
+                        return Ok(());
+                    }
+                }
+                object.properties.insert(key.into(), value);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn hoist_var_declarations(statements: &[Statement], scope: &Rc<Scope>) {
+    for statement in statements {
+        match statement {
+            Statement::Positioned(_, statement) => {
+                hoist_var_declarations(std::slice::from_ref(statement.as_ref()), scope);
+            }
+            Statement::Declaration(DeclarationKind::Var, declarators) => {
+                for (pattern, _) in declarators {
+                    hoist_pattern_names(pattern, scope);
+                }
+            }
+            Statement::ForOf(DeclarationKind::Var, pattern, _, body)
+            | Statement::ForIn(DeclarationKind::Var, pattern, _, body) => {
+                hoist_pattern_names(pattern, scope);
+                hoist_var_declarations(std::slice::from_ref(body.as_ref()), scope);
+            }
+            Statement::Block(statements) => hoist_var_declarations(statements, scope),
+            Statement::If(_, consequence, alternative) => {
+                hoist_var_declarations(std::slice::from_ref(consequence.as_ref()), scope);
+                if let Some(alternative) = alternative {
+                    hoist_var_declarations(std::slice::from_ref(alternative.as_ref()), scope);
+                }
+            }
+            Statement::For { init, body, .. } => {
+                if let Some(init) = init {
+                    hoist_var_declarations(std::slice::from_ref(init.as_ref()), scope);
+                }
+                hoist_var_declarations(std::slice::from_ref(body.as_ref()), scope);
+            }
+            Statement::ForOf(_, _, _, body)
+            | Statement::ForIn(_, _, _, body)
+            | Statement::While(_, body)
+            | Statement::DoWhile(body, _) => {
+                hoist_var_declarations(std::slice::from_ref(body.as_ref()), scope);
+            }
+            Statement::Try {
+                block,
+                handler,
+                finalizer,
+                ..
+            } => {
+                hoist_var_declarations(block, scope);
+                for statements in [handler, finalizer].into_iter().flatten() {
+                    hoist_var_declarations(statements, scope);
+                }
+            }
+            Statement::Switch(_, cases) => {
+                for (_, body) in cases {
+                    hoist_var_declarations(body, scope);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn hoist_pattern_names(pattern: &Pattern, scope: &Rc<Scope>) {
+    match pattern {
+        Pattern::Identifier(name) => {
+            if !scope.bindings.borrow().contains_key(name) {
+                scope.declare(name, Value::Undefined, true);
+            }
+        }
+        Pattern::Default(pattern, _) => hoist_pattern_names(pattern, scope),
+        Pattern::Array(elements, rest) => {
+            for element in elements.iter().flatten() {
+                hoist_pattern_names(element, scope);
+            }
+            if let Some(rest) = rest {
+                hoist_pattern_names(rest, scope);
+            }
+        }
+        Pattern::Object(properties, rest) => {
+            for (_, pattern) in properties {
+                hoist_pattern_names(pattern, scope);
+            }
+            if let Some(rest) = rest {
+                hoist_pattern_names(rest, scope);
+            }
+        }
+    }
+}
+
+fn to_primitive(value: &Value) -> Value {
+    match value {
+        Value::Object(_) => Value::String(value.to_js_string().into()),
+        _ => value.clone(),
+    }
+}
+
+pub fn to_int32(n: f64) -> i32 {
+    if n.is_finite() {
+        (n.trunc() as i64) as i32
+    } else {
+        0
+    }
+}
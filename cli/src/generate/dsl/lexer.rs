@@ -0,0 +1,513 @@
+use std::fmt;
+
+use anyhow::{anyhow, Result};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub row: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    Number(f64),
+    String(String),
+    Template {
+        strings: Vec<String>,
+        expressions: Vec<Vec<Token>>,
+    },
+    Regex {
+        pattern: String,
+        flags: String,
+    },
+    Punct(&'static str),
+    Eof,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub position: Position,
+    pub newline_before: bool,
+}
+
+// Sorted so that longer punctuators are matched before their prefixes.
+const PUNCTUATORS: &[&str] = &[
+    ">>>=", "...", "===", "!==", "**=", "<<=", ">>=", ">>>", "&&=", "||=", "??=", "=>", "==", "!=",
+    "<=", ">=", "&&", "||", "??", "?.", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=",
+    "**", "<<", ">>", "{", "}", "(", ")", "[", "]", ";", ",", "<", ">", "+", "-", "*", "/", "%",
+    "&", "|", "^", "!", "~", "?", ":", "=", ".", "@",
+];
+
+// Keywords after which a `/` starts a regular expression rather than a division.
+const REGEX_PREFIX_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "case",
+    "do",
+    "else",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "instanceof",
+    "yield",
+    "await",
+];
+
+pub struct Lexer<'a> {
+    chars: Vec<char>,
+    index: usize,
+    row: usize,
+    column: usize,
+    path: &'a str,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.row + 1, self.column + 1)
+    }
+}
+
+impl TokenKind {
+    pub fn is_punct(&self, punct: &str) -> bool {
+        matches!(self, Self::Punct(p) if *p == punct)
+    }
+
+    pub fn is_identifier(&self, name: &str) -> bool {
+        matches!(self, Self::Identifier(n) if n == name)
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &str, path: &'a str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            index: 0,
+            row: 0,
+            column: 0,
+            path,
+        }
+    }
+
+    pub fn tokenize(mut self) -> Result<Vec<Token>> {
+        if self.peek(0) == Some('#') && self.peek(1) == Some('!') {
+            while !matches!(self.peek(0), None | Some('\n')) {
+                self.advance();
+            }
+        }
+        let mut tokens = self.tokenize_until(false)?;
+        let position = self.position();
+        tokens.push(Token {
+            kind: TokenKind::Eof,
+            position,
+            newline_before: true,
+        });
+        Ok(tokens)
+    }
+
+    // Tokenize until the end of input or, when lexing the contents of a template
+    // substitution, until the `}` that closes the substitution.
+    fn tokenize_until(&mut self, in_template: bool) -> Result<Vec<Token>> {
+        let mut tokens = Vec::<Token>::new();
+        let mut brace_depth = 0usize;
+        loop {
+            let newline_before = self.skip_whitespace_and_comments()?;
+            let position = self.position();
+            let Some(c) = self.peek(0) else {
+                if in_template {
+                    return Err(self.error("Unterminated template literal"));
+                }
+                return Ok(tokens);
+            };
+
+            let kind = if c.is_ascii_digit() || (c == '.' && self.peek_is_digit(1)) {
+                self.number()?
+            } else if is_identifier_start(c) || c == '\\' {
+                self.identifier()?
+            } else if c == '"' || c == '\'' {
+                self.advance();
+                TokenKind::String(self.string(c)?)
+            } else if c == '`' {
+                self.advance();
+                self.template()?
+            } else if c == '/' && regex_allowed(tokens.last()) {
+                self.advance();
+                self.regex()?
+            } else {
+                let punct = PUNCTUATORS
+                    .iter()
+                    .find(|p| self.starts_with(p))
+                    .ok_or_else(|| self.error(&format!("Unexpected character {c:?}")))?;
+                if *punct == "{" {
+                    brace_depth += 1;
+                } else if *punct == "}" {
+                    if brace_depth == 0 && in_template {
+                        self.advance();
+                        return Ok(tokens);
+                    }
+                    brace_depth = brace_depth.saturating_sub(1);
+                }
+                for _ in 0..punct.len() {
+                    self.advance();
+                }
+                TokenKind::Punct(punct)
+            };
+
+            tokens.push(Token {
+                kind,
+                position,
+                newline_before,
+            });
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<bool> {
+        let mut saw_newline = false;
+        loop {
+            match self.peek(0) {
+                Some('\n' | '\u{2028}' | '\u{2029}') => {
+                    saw_newline = true;
+                    self.advance();
+                }
+                Some(c) if c.is_whitespace() || c == '\u{feff}' => {
+                    self.advance();
+                }
+                Some('/') if self.peek(1) == Some('/') => {
+                    while !matches!(self.peek(0), None | Some('\n')) {
+                        self.advance();
+                    }
+                }
+                Some('/') if self.peek(1) == Some('*') => {
+                    self.advance();
+                    self.advance();
+                    loop {
+                        match self.peek(0) {
+                            None => return Err(self.error("Unterminated comment")),
+                            Some('*') if self.peek(1) == Some('/') => {
+                                self.advance();
+                                self.advance();
+                                break;
+                            }
+                            Some(c) => {
+                                saw_newline |= c == '\n';
+                                self.advance();
+                            }
+                        }
+                    }
+                }
+                _ => return Ok(saw_newline),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<TokenKind> {
+        let radix = match (self.peek(0), self.peek(1).map(|c| c.to_ascii_lowercase())) {
+            (Some('0'), Some('x')) => 16,
+            (Some('0'), Some('o')) => 8,
+            (Some('0'), Some('b')) => 2,
+            _ => 10,
+        };
+
+        let mut text = String::new();
+        if radix == 10 {
+            let mut seen_exponent = false;
+            while let Some(c) = self.peek(0) {
+                if c.is_ascii_digit() || c == '.' {
+                    text.push(c);
+                } else if (c == 'e' || c == 'E') && !seen_exponent {
+                    seen_exponent = true;
+                    text.push(c);
+                    if let Some(sign @ ('+' | '-')) = self.peek(1) {
+                        self.advance();
+                        text.push(sign);
+                    }
+                } else if c != '_' {
+                    break;
+                }
+                self.advance();
+            }
+            text.parse::<f64>()
+                .map(TokenKind::Number)
+                .map_err(|_| self.error(&format!("Invalid number {text:?}")))
+        } else {
+            self.advance();
+            self.advance();
+            while let Some(c) = self.peek(0) {
+                if c.is_digit(radix) {
+                    text.push(c);
+                } else if c != '_' {
+                    break;
+                }
+                self.advance();
+            }
+            u64::from_str_radix(&text, radix)
+                .map(|n| TokenKind::Number(n as f64))
+                .map_err(|_| self.error(&format!("Invalid number {text:?}")))
+        }
+    }
+
+    fn identifier(&mut self) -> Result<TokenKind> {
+        let mut name = String::new();
+        while let Some(c) = self.peek(0) {
+            if c == '\\' && self.peek(1) == Some('u') {
+                self.advance();
+                self.advance();
+                name.push(self.unicode_escape()?);
+            } else if is_identifier_part(c) {
+                name.push(c);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        Ok(TokenKind::Identifier(name))
+    }
+
+    fn string(&mut self, quote: char) -> Result<String> {
+        let mut result = String::new();
+        loop {
+            match self.peek(0) {
+                None | Some('\n') => return Err(self.error("Unterminated string literal")),
+                Some(c) if c == quote => {
+                    self.advance();
+                    return Ok(result);
+                }
+                Some('\\') => {
+                    self.advance();
+                    if let Some(c) = self.escape()? {
+                        result.push(c);
+                    }
+                }
+                Some(c) => {
+                    result.push(c);
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn template(&mut self) -> Result<TokenKind> {
+        let mut strings = Vec::new();
+        let mut expressions = Vec::new();
+        let mut current = String::new();
+        loop {
+            match self.peek(0) {
+                None => return Err(self.error("Unterminated template literal")),
+                Some('`') => {
+                    self.advance();
+                    strings.push(current);
+                    return Ok(TokenKind::Template {
+                        strings,
+                        expressions,
+                    });
+                }
+                Some('$') if self.peek(1) == Some('{') => {
+                    self.advance();
+                    self.advance();
+                    strings.push(std::mem::take(&mut current));
+                    let mut tokens = self.tokenize_until(true)?;
+                    let position = self.position();
+                    tokens.push(Token {
+                        kind: TokenKind::Eof,
+                        position,
+                        newline_before: false,
+                    });
+                    expressions.push(tokens);
+                }
+                Some('\\') => {
+                    self.advance();
+                    if let Some(c) = self.escape()? {
+                        current.push(c);
+                    }
+                }
+                Some('\r') => {
+                    self.advance();
+                    if self.peek(0) == Some('\n') {
+                        self.advance();
+                    }
+                    current.push('\n');
+                }
+                Some(c) => {
+                    current.push(c);
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn regex(&mut self) -> Result<TokenKind> {
+        let mut pattern = String::new();
+        let mut in_class = false;
+        loop {
+            match self.peek(0) {
+                None | Some('\n') => {
+                    return Err(self.error("Unterminated regular expression literal"))
+                }
+                Some('\\') => {
+                    pattern.push('\\');
+                    self.advance();
+                    if let Some(c) = self.peek(0) {
+                        pattern.push(c);
+                        self.advance();
+                    }
+                }
+                Some('/') if !in_class => {
+                    self.advance();
+                    break;
+                }
+                Some(c) => {
+                    if c == '[' {
+                        in_class = true;
+                    } else if c == ']' {
+                        in_class = false;
+                    }
+                    pattern.push(c);
+                    self.advance();
+                }
+            }
+        }
+        let mut flags = String::new();
+        while let Some(c) = self.peek(0).filter(|c| is_identifier_part(*c)) {
+            flags.push(c);
+            self.advance();
+        }
+        Ok(TokenKind::Regex { pattern, flags })
+    }
+
+    // Consume the character(s) following a backslash. Returns `None` for line continuations.
+    fn escape(&mut self) -> Result<Option<char>> {
+        let Some(c) = self.peek(0) else {
+            return Err(self.error("Unterminated escape sequence"));
+        };
+        self.advance();
+        Ok(Some(match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' if !self.peek_is_digit(0) => '\0',
+            'x' => {
+                let code = self.hex_digits(2)?;
+                char::from_u32(code).ok_or_else(|| self.error("Invalid escape sequence"))?
+            }
+            'u' => self.unicode_escape()?,
+            '\r' => {
+                if self.peek(0) == Some('\n') {
+                    self.advance();
+                }
+                return Ok(None);
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => return Ok(None),
+            c => c,
+        }))
+    }
+
+    // Decode the part of a `\u` escape after the `u`, combining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char> {
+        let code = if self.peek(0) == Some('{') {
+            self.advance();
+            let mut code = 0u32;
+            while let Some(c) = self.peek(0) {
+                self.advance();
+                if c == '}' {
+                    break;
+                }
+                code = code * 16
+                    + c.to_digit(16)
+                        .ok_or_else(|| self.error("Invalid unicode escape"))?;
+            }
+            code
+        } else {
+            self.hex_digits(4)?
+        };
+
+        if (0xD800..0xDC00).contains(&code)
+            && self.peek(0) == Some('\\')
+            && self.peek(1) == Some('u')
+        {
+            let saved = (self.index, self.row, self.column);
+            self.advance();
+            self.advance();
+            let low = self.hex_digits(4)?;
+            if (0xDC00..0xE000).contains(&low) {
+                let combined = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                return char::from_u32(combined)
+                    .ok_or_else(|| self.error("Invalid unicode escape"));
+            }
+            (self.index, self.row, self.column) = saved;
+        }
+
+        Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn hex_digits(&mut self, count: usize) -> Result<u32> {
+        let mut code = 0;
+        for _ in 0..count {
+            let digit = self
+                .peek(0)
+                .and_then(|c| c.to_digit(16))
+                .ok_or_else(|| self.error("Invalid hexadecimal escape sequence"))?;
+            code = code * 16 + digit;
+            self.advance();
+        }
+        Ok(code)
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.index + offset).copied()
+    }
+
+    fn peek_is_digit(&self, offset: usize) -> bool {
+        self.peek(offset).is_some_and(|c| c.is_ascii_digit())
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn advance(&mut self) {
+        if let Some(c) = self.peek(0) {
+            self.index += 1;
+            if c == '\n' {
+                self.row += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+    }
+
+    const fn position(&self) -> Position {
+        Position {
+            row: self.row,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        anyhow!("{message} at {}:{}", self.path, self.position())
+    }
+}
+
+fn regex_allowed(previous: Option<&Token>) -> bool {
+    match previous.map(|t| &t.kind) {
+        None => true,
+        Some(TokenKind::Punct(p)) => !matches!(*p, ")" | "]" | "}"),
+        Some(TokenKind::Identifier(name)) => REGEX_PREFIX_KEYWORDS.contains(&name.as_str()),
+        Some(_) => false,
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || c.is_alphabetic()
+}
+
+fn is_identifier_part(c: char) -> bool {
+    c == '$' || c == '_' || c == '\u{200c}' || c == '\u{200d}' || c.is_alphanumeric()
+}
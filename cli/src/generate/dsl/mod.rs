@@ -0,0 +1,30 @@
+//! A JavaScript evaluator for `grammar.js` files, used by `tree-sitter generate` when the
+//! `native` JavaScript runtime is selected. It supports the subset of the language that
+//! grammars commonly use, and implements the grammar DSL functions from `dsl.js` natively.
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+
+use self::interpreter::Interpreter;
+
+mod builtins;
+mod grammar;
+mod interpreter;
+mod lexer;
+mod parser;
+mod value;
+
+/// Evaluate a `grammar.js` file, returning the grammar as pretty-printed JSON.
+pub fn load_grammar_file(grammar_path: &Path) -> Result<String> {
+    let mut interpreter = Interpreter::new();
+    let exports = interpreter.run_module(grammar_path)?;
+    let grammar = interpreter
+        .get_property(&exports, "grammar")
+        .ok()
+        .and_then(|grammar| grammar.to_json())
+        .ok_or_else(|| anyhow!("{grammar_path:?} does not export a grammar"))?;
+    Ok(serde_json::to_string_pretty(&grammar)
+        .with_context(|| "Failed to serialize grammar JSON")?
+        + "\n")
+}
@@ -0,0 +1,1073 @@
+use std::rc::Rc;
+
+use anyhow::{anyhow, Result};
+
+use super::lexer::{Position, Token, TokenKind};
+
+#[derive(Debug)]
+pub enum Statement {
+    Expression(Expression),
+    Declaration(DeclarationKind, Vec<(Pattern, Option<Expression>)>),
+    Function(String, Rc<Function>),
+    Return(Option<Expression>),
+    If(Expression, Box<Statement>, Option<Box<Statement>>),
+    Block(Vec<Statement>),
+    For {
+        init: Option<Box<Statement>>,
+        test: Option<Expression>,
+        update: Option<Expression>,
+        body: Box<Statement>,
+    },
+    ForOf(DeclarationKind, Pattern, Expression, Box<Statement>),
+    ForIn(DeclarationKind, Pattern, Expression, Box<Statement>),
+    While(Expression, Box<Statement>),
+    DoWhile(Box<Statement>, Expression),
+    Switch(Expression, Vec<(Option<Expression>, Vec<Statement>)>),
+    Try {
+        block: Vec<Statement>,
+        parameter: Option<Pattern>,
+        handler: Option<Vec<Statement>>,
+        finalizer: Option<Vec<Statement>>,
+    },
+    Throw(Expression),
+    Break,
+    Continue,
+    ExportDefault(Expression),
+    Empty,
+    Positioned(Position, Box<Statement>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Var,
+    Let,
+    Const,
+}
+
+#[derive(Debug)]
+pub enum Expression {
+    Identifier(String),
+    Number(f64),
+    String(Rc<str>),
+    Template(Vec<String>, Vec<Expression>),
+    Regex(String, String),
+    Array(Vec<Element>),
+    Object(Vec<Property>),
+    Function(Rc<Function>),
+    This,
+    Unary(&'static str, Box<Expression>),
+    Update {
+        operator: &'static str,
+        prefix: bool,
+        target: Box<Expression>,
+    },
+    Binary(&'static str, Box<Expression>, Box<Expression>),
+    Logical(&'static str, Box<Expression>, Box<Expression>),
+    Conditional(Box<Expression>, Box<Expression>, Box<Expression>),
+    Assign(&'static str, Box<Expression>, Box<Expression>),
+    Sequence(Vec<Expression>),
+    Member {
+        object: Box<Expression>,
+        property: Box<Expression>,
+        computed: bool,
+        optional: bool,
+    },
+    Call {
+        callee: Box<Expression>,
+        arguments: Vec<Element>,
+        optional: bool,
+        position: Position,
+    },
+    New {
+        callee: Box<Expression>,
+        arguments: Vec<Element>,
+        position: Position,
+    },
+}
+
+#[derive(Debug)]
+pub enum Element {
+    Expression(Expression),
+    Spread(Expression),
+    Hole,
+}
+
+#[derive(Debug)]
+pub enum Property {
+    KeyValue(PropertyKey, Expression),
+    Spread(Expression),
+}
+
+#[derive(Debug)]
+pub enum PropertyKey {
+    Static(Rc<str>),
+    Computed(Expression),
+}
+
+#[derive(Debug)]
+pub enum Pattern {
+    Identifier(String),
+    Array(Vec<Option<Pattern>>, Option<Box<Pattern>>),
+    Object(Vec<(PropertyKey, Pattern)>, Option<Box<Pattern>>),
+    Default(Box<Pattern>, Expression),
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: Option<String>,
+    pub parameters: Vec<Pattern>,
+    pub rest: Option<Pattern>,
+    pub body: FunctionBody,
+    pub is_arrow: bool,
+}
+
+#[derive(Debug)]
+pub enum FunctionBody {
+    Block(Vec<Statement>),
+    Expression(Expression),
+}
+
+pub struct Parser<'a> {
+    tokens: Vec<Token>,
+    index: usize,
+    path: &'a str,
+}
+
+const ASSIGNMENT_OPERATORS: &[&str] = &[
+    "=", "+=", "-=", "*=", "/=", "%=", "**=", "<<=", ">>=", ">>>=", "&=", "|=", "^=", "&&=", "||=",
+    "??=",
+];
+
+// Binary operators, from lowest to highest precedence.
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["??"],
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!=", "===", "!=="],
+    &["<", ">", "<=", ">=", "instanceof", "in"],
+    &["<<", ">>", ">>>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl<'a> Parser<'a> {
+    pub const fn new(tokens: Vec<Token>, path: &'a str) -> Self {
+        Self {
+            tokens,
+            index: 0,
+            path,
+        }
+    }
+
+    pub fn parse_program(mut self) -> Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+        while !matches!(self.peek(), TokenKind::Eof) {
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement> {
+        let position = self.position();
+        let statement = self.statement_inner()?;
+        Ok(Statement::Positioned(position, Box::new(statement)))
+    }
+
+    fn statement_inner(&mut self) -> Result<Statement> {
+        if self.eat_punct(";") {
+            return Ok(Statement::Empty);
+        }
+        if self.peek().is_punct("{") {
+            return Ok(Statement::Block(self.block()?));
+        }
+
+        let keyword = match self.peek() {
+            TokenKind::Identifier(name) => name.clone(),
+            _ => String::new(),
+        };
+        match keyword.as_str() {
+            "var" | "let" | "const" => {
+                let statement = self.declaration()?;
+                self.semicolon()?;
+                Ok(statement)
+            }
+            "function" => {
+                self.advance();
+                let function = self.function_rest()?;
+                let name = function
+                    .name
+                    .clone()
+                    .ok_or_else(|| self.error("Function declarations require a name"))?;
+                Ok(Statement::Function(name, Rc::new(function)))
+            }
+            "return" => {
+                self.advance();
+                let value = if self.at_statement_end() {
+                    None
+                } else {
+                    Some(self.expression()?)
+                };
+                self.semicolon()?;
+                Ok(Statement::Return(value))
+            }
+            "if" => {
+                self.advance();
+                self.expect_punct("(")?;
+                let condition = self.expression()?;
+                self.expect_punct(")")?;
+                let consequence = Box::new(self.statement()?);
+                let alternative = if self.eat_identifier("else") {
+                    Some(Box::new(self.statement()?))
+                } else {
+                    None
+                };
+                Ok(Statement::If(condition, consequence, alternative))
+            }
+            "for" => {
+                self.advance();
+                self.for_statement()
+            }
+            "while" => {
+                self.advance();
+                self.expect_punct("(")?;
+                let condition = self.expression()?;
+                self.expect_punct(")")?;
+                Ok(Statement::While(condition, Box::new(self.statement()?)))
+            }
+            "do" => {
+                self.advance();
+                let body = Box::new(self.statement()?);
+                self.expect_identifier("while")?;
+                self.expect_punct("(")?;
+                let condition = self.expression()?;
+                self.expect_punct(")")?;
+                self.eat_punct(";");
+                Ok(Statement::DoWhile(body, condition))
+            }
+            "switch" => {
+                self.advance();
+                self.switch_statement()
+            }
+            "try" => {
+                self.advance();
+                self.try_statement()
+            }
+            "throw" => {
+                self.advance();
+                let value = self.expression()?;
+                self.semicolon()?;
+                Ok(Statement::Throw(value))
+            }
+            "break" | "continue" => {
+                self.advance();
+                self.semicolon()?;
+                Ok(if keyword == "break" {
+                    Statement::Break
+                } else {
+                    Statement::Continue
+                })
+            }
+            "export" => {
+                self.advance();
+                self.expect_identifier("default")?;
+                let value = self.assignment()?;
+                self.semicolon()?;
+                Ok(Statement::ExportDefault(value))
+            }
+            "import" if !matches!(self.peek_at(1), TokenKind::Punct("(" | ".")) => Err(self.error(
+                "ES module imports are not supported by the native grammar evaluator, use `require`",
+            )),
+            "class" => Err(self.error("Classes are not supported by the native grammar evaluator")),
+            _ => {
+                let expression = self.expression()?;
+                self.semicolon()?;
+                Ok(Statement::Expression(expression))
+            }
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>> {
+        self.expect_punct("{")?;
+        let mut statements = Vec::new();
+        while !self.eat_punct("}") {
+            if matches!(self.peek(), TokenKind::Eof) {
+                return Err(self.error("Unexpected end of input"));
+            }
+            statements.push(self.statement()?);
+        }
+        Ok(statements)
+    }
+
+    fn declaration_kind(&mut self) -> Option<DeclarationKind> {
+        let kind = match self.peek() {
+            TokenKind::Identifier(name) if name == "var" => DeclarationKind::Var,
+            TokenKind::Identifier(name) if name == "let" => DeclarationKind::Let,
+            TokenKind::Identifier(name) if name == "const" => DeclarationKind::Const,
+            _ => return None,
+        };
+        self.advance();
+        Some(kind)
+    }
+
+    fn declaration(&mut self) -> Result<Statement> {
+        let kind = self.declaration_kind().unwrap();
+        let mut declarators = Vec::new();
+        loop {
+            let pattern = self.binding_pattern()?;
+            let init = if self.eat_punct("=") {
+                Some(self.assignment()?)
+            } else {
+                None
+            };
+            declarators.push((pattern, init));
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        Ok(Statement::Declaration(kind, declarators))
+    }
+
+    fn for_statement(&mut self) -> Result<Statement> {
+        self.expect_punct("(")?;
+
+        let start = self.index;
+        if let Some(kind) = self.declaration_kind() {
+            let pattern = self.binding_pattern()?;
+            if self.eat_identifier("of") {
+                let iterable = self.assignment()?;
+                self.expect_punct(")")?;
+                let body = Box::new(self.statement()?);
+                return Ok(Statement::ForOf(kind, pattern, iterable, body));
+            }
+            if self.eat_identifier("in") {
+                let object = self.expression()?;
+                self.expect_punct(")")?;
+                let body = Box::new(self.statement()?);
+                return Ok(Statement::ForIn(kind, pattern, object, body));
+            }
+            self.index = start;
+        }
+
+        let init = if self.peek().is_punct(";") {
+            None
+        } else if ["var", "let", "const"]
+            .iter()
+            .any(|k| self.peek().is_identifier(k))
+        {
+            Some(Box::new(self.declaration()?))
+        } else {
+            Some(Box::new(Statement::Expression(self.expression()?)))
+        };
+        self.expect_punct(";")?;
+        let test = if self.peek().is_punct(";") {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.expect_punct(";")?;
+        let update = if self.peek().is_punct(")") {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.expect_punct(")")?;
+        let body = Box::new(self.statement()?);
+        Ok(Statement::For {
+            init,
+            test,
+            update,
+            body,
+        })
+    }
+
+    fn switch_statement(&mut self) -> Result<Statement> {
+        self.expect_punct("(")?;
+        let discriminant = self.expression()?;
+        self.expect_punct(")")?;
+        self.expect_punct("{")?;
+        let mut cases = Vec::new();
+        while !self.eat_punct("}") {
+            let test = if self.eat_identifier("default") {
+                None
+            } else {
+                self.expect_identifier("case")?;
+                Some(self.expression()?)
+            };
+            self.expect_punct(":")?;
+            let mut body = Vec::new();
+            while !self.peek().is_punct("}")
+                && !self.peek().is_identifier("case")
+                && !self.peek().is_identifier("default")
+            {
+                body.push(self.statement()?);
+            }
+            cases.push((test, body));
+        }
+        Ok(Statement::Switch(discriminant, cases))
+    }
+
+    fn try_statement(&mut self) -> Result<Statement> {
+        let block = self.block()?;
+        let mut parameter = None;
+        let mut handler = None;
+        let mut finalizer = None;
+        if self.eat_identifier("catch") {
+            if self.eat_punct("(") {
+                parameter = Some(self.binding_pattern()?);
+                self.expect_punct(")")?;
+            }
+            handler = Some(self.block()?);
+        }
+        if self.eat_identifier("finally") {
+            finalizer = Some(self.block()?);
+        }
+        if handler.is_none() && finalizer.is_none() {
+            return Err(self.error("Missing catch or finally after try"));
+        }
+        Ok(Statement::Try {
+            block,
+            parameter,
+            handler,
+            finalizer,
+        })
+    }
+
+    fn expression(&mut self) -> Result<Expression> {
+        let first = self.assignment()?;
+        if !self.peek().is_punct(",") {
+            return Ok(first);
+        }
+        let mut expressions = vec![first];
+        while self.eat_punct(",") {
+            expressions.push(self.assignment()?);
+        }
+        Ok(Expression::Sequence(expressions))
+    }
+
+    fn assignment(&mut self) -> Result<Expression> {
+        if let Some(function) = self.try_arrow_function()? {
+            return Ok(Expression::Function(Rc::new(function)));
+        }
+
+        let target = self.conditional()?;
+        if let TokenKind::Punct(op) = self.peek() {
+            if let Some(op) = ASSIGNMENT_OPERATORS.iter().find(|o| *o == op) {
+                if !matches!(
+                    target,
+                    Expression::Identifier(_) | Expression::Member { .. }
+                ) {
+                    return Err(self.error("Invalid assignment target"));
+                }
+                self.advance();
+                let value = self.assignment()?;
+                return Ok(Expression::Assign(op, Box::new(target), Box::new(value)));
+            }
+        }
+        Ok(target)
+    }
+
+    fn try_arrow_function(&mut self) -> Result<Option<Function>> {
+        let start = self.index;
+        let is_async = self.peek().is_identifier("async")
+            && !self.tokens[self.index + 1].newline_before
+            && matches!(
+                self.peek_at(1),
+                TokenKind::Identifier(_) | TokenKind::Punct("(")
+            );
+        if is_async {
+            self.advance();
+        }
+
+        let (parameters, rest) = match self.peek().clone() {
+            TokenKind::Identifier(name) if self.peek_at(1).is_punct("=>") => {
+                self.advance();
+                (vec![Pattern::Identifier(name)], None)
+            }
+            TokenKind::Punct("(") => {
+                if let Ok(parameters) = self.parameters() {
+                    if self.peek().is_punct("=>") && !self.tokens[self.index].newline_before {
+                        parameters
+                    } else {
+                        self.index = start;
+                        return Ok(None);
+                    }
+                } else {
+                    self.index = start;
+                    return Ok(None);
+                }
+            }
+            _ => {
+                self.index = start;
+                return Ok(None);
+            }
+        };
+
+        self.expect_punct("=>")?;
+        let body = if self.peek().is_punct("{") {
+            FunctionBody::Block(self.block()?)
+        } else {
+            FunctionBody::Expression(self.assignment()?)
+        };
+        Ok(Some(Function {
+            name: None,
+            parameters,
+            rest,
+            body,
+            is_arrow: true,
+        }))
+    }
+
+    fn parameters(&mut self) -> Result<(Vec<Pattern>, Option<Pattern>)> {
+        self.expect_punct("(")?;
+        let mut parameters = Vec::new();
+        let mut rest = None;
+        while !self.eat_punct(")") {
+            if self.eat_punct("...") {
+                rest = Some(self.binding_pattern()?);
+                self.eat_punct(",");
+                self.expect_punct(")")?;
+                break;
+            }
+            parameters.push(self.binding_element()?);
+            if !self.eat_punct(",") {
+                self.expect_punct(")")?;
+                break;
+            }
+        }
+        Ok((parameters, rest))
+    }
+
+    fn binding_element(&mut self) -> Result<Pattern> {
+        let pattern = self.binding_pattern()?;
+        if self.eat_punct("=") {
+            Ok(Pattern::Default(Box::new(pattern), self.assignment()?))
+        } else {
+            Ok(pattern)
+        }
+    }
+
+    fn binding_pattern(&mut self) -> Result<Pattern> {
+        match self.peek().clone() {
+            TokenKind::Identifier(name) => {
+                self.advance();
+                Ok(Pattern::Identifier(name))
+            }
+            TokenKind::Punct("[") => {
+                self.advance();
+                let mut elements = Vec::new();
+                let mut rest = None;
+                while !self.eat_punct("]") {
+                    if self.eat_punct(",") {
+                        elements.push(None);
+                        continue;
+                    }
+                    if self.eat_punct("...") {
+                        rest = Some(Box::new(self.binding_pattern()?));
+                        self.expect_punct("]")?;
+                        break;
+                    }
+                    elements.push(Some(self.binding_element()?));
+                    if !self.eat_punct(",") {
+                        self.expect_punct("]")?;
+                        break;
+                    }
+                }
+                Ok(Pattern::Array(elements, rest))
+            }
+            TokenKind::Punct("{") => {
+                self.advance();
+                let mut properties = Vec::new();
+                let mut rest = None;
+                while !self.eat_punct("}") {
+                    if self.eat_punct("...") {
+                        rest = Some(Box::new(self.binding_pattern()?));
+                        self.expect_punct("}")?;
+                        break;
+                    }
+                    let shorthand = match self.peek() {
+                        TokenKind::Identifier(name) => Some(name.clone()),
+                        _ => None,
+                    };
+                    let key = self.property_key()?;
+                    let value = if self.eat_punct(":") {
+                        self.binding_element()?
+                    } else if let Some(name) = shorthand {
+                        let pattern = Pattern::Identifier(name);
+                        if self.eat_punct("=") {
+                            Pattern::Default(Box::new(pattern), self.assignment()?)
+                        } else {
+                            pattern
+                        }
+                    } else {
+                        return Err(self.error("Invalid destructuring pattern"));
+                    };
+                    properties.push((key, value));
+                    if !self.eat_punct(",") {
+                        self.expect_punct("}")?;
+                        break;
+                    }
+                }
+                Ok(Pattern::Object(properties, rest))
+            }
+            _ => Err(self.error("Expected a binding pattern")),
+        }
+    }
+
+    fn conditional(&mut self) -> Result<Expression> {
+        let condition = self.binary(0)?;
+        if !self.eat_punct("?") {
+            return Ok(condition);
+        }
+        let consequence = self.assignment()?;
+        self.expect_punct(":")?;
+        let alternative = self.assignment()?;
+        Ok(Expression::Conditional(
+            Box::new(condition),
+            Box::new(consequence),
+            Box::new(alternative),
+        ))
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expression> {
+        if level == BINARY_OPERATORS.len() {
+            return self.exponent();
+        }
+        let mut left = self.binary(level + 1)?;
+        loop {
+            let operator = match self.peek() {
+                TokenKind::Punct(p) => BINARY_OPERATORS[level].iter().find(|o| *o == p),
+                TokenKind::Identifier(name) => BINARY_OPERATORS[level].iter().find(|o| *o == name),
+                _ => None,
+            };
+            let Some(operator) = operator else {
+                return Ok(left);
+            };
+            self.advance();
+            let right = self.binary(level + 1)?;
+            left = if matches!(*operator, "&&" | "||" | "??") {
+                Expression::Logical(operator, Box::new(left), Box::new(right))
+            } else {
+                Expression::Binary(operator, Box::new(left), Box::new(right))
+            };
+        }
+    }
+
+    fn exponent(&mut self) -> Result<Expression> {
+        let base = self.unary()?;
+        if self.eat_punct("**") {
+            let exponent = self.exponent()?;
+            return Ok(Expression::Binary("**", Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn unary(&mut self) -> Result<Expression> {
+        let operator = match self.peek() {
+            TokenKind::Punct(p @ ("!" | "-" | "+" | "~")) => Some(*p),
+            TokenKind::Identifier(name) => match name.as_str() {
+                "typeof" => Some("typeof"),
+                "void" => Some("void"),
+                "delete" => Some("delete"),
+                _ => None,
+            },
+            TokenKind::Punct(p @ ("++" | "--")) => {
+                let operator = *p;
+                self.advance();
+                let target = self.unary()?;
+                return Ok(Expression::Update {
+                    operator,
+                    prefix: true,
+                    target: Box::new(target),
+                });
+            }
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            self.advance();
+            let argument = self.unary()?;
+            return Ok(Expression::Unary(operator, Box::new(argument)));
+        }
+
+        let expression = self.call_or_member()?;
+        if let TokenKind::Punct(operator @ ("++" | "--")) = self.peek() {
+            if !self.tokens[self.index].newline_before {
+                let operator = *operator;
+                self.advance();
+                return Ok(Expression::Update {
+                    operator,
+                    prefix: false,
+                    target: Box::new(expression),
+                });
+            }
+        }
+        Ok(expression)
+    }
+
+    fn call_or_member(&mut self) -> Result<Expression> {
+        let mut expression = if self.peek().is_identifier("new") {
+            let position = self.position();
+            self.advance();
+            let callee = self.member_only()?;
+            let arguments = if self.peek().is_punct("(") {
+                self.arguments()?
+            } else {
+                Vec::new()
+            };
+            Expression::New {
+                callee: Box::new(callee),
+                arguments,
+                position,
+            }
+        } else {
+            self.primary()?
+        };
+
+        loop {
+            let position = self.position();
+            if self.eat_punct(".") {
+                let name = self.property_name()?;
+                expression = member(expression, Expression::String(name.into()), false, false);
+            } else if self.eat_punct("?.") {
+                if self.peek().is_punct("(") {
+                    let arguments = self.arguments()?;
+                    expression = Expression::Call {
+                        callee: Box::new(expression),
+                        arguments,
+                        optional: true,
+                        position,
+                    };
+                } else if self.eat_punct("[") {
+                    let property = self.expression()?;
+                    self.expect_punct("]")?;
+                    expression = member(expression, property, true, true);
+                } else {
+                    let name = self.property_name()?;
+                    expression = member(expression, Expression::String(name.into()), false, true);
+                }
+            } else if self.peek().is_punct("[") {
+                self.advance();
+                let property = self.expression()?;
+                self.expect_punct("]")?;
+                expression = member(expression, property, true, false);
+            } else if self.peek().is_punct("(") {
+                let arguments = self.arguments()?;
+                expression = Expression::Call {
+                    callee: Box::new(expression),
+                    arguments,
+                    optional: false,
+                    position,
+                };
+            } else if matches!(self.peek(), TokenKind::Template { .. }) {
+                return Err(self
+                    .error("Tagged templates are not supported by the native grammar evaluator"));
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    // The callee of a `new` expression, which can contain member accesses but not calls.
+    fn member_only(&mut self) -> Result<Expression> {
+        let mut expression = self.primary()?;
+        loop {
+            if self.eat_punct(".") {
+                let name = self.property_name()?;
+                expression = member(expression, Expression::String(name.into()), false, false);
+            } else if self.eat_punct("[") {
+                let property = self.expression()?;
+                self.expect_punct("]")?;
+                expression = member(expression, property, true, false);
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    fn arguments(&mut self) -> Result<Vec<Element>> {
+        self.expect_punct("(")?;
+        let mut arguments = Vec::new();
+        while !self.eat_punct(")") {
+            if self.eat_punct("...") {
+                arguments.push(Element::Spread(self.assignment()?));
+            } else {
+                arguments.push(Element::Expression(self.assignment()?));
+            }
+            if !self.eat_punct(",") {
+                self.expect_punct(")")?;
+                break;
+            }
+        }
+        Ok(arguments)
+    }
+
+    fn primary(&mut self) -> Result<Expression> {
+        let token = self.advance().clone();
+        Ok(match token.kind {
+            TokenKind::Number(n) => Expression::Number(n),
+            TokenKind::String(s) => Expression::String(s.into()),
+            TokenKind::Regex { pattern, flags } => Expression::Regex(pattern, flags),
+            TokenKind::Template {
+                strings,
+                expressions,
+            } => {
+                let mut parsed = Vec::with_capacity(expressions.len());
+                for tokens in expressions {
+                    let mut parser = Parser::new(tokens, self.path);
+                    parsed.push(parser.expression()?);
+                    if !matches!(parser.peek(), TokenKind::Eof) {
+                        return Err(parser.error("Unexpected token in template substitution"));
+                    }
+                }
+                Expression::Template(strings, parsed)
+            }
+            TokenKind::Identifier(name) => match name.as_str() {
+                "function" => Expression::Function(Rc::new(self.function_rest()?)),
+                "async" if self.peek().is_identifier("function") => {
+                    self.advance();
+                    Expression::Function(Rc::new(self.function_rest()?))
+                }
+                "this" => Expression::This,
+                _ => Expression::Identifier(name),
+            },
+            TokenKind::Punct("(") => {
+                let expression = self.expression()?;
+                self.expect_punct(")")?;
+                expression
+            }
+            TokenKind::Punct("[") => {
+                let mut elements = Vec::new();
+                while !self.eat_punct("]") {
+                    if self.peek().is_punct(",") {
+                        self.advance();
+                        elements.push(Element::Hole);
+                        continue;
+                    }
+                    if self.eat_punct("...") {
+                        elements.push(Element::Spread(self.assignment()?));
+                    } else {
+                        elements.push(Element::Expression(self.assignment()?));
+                    }
+                    if !self.eat_punct(",") {
+                        self.expect_punct("]")?;
+                        break;
+                    }
+                }
+                Expression::Array(elements)
+            }
+            TokenKind::Punct("{") => self.object_literal()?,
+            _ => {
+                self.index -= 1;
+                return Err(self.error("Unexpected token"));
+            }
+        })
+    }
+
+    fn object_literal(&mut self) -> Result<Expression> {
+        let mut properties = Vec::new();
+        while !self.eat_punct("}") {
+            if self.eat_punct("...") {
+                properties.push(Property::Spread(self.assignment()?));
+            } else {
+                let is_accessor = ["get", "set"].iter().any(|k| self.peek().is_identifier(k))
+                    && !matches!(self.peek_at(1), TokenKind::Punct("(" | ":" | "," | "}"));
+                if is_accessor {
+                    return Err(self.error(
+                        "Getters and setters are not supported by the native grammar evaluator",
+                    ));
+                }
+                if self.peek().is_identifier("async")
+                    && !matches!(self.peek_at(1), TokenKind::Punct("(" | ":" | "," | "}"))
+                {
+                    self.advance();
+                }
+
+                let shorthand = match self.peek() {
+                    TokenKind::Identifier(name) => Some(name.clone()),
+                    _ => None,
+                };
+                let key = self.property_key()?;
+                if self.eat_punct(":") {
+                    properties.push(Property::KeyValue(key, self.assignment()?));
+                } else if self.peek().is_punct("(") {
+                    let name = match &key {
+                        PropertyKey::Static(name) => Some(name.to_string()),
+                        PropertyKey::Computed(_) => None,
+                    };
+                    let (parameters, rest) = self.parameters()?;
+                    let body = FunctionBody::Block(self.block()?);
+                    let function = Function {
+                        name,
+                        parameters,
+                        rest,
+                        body,
+                        is_arrow: false,
+                    };
+                    properties.push(Property::KeyValue(
+                        key,
+                        Expression::Function(Rc::new(function)),
+                    ));
+                } else if let Some(name) = shorthand {
+                    properties.push(Property::KeyValue(key, Expression::Identifier(name)));
+                } else {
+                    return Err(self.error("Expected `:` after property key"));
+                }
+            }
+            if !self.eat_punct(",") {
+                self.expect_punct("}")?;
+                break;
+            }
+        }
+        Ok(Expression::Object(properties))
+    }
+
+    fn property_key(&mut self) -> Result<PropertyKey> {
+        let token = self.advance().clone();
+        Ok(match token.kind {
+            TokenKind::Identifier(name) | TokenKind::String(name) => {
+                PropertyKey::Static(name.into())
+            }
+            TokenKind::Number(n) => PropertyKey::Static(super::value::number_to_string(n).into()),
+            TokenKind::Punct("[") => {
+                let key = self.assignment()?;
+                self.expect_punct("]")?;
+                PropertyKey::Computed(key)
+            }
+            _ => {
+                self.index -= 1;
+                return Err(self.error("Expected a property key"));
+            }
+        })
+    }
+
+    fn property_name(&mut self) -> Result<String> {
+        match self.advance().kind.clone() {
+            TokenKind::Identifier(name) => Ok(name),
+            _ => {
+                self.index -= 1;
+                Err(self.error("Expected a property name"))
+            }
+        }
+    }
+
+    // Parse the remainder of a `function` expression or declaration, after the keyword.
+    fn function_rest(&mut self) -> Result<Function> {
+        self.eat_punct("*");
+        let name = match self.peek() {
+            TokenKind::Identifier(name) => {
+                let name = name.clone();
+                self.advance();
+                Some(name)
+            }
+            _ => None,
+        };
+        let (parameters, rest) = self.parameters()?;
+        let body = FunctionBody::Block(self.block()?);
+        Ok(Function {
+            name,
+            parameters,
+            rest,
+            body,
+            is_arrow: false,
+        })
+    }
+
+    fn at_statement_end(&self) -> bool {
+        let token = &self.tokens[self.index];
+        token.newline_before || matches!(token.kind, TokenKind::Punct(";" | "}") | TokenKind::Eof)
+    }
+
+    // Consume a statement-terminating semicolon, applying automatic semicolon insertion.
+    fn semicolon(&mut self) -> Result<()> {
+        if self.eat_punct(";") || self.at_statement_end() {
+            Ok(())
+        } else {
+            Err(self.error("Unexpected token, expected `;`"))
+        }
+    }
+
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.index].kind
+    }
+
+    fn peek_at(&self, offset: usize) -> &TokenKind {
+        self.tokens
+            .get(self.index + offset)
+            .map_or(&TokenKind::Eof, |t| &t.kind)
+    }
+
+    fn position(&self) -> Position {
+        self.tokens[self.index].position
+    }
+
+    fn advance(&mut self) -> &Token {
+        let token = &self.tokens[self.index];
+        if !matches!(token.kind, TokenKind::Eof) {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        if self.peek().is_punct(punct) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_identifier(&mut self, name: &str) -> bool {
+        if self.peek().is_identifier(name) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<()> {
+        if self.eat_punct(punct) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{punct}`")))
+        }
+    }
+
+    fn expect_identifier(&mut self, name: &str) -> Result<()> {
+        if self.eat_identifier(name) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("Expected `{name}`")))
+        }
+    }
+
+    fn error(&self, message: &str) -> anyhow::Error {
+        let token = &self.tokens[self.index];
+        let found = match &token.kind {
+            TokenKind::Identifier(name) => format!("`{name}`"),
+            TokenKind::Number(n) => format!("`{n}`"),
+            TokenKind::String(s) => format!("{s:?}"),
+            TokenKind::Template { .. } => "template literal".to_string(),
+            TokenKind::Regex { pattern, flags } => format!("`/{pattern}/{flags}`"),
+            TokenKind::Punct(p) => format!("`{p}`"),
+            TokenKind::Eof => "end of input".to_string(),
+        };
+        anyhow!(
+            "{message} (found {found}) at {}:{}",
+            self.path,
+            token.position
+        )
+    }
+}
+
+fn member(object: Expression, property: Expression, computed: bool, optional: bool) -> Expression {
+    Expression::Member {
+        object: Box::new(object),
+        property: Box::new(property),
+        computed,
+        optional,
+    }
+}
@@ -0,0 +1,354 @@
+use std::{
+    cell::RefCell,
+    collections::HashSet,
+    fmt::{self, Write as _},
+    rc::Rc,
+};
+
+use indexmap::IndexMap;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use super::{
+    interpreter::{Interpreter, JsResult, Scope},
+    parser::Function,
+};
+
+pub type ObjectRef = Rc<RefCell<Object>>;
+
+pub type NativeFunction = fn(&mut Interpreter, Value, Vec<Value>) -> JsResult<Value>;
+
+#[derive(Clone)]
+pub enum Value {
+    Undefined,
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Object(ObjectRef),
+}
+
+pub struct Object {
+    pub kind: ObjectKind,
+    pub properties: IndexMap<Rc<str>, Value>,
+}
+
+pub enum ObjectKind {
+    Plain,
+    Array(Vec<Value>),
+    Function(Callable),
+    RegExp { source: String, flags: String },
+    Error,
+    // The `$` argument passed to rule functions. Property accesses produce symbols, or
+    // `ReferenceError`s for names that are not in the rule map.
+    RuleBuilder(Option<Rc<HashSet<String>>>),
+}
+
+#[derive(Clone)]
+pub enum Callable {
+    Closure {
+        function: Rc<Function>,
+        scope: Rc<Scope>,
+        this: Option<Value>,
+    },
+    Native(&'static str, NativeFunction),
+    Bound(Value, Value),
+}
+
+impl Value {
+    pub fn string(s: &str) -> Self {
+        Self::String(s.into())
+    }
+
+    pub fn new_object(properties: IndexMap<Rc<str>, Self>) -> Self {
+        Self::from_kind(ObjectKind::Plain, properties)
+    }
+
+    pub fn new_array(elements: Vec<Self>) -> Self {
+        Self::from_kind(ObjectKind::Array(elements), IndexMap::new())
+    }
+
+    pub fn new_native(name: &'static str, function: NativeFunction) -> Self {
+        Self::from_kind(
+            ObjectKind::Function(Callable::Native(name, function)),
+            IndexMap::new(),
+        )
+    }
+
+    pub fn from_kind(kind: ObjectKind, properties: IndexMap<Rc<str>, Self>) -> Self {
+        Self::Object(Rc::new(RefCell::new(Object { kind, properties })))
+    }
+
+    pub fn object_from_pairs<'a>(pairs: impl IntoIterator<Item = (&'a str, Self)>) -> Self {
+        Self::new_object(pairs.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    pub const fn is_nullish(&self) -> bool {
+        matches!(self, Self::Undefined | Self::Null)
+    }
+
+    pub fn truthy(&self) -> bool {
+        match self {
+            Self::Undefined | Self::Null => false,
+            Self::Bool(b) => *b,
+            Self::Number(n) => *n != 0.0 && !n.is_nan(),
+            Self::String(s) => !s.is_empty(),
+            Self::Object(_) => true,
+        }
+    }
+
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Self::Undefined => "undefined",
+            Self::Null => "object",
+            Self::Bool(_) => "boolean",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+            Self::Object(object) => {
+                if matches!(object.borrow().kind, ObjectKind::Function(_)) {
+                    "function"
+                } else {
+                    "object"
+                }
+            }
+        }
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(self, Self::Object(o) if matches!(o.borrow().kind, ObjectKind::Array(_)))
+    }
+
+    pub fn is_function(&self) -> bool {
+        matches!(self, Self::Object(o) if matches!(o.borrow().kind, ObjectKind::Function(_)))
+    }
+
+    pub fn is_regexp(&self) -> bool {
+        matches!(self, Self::Object(o) if matches!(o.borrow().kind, ObjectKind::RegExp { .. }))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Object(o) if matches!(o.borrow().kind, ObjectKind::Error))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn array_elements(&self) -> Option<Vec<Self>> {
+        match self {
+            Self::Object(o) => match &o.borrow().kind {
+                ObjectKind::Array(elements) => Some(elements.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn to_number(&self) -> f64 {
+        match self {
+            Self::Undefined => f64::NAN,
+            Self::Null => 0.0,
+            Self::Bool(b) => f64::from(u8::from(*b)),
+            Self::Number(n) => *n,
+            Self::String(s) => {
+                let s = s.trim();
+                if s.is_empty() {
+                    0.0
+                } else if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                    u64::from_str_radix(hex, 16).map_or(f64::NAN, |n| n as f64)
+                } else {
+                    s.parse().unwrap_or(f64::NAN)
+                }
+            }
+            Self::Object(_) => {
+                let s = self.to_js_string();
+                Self::String(s.into()).to_number()
+            }
+        }
+    }
+
+    pub fn to_js_string(&self) -> String {
+        match self {
+            Self::Undefined => "undefined".to_string(),
+            Self::Null => "null".to_string(),
+            Self::Bool(b) => b.to_string(),
+            Self::Number(n) => number_to_string(*n),
+            Self::String(s) => s.to_string(),
+            Self::Object(object) => {
+                let object = object.borrow();
+                match &object.kind {
+                    ObjectKind::Array(elements) => elements
+                        .iter()
+                        .map(|e| {
+                            if e.is_nullish() {
+                                String::new()
+                            } else {
+                                e.to_js_string()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    ObjectKind::RegExp { source, flags } => format!("/{source}/{flags}"),
+                    ObjectKind::Error => {
+                        let name = object
+                            .properties
+                            .get("name")
+                            .map_or_else(|| "Error".to_string(), Self::to_js_string);
+                        let message = object
+                            .properties
+                            .get("message")
+                            .map_or_else(String::new, Self::to_js_string);
+                        if message.is_empty() {
+                            name
+                        } else {
+                            format!("{name}: {message}")
+                        }
+                    }
+                    ObjectKind::Function(Callable::Closure { function, .. }) => format!(
+                        "function {}() {{ [code] }}",
+                        function.name.as_deref().unwrap_or_default()
+                    ),
+                    ObjectKind::Function(Callable::Native(name, _)) => {
+                        format!("function {name}() {{ [native code] }}")
+                    }
+                    ObjectKind::Function(Callable::Bound(..)) => {
+                        "function () { [native code] }".to_string()
+                    }
+                    ObjectKind::Plain | ObjectKind::RuleBuilder(_) => "[object Object]".to_string(),
+                }
+            }
+        }
+    }
+
+    pub fn to_property_key(&self) -> Rc<str> {
+        match self {
+            Self::String(s) => s.clone(),
+            _ => self.to_js_string().into(),
+        }
+    }
+
+    pub fn strict_equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Undefined, Self::Undefined) | (Self::Null, Self::Null) => true,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            (Self::Number(a), Self::Number(b)) => a == b,
+            (Self::String(a), Self::String(b)) => a == b,
+            (Self::Object(a), Self::Object(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    pub fn loose_equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Undefined | Self::Null, Self::Undefined | Self::Null) => true,
+            (Self::Undefined | Self::Null, _) | (_, Self::Undefined | Self::Null) => false,
+            (Self::Object(_), Self::Object(_)) => self.strict_equals(other),
+            (Self::Object(_), _) => Self::String(self.to_js_string().into()).loose_equals(other),
+            (_, Self::Object(_)) => self.loose_equals(&Self::String(other.to_js_string().into())),
+            (Self::String(a), Self::String(b)) => a == b,
+            _ => self.to_number() == other.to_number(),
+        }
+    }
+
+    // Convert a value to JSON following the rules of `JSON.stringify`. Returns `None` for
+    // values that `JSON.stringify` omits, such as `undefined` and functions.
+    pub fn to_json(&self) -> Option<JsonValue> {
+        Some(match self {
+            Self::Undefined => return None,
+            Self::Null => JsonValue::Null,
+            Self::Bool(b) => JsonValue::Bool(*b),
+            Self::Number(n) => number_to_json(*n),
+            Self::String(s) => JsonValue::String(s.to_string()),
+            Self::Object(object) => {
+                let object = object.borrow();
+                match &object.kind {
+                    ObjectKind::Function(_) => return None,
+                    ObjectKind::Array(elements) => JsonValue::Array(
+                        elements
+                            .iter()
+                            .map(|e| e.to_json().unwrap_or(JsonValue::Null))
+                            .collect(),
+                    ),
+                    _ => {
+                        let mut map = Map::new();
+                        for (key, value) in &object.properties {
+                            if let Some(value) = value.to_json() {
+                                map.insert(key.to_string(), value);
+                            }
+                        }
+                        JsonValue::Object(map)
+                    }
+                }
+            }
+        })
+    }
+
+    pub fn from_json(json: &JsonValue) -> Self {
+        match json {
+            JsonValue::Null => Self::Null,
+            JsonValue::Bool(b) => Self::Bool(*b),
+            JsonValue::Number(n) => Self::Number(n.as_f64().unwrap_or(f64::NAN)),
+            JsonValue::String(s) => Self::string(s),
+            JsonValue::Array(elements) => {
+                Self::new_array(elements.iter().map(Self::from_json).collect())
+            }
+            JsonValue::Object(map) => Self::new_object(
+                map.iter()
+                    .map(|(k, v)| (k.as_str().into(), Self::from_json(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::String(s) => write!(f, "{s:?}"),
+            Self::Object(_) => {
+                if self.is_function() || self.is_regexp() || self.is_error() {
+                    f.write_str(&self.to_js_string())
+                } else {
+                    let json = self.to_json().unwrap_or(JsonValue::Null);
+                    write!(f, "{json}")
+                }
+            }
+            _ => f.write_str(&self.to_js_string()),
+        }
+    }
+}
+
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        "NaN".to_string()
+    } else if n.is_infinite() {
+        if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else if n == 0.0 {
+        "0".to_string()
+    } else if n.abs() >= 1e21 || n.abs() < 1e-6 {
+        let mut result = String::new();
+        write!(result, "{n:e}").unwrap();
+        if !result.contains("e-") {
+            result = result.replace('e', "e+");
+        }
+        result
+    } else if n.fract() == 0.0 {
+        let mut result = String::new();
+        write!(result, "{n:.0}").unwrap();
+        result
+    } else {
+        n.to_string()
+    }
+}
+
+fn number_to_json(n: f64) -> JsonValue {
+    if !n.is_finite() {
+        JsonValue::Null
+    } else if n.fract() == 0.0 && n.abs() < 9_007_199_254_740_992.0 {
+        JsonValue::Number(Number::from(n as i64))
+    } else {
+        Number::from_f64(n).map_or(JsonValue::Null, JsonValue::Number)
+    }
+}
@@ -80,6 +80,10 @@ pub struct InlinedProductionMap {
     pub production_map: HashMap<(*const Production, u32), Vec<usize>>,
 }
 
+// The production pointers are only used as keys that identify productions, and are
+// never dereferenced, so the map can be shared between threads.
+unsafe impl Sync for InlinedProductionMap {}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SyntaxVariable {
     pub name: String,
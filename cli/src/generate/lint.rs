@@ -0,0 +1,397 @@
+use std::{collections::HashSet, fmt};
+
+use anyhow::Result;
+
+use super::{
+    build_tables::analyze_grammar,
+    grammars::{InputGrammar, LexicalGrammar, SyntaxGrammar, VariableType},
+    node_types::get_variable_info,
+    parse_grammar::parse_grammar,
+    prepare_grammar::prepare_grammar,
+    rules::{Rule, Symbol, SymbolType},
+};
+
+/// A problem with a grammar that does not prevent a parser from being generated,
+/// but which probably indicates a mistake.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintWarning {
+    pub kind: LintKind,
+    /// The names of the rules, tokens or fields that the warning refers to.
+    pub names: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintKind {
+    UnreachableRule,
+    EmptyRule,
+    UnnecessaryConflict,
+    RedundantPrecedence,
+    UnusedField,
+    IncompleteSupertype,
+    ShadowedToken,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Parse a `grammar.json` file and check it for problems, as in [`lint_grammar`].
+pub fn lint_grammar_json(grammar_json: &str) -> Result<Vec<LintWarning>> {
+    lint_grammar(&parse_grammar(grammar_json)?)
+}
+
+/// Check a grammar for declarations that are unused or have no effect. Errors that
+/// would prevent the grammar from being generated are returned as errors.
+pub fn lint_grammar(input_grammar: &InputGrammar) -> Result<Vec<LintWarning>> {
+    let (syntax_grammar, lexical_grammar, inlines, simple_aliases) =
+        prepare_grammar(input_grammar)?;
+    let variable_info = get_variable_info(&syntax_grammar, &lexical_grammar, &simple_aliases)?;
+    let analysis = analyze_grammar(&syntax_grammar, &lexical_grammar, &variable_info, &inlines)?;
+    let symbol_name = |symbol: Symbol| match symbol.kind {
+        SymbolType::NonTerminal => syntax_grammar.variables[symbol.index].name.clone(),
+        SymbolType::Terminal => lexical_grammar.variables[symbol.index].name.clone(),
+        SymbolType::External => syntax_grammar.external_tokens[symbol.index].name.clone(),
+        SymbolType::End | SymbolType::EndOfNonTerminalExtra => "EOF".to_string(),
+    };
+
+    let mut warnings = Vec::new();
+
+    let reachable_symbols = reachable_symbols(&syntax_grammar);
+    let start_rule_name = &syntax_grammar.variables[0].name;
+    let unreachable_rules = syntax_grammar
+        .variables
+        .iter()
+        .enumerate()
+        .filter(|(_, variable)| variable.kind != VariableType::Auxiliary)
+        .map(|(i, _)| Symbol::non_terminal(i))
+        .chain(
+            lexical_grammar
+                .variables
+                .iter()
+                .enumerate()
+                .filter(|(_, variable)| {
+                    matches!(variable.kind, VariableType::Named | VariableType::Hidden)
+                })
+                .map(|(i, _)| Symbol::terminal(i)),
+        )
+        .filter(|symbol| !reachable_symbols.contains(symbol));
+    for symbol in unreachable_rules {
+        let name = symbol_name(symbol);
+        warnings.push(LintWarning {
+            kind: LintKind::UnreachableRule,
+            message: format!(
+                "Rule `{name}` is not reachable from the start rule `{start_rule_name}`"
+            ),
+            names: vec![name],
+        });
+    }
+
+    for variable in &syntax_grammar.variables {
+        if variable.kind != VariableType::Auxiliary
+            && variable.productions.iter().all(|p| p.steps.is_empty())
+        {
+            warnings.push(LintWarning {
+                kind: LintKind::EmptyRule,
+                message: format!("Rule `{}` can only match the empty string", variable.name),
+                names: vec![variable.name.clone()],
+            });
+        }
+    }
+
+    for conflict in analysis.unnecessary_conflicts {
+        let list = conflict
+            .iter()
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        warnings.push(LintWarning {
+            kind: LintKind::UnnecessaryConflict,
+            message: format!("The conflict [{list}] does not correspond to any actual conflict"),
+            names: conflict,
+        });
+    }
+
+    for (i, variable) in syntax_grammar.variables.iter().enumerate() {
+        let symbol = Symbol::non_terminal(i);
+        if variable.kind == VariableType::Auxiliary
+            || syntax_grammar.variables_to_inline.contains(&symbol)
+            || analysis.symbols_with_used_precedence.contains(&symbol)
+        {
+            continue;
+        }
+        let has_precedence = variable.productions.iter().any(|production| {
+            production
+                .steps
+                .iter()
+                .any(|step| !step.precedence.is_none() || step.associativity.is_some())
+        });
+        if has_precedence {
+            warnings.push(LintWarning {
+                kind: LintKind::RedundantPrecedence,
+                message: format!(
+                    "The precedence in rule `{}` is never used to resolve a conflict",
+                    variable.name
+                ),
+                names: vec![variable.name.clone()],
+            });
+        }
+    }
+
+    let assigned_fields = syntax_grammar
+        .variables
+        .iter()
+        .flat_map(|variable| &variable.productions)
+        .chain(&inlines.productions)
+        .flat_map(|production| &production.steps)
+        .filter_map(|step| step.field_name.as_deref())
+        .collect::<HashSet<_>>();
+    let mut declared_fields = Vec::new();
+    for variable in &input_grammar.variables {
+        collect_field_names(&variable.rule, &mut declared_fields);
+    }
+    declared_fields.sort_unstable();
+    declared_fields.dedup();
+    for field_name in declared_fields {
+        if !assigned_fields.contains(field_name) {
+            warnings.push(LintWarning {
+                kind: LintKind::UnusedField,
+                message: format!("Field `{field_name}` is never assigned to a child node"),
+                names: vec![field_name.to_string()],
+            });
+        }
+    }
+
+    for supertype in &syntax_grammar.supertype_symbols {
+        let mut anonymous_tokens = Vec::new();
+        collect_anonymous_subtypes(
+            &syntax_grammar,
+            &lexical_grammar,
+            *supertype,
+            &mut HashSet::new(),
+            &mut anonymous_tokens,
+        );
+        if !anonymous_tokens.is_empty() {
+            let name = &syntax_grammar.variables[supertype.index].name;
+            warnings.push(LintWarning {
+                kind: LintKind::IncompleteSupertype,
+                message: format!(
+                    "Supertype `{name}` has anonymous choices, which are not matched by the supertype in queries: {}",
+                    anonymous_tokens.join(", ")
+                ),
+                names: vec![name.clone()],
+            });
+        }
+    }
+
+    if let Some(word_token) = syntax_grammar.word_token {
+        let word_token_name = symbol_name(word_token);
+        for token in analysis.tokens_shadowed_by_word_token {
+            let name = token_name(&lexical_grammar, token);
+            warnings.push(LintWarning {
+                kind: LintKind::ShadowedToken,
+                message: format!("Token {name} is shadowed by the word token `{word_token_name}`"),
+                names: vec![symbol_name(token)],
+            });
+        }
+    }
+
+    Ok(warnings)
+}
+
+// Find all of the symbols that can appear in a syntax tree: those that are
+// reachable from the start rule, from the extras, or from the word token.
+fn reachable_symbols(grammar: &SyntaxGrammar) -> HashSet<Symbol> {
+    let mut stack = vec![Symbol::non_terminal(0)];
+    stack.extend(&grammar.extra_symbols);
+    stack.extend(grammar.word_token);
+    stack.extend(
+        grammar
+            .external_tokens
+            .iter()
+            .filter_map(|token| token.corresponding_internal_token),
+    );
+
+    let mut result = HashSet::new();
+    while let Some(symbol) = stack.pop() {
+        if !result.insert(symbol) || !symbol.is_non_terminal() {
+            continue;
+        }
+        for production in &grammar.variables[symbol.index].productions {
+            stack.extend(production.steps.iter().map(|step| step.symbol));
+        }
+    }
+    result
+}
+
+// Find the choices of a supertype that are anonymous nodes, looking through any
+// hidden rules that the supertype's choices refer to.
+fn collect_anonymous_subtypes(
+    syntax_grammar: &SyntaxGrammar,
+    lexical_grammar: &LexicalGrammar,
+    symbol: Symbol,
+    visited: &mut HashSet<Symbol>,
+    result: &mut Vec<String>,
+) {
+    if !visited.insert(symbol) {
+        return;
+    }
+    for production in &syntax_grammar.variables[symbol.index].productions {
+        for step in &production.steps {
+            if let Some(alias) = &step.alias {
+                if !alias.is_named {
+                    result.push(format!("'{}'", alias.value));
+                }
+            } else if step.symbol.is_terminal() {
+                if lexical_grammar.variables[step.symbol.index].kind == VariableType::Anonymous {
+                    result.push(token_name(lexical_grammar, step.symbol));
+                }
+            } else if step.symbol.is_non_terminal()
+                && syntax_grammar.variables[step.symbol.index].kind == VariableType::Hidden
+            {
+                collect_anonymous_subtypes(
+                    syntax_grammar,
+                    lexical_grammar,
+                    step.symbol,
+                    visited,
+                    result,
+                );
+            }
+        }
+    }
+}
+
+fn collect_field_names<'a>(rule: &'a Rule, result: &mut Vec<&'a str>) {
+    match rule {
+        Rule::Metadata { params, rule } => {
+            if let Some(field_name) = &params.field_name {
+                result.push(field_name);
+            }
+            collect_field_names(rule, result);
+        }
+        Rule::Choice(elements) | Rule::Seq(elements) => {
+            for element in elements {
+                collect_field_names(element, result);
+            }
+        }
+        Rule::Repeat(rule) => collect_field_names(rule, result),
+        _ => {}
+    }
+}
+
+fn token_name(lexical_grammar: &LexicalGrammar, token: Symbol) -> String {
+    let variable = &lexical_grammar.variables[token.index];
+    if variable.kind == VariableType::Anonymous {
+        format!("'{}'", variable.name)
+    } else {
+        format!("`{}`", variable.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::builder::{
+        blank, field, pattern, prec, prec_left, repeat, string, sym, token, GrammarBuilder,
+    };
+    use crate::{choice, seq};
+
+    #[test]
+    fn test_lint_clean_grammar() {
+        let grammar = GrammarBuilder::new("test")
+            .rule("program", repeat(sym("_expression")))
+            .rule("_expression", choice![sym("sum"), sym("number")])
+            .rule(
+                "sum",
+                prec_left(
+                    1,
+                    seq![
+                        field("left", sym("_expression")),
+                        "+",
+                        field("right", sym("_expression")),
+                    ],
+                ),
+            )
+            .rule("number", pattern(r"\d+"))
+            .supertype("_expression")
+            .build()
+            .unwrap();
+
+        assert_eq!(lint_grammar(&grammar).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_lint_warnings() {
+        let grammar = GrammarBuilder::new("test")
+            .rule("program", repeat(sym("_statement")))
+            .rule("_statement", choice![sym("call"), sym("type_name"), ";"])
+            .rule(
+                "call",
+                prec(
+                    2,
+                    seq![
+                        field("function", sym("identifier")),
+                        token(seq![field("open", string("(")), ")"]),
+                    ],
+                ),
+            )
+            .rule("identifier", pattern("[a-zA-Z_]+"))
+            .rule("type_name", pattern("[A-Z][a-z0-9]*"))
+            .rule("unused", seq![sym("identifier"), sym("identifier")])
+            .rule("nothing", blank())
+            .conflict(["call", "program"])
+            .supertype("_statement")
+            .word("identifier")
+            .build()
+            .unwrap();
+
+        let mut warnings = lint_grammar(&grammar)
+            .unwrap()
+            .into_iter()
+            .map(|warning| (warning.kind, warning.message))
+            .collect::<Vec<_>>();
+        warnings.sort_unstable();
+        assert_eq!(
+            warnings,
+            [
+                (
+                    LintKind::UnreachableRule,
+                    "Rule `nothing` is not reachable from the start rule `program`".to_string()
+                ),
+                (
+                    LintKind::UnreachableRule,
+                    "Rule `unused` is not reachable from the start rule `program`".to_string()
+                ),
+                (
+                    LintKind::EmptyRule,
+                    "Rule `nothing` can only match the empty string".to_string()
+                ),
+                (
+                    LintKind::UnnecessaryConflict,
+                    "The conflict [`program`, `call`] does not correspond to any actual conflict"
+                        .to_string()
+                ),
+                (
+                    LintKind::RedundantPrecedence,
+                    "The precedence in rule `call` is never used to resolve a conflict"
+                        .to_string()
+                ),
+                (
+                    LintKind::UnusedField,
+                    "Field `open` is never assigned to a child node".to_string()
+                ),
+                (
+                    LintKind::IncompleteSupertype,
+                    "Supertype `_statement` has anonymous choices, which are not matched by the supertype in queries: ';'"
+                        .to_string()
+                ),
+                (
+                    LintKind::ShadowedToken,
+                    "Token `type_name` is shadowed by the word token `identifier`".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -6,26 +6,32 @@ use std::{
 };
 
 use anyhow::{anyhow, Context, Result};
-use build_tables::build_tables;
+use build_tables::{build_tables, ParseTableCache};
 use grammar_files::path_in_ignore;
 use grammars::InputGrammar;
 use lazy_static::lazy_static;
 use parse_grammar::parse_grammar;
 use prepare_grammar::prepare_grammar;
 use regex::{Regex, RegexBuilder};
-use render::render_c_code;
+use render::{render_c_code, render_rust_code};
 use semver::Version;
 
 mod build_tables;
+pub mod builder;
 mod dedup;
+pub mod diagnostics;
+mod dsl;
 mod grammar_files;
 mod grammars;
+pub mod lint;
 mod nfa;
 mod node_types;
+mod parallel;
 pub mod parse_grammar;
 mod prepare_grammar;
 mod render;
 mod rules;
+mod rust_ast;
 mod tables;
 
 pub use grammar_files::lookup_package_json_for_path;
@@ -37,13 +43,31 @@ lazy_static! {
         .unwrap();
 }
 
-struct GeneratedParser {
-    c_code: String,
-    node_types_json: String,
+pub struct GeneratedParser {
+    pub c_code: String,
+    pub node_types_json: String,
 }
 
 pub const ALLOC_HEADER: &str = include_str!("./templates/alloc.h");
 
+/// Options that control the code that is generated for a parser.
+#[derive(Clone, Copy, Default)]
+pub struct RenderOptions {
+    /// Write the parse tables as a Rust module, instead of as C code.
+    pub rust: bool,
+    /// Encode the lexer as tables that are interpreted at runtime, instead of as code.
+    pub table_driven_lexer: bool,
+    /// Also generate a Rust module with a typed view of each of the grammar's node types.
+    pub rust_ast: bool,
+}
+
+struct GeneratedCode {
+    parser_code: String,
+    node_types_json: String,
+    rust_ast: Option<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn generate_parser_in_directory(
     repo_path: &Path,
     grammar_path: Option<&str>,
@@ -51,6 +75,8 @@ pub fn generate_parser_in_directory(
     generate_bindings: bool,
     report_symbol_name: Option<&str>,
     js_runtime: Option<&str>,
+    cache_dir: Option<&Path>,
+    render_options: RenderOptions,
 ) -> Result<()> {
     let mut repo_path = repo_path.to_owned();
     let mut grammar_path = grammar_path;
@@ -105,15 +131,34 @@ pub fn generate_parser_in_directory(
 
     // Parse and preprocess the grammar.
     let input_grammar = parse_grammar(&grammar_json)?;
+    let parse_table_cache = cache_dir.map(|dir| {
+        let grammar_path = grammar_path.map_or(repo_path.join("grammar.js"), PathBuf::from);
+        ParseTableCache::new(dir, &input_grammar.name, &grammar_path)
+    });
 
     // Generate the parser and related files.
-    let GeneratedParser {
-        c_code,
+    let GeneratedCode {
+        parser_code,
         node_types_json,
-    } = generate_parser_for_grammar_with_opts(&input_grammar, abi_version, report_symbol_name)?;
+        rust_ast,
+    } = generate_parser_code(
+        &input_grammar,
+        abi_version,
+        report_symbol_name,
+        parse_table_cache.as_ref(),
+        render_options,
+    )?;
 
-    write_file(&src_path.join("parser.c"), c_code)?;
+    let parser_file_name = if render_options.rust {
+        "parser.rs"
+    } else {
+        "parser.c"
+    };
+    write_file(&src_path.join(parser_file_name), parser_code)?;
     write_file(&src_path.join("node-types.json"), node_types_json)?;
+    if let Some(rust_ast) = rust_ast {
+        write_file(&src_path.join("ast.rs"), rust_ast)?;
+    }
     write_file(&header_path.join("alloc.h"), ALLOC_HEADER)?;
     write_file(&header_path.join("array.h"), tree_sitter::ARRAY_HEADER)?;
     write_file(&header_path.join("parser.h"), tree_sitter::PARSER_HEADER)?;
@@ -128,16 +173,66 @@ pub fn generate_parser_in_directory(
 pub fn generate_parser_for_grammar(grammar_json: &str) -> Result<(String, String)> {
     let grammar_json = JSON_COMMENT_REGEX.replace_all(grammar_json, "\n");
     let input_grammar = parse_grammar(&grammar_json)?;
-    let parser =
-        generate_parser_for_grammar_with_opts(&input_grammar, tree_sitter::LANGUAGE_VERSION, None)?;
+    let parser = generate_parser_for_grammar_with_opts(
+        &input_grammar,
+        tree_sitter::LANGUAGE_VERSION,
+        None,
+        None,
+    )?;
     Ok((input_grammar.name.clone(), parser.c_code))
 }
 
+/// Generate a parser for the given grammar, with the given options for the generated
+/// code. When the parser is generated as a Rust module, the module exports the same
+/// `tree_sitter_{name}` function as the C code would.
+pub fn generate_parser_for_grammar_with_render_options(
+    grammar_json: &str,
+    render_options: RenderOptions,
+) -> Result<(String, String)> {
+    let grammar_json = JSON_COMMENT_REGEX.replace_all(grammar_json, "\n");
+    let input_grammar = parse_grammar(&grammar_json)?;
+    let code = generate_parser_code(
+        &input_grammar,
+        tree_sitter::LANGUAGE_VERSION,
+        None,
+        None,
+        render_options,
+    )?;
+    Ok((input_grammar.name, code.parser_code))
+}
+
+/// Generate a parser for a grammar that was constructed directly, for example using
+/// [`builder::GrammarBuilder`].
+pub fn generate_parser_for_input_grammar(input_grammar: &InputGrammar) -> Result<GeneratedParser> {
+    generate_parser_for_grammar_with_opts(input_grammar, tree_sitter::LANGUAGE_VERSION, None, None)
+}
+
 fn generate_parser_for_grammar_with_opts(
     input_grammar: &InputGrammar,
     abi_version: usize,
     report_symbol_name: Option<&str>,
+    parse_table_cache: Option<&ParseTableCache>,
 ) -> Result<GeneratedParser> {
+    let code = generate_parser_code(
+        input_grammar,
+        abi_version,
+        report_symbol_name,
+        parse_table_cache,
+        RenderOptions::default(),
+    )?;
+    Ok(GeneratedParser {
+        c_code: code.parser_code,
+        node_types_json: code.node_types_json,
+    })
+}
+
+fn generate_parser_code(
+    input_grammar: &InputGrammar,
+    abi_version: usize,
+    report_symbol_name: Option<&str>,
+    parse_table_cache: Option<&ParseTableCache>,
+    render_options: RenderOptions,
+) -> Result<GeneratedCode> {
     let (syntax_grammar, lexical_grammar, inlines, simple_aliases) =
         prepare_grammar(input_grammar)?;
     let variable_info =
@@ -148,6 +243,9 @@ fn generate_parser_for_grammar_with_opts(
         &simple_aliases,
         &variable_info,
     );
+    let rust_ast = render_options
+        .rust_ast
+        .then(|| rust_ast::generate_rust_ast(&input_grammar.name, &node_types_json));
     let tables = build_tables(
         &syntax_grammar,
         &lexical_grammar,
@@ -155,18 +253,26 @@ fn generate_parser_for_grammar_with_opts(
         &variable_info,
         &inlines,
         report_symbol_name,
+        parse_table_cache,
     )?;
-    let c_code = render_c_code(
+    let render = if render_options.rust {
+        render_rust_code
+    } else {
+        render_c_code
+    };
+    let parser_code = render(
         &input_grammar.name,
         tables,
         syntax_grammar,
         lexical_grammar,
         simple_aliases,
         abi_version,
+        render_options.table_driven_lexer,
     );
-    Ok(GeneratedParser {
-        c_code,
+    Ok(GeneratedCode {
+        parser_code,
         node_types_json: serde_json::to_string_pretty(&node_types_json).unwrap(),
+        rust_ast,
     })
 }
 
@@ -189,6 +295,10 @@ pub fn load_grammar_file(grammar_path: &Path, js_runtime: Option<&str>) -> Resul
 fn load_js_grammar_file(grammar_path: &Path, js_runtime: Option<&str>) -> Result<String> {
     let grammar_path = fs::canonicalize(grammar_path)?;
 
+    if js_runtime == Some("native") {
+        return dsl::load_grammar_file(&grammar_path);
+    }
+
     let js_runtime = js_runtime.unwrap_or("node");
 
     let mut node_process = Command::new(js_runtime)
@@ -0,0 +1,174 @@
+use std::{cell::Cell, num::NonZeroUsize, thread};
+
+thread_local! {
+    // The number of threads to use for every parallel map, regardless of the number
+    // of items. This is only set in tests, which compare the output of the parallel
+    // and sequential paths.
+    static THREAD_COUNT: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Apply `f` to each of the given items, returning the results in the same order as
+/// the items, so that the output does not depend on how the work was scheduled.
+///
+/// The items are distributed among as many threads as are available, as long as
+/// each thread receives at least `min_items_per_thread` items. Each thread creates
+/// its own scratch state by calling `init`. Items are assigned to threads in an
+/// interleaved fashion, so that the threads receive similar amounts of work even
+/// when the cost of the items increases steadily, as it does for the rows of a
+/// triangular matrix.
+pub fn parallel_map<T, S, R>(
+    items: &[T],
+    min_items_per_thread: usize,
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, &T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    if let Some(thread_count) = THREAD_COUNT.get() {
+        return map_on_threads(items, thread_count, init, f);
+    }
+    let thread_count = thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(items.len() / min_items_per_thread.max(1));
+    map_on_threads(items, thread_count, init, f)
+}
+
+fn map_on_threads<T, S, R>(
+    items: &[T],
+    thread_count: usize,
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, &T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    if thread_count <= 1 {
+        let mut state = init();
+        return items.iter().map(|item| f(&mut state, item)).collect();
+    }
+
+    let results_by_thread = thread::scope(|scope| {
+        let handles = (0..thread_count)
+            .map(|thread_index| {
+                let (init, f) = (&init, &f);
+                scope.spawn(move || {
+                    let mut state = init();
+                    items
+                        .iter()
+                        .skip(thread_index)
+                        .step_by(thread_count)
+                        .map(|item| f(&mut state, item))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut iters = results_by_thread
+        .into_iter()
+        .map(Vec::into_iter)
+        .collect::<Vec<_>>();
+    (0..items.len())
+        .map(|i| iters[i % thread_count].next().unwrap())
+        .collect()
+}
+
+/// Run `f`, using the given number of threads for each parallel map that it performs
+/// on the current thread.
+#[cfg(test)]
+pub fn with_thread_count<R>(thread_count: usize, f: impl FnOnce() -> R) -> R {
+    let previous = THREAD_COUNT.replace(Some(thread_count));
+    let result = f();
+    THREAD_COUNT.set(previous);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::generate::{
+        build_tables::build_tables, generate_parser_for_grammar_with_opts, load_grammar_file,
+        node_types, parse_grammar::parse_grammar, prepare_grammar::prepare_grammar,
+    };
+
+    #[test]
+    fn test_parallel_map_preserves_order() {
+        let items = (0..1000).collect::<Vec<usize>>();
+        let expected = items.iter().map(|item| item * item).collect::<Vec<_>>();
+        for thread_count in [1, 3, 8] {
+            let results = map_on_threads(&items, thread_count, || (), |(), item| item * item);
+            assert_eq!(results, expected);
+        }
+        assert!(parallel_map(&[] as &[usize], 1, || (), |(), item| *item).is_empty());
+    }
+
+    #[test]
+    fn test_parallel_output_matches_sequential_output() {
+        let grammars_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .unwrap()
+            .join("test/fixtures/test_grammars");
+        for entry in fs::read_dir(grammars_dir).unwrap() {
+            let grammar_path = entry.unwrap().path().join("grammar.js");
+            if !grammar_path.exists() {
+                continue;
+            }
+            let grammar_json = load_grammar_file(&grammar_path, None).unwrap();
+            let input_grammar = parse_grammar(&grammar_json).unwrap();
+
+            let generate = |thread_count| {
+                with_thread_count(thread_count, || {
+                    let (syntax_grammar, lexical_grammar, inlines, simple_aliases) =
+                        prepare_grammar(&input_grammar)?;
+                    let variable_info = node_types::get_variable_info(
+                        &syntax_grammar,
+                        &lexical_grammar,
+                        &simple_aliases,
+                    )?;
+                    let tables = build_tables(
+                        &syntax_grammar,
+                        &lexical_grammar,
+                        &simple_aliases,
+                        &variable_info,
+                        &inlines,
+                        None,
+                        None,
+                    )?;
+                    let parser = generate_parser_for_grammar_with_opts(
+                        &input_grammar,
+                        tree_sitter::LANGUAGE_VERSION,
+                        None,
+                        None,
+                    )?;
+                    anyhow::Ok((
+                        tables.parse_table,
+                        tables.main_lex_table,
+                        tables.keyword_lex_table,
+                        parser.c_code,
+                    ))
+                })
+                .map_err(|e| e.to_string())
+            };
+
+            // Some of the grammars are expected to fail, and they should fail in the
+            // same way.
+            let sequential = generate(1);
+            for thread_count in [2, 5] {
+                let parallel = generate(thread_count);
+                assert!(
+                    parallel == sequential,
+                    "output of {grammar_path:?} differs with {thread_count} threads"
+                );
+            }
+        }
+    }
+}
@@ -165,20 +165,7 @@ fn parse_rule(json: RuleJSON) -> Rule {
         RuleJSON::STRING { value } => Rule::String(value),
         RuleJSON::PATTERN { value, flags } => Rule::Pattern(
             value,
-            flags.map_or(String::new(), |f| {
-                f.matches(|c| {
-                    if c == 'i' {
-                        true
-                    } else {
-                        // silently ignore unicode flags
-                        if c != 'u' && c != 'v' {
-                            eprintln!("Warning: unsupported flag {c}");
-                        }
-                        false
-                    }
-                })
-                .collect()
-            }),
+            flags.map_or(String::new(), |f| parse_pattern_flags(&f)),
         ),
         RuleJSON::SYMBOL { name } => Rule::NamedSymbol(name),
         RuleJSON::CHOICE { members } => Rule::choice(members.into_iter().map(parse_rule).collect()),
@@ -203,6 +190,22 @@ fn parse_rule(json: RuleJSON) -> Rule {
     }
 }
 
+pub(super) fn parse_pattern_flags(flags: &str) -> String {
+    flags
+        .matches(|c| {
+            if c == 'i' {
+                true
+            } else {
+                // silently ignore unicode flags
+                if c != 'u' && c != 'v' {
+                    eprintln!("Warning: unsupported flag {c}");
+                }
+                false
+            }
+        })
+        .collect()
+}
+
 impl From<PrecedenceValueJSON> for Precedence {
     fn from(val: PrecedenceValueJSON) -> Self {
         match val {
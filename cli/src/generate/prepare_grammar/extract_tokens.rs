@@ -1,9 +1,10 @@
 use std::{collections::HashMap, mem};
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 
 use super::{ExtractedLexicalGrammar, ExtractedSyntaxGrammar, InternedGrammar};
 use crate::generate::{
+    diagnostics::{Diagnostic, DiagnosticKind},
     grammars::{ExternalToken, Variable, VariableType},
     rules::{MetadataParams, Rule, Symbol, SymbolType},
 };
@@ -109,10 +110,15 @@ pub(super) fn extract_tokens(
         let rule = symbol_replacer.replace_symbols_in_rule(&external_token.rule);
         if let Rule::Symbol(symbol) = rule {
             if symbol.is_non_terminal() {
-                return Err(anyhow!(
-                    "Rule '{}' cannot be used as both an external token and a non-terminal rule",
-                    &variables[symbol.index].name,
-                ));
+                let name = &variables[symbol.index].name;
+                return Err(Diagnostic::new(
+                    DiagnosticKind::InvalidExternalToken,
+                    format!(
+                        "Rule '{name}' cannot be used as both an external token and a non-terminal rule",
+                    ),
+                )
+                .with_rules([name])
+                .into());
             }
 
             if symbol.is_external() {
@@ -129,9 +135,11 @@ pub(super) fn extract_tokens(
                 });
             }
         } else {
-            return Err(anyhow!(
-                "Non-symbol rules cannot be used as external tokens"
-            ));
+            return Err(Diagnostic::new(
+                DiagnosticKind::InvalidExternalToken,
+                "Non-symbol rules cannot be used as external tokens",
+            )
+            .into());
         }
     }
 
@@ -139,10 +147,13 @@ pub(super) fn extract_tokens(
     if let Some(token) = grammar.word_token {
         let token = symbol_replacer.replace_symbol(token);
         if token.is_non_terminal() {
-            return Err(anyhow!(
-                "Non-terminal symbol '{}' cannot be used as the word token",
-                &variables[token.index].name
-            ));
+            let name = &variables[token.index].name;
+            return Err(Diagnostic::new(
+                DiagnosticKind::InvalidWordToken,
+                format!("Non-terminal symbol '{name}' cannot be used as the word token"),
+            )
+            .with_rules([name])
+            .into());
         }
         word_token = Some(token);
     }
@@ -1,7 +1,8 @@
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 
 use super::InternedGrammar;
 use crate::generate::{
+    diagnostics::{Diagnostic, DiagnosticKind},
     grammars::{InputGrammar, Variable, VariableType},
     rules::{Rule, Symbol},
 };
@@ -10,7 +11,12 @@ pub(super) fn intern_symbols(grammar: &InputGrammar) -> Result<InternedGrammar>
     let interner = Interner { grammar };
 
     if variable_type_for_name(&grammar.variables[0].name) == VariableType::Hidden {
-        return Err(anyhow!("A grammar's start rule must be visible."));
+        return Err(Diagnostic::new(
+            DiagnosticKind::InvisibleStartRule,
+            "A grammar's start rule must be visible.",
+        )
+        .with_rules([&grammar.variables[0].name])
+        .into());
     }
 
     let mut variables = Vec::with_capacity(grammar.variables.len());
@@ -18,7 +24,12 @@ pub(super) fn intern_symbols(grammar: &InputGrammar) -> Result<InternedGrammar>
         variables.push(Variable {
             name: variable.name.clone(),
             kind: variable_type_for_name(&variable.name),
-            rule: interner.intern_rule(&variable.rule)?,
+            rule: interner.intern_rule(&variable.rule).map_err(|error| {
+                match error.downcast::<Diagnostic>() {
+                    Ok(diagnostic) => diagnostic.with_rules([&variable.name]).into(),
+                    Err(error) => error,
+                }
+            })?,
         });
     }
 
@@ -43,7 +54,7 @@ pub(super) fn intern_symbols(grammar: &InputGrammar) -> Result<InternedGrammar>
         supertype_symbols.push(
             interner
                 .intern_name(supertype_symbol_name)
-                .ok_or_else(|| anyhow!("Undefined symbol `{supertype_symbol_name}`"))?,
+                .ok_or_else(|| undefined_symbol(supertype_symbol_name))?,
         );
     }
 
@@ -54,7 +65,7 @@ pub(super) fn intern_symbols(grammar: &InputGrammar) -> Result<InternedGrammar>
             interned_conflict.push(
                 interner
                     .intern_name(name)
-                    .ok_or_else(|| anyhow!("Undefined symbol `{name}`"))?,
+                    .ok_or_else(|| undefined_symbol(name))?,
             );
         }
         expected_conflicts.push(interned_conflict);
@@ -72,7 +83,7 @@ pub(super) fn intern_symbols(grammar: &InputGrammar) -> Result<InternedGrammar>
         word_token = Some(
             interner
                 .intern_name(name)
-                .ok_or_else(|| anyhow!("Undefined symbol `{name}`"))?,
+                .ok_or_else(|| undefined_symbol(name))?,
         );
     }
 
@@ -94,6 +105,15 @@ pub(super) fn intern_symbols(grammar: &InputGrammar) -> Result<InternedGrammar>
     })
 }
 
+fn undefined_symbol(name: &str) -> anyhow::Error {
+    Diagnostic::new(
+        DiagnosticKind::UndefinedSymbol,
+        format!("Undefined symbol `{name}`"),
+    )
+    .with_symbols([name])
+    .into()
+}
+
 struct Interner<'a> {
     grammar: &'a InputGrammar,
 }
@@ -122,7 +142,7 @@ impl<'a> Interner<'a> {
             }),
 
             Rule::NamedSymbol(name) => self.intern_name(name).map_or_else(
-                || Err(anyhow!("Undefined symbol `{name}`")),
+                || Err(undefined_symbol(name)),
                 |symbol| Ok(Rule::Symbol(symbol)),
             ),
 
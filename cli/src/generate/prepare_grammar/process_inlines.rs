@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 
 use crate::generate::{
+    diagnostics::{Diagnostic, DiagnosticKind},
     grammars::{InlinedProductionMap, LexicalGrammar, Production, ProductionStep, SyntaxGrammar},
     rules::SymbolType,
 };
@@ -192,27 +193,27 @@ pub(super) fn process_inlines(
     lexical_grammar: &LexicalGrammar,
 ) -> Result<InlinedProductionMap> {
     for symbol in &grammar.variables_to_inline {
-        match symbol.kind {
+        let (name, message) = match symbol.kind {
             SymbolType::External => {
-                return Err(anyhow!(
-                    "External token `{}` cannot be inlined",
-                    grammar.external_tokens[symbol.index].name
-                ))
+                let name = &grammar.external_tokens[symbol.index].name;
+                (name, format!("External token `{name}` cannot be inlined"))
             }
             SymbolType::Terminal => {
-                return Err(anyhow!(
-                    "Token `{}` cannot be inlined",
-                    lexical_grammar.variables[symbol.index].name,
-                ))
+                let name = &lexical_grammar.variables[symbol.index].name;
+                (name, format!("Token `{name}` cannot be inlined"))
             }
             SymbolType::NonTerminal if symbol.index == 0 => {
-                return Err(anyhow!(
-                    "Rule `{}` cannot be inlined because it is the first rule",
-                    grammar.variables[symbol.index].name,
-                ))
+                let name = &grammar.variables[symbol.index].name;
+                (
+                    name,
+                    format!("Rule `{name}` cannot be inlined because it is the first rule"),
+                )
             }
-            _ => {}
-        }
+            _ => continue,
+        };
+        return Err(Diagnostic::new(DiagnosticKind::InvalidInlineRule, message)
+            .with_rules([name])
+            .into());
     }
 
     Ok(InlinedProductionMapBuilder {
@@ -0,0 +1,238 @@
+use std::{collections::HashMap, fmt::Write};
+
+use super::{Generator, Target};
+use crate::generate::{
+    nfa::CharacterSet,
+    tables::{LexState, LexTable},
+};
+
+/// The character ranges that are referenced by the transitions of table-driven lexers.
+/// All of the lex functions in a parser share a single array of ranges, and identical
+/// character sets, such as the large character sets that are shared between tokens,
+/// are only stored once.
+#[derive(Default)]
+pub(super) struct LexCharacterRanges {
+    ranges: Vec<(char, char)>,
+    indices: HashMap<CharacterSet, usize>,
+}
+
+struct LexTransition {
+    range_index: usize,
+    range_count: usize,
+    state: usize,
+    skip: bool,
+}
+
+impl LexCharacterRanges {
+    fn index(&mut self, characters: &CharacterSet) -> usize {
+        if let Some(index) = self.indices.get(characters) {
+            return *index;
+        }
+        let index = self.ranges.len();
+        self.ranges.extend(
+            characters
+                .ranges()
+                .map(|range| (*range.start(), *range.end())),
+        );
+        self.indices.insert(characters.clone(), index);
+        index
+    }
+}
+
+impl Generator {
+    /// Writes a lex function that passes a table of states and transitions to the generic
+    /// interpreter in the runtime, instead of encoding each state as code.
+    pub(super) fn add_lex_table_function(&mut self, name: &str, lex_table: LexTable) {
+        let mut transitions = Vec::new();
+        let mut transition_slices = Vec::with_capacity(lex_table.states.len());
+        for state in &lex_table.states {
+            let state_transitions = self.lex_state_transitions(state);
+            transition_slices.push((transitions.len(), state_transitions.len()));
+            transitions.extend(state_transitions);
+        }
+
+        match self.target {
+            Target::C => add_line!(
+                self,
+                "static const TSLexTransition {name}_transitions[] = {{"
+            ),
+            Target::Rust => add_line!(
+                self,
+                "static {name}_transitions: [TSLexTransition; {}] = [",
+                transitions.len()
+            ),
+        }
+        indent!(self);
+        if transitions.is_empty() && self.target == Target::C {
+            add_line!(self, "{{0}},");
+        }
+        for transition in &transitions {
+            let LexTransition {
+                range_index,
+                range_count,
+                state,
+                skip,
+            } = transition;
+            match self.target {
+                Target::C if *skip => {
+                    add_line!(self, "{{{range_index}, {range_count}, {state}, true}},");
+                }
+                Target::C => add_line!(self, "{{{range_index}, {range_count}, {state}}},"),
+                Target::Rust => add_line!(
+                    self,
+                    "lex_transition({range_index}, {range_count}, {state}, {skip}),"
+                ),
+            }
+        }
+        dedent!(self);
+        self.add_lex_table_array_end();
+
+        let state_count = lex_table.states.len();
+        match self.target {
+            Target::C => add_line!(
+                self,
+                "static const TSLexTableState {name}_states[{state_count}] = {{"
+            ),
+            Target::Rust => add_line!(
+                self,
+                "static {name}_states: [TSLexTableState; {state_count}] = ["
+            ),
+        }
+        indent!(self);
+        for (state, (transition_index, transition_count)) in
+            lex_table.states.iter().zip(transition_slices)
+        {
+            let eof_state = state
+                .eof_action
+                .as_ref()
+                .map_or("TS_LEX_NO_STATE".to_string(), |action| {
+                    action.state.to_string()
+                });
+            let accept_symbol = state.accept_action.map(|symbol| &self.symbol_ids[&symbol]);
+            match (self.target, accept_symbol) {
+                (Target::C, Some(symbol)) => add_line!(
+                    self,
+                    "{{{transition_index}, {transition_count}, {eof_state}, {symbol}, true}},"
+                ),
+                (Target::C, None) => add_line!(
+                    self,
+                    "{{{transition_index}, {transition_count}, {eof_state}}},"
+                ),
+                (Target::Rust, Some(symbol)) => add_line!(
+                    self,
+                    "lex_table_state({transition_index}, {transition_count}, {eof_state}, Some({symbol})),"
+                ),
+                (Target::Rust, None) => add_line!(
+                    self,
+                    "lex_table_state({transition_index}, {transition_count}, {eof_state}, None),"
+                ),
+            }
+        }
+        dedent!(self);
+        self.add_lex_table_array_end();
+
+        match self.target {
+            Target::C => {
+                add_line!(
+                    self,
+                    "static bool {name}(TSLexer *lexer, TSStateId state) {{"
+                );
+                indent!(self);
+                add_line!(
+                    self,
+                    "return ts_lex_with_table(lexer, state, {name}_states, {state_count}, {name}_transitions, ts_lex_character_ranges);",
+                );
+            }
+            Target::Rust => {
+                add_line!(
+                    self,
+                    "unsafe extern \"C\" fn {name}(lexer: *mut TSLexer, state: TSStateId) -> bool {{",
+                );
+                indent!(self);
+                add_line!(
+                    self,
+                    "lex_with_table(lexer, state, &{name}_states, &{name}_transitions, &ts_lex_character_ranges)",
+                );
+            }
+        }
+        dedent!(self);
+        add_line!(self, "}}");
+        add_line!(self, "");
+    }
+
+    /// Writes the character ranges that are referenced by the table-driven lex functions.
+    pub(super) fn add_lex_character_ranges(&mut self) {
+        let mut ranges = std::mem::take(&mut self.lex_character_ranges.ranges);
+        if ranges.is_empty() && self.target == Target::C {
+            // Work around MSVC's intolerance of empty array initializers.
+            ranges.push(('\0', '\0'));
+        }
+        match self.target {
+            // The array is not `const`, because `set_contains` takes a mutable pointer.
+            Target::C => add_line!(
+                self,
+                "static TSCharacterRange ts_lex_character_ranges[] = {{"
+            ),
+            Target::Rust => add_line!(
+                self,
+                "static ts_lex_character_ranges: [(i32, i32); {}] = [",
+                ranges.len()
+            ),
+        }
+        indent!(self);
+        let (open, close) = match self.target {
+            Target::C => ("{", "}"),
+            Target::Rust => ("(", ")"),
+        };
+        for (ix, (start, end)) in ranges.into_iter().enumerate() {
+            let column = ix % 8;
+            if column == 0 {
+                if ix > 0 {
+                    add!(self, "\n");
+                }
+                add_whitespace!(self);
+            } else {
+                add!(self, " ");
+            }
+            add!(self, "{open}");
+            self.add_character(start);
+            add!(self, ", ");
+            self.add_character(end);
+            add!(self, "{close},");
+        }
+        add!(self, "\n");
+        dedent!(self);
+        self.add_lex_table_array_end();
+    }
+
+    fn lex_state_transitions(&mut self, state: &LexState) -> Vec<LexTransition> {
+        let mut transitions = Vec::with_capacity(state.advance_actions.len());
+        let mut ruled_out_chars = CharacterSet::empty();
+        for (chars, action) in &state.advance_actions {
+            // As in the generated lex functions, a transition whose characters are all
+            // ruled out by the preceding transitions doesn't need to be checked.
+            let (range_index, range_count) = if chars.simplify_ignoring(&ruled_out_chars).is_empty()
+            {
+                (0, 0)
+            } else {
+                (self.lex_character_ranges.index(chars), chars.range_count())
+            };
+            ruled_out_chars = ruled_out_chars.add(chars);
+            transitions.push(LexTransition {
+                range_index,
+                range_count,
+                state: action.state,
+                skip: !action.in_main_token,
+            });
+        }
+        transitions
+    }
+
+    fn add_lex_table_array_end(&mut self) {
+        match self.target {
+            Target::C => add_line!(self, "}};"),
+            Target::Rust => add_line!(self, "];"),
+        }
+        add_line!(self, "");
+    }
+}
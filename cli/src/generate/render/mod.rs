@@ -1,10 +1,11 @@
 use std::{
     cmp,
     collections::{HashMap, HashSet},
-    fmt::Write,
+    fmt::{self, Write},
     mem::swap,
 };
 
+use self::lex_table::LexCharacterRanges;
 use super::{
     build_tables::Tables,
     grammars::{ExternalToken, LexicalGrammar, SyntaxGrammar, VariableType},
@@ -36,11 +37,11 @@ macro_rules! add_whitespace {
 }
 
 macro_rules! add_line {
-    ($this: tt, $($arg: tt)*) => {
+    ($this: tt, $($arg: tt)*) => {{
         add_whitespace!($this);
         $this.buffer.write_fmt(format_args!($($arg)*)).unwrap();
         $this.buffer += "\n";
-    }
+    }}
 }
 
 macro_rules! indent {
@@ -56,7 +57,18 @@ macro_rules! dedent {
     };
 }
 
+mod lex_table;
+mod rust;
+
+/// The language in which the parser is generated.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    C,
+    Rust,
+}
+
 struct Generator {
+    target: Target,
     buffer: String,
     indent_level: usize,
     language_name: String,
@@ -65,6 +77,8 @@ struct Generator {
     keyword_lex_table: LexTable,
     large_character_sets: Vec<(Option<Symbol>, CharacterSet)>,
     large_character_set_info: Vec<LargeCharacterSetInfo>,
+    table_driven_lexer: bool,
+    lex_character_ranges: LexCharacterRanges,
     large_state_count: usize,
     keyword_capture_token: Option<Symbol>,
     syntax_grammar: SyntaxGrammar,
@@ -81,6 +95,32 @@ struct Generator {
     abi_version: usize,
 }
 
+struct ParseTableLayout {
+    large_states: Vec<Vec<(Symbol, ParseTableValue)>>,
+    small_states: Vec<Vec<(ParseTableValue, Vec<Symbol>)>>,
+    small_state_indices: Vec<usize>,
+    small_parse_table_len: usize,
+    parse_actions: Vec<(usize, ParseTableEntry)>,
+    parse_actions_len: usize,
+}
+
+/// A value in the parse table: either the state to go to after reducing a non-terminal,
+/// or the index of a list of actions to perform for a terminal.
+#[derive(Clone, Copy)]
+enum ParseTableValue {
+    State(usize),
+    Actions(usize),
+}
+
+impl fmt::Display for ParseTableValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::State(state) => write!(f, "STATE({state})"),
+            Self::Actions(id) => write!(f, "ACTIONS({id})"),
+        }
+    }
+}
+
 struct LargeCharacterSetInfo {
     constant_name: String,
     is_used: bool,
@@ -113,28 +153,7 @@ impl Generator {
             self.add_primary_state_id_list();
         }
 
-        let buffer_offset_before_lex_functions = self.buffer.len();
-
-        let mut main_lex_table = LexTable::default();
-        swap(&mut main_lex_table, &mut self.main_lex_table);
-        self.add_lex_function("ts_lex", main_lex_table);
-
-        if self.keyword_capture_token.is_some() {
-            let mut keyword_lex_table = LexTable::default();
-            swap(&mut keyword_lex_table, &mut self.keyword_lex_table);
-            self.add_lex_function("ts_lex_keywords", keyword_lex_table);
-        }
-
-        // Once the lex functions are generated, and we've determined which large
-        // character sets are actually used, we can generate the large character set
-        // constants. Insert them into the output buffer before the lex functions.
-        let lex_functions = self.buffer[buffer_offset_before_lex_functions..].to_string();
-        self.buffer.truncate(buffer_offset_before_lex_functions);
-        for ix in 0..self.large_character_sets.len() {
-            self.add_character_set(ix);
-        }
-        self.buffer.push_str(&lex_functions);
-
+        self.add_lex_functions();
         self.add_lex_modes_list();
         self.add_parse_table();
 
@@ -154,6 +173,12 @@ impl Generator {
         for i in 0..self.parse_table.symbols.len() {
             self.assign_symbol_id(self.parse_table.symbols[i], &mut symbol_identifiers);
         }
+        self.symbol_order.insert(Symbol::end(), 0);
+        for symbol in &self.parse_table.symbols {
+            if *symbol != Symbol::end() {
+                self.symbol_order.insert(*symbol, self.symbol_order.len());
+            }
+        }
         self.symbol_ids.insert(
             Symbol::end_of_nonterminal_extra(),
             self.symbol_ids[&Symbol::end()].clone(),
@@ -297,7 +322,7 @@ impl Generator {
         // Compiling large lexer functions can be very slow. Disabling optimizations
         // is not ideal, but only a very small fraction of overall parse time is
         // spent lexing, so the performance impact of this is negligible.
-        if self.main_lex_table.states.len() > 300 {
+        if self.main_lex_table.states.len() > 300 && !self.table_driven_lexer {
             add_line!(self, "#ifdef _MSC_VER");
             add_line!(self, "#pragma optimize(\"\", off)");
             add_line!(self, "#elif defined(__clang__)");
@@ -364,11 +389,9 @@ impl Generator {
     fn add_symbol_enum(&mut self) {
         add_line!(self, "enum ts_symbol_identifiers {{");
         indent!(self);
-        self.symbol_order.insert(Symbol::end(), 0);
         let mut i = 1;
         for symbol in &self.parse_table.symbols {
             if *symbol != Symbol::end() {
-                self.symbol_order.insert(*symbol, i);
                 add_line!(self, "{} = {},", self.symbol_ids[symbol], i);
                 i += 1;
             }
@@ -545,39 +568,15 @@ impl Generator {
     }
 
     fn add_non_terminal_alias_map(&mut self) {
-        let mut alias_ids_by_symbol = HashMap::new();
-        for variable in &self.syntax_grammar.variables {
-            for production in &variable.productions {
-                for step in &production.steps {
-                    if let Some(alias) = &step.alias {
-                        if step.symbol.is_non_terminal()
-                            && Some(alias) != self.default_aliases.get(&step.symbol)
-                            && self.symbol_ids.contains_key(&step.symbol)
-                        {
-                            if let Some(alias_id) = self.alias_ids.get(alias) {
-                                let alias_ids =
-                                    alias_ids_by_symbol.entry(step.symbol).or_insert(Vec::new());
-                                if let Err(i) = alias_ids.binary_search(&alias_id) {
-                                    alias_ids.insert(i, alias_id);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let mut alias_ids_by_symbol = alias_ids_by_symbol.iter().collect::<Vec<_>>();
-        alias_ids_by_symbol.sort_unstable_by_key(|e| e.0);
-
+        let alias_ids_by_symbol = self.non_terminal_alias_ids();
         add_line!(
             self,
             "static const uint16_t ts_non_terminal_alias_map[] = {{"
         );
         indent!(self);
         for (symbol, alias_ids) in alias_ids_by_symbol {
-            let symbol_id = &self.symbol_ids[symbol];
-            let public_symbol_id = &self.symbol_ids[&self.symbol_map[symbol]];
+            let symbol_id = &self.symbol_ids[&symbol];
+            let public_symbol_id = &self.symbol_ids[&self.symbol_map[&symbol]];
             add_line!(self, "{symbol_id}, {},", 1 + alias_ids.len());
             indent!(self);
             add_line!(self, "{public_symbol_id},");
@@ -592,22 +591,13 @@ impl Generator {
         add_line!(self, "");
     }
 
-    /// Produces a list of the "primary state" for every state in the grammar.
-    ///
-    /// The "primary state" for a given state is the first encountered state that behaves
-    /// identically with respect to query analysis. We derive this by keeping track of the `core_id`
-    /// for each state and treating the first state with a given `core_id` as primary.
     fn add_primary_state_id_list(&mut self) {
         add_line!(
             self,
             "static const TSStateId ts_primary_state_ids[STATE_COUNT] = {{"
         );
         indent!(self);
-        let mut first_state_for_each_core_id = HashMap::new();
-        for (idx, state) in self.parse_table.states.iter().enumerate() {
-            let primary_state = first_state_for_each_core_id
-                .entry(state.core_id)
-                .or_insert(idx);
+        for (idx, primary_state) in self.primary_state_ids().into_iter().enumerate() {
             add_line!(self, "[{idx}] = {primary_state},");
         }
         dedent!(self);
@@ -616,35 +606,7 @@ impl Generator {
     }
 
     fn add_field_sequences(&mut self) {
-        let mut flat_field_maps = vec![];
-        let mut next_flat_field_map_index = 0;
-        self.get_field_map_id(
-            Vec::new(),
-            &mut flat_field_maps,
-            &mut next_flat_field_map_index,
-        );
-
-        let mut field_map_ids = Vec::new();
-        for production_info in &self.parse_table.production_infos {
-            if production_info.field_map.is_empty() {
-                field_map_ids.push((0, 0));
-            } else {
-                let mut flat_field_map = Vec::new();
-                for (field_name, locations) in &production_info.field_map {
-                    for location in locations {
-                        flat_field_map.push((field_name.clone(), *location));
-                    }
-                }
-                field_map_ids.push((
-                    self.get_field_map_id(
-                        flat_field_map.clone(),
-                        &mut flat_field_maps,
-                        &mut next_flat_field_map_index,
-                    ),
-                    flat_field_map.len(),
-                ));
-            }
-        }
+        let (field_map_ids, flat_field_maps) = self.field_map_ids();
 
         add_line!(
             self,
@@ -687,7 +649,44 @@ impl Generator {
         add_line!(self, "");
     }
 
+    fn add_lex_functions(&mut self) {
+        let buffer_offset_before_lex_functions = self.buffer.len();
+
+        let mut main_lex_table = LexTable::default();
+        swap(&mut main_lex_table, &mut self.main_lex_table);
+        self.add_lex_function("ts_lex", main_lex_table);
+
+        if self.keyword_capture_token.is_some() {
+            let mut keyword_lex_table = LexTable::default();
+            swap(&mut keyword_lex_table, &mut self.keyword_lex_table);
+            self.add_lex_function("ts_lex_keywords", keyword_lex_table);
+        }
+
+        // Once the lex functions are generated, and we've determined which large
+        // character sets are actually used, we can generate the large character set
+        // constants. Insert them into the output buffer before the lex functions.
+        let lex_functions = self.buffer[buffer_offset_before_lex_functions..].to_string();
+        self.buffer.truncate(buffer_offset_before_lex_functions);
+        for ix in 0..self.large_character_sets.len() {
+            self.add_character_set(ix);
+        }
+        if self.table_driven_lexer {
+            self.add_lex_character_ranges();
+        }
+        self.buffer.push_str(&lex_functions);
+    }
+
     fn add_lex_function(&mut self, name: &str, lex_table: LexTable) {
+        if self.table_driven_lexer {
+            self.add_lex_table_function(name, lex_table);
+            return;
+        }
+
+        if self.target == Target::Rust {
+            self.add_rust_lex_function(name, lex_table);
+            return;
+        }
+
         add_line!(
             self,
             "static bool {name}(TSLexer *lexer, TSStateId state) {{",
@@ -720,12 +719,21 @@ impl Generator {
     }
 
     fn add_lex_state(&mut self, _state_ix: usize, state: LexState) {
+        let is_rust = self.target == Target::Rust;
         if let Some(accept_action) = state.accept_action {
-            add_line!(self, "ACCEPT_TOKEN({});", self.symbol_ids[&accept_action]);
+            if is_rust {
+                add_line!(self, "accept_token!({});", self.symbol_ids[&accept_action]);
+            } else {
+                add_line!(self, "ACCEPT_TOKEN({});", self.symbol_ids[&accept_action]);
+            }
         }
 
         if let Some(eof_action) = state.eof_action {
-            add_line!(self, "if (eof) ADVANCE({});", eof_action.state);
+            if is_rust {
+                add_line!(self, "if eof {{ advance!({}); }}", eof_action.state);
+            } else {
+                add_line!(self, "if (eof) ADVANCE({});", eof_action.state);
+            }
         }
 
         let mut chars_copy = CharacterSet::empty();
@@ -754,7 +762,30 @@ impl Generator {
             }
         }
 
-        if leading_simple_transition_range_count >= 8 {
+        if leading_simple_transition_range_count >= 8 && is_rust {
+            // Rust has no equivalent of the `ADVANCE_MAP` macro's static array, but
+            // a `match` expression serves the same purpose.
+            add_line!(self, "match lookahead {{");
+            indent!(self);
+            for (chars, action) in &state.advance_actions[0..leading_simple_transition_count] {
+                add_whitespace!(self);
+                for (i, range) in chars.ranges().enumerate() {
+                    if i > 0 {
+                        add!(self, " | ");
+                    }
+                    self.add_character(*range.start());
+                    if range.end() > range.start() {
+                        add!(self, " | ");
+                        self.add_character(*range.end());
+                    }
+                }
+                add!(self, " => advance!({}),\n", action.state);
+                ruled_out_chars = ruled_out_chars.add(chars);
+            }
+            add_line!(self, "_ => {{}}");
+            dedent!(self);
+            add_line!(self, "}}");
+        } else if leading_simple_transition_range_count >= 8 {
             add_line!(self, "ADVANCE_MAP(");
             indent!(self);
             for (chars, action) in &state.advance_actions[0..leading_simple_transition_count] {
@@ -841,7 +872,7 @@ impl Generator {
             let has_negative_condition = !negated_chars.is_empty();
             let has_condition = has_positive_condition || has_negative_condition;
             if has_condition {
-                add!(self, "if (");
+                add!(self, "{}", if is_rust { "if " } else { "if (" });
                 if has_positive_condition && has_negative_condition {
                     add!(self, "(");
                 }
@@ -859,12 +890,20 @@ impl Generator {
 
                 let char_set_info = &mut self.large_character_set_info[large_char_set_ix];
                 char_set_info.is_used = true;
-                add!(
-                    self,
-                    "set_contains({}, {}, lookahead)",
-                    &char_set_info.constant_name,
-                    large_set.range_count(),
-                );
+                if is_rust {
+                    add!(
+                        self,
+                        "set_contains(&{}, lookahead)",
+                        &char_set_info.constant_name
+                    );
+                } else {
+                    add!(
+                        self,
+                        "set_contains({}, {}, lookahead)",
+                        &char_set_info.constant_name,
+                        large_set.range_count(),
+                    );
+                }
                 if check_eof {
                     add!(self, ")");
                 }
@@ -893,15 +932,24 @@ impl Generator {
                 self.add_character_range_conditions(&negated_chars, false, &line_break);
             }
 
-            if has_condition {
-                add!(self, ") ");
+            if is_rust && has_condition {
+                add!(self, " {{ ");
+                self.add_advance_action(action);
+                add!(self, " }}");
+            } else {
+                if has_condition {
+                    add!(self, ") ");
+                }
+                self.add_advance_action(action);
             }
-
-            self.add_advance_action(action);
             add!(self, "\n");
         }
 
-        add_line!(self, "END_STATE();");
+        if is_rust {
+            add_line!(self, "return result;");
+        } else {
+            add_line!(self, "END_STATE();");
+        }
     }
 
     fn add_character_range_conditions(
@@ -976,11 +1024,20 @@ impl Generator {
             return;
         }
 
-        add_line!(
-            self,
-            "static TSCharacterRange {}[] = {{",
-            info.constant_name
-        );
+        if self.target == Target::Rust {
+            add_line!(
+                self,
+                "static {}: [(i32, i32); {}] = [",
+                info.constant_name,
+                characters.range_count()
+            );
+        } else {
+            add_line!(
+                self,
+                "static TSCharacterRange {}[] = {{",
+                info.constant_name
+            );
+        }
 
         indent!(self);
         for (ix, range) in characters.ranges().enumerate() {
@@ -993,23 +1050,32 @@ impl Generator {
             } else {
                 add!(self, " ");
             }
-            add!(self, "{{");
+            let (open, close) = match self.target {
+                Target::C => ("{", "}"),
+                Target::Rust => ("(", ")"),
+            };
+            add!(self, "{open}");
             self.add_character(*range.start());
             add!(self, ", ");
             self.add_character(*range.end());
-            add!(self, "}},");
+            add!(self, "{close},");
         }
         add!(self, "\n");
         dedent!(self);
-        add_line!(self, "}};");
+        if self.target == Target::Rust {
+            add_line!(self, "];");
+        } else {
+            add_line!(self, "}};");
+        }
         add_line!(self, "");
     }
 
     fn add_advance_action(&mut self, action: &AdvanceAction) {
-        if action.in_main_token {
-            add!(self, "ADVANCE({});", action.state);
-        } else {
-            add!(self, "SKIP({});", action.state);
+        match (self.target, action.in_main_token) {
+            (Target::C, true) => add!(self, "ADVANCE({});", action.state),
+            (Target::C, false) => add!(self, "SKIP({});", action.state),
+            (Target::Rust, true) => add!(self, "advance!({});", action.state),
+            (Target::Rust, false) => add!(self, "skip!({});", action.state),
         }
     }
 
@@ -1105,6 +1171,72 @@ impl Generator {
     }
 
     fn add_parse_table(&mut self) {
+        let layout = self.parse_table_layout();
+
+        add_line!(
+            self,
+            "static const uint16_t ts_parse_table[LARGE_STATE_COUNT][SYMBOL_COUNT] = {{",
+        );
+        indent!(self);
+        for (i, entries) in layout.large_states.iter().enumerate() {
+            add_line!(self, "[{}] = {{", i);
+            indent!(self);
+            for (symbol, value) in entries {
+                add_line!(self, "[{}] = {value},", self.symbol_ids[symbol]);
+            }
+            dedent!(self);
+            add_line!(self, "}},");
+        }
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+
+        if !layout.small_states.is_empty() {
+            add_line!(self, "static const uint16_t ts_small_parse_table[] = {{");
+            indent!(self);
+            for (index, groups) in layout.small_state_indices.iter().zip(&layout.small_states) {
+                add_line!(self, "[{index}] = {},", groups.len());
+                indent!(self);
+                for (value, symbols) in groups {
+                    add_line!(self, "{value}, {},", symbols.len());
+                    indent!(self);
+                    for symbol in symbols {
+                        add_line!(self, "{},", self.symbol_ids[symbol]);
+                    }
+                    dedent!(self);
+                }
+                dedent!(self);
+            }
+            dedent!(self);
+            add_line!(self, "}};");
+            add_line!(self, "");
+
+            add_line!(
+                self,
+                "static const uint32_t ts_small_parse_table_map[] = {{"
+            );
+            indent!(self);
+            for (i, index) in layout.small_state_indices.iter().enumerate() {
+                add_line!(
+                    self,
+                    "[SMALL_STATE({})] = {index},",
+                    self.large_state_count + i
+                );
+            }
+            dedent!(self);
+            add_line!(self, "}};");
+            add_line!(self, "");
+        }
+
+        self.add_parse_action_list(layout.parse_actions);
+    }
+
+    /// Arranges the parse table into the representation that is used in the generated
+    /// code. The first `large_state_count` states are stored as rows of a two-dimensional
+    /// array. The remaining "small" states are stored more compactly as lists of values,
+    /// each followed by the symbols that map to that value. Each list of parse actions is
+    /// assigned an index into a flat array of parse actions.
+    fn parse_table_layout(&self) -> ParseTableLayout {
         let mut parse_table_entries = HashMap::new();
         let mut next_parse_action_list_index = 0;
 
@@ -1117,15 +1249,10 @@ impl Generator {
             &mut next_parse_action_list_index,
         );
 
-        add_line!(
-            self,
-            "static const uint16_t ts_parse_table[LARGE_STATE_COUNT][SYMBOL_COUNT] = {{",
-        );
-        indent!(self);
-
         let mut terminal_entries = Vec::new();
         let mut nonterminal_entries = Vec::new();
 
+        let mut large_states = Vec::with_capacity(self.large_state_count);
         for (i, state) in self
             .parse_table
             .states
@@ -1133,9 +1260,6 @@ impl Generator {
             .enumerate()
             .take(self.large_state_count)
         {
-            add_line!(self, "[{}] = {{", i);
-            indent!(self);
-
             // Ensure the entries are in a deterministic order, since they are
             // internally represented as a hash map.
             terminal_entries.clear();
@@ -1145,16 +1269,13 @@ impl Generator {
             terminal_entries.sort_unstable_by_key(|e| self.symbol_order.get(e.0));
             nonterminal_entries.sort_unstable_by_key(|k| k.0);
 
+            let mut entries = Vec::new();
             for (symbol, action) in &nonterminal_entries {
-                add_line!(
-                    self,
-                    "[{}] = STATE({}),",
-                    self.symbol_ids[symbol],
-                    match action {
-                        GotoAction::Goto(state) => *state,
-                        GotoAction::ShiftExtra => i,
-                    }
-                );
+                let state_id = match action {
+                    GotoAction::Goto(state) => *state,
+                    GotoAction::ShiftExtra => i,
+                };
+                entries.push((**symbol, ParseTableValue::State(state_id)));
             }
 
             for (symbol, entry) in &terminal_entries {
@@ -1163,115 +1284,90 @@ impl Generator {
                     &mut parse_table_entries,
                     &mut next_parse_action_list_index,
                 );
-                add_line!(self, "[{}] = ACTIONS({entry_id}),", self.symbol_ids[symbol]);
+                entries.push((**symbol, ParseTableValue::Actions(entry_id)));
             }
-            dedent!(self);
-            add_line!(self, "}},");
+            large_states.push(entries);
         }
-        dedent!(self);
-        add_line!(self, "}};");
-        add_line!(self, "");
-
-        if self.large_state_count < self.parse_table.states.len() {
-            add_line!(self, "static const uint16_t ts_small_parse_table[] = {{");
-            indent!(self);
 
-            let mut index = 0;
-            let mut small_state_indices = Vec::new();
-            let mut symbols_by_value = HashMap::<(usize, SymbolType), Vec<Symbol>>::new();
-            for state in self.parse_table.states.iter().skip(self.large_state_count) {
-                small_state_indices.push(index);
-                symbols_by_value.clear();
-
-                terminal_entries.clear();
-                terminal_entries.extend(state.terminal_entries.iter());
-                terminal_entries.sort_unstable_by_key(|e| self.symbol_order.get(e.0));
-
-                // In a given parse state, many lookahead symbols have the same actions.
-                // So in the "small state" representation, group symbols by their action
-                // in order to avoid repeating the action.
-                for (symbol, entry) in &terminal_entries {
-                    let entry_id = self.get_parse_action_list_id(
-                        entry,
-                        &mut parse_table_entries,
-                        &mut next_parse_action_list_index,
-                    );
-                    symbols_by_value
-                        .entry((entry_id, SymbolType::Terminal))
-                        .or_default()
-                        .push(**symbol);
-                }
-                for (symbol, action) in &state.nonterminal_entries {
-                    let state_id = match action {
-                        GotoAction::Goto(i) => *i,
-                        GotoAction::ShiftExtra => {
-                            self.large_state_count + small_state_indices.len() - 1
-                        }
-                    };
-                    symbols_by_value
-                        .entry((state_id, SymbolType::NonTerminal))
-                        .or_default()
-                        .push(*symbol);
-                }
+        let mut index = 0;
+        let mut small_states = Vec::new();
+        let mut small_state_indices = Vec::new();
+        let mut symbols_by_value = HashMap::<(usize, SymbolType), Vec<Symbol>>::new();
+        for state in self.parse_table.states.iter().skip(self.large_state_count) {
+            small_state_indices.push(index);
+            symbols_by_value.clear();
 
-                let mut values_with_symbols = symbols_by_value.drain().collect::<Vec<_>>();
-                values_with_symbols.sort_unstable_by_key(|((value, kind), symbols)| {
-                    (symbols.len(), *kind, *value, symbols[0])
-                });
-
-                add_line!(self, "[{index}] = {},", values_with_symbols.len());
-                indent!(self);
-
-                for ((value, kind), symbols) in &mut values_with_symbols {
-                    if *kind == SymbolType::NonTerminal {
-                        add_line!(self, "STATE({value}), {},", symbols.len());
-                    } else {
-                        add_line!(self, "ACTIONS({value}), {},", symbols.len());
-                    }
+            terminal_entries.clear();
+            terminal_entries.extend(state.terminal_entries.iter());
+            terminal_entries.sort_unstable_by_key(|e| self.symbol_order.get(e.0));
 
-                    symbols.sort_unstable();
-                    indent!(self);
-                    for symbol in symbols {
-                        add_line!(self, "{},", self.symbol_ids[symbol]);
+            // In a given parse state, many lookahead symbols have the same actions.
+            // So in the "small state" representation, group symbols by their action
+            // in order to avoid repeating the action.
+            for (symbol, entry) in &terminal_entries {
+                let entry_id = self.get_parse_action_list_id(
+                    entry,
+                    &mut parse_table_entries,
+                    &mut next_parse_action_list_index,
+                );
+                symbols_by_value
+                    .entry((entry_id, SymbolType::Terminal))
+                    .or_default()
+                    .push(**symbol);
+            }
+            for (symbol, action) in &state.nonterminal_entries {
+                let state_id = match action {
+                    GotoAction::Goto(i) => *i,
+                    GotoAction::ShiftExtra => {
+                        self.large_state_count + small_state_indices.len() - 1
                     }
-                    dedent!(self);
-                }
-
-                dedent!(self);
-
-                index += 1 + values_with_symbols
-                    .iter()
-                    .map(|(_, symbols)| 2 + symbols.len())
-                    .sum::<usize>();
+                };
+                symbols_by_value
+                    .entry((state_id, SymbolType::NonTerminal))
+                    .or_default()
+                    .push(*symbol);
             }
 
-            dedent!(self);
-            add_line!(self, "}};");
-            add_line!(self, "");
+            let mut values_with_symbols = symbols_by_value.drain().collect::<Vec<_>>();
+            values_with_symbols.sort_unstable_by_key(|((value, kind), symbols)| {
+                (symbols.len(), *kind, *value, symbols[0])
+            });
 
-            add_line!(
-                self,
-                "static const uint32_t ts_small_parse_table_map[] = {{"
+            index += 1 + values_with_symbols
+                .iter()
+                .map(|(_, symbols)| 2 + symbols.len())
+                .sum::<usize>();
+
+            small_states.push(
+                values_with_symbols
+                    .into_iter()
+                    .map(|((value, kind), mut symbols)| {
+                        symbols.sort_unstable();
+                        let value = if kind == SymbolType::NonTerminal {
+                            ParseTableValue::State(value)
+                        } else {
+                            ParseTableValue::Actions(value)
+                        };
+                        (value, symbols)
+                    })
+                    .collect(),
             );
-            indent!(self);
-            for i in self.large_state_count..self.parse_table.states.len() {
-                add_line!(
-                    self,
-                    "[SMALL_STATE({i})] = {},",
-                    small_state_indices[i - self.large_state_count]
-                );
-            }
-            dedent!(self);
-            add_line!(self, "}};");
-            add_line!(self, "");
         }
 
-        let mut parse_table_entries = parse_table_entries
+        let mut parse_actions = parse_table_entries
             .into_iter()
             .map(|(entry, i)| (i, entry))
             .collect::<Vec<_>>();
-        parse_table_entries.sort_by_key(|(index, _)| *index);
-        self.add_parse_action_list(parse_table_entries);
+        parse_actions.sort_by_key(|(index, _)| *index);
+
+        ParseTableLayout {
+            large_states,
+            small_states,
+            small_state_indices,
+            small_parse_table_len: index,
+            parse_actions,
+            parse_actions_len: next_parse_action_list_index,
+        }
     }
 
     fn add_parse_action_list(&mut self, parse_table_entries: Vec<(usize, ParseTableEntry)>) {
@@ -1449,6 +1545,99 @@ impl Generator {
         add_line!(self, "#endif");
     }
 
+    /// Produces a list of the "primary state" for every state in the grammar.
+    ///
+    /// The "primary state" for a given state is the first encountered state that behaves
+    /// identically with respect to query analysis. We derive this by keeping track of the `core_id`
+    /// for each state and treating the first state with a given `core_id` as primary.
+    fn primary_state_ids(&self) -> Vec<usize> {
+        let mut first_state_for_each_core_id = HashMap::new();
+        self.parse_table
+            .states
+            .iter()
+            .enumerate()
+            .map(|(idx, state)| {
+                *first_state_for_each_core_id
+                    .entry(state.core_id)
+                    .or_insert(idx)
+            })
+            .collect()
+    }
+
+    /// Returns the index and length of each production's field map within the list of
+    /// field map entries, and the list of distinct field maps along with their indices.
+    #[allow(clippy::type_complexity)]
+    fn field_map_ids(
+        &self,
+    ) -> (
+        Vec<(usize, usize)>,
+        Vec<(usize, Vec<(String, FieldLocation)>)>,
+    ) {
+        let mut flat_field_maps = vec![];
+        let mut next_flat_field_map_index = 0;
+        self.get_field_map_id(
+            Vec::new(),
+            &mut flat_field_maps,
+            &mut next_flat_field_map_index,
+        );
+
+        let mut field_map_ids = Vec::new();
+        for production_info in &self.parse_table.production_infos {
+            if production_info.field_map.is_empty() {
+                field_map_ids.push((0, 0));
+            } else {
+                let mut flat_field_map = Vec::new();
+                for (field_name, locations) in &production_info.field_map {
+                    for location in locations {
+                        flat_field_map.push((field_name.clone(), *location));
+                    }
+                }
+                field_map_ids.push((
+                    self.get_field_map_id(
+                        flat_field_map.clone(),
+                        &mut flat_field_maps,
+                        &mut next_flat_field_map_index,
+                    ),
+                    flat_field_map.len(),
+                ));
+            }
+        }
+        (field_map_ids, flat_field_maps)
+    }
+
+    /// Returns the aliases that are applied to each non-terminal symbol in some, but not
+    /// all, of the places where it is used, sorted by symbol.
+    fn non_terminal_alias_ids(&self) -> Vec<(Symbol, Vec<String>)> {
+        let mut alias_ids_by_symbol = HashMap::new();
+        for variable in &self.syntax_grammar.variables {
+            for production in &variable.productions {
+                for step in &production.steps {
+                    if let Some(alias) = &step.alias {
+                        if step.symbol.is_non_terminal()
+                            && Some(alias) != self.default_aliases.get(&step.symbol)
+                            && self.symbol_ids.contains_key(&step.symbol)
+                        {
+                            if let Some(alias_id) = self.alias_ids.get(alias) {
+                                let alias_ids =
+                                    alias_ids_by_symbol.entry(step.symbol).or_insert(Vec::new());
+                                if let Err(i) = alias_ids.binary_search(&alias_id) {
+                                    alias_ids.insert(i, alias_id);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut alias_ids_by_symbol = alias_ids_by_symbol
+            .into_iter()
+            .map(|(symbol, alias_ids)| (symbol, alias_ids.into_iter().cloned().collect()))
+            .collect::<Vec<_>>();
+        alias_ids_by_symbol.sort_unstable_by_key(|e| e.0);
+        alias_ids_by_symbol
+    }
+
     fn get_parse_action_list_id(
         &self,
         entry: &ParseTableEntry,
@@ -1660,6 +1849,13 @@ impl Generator {
     }
 
     fn add_character(&mut self, c: char) {
+        // In Rust, the lookahead is compared with plain integers, because a cast like
+        // `'a' as i32` cannot be followed by a `<` operator.
+        if self.target == Target::Rust {
+            add!(self, "0x{:02x}", c as u32);
+            return;
+        }
+
         match c {
             '\'' => add!(self, "'\\''"),
             '\\' => add!(self, "'\\\\'"),
@@ -1697,6 +1893,10 @@ impl Generator {
 /// * `abi_version` - The language ABI version that should be generated. Usually you want
 ///   Tree-sitter's current version, but right after making an ABI change, it may be useful to
 ///   generate code with the previous ABI.
+/// * `table_driven_lexer` - Whether to encode the lexer as tables of states and transitions,
+///   which are interpreted by a generic function in `parser.h`, instead of as code. This makes
+///   the generated code much smaller for grammars with many Unicode character classes, at a
+///   small cost in lexing speed.
 #[allow(clippy::too_many_arguments)]
 pub fn render_c_code(
     name: &str,
@@ -1705,33 +1905,91 @@ pub fn render_c_code(
     lexical_grammar: LexicalGrammar,
     default_aliases: AliasMap,
     abi_version: usize,
+    table_driven_lexer: bool,
 ) -> String {
-    assert!(
-        (ABI_VERSION_MIN..=ABI_VERSION_MAX).contains(&abi_version),
-        "This version of Tree-sitter can only generate parsers with ABI version {ABI_VERSION_MIN} - {ABI_VERSION_MAX}, not {abi_version}",
-    );
-
-    Generator {
-        buffer: String::new(),
-        indent_level: 0,
-        language_name: name.to_string(),
-        large_state_count: 0,
-        parse_table: tables.parse_table,
-        main_lex_table: tables.main_lex_table,
-        keyword_lex_table: tables.keyword_lex_table,
-        keyword_capture_token: tables.word_token,
-        large_character_sets: tables.large_character_sets,
-        large_character_set_info: Vec::new(),
+    Generator::new(
+        Target::C,
+        name,
+        tables,
         syntax_grammar,
         lexical_grammar,
         default_aliases,
-        symbol_ids: HashMap::new(),
-        symbol_order: HashMap::new(),
-        alias_ids: HashMap::new(),
-        symbol_map: HashMap::new(),
-        unique_aliases: Vec::new(),
-        field_names: Vec::new(),
         abi_version,
-    }
+        table_driven_lexer,
+    )
     .generate()
 }
+
+/// Returns a String of Rust code for the given components of a parser.
+///
+/// The generated module contains the same tables as the output of [`render_c_code`],
+/// laid out in the same `TSLanguage` struct, along with a Rust implementation of the lex
+/// function. It only depends on `core`, so a grammar can be built without a C compiler.
+/// The arguments are the same as those of [`render_c_code`].
+#[allow(clippy::too_many_arguments)]
+pub fn render_rust_code(
+    name: &str,
+    tables: Tables,
+    syntax_grammar: SyntaxGrammar,
+    lexical_grammar: LexicalGrammar,
+    default_aliases: AliasMap,
+    abi_version: usize,
+    table_driven_lexer: bool,
+) -> String {
+    Generator::new(
+        Target::Rust,
+        name,
+        tables,
+        syntax_grammar,
+        lexical_grammar,
+        default_aliases,
+        abi_version,
+        table_driven_lexer,
+    )
+    .generate_rust()
+}
+
+impl Generator {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        target: Target,
+        name: &str,
+        tables: Tables,
+        syntax_grammar: SyntaxGrammar,
+        lexical_grammar: LexicalGrammar,
+        default_aliases: AliasMap,
+        abi_version: usize,
+        table_driven_lexer: bool,
+    ) -> Self {
+        assert!(
+            (ABI_VERSION_MIN..=ABI_VERSION_MAX).contains(&abi_version),
+            "This version of Tree-sitter can only generate parsers with ABI version {ABI_VERSION_MIN} - {ABI_VERSION_MAX}, not {abi_version}",
+        );
+
+        Self {
+            target,
+            buffer: String::new(),
+            indent_level: 0,
+            language_name: name.to_string(),
+            large_state_count: 0,
+            parse_table: tables.parse_table,
+            main_lex_table: tables.main_lex_table,
+            keyword_lex_table: tables.keyword_lex_table,
+            keyword_capture_token: tables.word_token,
+            large_character_sets: tables.large_character_sets,
+            large_character_set_info: Vec::new(),
+            table_driven_lexer,
+            lex_character_ranges: LexCharacterRanges::default(),
+            syntax_grammar,
+            lexical_grammar,
+            default_aliases,
+            symbol_ids: HashMap::new(),
+            symbol_order: HashMap::new(),
+            alias_ids: HashMap::new(),
+            symbol_map: HashMap::new(),
+            unique_aliases: Vec::new(),
+            field_names: Vec::new(),
+            abi_version,
+        }
+    }
+}
@@ -0,0 +1,774 @@
+use std::fmt::Write;
+
+use super::{Generator, ParseTableValue, ABI_VERSION_WITH_PRIMARY_STATES};
+use crate::generate::{
+    grammars::VariableType,
+    rules::{Alias, Symbol},
+    tables::{LexTable, ParseAction},
+};
+
+const PARSER_PRELUDE: &str = include_str!("../templates/parser.rs");
+
+impl Generator {
+    pub(super) fn generate_rust(mut self) -> String {
+        self.init();
+        self.buffer += PARSER_PRELUDE;
+        add_line!(self, "");
+        self.add_rust_stats();
+        self.add_rust_symbol_constants();
+        self.add_rust_symbol_names_list();
+        self.add_rust_unique_symbol_map();
+        self.add_rust_symbol_metadata_list();
+
+        if !self.field_names.is_empty() {
+            self.add_rust_field_name_constants();
+            self.add_rust_field_name_names_list();
+            self.add_rust_field_sequences();
+        }
+
+        if !self.parse_table.production_infos.is_empty() {
+            self.add_rust_alias_sequences();
+        }
+
+        self.add_rust_non_terminal_alias_map();
+
+        if self.abi_version >= ABI_VERSION_WITH_PRIMARY_STATES {
+            self.add_rust_primary_state_id_list();
+        }
+
+        self.add_lex_functions();
+        self.add_rust_lex_modes_list();
+        self.add_rust_parse_table();
+
+        if !self.syntax_grammar.external_tokens.is_empty() {
+            self.add_rust_external_token_constants();
+            self.add_rust_external_scanner_symbol_map();
+            self.add_rust_external_scanner_states_list();
+        }
+
+        self.add_rust_parser_export();
+
+        self.buffer
+    }
+
+    fn add_rust_stats(&mut self) {
+        let token_count = self
+            .parse_table
+            .symbols
+            .iter()
+            .filter(|symbol| {
+                if symbol.is_terminal() || symbol.is_eof() {
+                    true
+                } else if symbol.is_external() {
+                    self.syntax_grammar.external_tokens[symbol.index]
+                        .corresponding_internal_token
+                        .is_none()
+                } else {
+                    false
+                }
+            })
+            .count();
+
+        let stats = [
+            ("LANGUAGE_VERSION", self.abi_version),
+            ("STATE_COUNT", self.parse_table.states.len()),
+            ("LARGE_STATE_COUNT", self.large_state_count),
+            ("SYMBOL_COUNT", self.parse_table.symbols.len()),
+            ("ALIAS_COUNT", self.unique_aliases.len()),
+            ("TOKEN_COUNT", token_count),
+            (
+                "EXTERNAL_TOKEN_COUNT",
+                self.syntax_grammar.external_tokens.len(),
+            ),
+            ("FIELD_COUNT", self.field_names.len()),
+            (
+                "MAX_ALIAS_SEQUENCE_LENGTH",
+                self.parse_table.max_aliased_production_length,
+            ),
+            (
+                "PRODUCTION_ID_COUNT",
+                self.parse_table.production_infos.len(),
+            ),
+        ];
+        for (name, value) in stats {
+            add_line!(self, "const {name}: usize = {value};");
+        }
+        add_line!(self, "");
+    }
+
+    fn add_rust_symbol_constants(&mut self) {
+        let mut i = 1;
+        for symbol in &self.parse_table.symbols {
+            if *symbol != Symbol::end() {
+                add_line!(self, "const {}: TSSymbol = {i};", self.symbol_ids[symbol]);
+                i += 1;
+            }
+        }
+        for alias in &self.unique_aliases {
+            add_line!(self, "const {}: TSSymbol = {i};", self.alias_ids[alias]);
+            i += 1;
+        }
+        add_line!(self, "");
+    }
+
+    fn add_rust_symbol_names_list(&mut self) {
+        self.add_rust_sparse_array_start(
+            "ts_symbol_names",
+            "SyncPtr<c_char>",
+            "SYMBOL_COUNT + ALIAS_COUNT",
+            "SyncPtr::NULL",
+        );
+        for symbol in &self.parse_table.symbols {
+            let name = sanitize_byte_string(
+                self.default_aliases
+                    .get(symbol)
+                    .map_or(self.metadata_for_symbol(*symbol).0, |alias| {
+                        alias.value.as_str()
+                    }),
+            );
+            add_line!(
+                self,
+                "t[{} as usize] = name(b\"{name}\\0\");",
+                self.symbol_ids[symbol],
+            );
+        }
+        for alias in &self.unique_aliases {
+            add_line!(
+                self,
+                "t[{} as usize] = name(b\"{}\\0\");",
+                self.alias_ids[alias],
+                sanitize_byte_string(&alias.value)
+            );
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_unique_symbol_map(&mut self) {
+        self.add_rust_sparse_array_start(
+            "ts_symbol_map",
+            "TSSymbol",
+            "SYMBOL_COUNT + ALIAS_COUNT",
+            "0",
+        );
+        for symbol in &self.parse_table.symbols {
+            add_line!(
+                self,
+                "t[{} as usize] = {};",
+                self.symbol_ids[symbol],
+                self.symbol_ids[&self.symbol_map[symbol]],
+            );
+        }
+        for alias in &self.unique_aliases {
+            add_line!(
+                self,
+                "t[{} as usize] = {};",
+                self.alias_ids[alias],
+                self.alias_ids[alias],
+            );
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_symbol_metadata_list(&mut self) {
+        self.add_rust_sparse_array_start(
+            "ts_symbol_metadata",
+            "TSSymbolMetadata",
+            "SYMBOL_COUNT + ALIAS_COUNT",
+            "metadata(false, false, false)",
+        );
+        for symbol in &self.parse_table.symbols {
+            let (visible, named, supertype) =
+                if let Some(Alias { is_named, .. }) = self.default_aliases.get(symbol) {
+                    (true, *is_named, false)
+                } else {
+                    match self.metadata_for_symbol(*symbol).1 {
+                        VariableType::Named => (true, true, false),
+                        VariableType::Anonymous => (true, false, false),
+                        VariableType::Hidden => (
+                            false,
+                            true,
+                            self.syntax_grammar.supertype_symbols.contains(symbol),
+                        ),
+                        VariableType::Auxiliary => (false, false, false),
+                    }
+                };
+            add_line!(
+                self,
+                "t[{} as usize] = metadata({visible}, {named}, {supertype});",
+                self.symbol_ids[symbol]
+            );
+        }
+        for alias in &self.unique_aliases {
+            add_line!(
+                self,
+                "t[{} as usize] = metadata(true, {}, false);",
+                self.alias_ids[alias],
+                alias.is_named
+            );
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_field_name_constants(&mut self) {
+        for (i, field_name) in self.field_names.iter().enumerate() {
+            add_line!(
+                self,
+                "const {}: TSFieldId = {};",
+                self.field_id(field_name),
+                i + 1
+            );
+        }
+        add_line!(self, "");
+    }
+
+    fn add_rust_field_name_names_list(&mut self) {
+        self.add_rust_sparse_array_start(
+            "ts_field_names",
+            "SyncPtr<c_char>",
+            "FIELD_COUNT + 1",
+            "SyncPtr::NULL",
+        );
+        for field_name in &self.field_names {
+            add_line!(
+                self,
+                "t[{} as usize] = name(b\"{field_name}\\0\");",
+                self.field_id(field_name),
+            );
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_field_sequences(&mut self) {
+        let (field_map_ids, flat_field_maps) = self.field_map_ids();
+
+        self.add_rust_sparse_array_start(
+            "ts_field_map_slices",
+            "TSFieldMapSlice",
+            "PRODUCTION_ID_COUNT",
+            "TSFieldMapSlice { index: 0, length: 0 }",
+        );
+        for (production_id, (row_id, length)) in field_map_ids.into_iter().enumerate() {
+            if length > 0 {
+                add_line!(
+                    self,
+                    "t[{production_id}] = TSFieldMapSlice {{ index: {row_id}, length: {length} }};",
+                );
+            }
+        }
+        self.add_rust_sparse_array_end();
+
+        let entry_count = flat_field_maps
+            .iter()
+            .map(|(_, field_pairs)| field_pairs.len())
+            .sum::<usize>();
+        add_line!(
+            self,
+            "static ts_field_map_entries: [TSFieldMapEntry; {entry_count}] = [",
+        );
+        indent!(self);
+        for (row_index, field_pairs) in flat_field_maps.into_iter().skip(1) {
+            add_line!(self, "// {row_index}");
+            for (field_name, location) in field_pairs {
+                add_line!(
+                    self,
+                    "field_map_entry({}, {}, {}),",
+                    self.field_id(&field_name),
+                    location.index,
+                    location.inherited
+                );
+            }
+        }
+        dedent!(self);
+        add_line!(self, "];");
+        add_line!(self, "");
+    }
+
+    fn add_rust_alias_sequences(&mut self) {
+        add_line!(
+            self,
+            "static ts_alias_sequences: [[TSSymbol; MAX_ALIAS_SEQUENCE_LENGTH]; PRODUCTION_ID_COUNT] = {{",
+        );
+        indent!(self);
+        add_line!(
+            self,
+            "let mut t = [[0; MAX_ALIAS_SEQUENCE_LENGTH]; PRODUCTION_ID_COUNT];"
+        );
+        for (i, production_info) in self.parse_table.production_infos.iter().enumerate() {
+            for (j, alias) in production_info.alias_sequence.iter().enumerate() {
+                if let Some(alias) = alias {
+                    add_line!(self, "t[{i}][{j}] = {};", self.alias_ids[alias]);
+                }
+            }
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_non_terminal_alias_map(&mut self) {
+        let alias_ids_by_symbol = self.non_terminal_alias_ids();
+        let length = 1 + alias_ids_by_symbol
+            .iter()
+            .map(|(_, alias_ids)| 3 + alias_ids.len())
+            .sum::<usize>();
+        add_line!(
+            self,
+            "static ts_non_terminal_alias_map: [u16; {length}] = ["
+        );
+        indent!(self);
+        for (symbol, alias_ids) in alias_ids_by_symbol {
+            let symbol_id = &self.symbol_ids[&symbol];
+            let public_symbol_id = &self.symbol_ids[&self.symbol_map[&symbol]];
+            add_line!(self, "{symbol_id}, {},", 1 + alias_ids.len());
+            indent!(self);
+            add_line!(self, "{public_symbol_id},");
+            for alias_id in alias_ids {
+                add_line!(self, "{alias_id},");
+            }
+            dedent!(self);
+        }
+        add_line!(self, "0,");
+        dedent!(self);
+        add_line!(self, "];");
+        add_line!(self, "");
+    }
+
+    fn add_rust_primary_state_id_list(&mut self) {
+        add_line!(
+            self,
+            "static ts_primary_state_ids: [TSStateId; STATE_COUNT] = ["
+        );
+        indent!(self);
+        for (idx, primary_state) in self.primary_state_ids().into_iter().enumerate() {
+            add_line!(self, "{primary_state}, // {idx}");
+        }
+        dedent!(self);
+        add_line!(self, "];");
+        add_line!(self, "");
+    }
+
+    /// Writes a lex function that mirrors the generated C code. The C code jumps to the
+    /// next state using `goto`. Here, the states are the arms of a `match` expression
+    /// within a loop, and the macros that transition to a new state `continue` the loop.
+    pub(super) fn add_rust_lex_function(&mut self, name: &str, lex_table: LexTable) {
+        add_line!(
+            self,
+            "unsafe extern \"C\" fn {name}(lexer: *mut TSLexer, mut state: TSStateId) -> bool {{",
+        );
+        indent!(self);
+        add_line!(self, "let mut result = false;");
+        add_line!(self, "macro_rules! transition {{");
+        indent!(self);
+        add_line!(self, "($state:expr, $skip:expr) => {{{{");
+        indent!(self);
+        add_line!(self, "state = $state;");
+        add_line!(self, "((*lexer).advance)(lexer, $skip);");
+        add_line!(self, "continue;");
+        dedent!(self);
+        add_line!(self, "}}}};");
+        dedent!(self);
+        add_line!(self, "}}");
+        add_line!(self, "macro_rules! advance {{");
+        add_line!(self, "  ($state:expr) => {{ transition!($state, false) }};");
+        add_line!(self, "}}");
+        add_line!(self, "macro_rules! skip {{");
+        add_line!(self, "  ($state:expr) => {{ transition!($state, true) }};");
+        add_line!(self, "}}");
+        add_line!(self, "macro_rules! accept_token {{");
+        indent!(self);
+        add_line!(self, "($symbol:expr) => {{{{");
+        indent!(self);
+        add_line!(self, "result = true;");
+        add_line!(self, "(*lexer).result_symbol = $symbol;");
+        add_line!(self, "((*lexer).mark_end)(lexer);");
+        dedent!(self);
+        add_line!(self, "}}}};");
+        dedent!(self);
+        add_line!(self, "}}");
+        add_line!(self, "loop {{");
+        indent!(self);
+        add_line!(self, "let lookahead = (*lexer).lookahead;");
+        add_line!(self, "let eof = ((*lexer).eof)(lexer);");
+        add_line!(self, "match state {{");
+        indent!(self);
+        for (i, state) in lex_table.states.into_iter().enumerate() {
+            add_line!(self, "{i} => {{");
+            indent!(self);
+            self.add_lex_state(i, state);
+            dedent!(self);
+            add_line!(self, "}}");
+        }
+        add_line!(self, "_ => return false,");
+        dedent!(self);
+        add_line!(self, "}}");
+        dedent!(self);
+        add_line!(self, "}}");
+        dedent!(self);
+        add_line!(self, "}}");
+        add_line!(self, "");
+    }
+
+    fn add_rust_lex_modes_list(&mut self) {
+        add_line!(self, "static ts_lex_modes: [TSLexMode; STATE_COUNT] = [");
+        indent!(self);
+        for (i, state) in self.parse_table.states.iter().enumerate() {
+            if state.is_end_of_non_terminal_extra() {
+                add_line!(self, "lex_mode(TSStateId::MAX, 0), // {i}");
+            } else {
+                add_line!(
+                    self,
+                    "lex_mode({}, {}), // {i}",
+                    state.lex_state_id,
+                    state.external_lex_state_id
+                );
+            }
+        }
+        dedent!(self);
+        add_line!(self, "];");
+        add_line!(self, "");
+    }
+
+    fn add_rust_parse_table(&mut self) {
+        let layout = self.parse_table_layout();
+
+        add_line!(
+            self,
+            "static ts_parse_table: [[u16; SYMBOL_COUNT]; LARGE_STATE_COUNT] = {{",
+        );
+        indent!(self);
+        add_line!(self, "let mut t = [[0; SYMBOL_COUNT]; LARGE_STATE_COUNT];");
+        for (i, entries) in layout.large_states.iter().enumerate() {
+            for (symbol, value) in entries {
+                add_line!(
+                    self,
+                    "t[{i}][{} as usize] = {};",
+                    self.symbol_ids[symbol],
+                    value.index()
+                );
+            }
+        }
+        self.add_rust_sparse_array_end();
+
+        if !layout.small_states.is_empty() {
+            add_line!(
+                self,
+                "static ts_small_parse_table: [u16; {}] = [",
+                layout.small_parse_table_len
+            );
+            indent!(self);
+            for (index, groups) in layout.small_state_indices.iter().zip(&layout.small_states) {
+                add_line!(self, "// {index}");
+                add_line!(self, "{},", groups.len());
+                indent!(self);
+                for (value, symbols) in groups {
+                    add_line!(self, "{}, {},", value.index(), symbols.len());
+                    indent!(self);
+                    for symbol in symbols {
+                        add_line!(self, "{},", self.symbol_ids[symbol]);
+                    }
+                    dedent!(self);
+                }
+                dedent!(self);
+            }
+            dedent!(self);
+            add_line!(self, "];");
+            add_line!(self, "");
+
+            add_line!(
+                self,
+                "static ts_small_parse_table_map: [u32; STATE_COUNT - LARGE_STATE_COUNT] = ["
+            );
+            indent!(self);
+            for (i, index) in layout.small_state_indices.iter().enumerate() {
+                add_line!(self, "{index}, // {}", self.large_state_count + i);
+            }
+            dedent!(self);
+            add_line!(self, "];");
+            add_line!(self, "");
+        }
+
+        add_line!(
+            self,
+            "static ts_parse_actions: [TSParseActionEntry; {}] = [",
+            layout.parse_actions_len
+        );
+        indent!(self);
+        for (i, entry) in layout.parse_actions {
+            add_whitespace!(self);
+            add!(
+                self,
+                "/* {i} */ entry({}, {}),",
+                entry.actions.len(),
+                entry.reusable
+            );
+            for action in entry.actions {
+                match action {
+                    ParseAction::Accept => add!(self, " accept_input(),"),
+                    ParseAction::Recover => add!(self, " recover(),"),
+                    ParseAction::ShiftExtra => add!(self, " shift_extra(),"),
+                    ParseAction::Shift {
+                        state,
+                        is_repetition,
+                    } => {
+                        if is_repetition {
+                            add!(self, " shift_repeat({state}),");
+                        } else {
+                            add!(self, " shift({state}),");
+                        }
+                    }
+                    ParseAction::Reduce {
+                        symbol,
+                        child_count,
+                        dynamic_precedence,
+                        production_id,
+                        ..
+                    } => {
+                        add!(
+                            self,
+                            " reduce({}, {child_count}, {dynamic_precedence}, {production_id}),",
+                            self.symbol_ids[&symbol]
+                        );
+                    }
+                }
+            }
+            add!(self, "\n");
+        }
+        dedent!(self);
+        add_line!(self, "];");
+        add_line!(self, "");
+    }
+
+    fn add_rust_external_token_constants(&mut self) {
+        for i in 0..self.syntax_grammar.external_tokens.len() {
+            add_line!(
+                self,
+                "const {}: usize = {i};",
+                self.external_token_id(&self.syntax_grammar.external_tokens[i]),
+            );
+        }
+        add_line!(self, "");
+    }
+
+    fn add_rust_external_scanner_symbol_map(&mut self) {
+        self.add_rust_sparse_array_start(
+            "ts_external_scanner_symbol_map",
+            "TSSymbol",
+            "EXTERNAL_TOKEN_COUNT",
+            "0",
+        );
+        for i in 0..self.syntax_grammar.external_tokens.len() {
+            let token = &self.syntax_grammar.external_tokens[i];
+            let id_token = token
+                .corresponding_internal_token
+                .unwrap_or_else(|| Symbol::external(i));
+            add_line!(
+                self,
+                "t[{}] = {};",
+                self.external_token_id(token),
+                self.symbol_ids[&id_token],
+            );
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_external_scanner_states_list(&mut self) {
+        let state_count = self.parse_table.external_lex_states.len();
+        add_line!(
+            self,
+            "static ts_external_scanner_states: [[bool; EXTERNAL_TOKEN_COUNT]; {state_count}] = {{",
+        );
+        indent!(self);
+        add_line!(
+            self,
+            "let mut t = [[false; EXTERNAL_TOKEN_COUNT]; {state_count}];"
+        );
+        for i in 0..state_count {
+            for token in self.parse_table.external_lex_states[i].iter() {
+                add_line!(
+                    self,
+                    "t[{i}][{}] = true;",
+                    self.external_token_id(&self.syntax_grammar.external_tokens[token.index])
+                );
+            }
+        }
+        self.add_rust_sparse_array_end();
+    }
+
+    fn add_rust_parser_export(&mut self) {
+        let language_function_name = format!("tree_sitter_{}", self.language_name);
+        let external_scanner_name = format!("{language_function_name}_external_scanner");
+        let has_external_scanner = !self.syntax_grammar.external_tokens.is_empty();
+
+        if has_external_scanner {
+            add_line!(self, "extern \"C\" {{");
+            indent!(self);
+            add_line!(self, "fn {external_scanner_name}_create() -> *mut c_void;");
+            add_line!(
+                self,
+                "fn {external_scanner_name}_destroy(payload: *mut c_void);"
+            );
+            add_line!(
+                self,
+                "fn {external_scanner_name}_scan(payload: *mut c_void, lexer: *mut TSLexer, valid_symbols: *const bool) -> bool;",
+            );
+            add_line!(
+                self,
+                "fn {external_scanner_name}_serialize(payload: *mut c_void, buffer: *mut c_char) -> u32;",
+            );
+            add_line!(
+                self,
+                "fn {external_scanner_name}_deserialize(payload: *mut c_void, buffer: *const c_char, length: u32);",
+            );
+            dedent!(self);
+            add_line!(self, "}}");
+            add_line!(self, "");
+        }
+
+        add_line!(self, "static LANGUAGE: TSLanguage = TSLanguage {{");
+        indent!(self);
+        add_line!(self, "version: LANGUAGE_VERSION as u32,");
+
+        // Quantities
+        add_line!(self, "symbol_count: SYMBOL_COUNT as u32,");
+        add_line!(self, "alias_count: ALIAS_COUNT as u32,");
+        add_line!(self, "token_count: TOKEN_COUNT as u32,");
+        add_line!(self, "external_token_count: EXTERNAL_TOKEN_COUNT as u32,");
+        add_line!(self, "state_count: STATE_COUNT as u32,");
+        add_line!(self, "large_state_count: LARGE_STATE_COUNT as u32,");
+        add_line!(self, "production_id_count: PRODUCTION_ID_COUNT as u32,");
+        add_line!(self, "field_count: FIELD_COUNT as u32,");
+        add_line!(
+            self,
+            "max_alias_sequence_length: MAX_ALIAS_SEQUENCE_LENGTH as u16,"
+        );
+
+        // Parse table
+        add_line!(self, "parse_table: ts_parse_table.as_ptr().cast(),");
+        if self.large_state_count < self.parse_table.states.len() {
+            add_line!(self, "small_parse_table: ts_small_parse_table.as_ptr(),");
+            add_line!(
+                self,
+                "small_parse_table_map: ts_small_parse_table_map.as_ptr(),"
+            );
+        } else {
+            add_line!(self, "small_parse_table: core::ptr::null(),");
+            add_line!(self, "small_parse_table_map: core::ptr::null(),");
+        }
+        add_line!(self, "parse_actions: ts_parse_actions.as_ptr(),");
+
+        // Metadata
+        add_line!(self, "symbol_names: ts_symbol_names.as_ptr().cast(),");
+        if self.field_names.is_empty() {
+            add_line!(self, "field_names: core::ptr::null(),");
+            add_line!(self, "field_map_slices: core::ptr::null(),");
+            add_line!(self, "field_map_entries: core::ptr::null(),");
+        } else {
+            add_line!(self, "field_names: ts_field_names.as_ptr().cast(),");
+            add_line!(self, "field_map_slices: ts_field_map_slices.as_ptr(),");
+            add_line!(self, "field_map_entries: ts_field_map_entries.as_ptr(),");
+        }
+        add_line!(self, "symbol_metadata: ts_symbol_metadata.as_ptr(),");
+        add_line!(self, "public_symbol_map: ts_symbol_map.as_ptr(),");
+        add_line!(self, "alias_map: ts_non_terminal_alias_map.as_ptr(),");
+        if self.parse_table.production_infos.is_empty() {
+            add_line!(self, "alias_sequences: core::ptr::null(),");
+        } else {
+            add_line!(self, "alias_sequences: ts_alias_sequences.as_ptr().cast(),");
+        }
+
+        // Lexing
+        add_line!(self, "lex_modes: ts_lex_modes.as_ptr(),");
+        add_line!(self, "lex_fn: Some(ts_lex),");
+        if let Some(keyword_capture_token) = self.keyword_capture_token {
+            add_line!(self, "keyword_lex_fn: Some(ts_lex_keywords),");
+            add_line!(
+                self,
+                "keyword_capture_token: {},",
+                self.symbol_ids[&keyword_capture_token]
+            );
+        } else {
+            add_line!(self, "keyword_lex_fn: None,");
+            add_line!(self, "keyword_capture_token: 0,");
+        }
+
+        add_line!(self, "external_scanner: TSExternalScanner {{");
+        indent!(self);
+        if has_external_scanner {
+            add_line!(self, "states: ts_external_scanner_states.as_ptr().cast(),");
+            add_line!(self, "symbol_map: ts_external_scanner_symbol_map.as_ptr(),");
+            add_line!(self, "create: Some({external_scanner_name}_create),");
+            add_line!(self, "destroy: Some({external_scanner_name}_destroy),");
+            add_line!(self, "scan: Some({external_scanner_name}_scan),");
+            add_line!(self, "serialize: Some({external_scanner_name}_serialize),");
+            add_line!(
+                self,
+                "deserialize: Some({external_scanner_name}_deserialize),"
+            );
+        } else {
+            add_line!(self, "states: core::ptr::null(),");
+            add_line!(self, "symbol_map: core::ptr::null(),");
+            add_line!(self, "create: None,");
+            add_line!(self, "destroy: None,");
+            add_line!(self, "scan: None,");
+            add_line!(self, "serialize: None,");
+            add_line!(self, "deserialize: None,");
+        }
+        dedent!(self);
+        add_line!(self, "}},");
+
+        if self.abi_version >= ABI_VERSION_WITH_PRIMARY_STATES {
+            add_line!(self, "primary_state_ids: ts_primary_state_ids.as_ptr(),");
+        } else {
+            add_line!(self, "primary_state_ids: core::ptr::null(),");
+        }
+
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+
+        add_line!(self, "#[no_mangle]");
+        add_line!(
+            self,
+            "pub extern \"C\" fn {language_function_name}() -> *const TSLanguage {{",
+        );
+        indent!(self);
+        add_line!(self, "&LANGUAGE");
+        dedent!(self);
+        add_line!(self, "}}");
+    }
+
+    /// Starts a static array whose elements are assigned individually, like the designated
+    /// initializers in the generated C code. The assignments refer to a variable `t`.
+    fn add_rust_sparse_array_start(&mut self, name: &str, ty: &str, len: &str, default: &str) {
+        add_line!(self, "static {name}: [{ty}; {len}] = {{");
+        indent!(self);
+        add_line!(self, "let mut t = [{default}; {len}];");
+    }
+
+    fn add_rust_sparse_array_end(&mut self) {
+        add_line!(self, "t");
+        dedent!(self);
+        add_line!(self, "}};");
+        add_line!(self, "");
+    }
+}
+
+impl ParseTableValue {
+    const fn index(self) -> usize {
+        match self {
+            Self::State(index) | Self::Actions(index) => index,
+        }
+    }
+}
+
+/// Escapes a string so that it can be written as a Rust byte string literal.
+fn sanitize_byte_string(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    for byte in name.bytes() {
+        match byte {
+            b'"' => result += "\\\"",
+            b'\\' => result += "\\\\",
+            b' '..=b'~' => result.push(byte as char),
+            _ => write!(&mut result, "\\x{byte:02x}").unwrap(),
+        }
+    }
+    result
+}
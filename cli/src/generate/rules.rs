@@ -1,10 +1,11 @@
 use std::{collections::HashMap, fmt};
 
+use serde::{Deserialize, Serialize};
 use smallbitvec::SmallBitVec;
 
 use super::grammars::VariableType;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum SymbolType {
     External,
     End,
@@ -19,7 +20,7 @@ pub enum Associativity {
     Right,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Alias {
     pub value: String,
     pub is_named: bool,
@@ -48,7 +49,7 @@ pub struct MetadataParams {
     pub field_name: Option<String>,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Symbol {
     pub kind: SymbolType,
     pub index: usize,
@@ -74,7 +75,8 @@ pub enum Rule {
 // sets of tokens can be efficiently represented as bit vectors with each
 // index corresponding to a token, and each value representing whether or not
 // the token is present in the set.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "SerializedTokenSet", from = "SerializedTokenSet")]
 pub struct TokenSet {
     terminal_bits: SmallBitVec,
     external_bits: SmallBitVec,
@@ -82,6 +84,16 @@ pub struct TokenSet {
     end_of_nonterminal_extra: bool,
 }
 
+// Token sets are serialized with their bit vectors written as strings of `0` and
+// `1`, so that the lengths of the vectors, which affect equality, are preserved.
+#[derive(Serialize, Deserialize)]
+struct SerializedTokenSet {
+    terminal_bits: String,
+    external_bits: String,
+    eof: bool,
+    end_of_nonterminal_extra: bool,
+}
+
 impl Rule {
     pub fn field(name: String, content: Self) -> Self {
         add_metadata(content, move |params| {
@@ -271,6 +283,59 @@ impl From<Symbol> for Rule {
     }
 }
 
+impl From<&str> for Rule {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<String> for Rule {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<i32> for Precedence {
+    fn from(value: i32) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<&str> for Precedence {
+    fn from(value: &str) -> Self {
+        Self::Name(value.to_string())
+    }
+}
+
+impl From<TokenSet> for SerializedTokenSet {
+    fn from(set: TokenSet) -> Self {
+        let bits_to_string = |bits: &SmallBitVec| {
+            bits.iter()
+                .map(|bit| if bit { '1' } else { '0' })
+                .collect::<String>()
+        };
+        Self {
+            terminal_bits: bits_to_string(&set.terminal_bits),
+            external_bits: bits_to_string(&set.external_bits),
+            eof: set.eof,
+            end_of_nonterminal_extra: set.end_of_nonterminal_extra,
+        }
+    }
+}
+
+impl From<SerializedTokenSet> for TokenSet {
+    fn from(set: SerializedTokenSet) -> Self {
+        let string_to_bits =
+            |string: &str| string.chars().map(|c| c == '1').collect::<SmallBitVec>();
+        Self {
+            terminal_bits: string_to_bits(&set.terminal_bits),
+            external_bits: string_to_bits(&set.external_bits),
+            eof: set.eof,
+            end_of_nonterminal_extra: set.end_of_nonterminal_extra,
+        }
+    }
+}
+
 impl TokenSet {
     pub fn new() -> Self {
         Self {
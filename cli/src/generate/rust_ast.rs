@@ -0,0 +1,471 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+};
+
+use tree_sitter::RUST_KEYWORDS;
+
+use super::node_types::{FieldInfoJSON, NodeInfoJSON, NodeTypeJSON};
+
+const AST_PRELUDE: &str = include_str!("./templates/ast.rs");
+
+/// Keywords that cannot be used as raw identifiers.
+const RESERVED_IDENTIFIERS: &[&str] = &["crate", "self", "Self", "super", "_"];
+
+/// Type names that are used by the generated code, and so can't be used for node types.
+const RESERVED_TYPE_NAMES: &[&str] = &[
+    "AstNode", "Iterator", "Node", "None", "Option", "Self", "Sized", "Some",
+];
+
+/// Generates a Rust module with a typed view of each of the grammar's named node types.
+///
+/// Every named node type becomes a struct that wraps a `tree_sitter::Node`, with an
+/// accessor method for each of its fields. Supertypes, and fields that can contain more
+/// than one type of node, become enums with one variant per node type.
+pub fn generate_rust_ast(language_name: &str, node_types: &[NodeInfoJSON]) -> String {
+    let mut generator = AstGenerator {
+        used_type_names: RESERVED_TYPE_NAMES
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        ..Default::default()
+    };
+    for node_type in node_types.iter().filter(|node_type| node_type.named) {
+        let name = generator.unique_type_name(&node_type.kind);
+        generator.type_names.insert(node_type.kind.clone(), name);
+    }
+
+    generator.buffer = format!(
+        "// Typed syntax nodes for the `{language_name}` grammar, generated from its node types.\n\n"
+    );
+    generator.buffer += AST_PRELUDE;
+    for node_type in node_types.iter().filter(|node_type| node_type.named) {
+        if let Some(subtypes) = &node_type.subtypes {
+            let name = generator.type_names[&node_type.kind].clone();
+            let doc = format!("A node of one of the subtypes of `{}`.", node_type.kind);
+            generator.add_enum(&name, &doc, subtypes);
+        } else {
+            generator.add_struct(node_type);
+        }
+    }
+
+    for (name, doc, types) in std::mem::take(&mut generator.pending_enums) {
+        generator.add_enum(&name, &doc, &types);
+    }
+    generator.buffer
+}
+
+#[derive(Default)]
+struct AstGenerator {
+    buffer: String,
+    type_names: HashMap<String, String>,
+    used_type_names: HashSet<String>,
+    enum_names: BTreeMap<Vec<NodeTypeJSON>, String>,
+    pending_enums: Vec<(String, String, Vec<NodeTypeJSON>)>,
+}
+
+impl AstGenerator {
+    fn add_struct(&mut self, node_type: &NodeInfoJSON) {
+        let name = self.type_names[&node_type.kind].clone();
+        let kind = &node_type.kind;
+        let mut methods = String::new();
+        let mut method_names = HashSet::new();
+        if let Some(fields) = &node_type.fields {
+            for (field_name, field) in fields {
+                let method_name = method_name(field_name);
+                let doc = format!("The `{field_name}` field of `{kind}` nodes.");
+                let children = format!("field_children(self.0, {field_name:?})");
+                let child = format!("self.0.child_by_field_name({field_name:?})");
+                let type_name = self.field_type_name(
+                    &name,
+                    field_name,
+                    format!("A node in the `{field_name}` field of `{kind}` nodes."),
+                    field,
+                );
+                write_accessor(
+                    &mut methods,
+                    &method_name,
+                    &doc,
+                    field,
+                    &type_name,
+                    &children,
+                    &child,
+                );
+                method_names.insert(method_name);
+            }
+        }
+        if let Some(children) = &node_type.children {
+            let method_name = if children.multiple {
+                "children"
+            } else {
+                "child"
+            };
+            let method_name = if method_names.contains(method_name) {
+                format!("{method_name}_without_field")
+            } else {
+                method_name.to_string()
+            };
+            let doc = if children.multiple {
+                format!("The named children of `{kind}` nodes that are not in any field.")
+            } else {
+                format!("The named child of `{kind}` nodes that is not in any field.")
+            };
+            let type_name = self.field_type_name(
+                &name,
+                "children",
+                format!("A named child of `{kind}` nodes that is not in any field."),
+                children,
+            );
+            write_accessor(
+                &mut methods,
+                &method_name,
+                &doc,
+                children,
+                &type_name,
+                "children_without_fields(self.0)",
+                "children_without_fields(self.0).next()",
+            );
+        }
+
+        add_line(&mut self.buffer, &format!("/// A `{kind}` node."));
+        add_derives(&mut self.buffer);
+        writeln!(self.buffer, "pub struct {name}<'tree>(Node<'tree>);\n").unwrap();
+        writeln!(self.buffer, "impl<'tree> {name}<'tree> {{").unwrap();
+        writeln!(
+            self.buffer,
+            "    pub const KIND: &'static str = {kind:?};\n{methods}}}\n"
+        )
+        .unwrap();
+        writeln!(
+            self.buffer,
+            "impl<'tree> AstNode<'tree> for {name}<'tree> {{
+    fn cast(node: Node<'tree>) -> Option<Self> {{
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }}
+
+    fn node(&self) -> Node<'tree> {{
+        self.0
+    }}
+}}
+"
+        )
+        .unwrap();
+    }
+
+    fn add_enum(&mut self, name: &str, doc: &str, types: &[NodeTypeJSON]) {
+        let variants = types
+            .iter()
+            .filter(|node_type| node_type.named)
+            .filter_map(|node_type| self.type_names.get(&node_type.kind))
+            .collect::<Vec<_>>();
+        let anonymous_kinds = types
+            .iter()
+            .filter(|node_type| !node_type.named)
+            .map(|node_type| format!("{:?}", node_type.kind))
+            .collect::<Vec<_>>();
+
+        add_line(&mut self.buffer, &format!("/// {doc}"));
+        add_derives(&mut self.buffer);
+        writeln!(self.buffer, "pub enum {name}<'tree> {{").unwrap();
+        for variant in &variants {
+            writeln!(self.buffer, "    {variant}({variant}<'tree>),").unwrap();
+        }
+        if !anonymous_kinds.is_empty() {
+            writeln!(
+                self.buffer,
+                "    /// An anonymous node: {}.",
+                anonymous_kinds.join(", ")
+            )
+            .unwrap();
+            writeln!(self.buffer, "    Anonymous(Node<'tree>),").unwrap();
+        }
+        writeln!(self.buffer, "}}\n").unwrap();
+
+        writeln!(
+            self.buffer,
+            "impl<'tree> AstNode<'tree> for {name}<'tree> {{"
+        )
+        .unwrap();
+        writeln!(
+            self.buffer,
+            "    fn cast(node: Node<'tree>) -> Option<Self> {{"
+        )
+        .unwrap();
+        for variant in &variants {
+            writeln!(
+                self.buffer,
+                "        if let Some(node) = {variant}::cast(node) {{
+            return Some(Self::{variant}(node));
+        }}"
+            )
+            .unwrap();
+        }
+        if !anonymous_kinds.is_empty() {
+            writeln!(
+                self.buffer,
+                "        if !node.is_named() && matches!(node.kind(), {}) {{
+            return Some(Self::Anonymous(node));
+        }}",
+                anonymous_kinds.join(" | ")
+            )
+            .unwrap();
+        }
+        writeln!(self.buffer, "        None\n    }}\n").unwrap();
+        writeln!(self.buffer, "    fn node(&self) -> Node<'tree> {{").unwrap();
+        writeln!(self.buffer, "        match self {{").unwrap();
+        for variant in &variants {
+            writeln!(
+                self.buffer,
+                "            Self::{variant}(node) => AstNode::node(node),"
+            )
+            .unwrap();
+        }
+        if !anonymous_kinds.is_empty() {
+            writeln!(self.buffer, "            Self::Anonymous(node) => *node,").unwrap();
+        }
+        if variants.is_empty() && anonymous_kinds.is_empty() {
+            writeln!(self.buffer, "            _ => unreachable!(),").unwrap();
+        }
+        writeln!(self.buffer, "        }}\n    }}\n}}\n").unwrap();
+    }
+
+    /// Returns the name of the type that represents the children of the given field. When
+    /// the field can contain more than one type of node, an enum is generated for it, unless
+    /// another field already has an enum for the same set of types.
+    fn field_type_name(
+        &mut self,
+        parent_name: &str,
+        field_name: &str,
+        enum_doc: String,
+        field: &FieldInfoJSON,
+    ) -> String {
+        if let [node_type] = field.types.as_slice() {
+            if node_type.named {
+                if let Some(name) = self.type_names.get(&node_type.kind) {
+                    return name.clone();
+                }
+            }
+        }
+        if let Some(name) = self.enum_names.get(&field.types) {
+            return name.clone();
+        }
+        let name = self.unique_type_name(&format!("{parent_name}_{field_name}"));
+        self.enum_names.insert(field.types.clone(), name.clone());
+        self.pending_enums
+            .push((name.clone(), enum_doc, field.types.clone()));
+        name
+    }
+
+    fn unique_type_name(&mut self, kind: &str) -> String {
+        let base_name = type_name(kind);
+        let mut name = base_name.clone();
+        let mut suffix = 1;
+        while !self.used_type_names.insert(name.clone()) {
+            suffix += 1;
+            name = format!("{base_name}{suffix}");
+        }
+        name
+    }
+}
+
+fn write_accessor(
+    buffer: &mut String,
+    method_name: &str,
+    doc: &str,
+    field: &FieldInfoJSON,
+    type_name: &str,
+    children: &str,
+    child: &str,
+) {
+    writeln!(buffer, "\n    /// {doc}").unwrap();
+    if field.multiple {
+        writeln!(
+            buffer,
+            "    pub fn {method_name}(&self) -> impl Iterator<Item = {type_name}<'tree>> + 'tree {{
+        {children}.filter_map({type_name}::cast)
+    }}"
+        )
+        .unwrap();
+    } else {
+        if field.required {
+            writeln!(
+                buffer,
+                "    ///\n    /// This is always present, unless the node contains errors."
+            )
+            .unwrap();
+        }
+        writeln!(
+            buffer,
+            "    pub fn {method_name}(&self) -> Option<{type_name}<'tree>> {{
+        {child}.and_then({type_name}::cast)
+    }}"
+        )
+        .unwrap();
+    }
+}
+
+fn add_line(buffer: &mut String, line: &str) {
+    buffer.push_str(line);
+    buffer.push('\n');
+}
+
+fn add_derives(buffer: &mut String) {
+    add_line(buffer, "#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]");
+}
+
+/// Converts a node kind such as `_binary_expression` into a type name such as
+/// `BinaryExpression`.
+fn type_name(kind: &str) -> String {
+    let mut result = String::new();
+    for word in kind
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            result.push(first.to_ascii_uppercase());
+            result.extend(chars);
+        }
+    }
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert_str(0, "Node");
+    }
+    result
+}
+
+fn method_name(field_name: &str) -> String {
+    let mut result = field_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if result.is_empty() || result.starts_with(|c: char| c.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    if RESERVED_IDENTIFIERS.contains(&result.as_str()) {
+        result.push('_');
+    } else if RUST_KEYWORDS.contains(&result.as_str()) {
+        result.insert_str(0, "r#");
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::*;
+    use crate::generate::{
+        grammars::{InputGrammar, Variable, VariableType},
+        load_grammar_file,
+        node_types::{generate_node_types_json, get_variable_info},
+        parse_grammar::parse_grammar,
+        prepare_grammar::prepare_grammar,
+        rules::Rule,
+    };
+
+    #[test]
+    fn test_rust_ast_for_fields_and_supertypes() {
+        let code = get_rust_ast(&InputGrammar {
+            name: "test".to_string(),
+            supertype_symbols: vec!["_expression".to_string()],
+            variables: vec![
+                Variable {
+                    name: "program".to_string(),
+                    kind: VariableType::Named,
+                    rule: Rule::repeat(Rule::named("_expression")),
+                },
+                Variable {
+                    name: "_expression".to_string(),
+                    kind: VariableType::Hidden,
+                    rule: Rule::choice(vec![Rule::named("identifier"), Rule::named("call")]),
+                },
+                Variable {
+                    name: "call".to_string(),
+                    kind: VariableType::Named,
+                    rule: Rule::seq(vec![
+                        Rule::field("function".to_string(), Rule::named("identifier")),
+                        Rule::string("("),
+                        Rule::repeat(Rule::field(
+                            "arguments".to_string(),
+                            Rule::named("_expression"),
+                        )),
+                        Rule::string(")"),
+                        Rule::field(
+                            "type".to_string(),
+                            Rule::choice(vec![
+                                Rule::string("!"),
+                                Rule::named("identifier"),
+                                Rule::Blank,
+                            ]),
+                        ),
+                    ]),
+                },
+                Variable {
+                    name: "identifier".to_string(),
+                    kind: VariableType::Named,
+                    rule: Rule::pattern("[a-z]+", ""),
+                },
+            ],
+            ..Default::default()
+        });
+
+        assert!(code.contains("pub enum Expression<'tree> {\n    Call(Call<'tree>),\n    Identifier(Identifier<'tree>),\n}"));
+        assert!(code.contains("pub struct Call<'tree>(Node<'tree>);"));
+        assert!(code.contains("pub const KIND: &'static str = \"call\";"));
+        assert!(code.contains(
+            "pub fn function(&self) -> Option<Identifier<'tree>> {\n        self.0.child_by_field_name(\"function\").and_then(Identifier::cast)"
+        ));
+        assert!(code.contains(
+            "pub fn arguments(&self) -> impl Iterator<Item = Expression<'tree>> + 'tree {\n        field_children(self.0, \"arguments\").filter_map(Expression::cast)"
+        ));
+        assert!(code.contains("pub fn r#type(&self) -> Option<CallType<'tree>>"));
+        assert!(code.contains("pub enum CallType<'tree> {\n    Identifier(Identifier<'tree>),\n    /// An anonymous node: \"!\".\n    Anonymous(Node<'tree>),\n}"));
+        assert!(code.contains(
+            "pub fn children(&self) -> impl Iterator<Item = Expression<'tree>> + 'tree {\n        children_without_fields(self.0).filter_map(Expression::cast)"
+        ));
+    }
+
+    #[test]
+    fn test_rust_ast_identifiers() {
+        assert_eq!(type_name("_binary_expression"), "BinaryExpression");
+        assert_eq!(type_name("jsx_element"), "JsxElement");
+        assert_eq!(type_name("2d_point"), "Node2dPoint");
+        assert_eq!(method_name("body"), "body");
+        assert_eq!(method_name("type"), "r#type");
+        assert_eq!(method_name("self"), "self_");
+    }
+
+    #[test]
+    fn test_rust_ast_is_compiled_with_the_tests() {
+        // The `tree-sitter-tests-rust-ast` crate contains the code that is generated for
+        // some of the test grammars, so that the tests can't pass if it doesn't compile.
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let grammar_path = manifest_dir
+            .parent()
+            .unwrap()
+            .join("test/fixtures/test_grammars/fields_and_supertypes/grammar.js");
+        let grammar_json = load_grammar_file(&grammar_path, None).unwrap();
+        let code = get_rust_ast(&parse_grammar(&grammar_json).unwrap());
+
+        let compiled_path = manifest_dir.join("src/tests/rust_ast/src/fields_and_supertypes.rs");
+        assert!(
+            fs::read_to_string(&compiled_path).unwrap() == code,
+            "{compiled_path:?} is out of date. Run `tree-sitter generate --rust-ast` for the \
+             grammar and copy the generated src/ast.rs to it."
+        );
+    }
+
+    fn get_rust_ast(grammar: &InputGrammar) -> String {
+        let (syntax_grammar, lexical_grammar, _, default_aliases) =
+            prepare_grammar(grammar).unwrap();
+        let variable_info =
+            get_variable_info(&syntax_grammar, &lexical_grammar, &default_aliases).unwrap();
+        let node_types = generate_node_types_json(
+            &syntax_grammar,
+            &lexical_grammar,
+            &default_aliases,
+            &variable_info,
+        );
+        generate_rust_ast(&grammar.name, &node_types)
+    }
+}
@@ -0,0 +1,45 @@
+#![allow(dead_code, clippy::all)]
+
+use tree_sitter::Node;
+
+/// A typed view of a syntax node.
+pub trait AstNode<'tree>: Sized {
+    /// Returns a typed view of the given node, if the node has this type.
+    fn cast(node: Node<'tree>) -> Option<Self>;
+
+    /// Returns the underlying syntax node.
+    fn node(&self) -> Node<'tree>;
+}
+
+/// Iterates over the children of a node, along with the names of their fields.
+fn children_with_field_names<'tree>(
+    node: Node<'tree>,
+) -> impl Iterator<Item = (Option<&'static str>, Node<'tree>)> {
+    let mut cursor = node.walk();
+    let mut has_next = cursor.goto_first_child();
+    core::iter::from_fn(move || {
+        if !has_next {
+            return None;
+        }
+        let item = (cursor.field_name(), cursor.node());
+        has_next = cursor.goto_next_sibling();
+        Some(item)
+    })
+}
+
+/// Iterates over the children of a node that are stored in the given field.
+fn field_children<'tree>(
+    node: Node<'tree>,
+    field_name: &'static str,
+) -> impl Iterator<Item = Node<'tree>> {
+    children_with_field_names(node)
+        .filter(move |(name, _)| *name == Some(field_name))
+        .map(|(_, child)| child)
+}
+
+/// Iterates over the named children of a node that are not stored in any field.
+fn children_without_fields<'tree>(node: Node<'tree>) -> impl Iterator<Item = Node<'tree>> {
+    children_with_field_names(node)
+        .filter(|(name, child)| name.is_none() && child.is_named() && !child.is_extra())
+        .map(|(_, child)| child)
+}
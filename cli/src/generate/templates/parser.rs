@@ -0,0 +1,370 @@
+#![allow(
+    dead_code,
+    non_camel_case_types,
+    non_upper_case_globals,
+    unreachable_code,
+    unused,
+    clippy::all
+)]
+
+use core::ffi::{c_char, c_void};
+
+pub type TSStateId = u16;
+pub type TSSymbol = u16;
+pub type TSFieldId = u16;
+
+pub const ts_builtin_sym_end: TSSymbol = 0;
+
+#[repr(C)]
+pub struct TSLexer {
+    pub lookahead: i32,
+    pub result_symbol: TSSymbol,
+    pub advance: unsafe extern "C" fn(*mut TSLexer, bool),
+    pub mark_end: unsafe extern "C" fn(*mut TSLexer),
+    pub get_column: unsafe extern "C" fn(*mut TSLexer) -> u32,
+    pub is_at_included_range_start: unsafe extern "C" fn(*const TSLexer) -> bool,
+    pub eof: unsafe extern "C" fn(*const TSLexer) -> bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSFieldMapEntry {
+    pub field_id: TSFieldId,
+    pub child_index: u8,
+    pub inherited: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSFieldMapSlice {
+    pub index: u16,
+    pub length: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSSymbolMetadata {
+    pub visible: bool,
+    pub named: bool,
+    pub supertype: bool,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSLexMode {
+    pub lex_state: u16,
+    pub external_lex_state: u16,
+}
+
+const TSParseActionTypeShift: u8 = 0;
+const TSParseActionTypeReduce: u8 = 1;
+const TSParseActionTypeAccept: u8 = 2;
+const TSParseActionTypeRecover: u8 = 3;
+
+// Every variant is padded to the full size of the union, so that no byte of the
+// parse action table is left uninitialized.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSParseActionShift {
+    pub type_: u8,
+    _padding: u8,
+    pub state: TSStateId,
+    pub extra: bool,
+    pub repetition: bool,
+    _unused: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSParseActionReduce {
+    pub type_: u8,
+    pub child_count: u8,
+    pub symbol: TSSymbol,
+    pub dynamic_precedence: i16,
+    pub production_id: u16,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TSParseActionEntryHeader {
+    pub count: u8,
+    pub reusable: bool,
+    _unused: [u8; 6],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union TSParseActionEntry {
+    pub shift: TSParseActionShift,
+    pub reduce: TSParseActionReduce,
+    pub entry: TSParseActionEntryHeader,
+}
+
+const fn entry(count: u8, reusable: bool) -> TSParseActionEntry {
+    TSParseActionEntry {
+        entry: TSParseActionEntryHeader {
+            count,
+            reusable,
+            _unused: [0; 6],
+        },
+    }
+}
+
+const fn shift_action(state: TSStateId, extra: bool, repetition: bool) -> TSParseActionEntry {
+    TSParseActionEntry {
+        shift: TSParseActionShift {
+            type_: TSParseActionTypeShift,
+            _padding: 0,
+            state,
+            extra,
+            repetition,
+            _unused: 0,
+        },
+    }
+}
+
+const fn shift(state: TSStateId) -> TSParseActionEntry {
+    shift_action(state, false, false)
+}
+
+const fn shift_repeat(state: TSStateId) -> TSParseActionEntry {
+    shift_action(state, false, true)
+}
+
+const fn shift_extra() -> TSParseActionEntry {
+    shift_action(0, true, false)
+}
+
+const fn reduce(
+    symbol: TSSymbol,
+    child_count: u8,
+    dynamic_precedence: i16,
+    production_id: u16,
+) -> TSParseActionEntry {
+    TSParseActionEntry {
+        reduce: TSParseActionReduce {
+            type_: TSParseActionTypeReduce,
+            child_count,
+            symbol,
+            dynamic_precedence,
+            production_id,
+        },
+    }
+}
+
+const fn recover() -> TSParseActionEntry {
+    TSParseActionEntry {
+        reduce: TSParseActionReduce {
+            type_: TSParseActionTypeRecover,
+            child_count: 0,
+            symbol: 0,
+            dynamic_precedence: 0,
+            production_id: 0,
+        },
+    }
+}
+
+const fn accept_input() -> TSParseActionEntry {
+    TSParseActionEntry {
+        reduce: TSParseActionReduce {
+            type_: TSParseActionTypeAccept,
+            child_count: 0,
+            symbol: 0,
+            dynamic_precedence: 0,
+            production_id: 0,
+        },
+    }
+}
+
+const fn lex_mode(lex_state: u16, external_lex_state: u16) -> TSLexMode {
+    TSLexMode {
+        lex_state,
+        external_lex_state,
+    }
+}
+
+const fn metadata(visible: bool, named: bool, supertype: bool) -> TSSymbolMetadata {
+    TSSymbolMetadata {
+        visible,
+        named,
+        supertype,
+    }
+}
+
+const fn field_map_entry(field_id: TSFieldId, child_index: u8, inherited: bool) -> TSFieldMapEntry {
+    TSFieldMapEntry {
+        field_id,
+        child_index,
+        inherited,
+    }
+}
+
+/// A pointer that can be stored in a static, because it only ever points to
+/// immutable static data.
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+pub struct SyncPtr<T>(*const T);
+
+unsafe impl<T> Sync for SyncPtr<T> {}
+
+impl<T> SyncPtr<T> {
+    const NULL: Self = Self(core::ptr::null());
+}
+
+const fn name(value: &'static [u8]) -> SyncPtr<c_char> {
+    SyncPtr(value.as_ptr().cast())
+}
+
+#[repr(C)]
+pub struct TSExternalScanner {
+    pub states: *const bool,
+    pub symbol_map: *const TSSymbol,
+    pub create: Option<unsafe extern "C" fn() -> *mut c_void>,
+    pub destroy: Option<unsafe extern "C" fn(*mut c_void)>,
+    pub scan: Option<unsafe extern "C" fn(*mut c_void, *mut TSLexer, *const bool) -> bool>,
+    pub serialize: Option<unsafe extern "C" fn(*mut c_void, *mut c_char) -> u32>,
+    pub deserialize: Option<unsafe extern "C" fn(*mut c_void, *const c_char, u32)>,
+}
+
+#[repr(C)]
+pub struct TSLanguage {
+    pub version: u32,
+    pub symbol_count: u32,
+    pub alias_count: u32,
+    pub token_count: u32,
+    pub external_token_count: u32,
+    pub state_count: u32,
+    pub large_state_count: u32,
+    pub production_id_count: u32,
+    pub field_count: u32,
+    pub max_alias_sequence_length: u16,
+    pub parse_table: *const u16,
+    pub small_parse_table: *const u16,
+    pub small_parse_table_map: *const u32,
+    pub parse_actions: *const TSParseActionEntry,
+    pub symbol_names: *const *const c_char,
+    pub field_names: *const *const c_char,
+    pub field_map_slices: *const TSFieldMapSlice,
+    pub field_map_entries: *const TSFieldMapEntry,
+    pub symbol_metadata: *const TSSymbolMetadata,
+    pub public_symbol_map: *const TSSymbol,
+    pub alias_map: *const u16,
+    pub alias_sequences: *const TSSymbol,
+    pub lex_modes: *const TSLexMode,
+    pub lex_fn: Option<unsafe extern "C" fn(*mut TSLexer, TSStateId) -> bool>,
+    pub keyword_lex_fn: Option<unsafe extern "C" fn(*mut TSLexer, TSStateId) -> bool>,
+    pub keyword_capture_token: TSSymbol,
+    pub external_scanner: TSExternalScanner,
+    pub primary_state_ids: *const TSStateId,
+}
+
+// The language only refers to immutable static data.
+unsafe impl Sync for TSLanguage {}
+
+#[repr(C)]
+pub struct TSLexTransition {
+    pub range_index: u32,
+    pub range_count: u16,
+    pub state: TSStateId,
+    pub skip: bool,
+}
+
+#[repr(C)]
+pub struct TSLexTableState {
+    pub transition_index: u32,
+    pub transition_count: u16,
+    pub eof_state: TSStateId,
+    pub accept_symbol: TSSymbol,
+    pub accepts: bool,
+}
+
+const TS_LEX_NO_STATE: TSStateId = TSStateId::MAX;
+
+const fn lex_transition(
+    range_index: u32,
+    range_count: u16,
+    state: TSStateId,
+    skip: bool,
+) -> TSLexTransition {
+    TSLexTransition {
+        range_index,
+        range_count,
+        state,
+        skip,
+    }
+}
+
+const fn lex_table_state(
+    transition_index: u32,
+    transition_count: u16,
+    eof_state: TSStateId,
+    accept_symbol: Option<TSSymbol>,
+) -> TSLexTableState {
+    TSLexTableState {
+        transition_index,
+        transition_count,
+        eof_state,
+        accept_symbol: match accept_symbol {
+            Some(symbol) => symbol,
+            None => 0,
+        },
+        accepts: accept_symbol.is_some(),
+    }
+}
+
+/// Interprets a lexer that was generated as a table of transitions, rather than as
+/// a function. A transition without any character ranges always applies.
+unsafe fn lex_with_table(
+    lexer: *mut TSLexer,
+    mut state: TSStateId,
+    states: &[TSLexTableState],
+    transitions: &[TSLexTransition],
+    ranges: &[(i32, i32)],
+) -> bool {
+    let mut result = false;
+    loop {
+        let Some(entry) = states.get(state as usize) else {
+            return result;
+        };
+        if entry.accepts {
+            result = true;
+            (*lexer).result_symbol = entry.accept_symbol;
+            ((*lexer).mark_end)(lexer);
+        }
+
+        let lookahead = (*lexer).lookahead;
+        let eof = ((*lexer).eof)(lexer);
+        let mut skip = false;
+        if eof && entry.eof_state != TS_LEX_NO_STATE {
+            state = entry.eof_state;
+        } else {
+            let start = entry.transition_index as usize;
+            let end = start + entry.transition_count as usize;
+            let Some(transition) = transitions[start..end].iter().find(|transition| {
+                let start = transition.range_index as usize;
+                let end = start + transition.range_count as usize;
+                transition.range_count == 0
+                    || (!eof && set_contains(&ranges[start..end], lookahead))
+            }) else {
+                return result;
+            };
+            state = transition.state;
+            skip = transition.skip;
+        }
+        ((*lexer).advance)(lexer, skip);
+    }
+}
+
+fn set_contains(ranges: &[(i32, i32)], lookahead: i32) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if lookahead < start {
+                core::cmp::Ordering::Greater
+            } else if lookahead > end {
+                core::cmp::Ordering::Less
+            } else {
+                core::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
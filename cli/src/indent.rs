@@ -0,0 +1,72 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+use tree_sitter_indent::Indenter;
+use tree_sitter_loader::{Config, Loader};
+
+use super::util;
+
+pub fn indent_files(
+    loader: &Loader,
+    loader_config: &Config,
+    scope: Option<&str>,
+    paths: &[String],
+) -> Result<()> {
+    let mut lang = None;
+    if let Some(scope) = scope {
+        lang = loader.language_configuration_for_scope(scope)?;
+        if lang.is_none() {
+            return Err(anyhow!("Unknown scope '{scope}'"));
+        }
+    }
+
+    let mut indenter = Indenter::new();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for path in paths {
+        let path = Path::new(&path);
+        let (language, language_config) = match lang.clone() {
+            Some(v) => v,
+            None => {
+                if let Some(v) = loader.language_configuration_for_file_name(path)? {
+                    v
+                } else {
+                    eprintln!("{}", util::lang_not_found_for_path(path, loader_config));
+                    continue;
+                }
+            }
+        };
+
+        if let Some(indent_config) = language_config.indent_config(language)? {
+            let source = fs::read(path)?;
+            indenter.parser.set_language(&indent_config.language)?;
+            let tree = indenter.parser.parse(&source, None).unwrap();
+
+            if paths.len() > 1 {
+                writeln!(&mut stdout, "{}", path.to_string_lossy())?;
+            }
+            for (row, line) in source.split_inclusive(|c| *c == b'\n').enumerate() {
+                let indentation = indenter
+                    .indent_for_line(indent_config, &tree, &source, row, |string| {
+                        loader.indent_config_for_injection_string(string)
+                    })
+                    .with_context(|| format!("Error indenting {}", path.display()))?;
+                writeln!(
+                    &mut stdout,
+                    "{}:\t{indentation}\t{}",
+                    row + 1,
+                    String::from_utf8_lossy(line).trim()
+                )?;
+            }
+        } else {
+            eprintln!("No indent config found for path {path:?}");
+        }
+    }
+
+    Ok(())
+}
@@ -1,15 +1,21 @@
 #![doc = include_str!("../README.md")]
 
+pub mod diff;
+pub mod folds;
+pub mod format;
 pub mod generate;
 pub mod highlight;
+pub mod indent;
 pub mod logger;
 pub mod parse;
 pub mod playground;
 pub mod query;
 pub mod query_testing;
+pub mod rewrite;
 pub mod tags;
 pub mod test;
 pub mod test_highlight;
+pub mod test_indent;
 pub mod test_tags;
 pub mod util;
 pub mod wasm;
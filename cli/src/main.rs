@@ -11,17 +11,21 @@ use glob::glob;
 use regex::Regex;
 use tree_sitter::{ffi, Parser, Point};
 use tree_sitter_cli::{
+    diff, folds,
+    format::{self, FormatOptions},
     generate::{self, lookup_package_json_for_path},
-    highlight, logger,
+    highlight, indent, logger,
     parse::{self, ParseFileOptions, ParseOutput},
-    playground, query, tags,
+    playground, query, rewrite, tags,
     test::{self, TestOptions},
-    test_highlight, test_tags, util, wasm,
+    test_highlight, test_indent, test_tags, util, wasm,
 };
 use tree_sitter_config::Config;
 use tree_sitter_highlight::Highlighter;
+use tree_sitter_indent::Indenter;
 use tree_sitter_loader as loader;
 use tree_sitter_tags::TagsContext;
+use tree_sitter_theme::Theme;
 
 const BUILD_VERSION: &str = env!("CARGO_PKG_VERSION");
 const BUILD_SHA: Option<&'static str> = option_env!("BUILD_SHA");
@@ -32,13 +36,19 @@ const DEFAULT_GENERATE_ABI_VERSION: usize = 14;
 enum Commands {
     InitConfig(InitConfig),
     Generate(Generate),
+    LintGrammar(LintGrammar),
     Build(Build),
     BuildWasm(BuildWasm),
     Parse(Parse),
     Test(Test),
     Query(Query),
+    Diff(Diff),
+    Rewrite(Rewrite),
     Highlight(Highlight),
     Tags(Tags),
+    Format(Format),
+    Indent(Indent),
+    Folds(Folds),
     Playground(Playground),
     DumpLanguages(DumpLanguages),
 }
@@ -92,7 +102,50 @@ struct Generate {
         long,
         value_name = "EXECUTABLE",
         env = "TREE_SITTER_JS_RUNTIME",
-        help = "The path to the JavaScript runtime to use for generating parsers"
+        help = "The path to the JavaScript runtime to use for generating parsers, or `native` to use the built-in evaluator"
+    )]
+    pub js_runtime: Option<String>,
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_parser = ["text", "json"],
+        default_value = "text",
+        help = "The format in which to report errors in the grammar"
+    )]
+    pub diagnostics_format: String,
+    #[arg(
+        long,
+        help = "Don't reuse or store the parse table from previous runs in the cache directory"
+    )]
+    pub no_cache: bool,
+    #[arg(
+        long,
+        conflicts_with = "build",
+        help = "Write the parse tables as a Rust module, src/parser.rs, instead of src/parser.c"
+    )]
+    pub rust_parser: bool,
+    #[arg(
+        long,
+        help = "Encode the lexer as tables that are interpreted at runtime, to reduce the size of the generated code"
+    )]
+    pub table_driven_lexer: bool,
+    #[arg(
+        long,
+        help = "Also write a typed Rust module for the grammar's node types, src/ast.rs"
+    )]
+    pub rust_ast: bool,
+}
+
+#[derive(Args)]
+#[command(about = "Check a grammar for unused or ineffective declarations")]
+struct LintGrammar {
+    #[arg(index = 1, help = "The path to the grammar file")]
+    pub grammar_path: Option<String>,
+    #[arg(
+        long,
+        value_name = "EXECUTABLE",
+        env = "TREE_SITTER_JS_RUNTIME",
+        help = "The path to the JavaScript runtime to use for loading the grammar, or `native` to use the built-in evaluator"
     )]
     pub js_runtime: Option<String>,
 }
@@ -172,6 +225,12 @@ struct Parse {
         help = "Output the parse data in XML format"
     )]
     pub output_xml: bool,
+    #[arg(
+        long = "json",
+        short = 'j',
+        help = "Output the parse data in JSON format"
+    )]
+    pub output_json: bool,
     #[arg(long, short, help = "Show parsing statistic")]
     pub stat: bool,
     #[arg(long, help = "Interrupt the parsing process by timeout (µs)")]
@@ -287,11 +346,72 @@ struct Query {
     pub config_path: Option<PathBuf>,
 }
 
+#[derive(Args)]
+#[command(about = "Compare the syntax trees of two files")]
+struct Diff {
+    #[arg(help = "The original source file", index = 1, required = true)]
+    pub old_path: PathBuf,
+    #[arg(help = "The changed source file", index = 2, required = true)]
+    pub new_path: PathBuf,
+    #[arg(
+        long,
+        help = "Select a language by the scope instead of a file extension"
+    )]
+    pub scope: Option<String>,
+    #[arg(long, help = "The path to an alternative config.json file")]
+    pub config_path: Option<PathBuf>,
+}
+
+#[derive(Args)]
+#[command(about = "Replace the matches of a query in source files")]
+struct Rewrite {
+    #[arg(long, help = "Path to a file with queries", required = true)]
+    pub query: PathBuf,
+    #[arg(
+        long,
+        help = "Path to a file with the replacement template, which can refer to captures as `@name`",
+        required = true
+    )]
+    pub template: PathBuf,
+    #[arg(
+        long = "paths",
+        help = "The path to a file with paths to source file(s)"
+    )]
+    pub paths_file: Option<String>,
+    #[arg(num_args=1.., help = "The source file(s) to rewrite")]
+    pub paths: Option<Vec<String>>,
+    #[arg(
+        long,
+        help = "Print the changes as a unified diff instead of writing them"
+    )]
+    pub dry_run: bool,
+    #[arg(
+        long,
+        help = "Select a language by the scope instead of a file extension"
+    )]
+    pub scope: Option<String>,
+    #[arg(long, help = "The path to an alternative config.json file")]
+    pub config_path: Option<PathBuf>,
+}
+
 #[derive(Args)]
 #[command(about = "Highlight a file", alias = "hi")]
 struct Highlight {
     #[arg(long, short = 'H', help = "Generate highlighting as an HTML document")]
     pub html: bool,
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_parser = highlight::OUTPUT_FORMATS.to_vec(),
+        conflicts_with = "html",
+        help = "The format of the highlighted output"
+    )]
+    pub format: Option<String>,
+    #[arg(
+        long,
+        help = "The path to a theme, in Tree-sitter's format or as a VS Code or TextMate theme"
+    )]
+    pub theme: Option<PathBuf>,
     #[arg(
         long,
         help = "Check that highlighting captures conform strictly to standards"
@@ -344,6 +464,82 @@ struct Tags {
     pub config_path: Option<PathBuf>,
 }
 
+#[derive(Args)]
+#[command(about = "Format source files using a formatting query", alias = "fmt")]
+struct Format {
+    #[arg(
+        long,
+        help = "Select a language by the scope instead of a file extension"
+    )]
+    pub scope: Option<String>,
+    #[arg(
+        long,
+        short,
+        help = "Overwrite the files instead of printing the formatted code"
+    )]
+    pub write: bool,
+    #[arg(long, default_value_t = 80, help = "The maximum line width")]
+    pub line_width: usize,
+    #[arg(
+        long,
+        default_value_t = 2,
+        help = "The number of spaces per indentation level"
+    )]
+    pub indent_width: usize,
+    #[arg(
+        long = "paths",
+        help = "The path to a file with paths to source file(s)"
+    )]
+    pub paths_file: Option<String>,
+    #[arg(num_args = 1.., help = "The source file(s) to use")]
+    pub paths: Option<Vec<String>>,
+    #[arg(long, help = "The path to an alternative config.json file")]
+    pub config_path: Option<PathBuf>,
+}
+
+#[derive(Args)]
+#[command(about = "Compute the indentation of source files using an indentation query")]
+struct Indent {
+    #[arg(
+        long,
+        help = "Check the indentation assertions in the source files instead of printing the indentation"
+    )]
+    pub check: bool,
+    #[arg(
+        long,
+        help = "Select a language by the scope instead of a file extension"
+    )]
+    pub scope: Option<String>,
+    #[arg(
+        long = "paths",
+        help = "The path to a file with paths to source file(s)"
+    )]
+    pub paths_file: Option<String>,
+    #[arg(num_args = 1.., help = "The source file(s) to use")]
+    pub paths: Option<Vec<String>>,
+    #[arg(long, help = "The path to an alternative config.json file")]
+    pub config_path: Option<PathBuf>,
+}
+
+#[derive(Args)]
+#[command(about = "Print the foldable ranges of source files as JSON, using a folding query")]
+struct Folds {
+    #[arg(
+        long,
+        help = "Select a language by the scope instead of a file extension"
+    )]
+    pub scope: Option<String>,
+    #[arg(
+        long = "paths",
+        help = "The path to a file with paths to source file(s)"
+    )]
+    pub paths_file: Option<String>,
+    #[arg(num_args = 1.., help = "The source file(s) to use")]
+    pub paths: Option<Vec<String>>,
+    #[arg(long, help = "The path to an alternative config.json file")]
+    pub config_path: Option<PathBuf>,
+}
+
 #[derive(Args)]
 #[command(
     about = "Start local playground for a parser in the browser",
@@ -447,14 +643,33 @@ fn run() -> Result<()> {
                     }
                 },
             );
-            generate::generate_parser_in_directory(
+            let cache_dir = if generate_options.no_cache {
+                None
+            } else {
+                dirs::cache_dir().map(|dir| dir.join("tree-sitter").join("generate"))
+            };
+            let result = generate::generate_parser_in_directory(
                 &current_dir,
                 generate_options.grammar_path.as_deref(),
                 abi_version,
                 !generate_options.no_bindings,
                 generate_options.report_states_for_rule.as_deref(),
                 generate_options.js_runtime.as_deref(),
-            )?;
+                cache_dir.as_deref(),
+                generate::RenderOptions {
+                    rust: generate_options.rust_parser,
+                    table_driven_lexer: generate_options.table_driven_lexer,
+                    rust_ast: generate_options.rust_ast,
+                },
+            );
+            if let Err(error) = result {
+                if generate_options.diagnostics_format == "json" {
+                    let diagnostic = generate::diagnostics::Diagnostic::from_error(&error);
+                    println!("{}", serde_json::to_string_pretty(&diagnostic)?);
+                    return Err(anyhow!(""));
+                }
+                return Err(error);
+            }
             if generate_options.build {
                 if let Some(path) = generate_options.libdir {
                     loader = loader::Loader::with_parser_lib_path(PathBuf::from(path));
@@ -464,6 +679,25 @@ fn run() -> Result<()> {
             }
         }
 
+        Commands::LintGrammar(lint_options) => {
+            let grammar_path = lint_options
+                .grammar_path
+                .map_or_else(|| current_dir.join("grammar.js"), PathBuf::from);
+            let grammar_json =
+                generate::load_grammar_file(&grammar_path, lint_options.js_runtime.as_deref())?;
+            let warnings = generate::lint::lint_grammar_json(&grammar_json)?;
+            for warning in &warnings {
+                println!("warning: {warning}");
+            }
+            if !warnings.is_empty() {
+                return Err(anyhow!(
+                    "Found {} problem{} in the grammar",
+                    warnings.len(),
+                    if warnings.len() == 1 { "" } else { "s" }
+                ));
+            }
+        }
+
         Commands::Build(build_options) => {
             if build_options.wasm {
                 let grammar_path =
@@ -542,6 +776,8 @@ fn run() -> Result<()> {
                 ParseOutput::Dot
             } else if parse_options.output_xml {
                 ParseOutput::Xml
+            } else if parse_options.output_json {
+                ParseOutput::Json
             } else if parse_options.quiet {
                 ParseOutput::Quiet
             } else {
@@ -739,6 +975,20 @@ fn run() -> Result<()> {
                     &test_tag_dir,
                     color,
                 )?;
+                parser = tags_context.parser;
+            }
+
+            let test_indent_dir = test_dir.join("indent");
+            if test_indent_dir.is_dir() {
+                let mut indenter = Indenter::new();
+                indenter.parser = parser;
+                test_indent::test_indents(
+                    &loader,
+                    &config.get()?,
+                    &mut indenter,
+                    &test_indent_dir,
+                    color,
+                )?;
             }
         }
 
@@ -780,15 +1030,58 @@ fn run() -> Result<()> {
             )?;
         }
 
+        Commands::Diff(diff_options) => {
+            let config = Config::load(diff_options.config_path)?;
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let language = loader.select_language(
+                &diff_options.old_path,
+                &current_dir,
+                diff_options.scope.as_deref(),
+            )?;
+            diff::diff_files(&language, &diff_options.old_path, &diff_options.new_path)?;
+        }
+
+        Commands::Rewrite(rewrite_options) => {
+            let config = Config::load(rewrite_options.config_path)?;
+            let paths =
+                collect_paths(rewrite_options.paths_file.as_deref(), rewrite_options.paths)?;
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let language = loader.select_language(
+                Path::new(&paths[0]),
+                &current_dir,
+                rewrite_options.scope.as_deref(),
+            )?;
+            rewrite::rewrite_files_at_paths(
+                &language,
+                paths,
+                &rewrite_options.query,
+                &rewrite_options.template,
+                rewrite_options.dry_run,
+            )?;
+        }
+
         Commands::Highlight(highlight_options) => {
             let config = Config::load(highlight_options.config_path)?;
-            let theme_config: tree_sitter_cli::highlight::ThemeConfig = config.get()?;
+            let mut theme_config: tree_sitter_cli::highlight::ThemeConfig = config.get()?;
+            if let Some(theme_path) = highlight_options.theme.as_deref() {
+                theme_config.theme = Theme::load(theme_path).with_context(|| {
+                    format!("Failed to load theme from {}", theme_path.display())
+                })?;
+            }
             loader.configure_highlights(&theme_config.theme.highlight_names);
             let loader_config = config.get()?;
             loader.find_all_languages(&loader_config)?;
 
             let quiet = highlight_options.quiet;
-            let html_mode = quiet || highlight_options.html;
+            let format = if highlight_options.html {
+                "html"
+            } else {
+                highlight_options.format.as_deref().unwrap_or("ansi")
+            };
+            let html_mode = quiet || format == "html";
+            let mut renderer = highlight::renderer_for_format(format);
             let paths = collect_paths(
                 highlight_options.paths_file.as_deref(),
                 highlight_options.paths,
@@ -870,10 +1163,11 @@ fn run() -> Result<()> {
                             highlight_options.time,
                             Some(&cancellation_flag),
                         )?;
-                    } else {
-                        highlight::ansi(
+                    } else if let Some(renderer) = renderer.as_deref_mut() {
+                        highlight::render(
                             &loader,
                             &theme_config.theme,
+                            renderer,
                             &source,
                             highlight_config,
                             highlight_options.time,
@@ -905,6 +1199,62 @@ fn run() -> Result<()> {
             )?;
         }
 
+        Commands::Format(format_options) => {
+            let config = Config::load(format_options.config_path)?;
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let paths = collect_paths(format_options.paths_file.as_deref(), format_options.paths)?;
+            format::format_files(
+                &loader,
+                &loader_config,
+                format_options.scope.as_deref(),
+                &paths,
+                &FormatOptions {
+                    line_width: format_options.line_width,
+                    indent_width: format_options.indent_width,
+                    write: format_options.write,
+                },
+            )?;
+        }
+
+        Commands::Indent(indent_options) => {
+            let config = Config::load(indent_options.config_path)?;
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let paths = collect_paths(indent_options.paths_file.as_deref(), indent_options.paths)?;
+            if indent_options.check {
+                let paths = paths.into_iter().map(PathBuf::from).collect::<Vec<_>>();
+                test_indent::test_indent_files(
+                    &loader,
+                    &loader_config,
+                    &mut Indenter::new(),
+                    indent_options.scope.as_deref(),
+                    &paths,
+                    color,
+                )?;
+            } else {
+                indent::indent_files(
+                    &loader,
+                    &loader_config,
+                    indent_options.scope.as_deref(),
+                    &paths,
+                )?;
+            }
+        }
+
+        Commands::Folds(folds_options) => {
+            let config = Config::load(folds_options.config_path)?;
+            let loader_config = config.get()?;
+            loader.find_all_languages(&loader_config)?;
+            let paths = collect_paths(folds_options.paths_file.as_deref(), folds_options.paths)?;
+            folds::print_folds(
+                &loader,
+                &loader_config,
+                folds_options.scope.as_deref(),
+                &paths,
+            )?;
+        }
+
         Commands::Playground(playground_options) => {
             let open_in_browser = !playground_options.quiet;
             let grammar_path = playground_options
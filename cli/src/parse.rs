@@ -50,6 +50,7 @@ pub enum ParseOutput {
     Normal,
     Quiet,
     Xml,
+    Json,
     Dot,
 }
 
@@ -301,6 +302,14 @@ pub fn parse_file_at_path(parser: &mut Parser, opts: &ParseFileOptions) -> Resul
             println!();
         }
 
+        if opts.output == ParseOutput::Json {
+            serde_json::to_writer(
+                &mut stdout,
+                &tree.root_node().serializable().with_text(&source_code),
+            )?;
+            println!();
+        }
+
         if opts.output == ParseOutput::Dot {
             util::print_tree_graph(&tree, "log.html", opts.open_log).unwrap();
         }
@@ -0,0 +1,160 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use tree_sitter::{InputEdit, Language, Parser, Query, Rewriter};
+
+const DIFF_CONTEXT_LINES: usize = 3;
+
+pub fn rewrite_files_at_paths(
+    language: &Language,
+    paths: Vec<String>,
+    query_path: &Path,
+    template_path: &Path,
+    dry_run: bool,
+) -> Result<()> {
+    let query_source = fs::read_to_string(query_path)
+        .with_context(|| format!("Error reading query file {query_path:?}"))?;
+    let query = Query::new(language, &query_source).with_context(|| "Query compilation failed")?;
+    let template = fs::read_to_string(template_path)
+        .with_context(|| format!("Error reading template file {template_path:?}"))?;
+    let rewriter = Rewriter::new(&query, &template).with_context(|| "Invalid template")?;
+
+    let mut parser = Parser::new();
+    parser.set_language(language)?;
+
+    // Rewrite all of the files before writing any of them, so that nothing is
+    // changed if any of the rewrites fails.
+    let mut outputs = Vec::new();
+    for path in paths {
+        let source_code =
+            fs::read(&path).with_context(|| format!("Error reading source file {path:?}"))?;
+        let tree = parser.parse(&source_code, None).unwrap();
+        let output = rewriter
+            .rewrite(&mut parser, &tree, &source_code)
+            .with_context(|| format!("Error rewriting {path}"))?;
+        if !output.edits.is_empty() {
+            outputs.push((path, source_code, output));
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for (path, source_code, output) in outputs {
+        if dry_run {
+            write!(
+                &mut stdout,
+                "{}",
+                unified_diff(&path, &source_code, &output.source, &output.edits)
+            )?;
+        } else {
+            fs::write(&path, &output.source)
+                .with_context(|| format!("Error writing source file {path:?}"))?;
+            writeln!(&mut stdout, "{path}: {} rewrites", output.edits.len())?;
+        }
+    }
+    Ok(())
+}
+
+/// Render the changes that the given edits made to a file as a unified diff.
+///
+/// The edits can be given in any order, but they must not overlap, and their
+/// positions must be relative to the text that precedes them in the old file.
+#[must_use]
+pub fn unified_diff(path: &str, old_text: &[u8], new_text: &[u8], edits: &[InputEdit]) -> String {
+    let old_lines = old_text
+        .split_inclusive(|c| *c == b'\n')
+        .collect::<Vec<_>>();
+    let new_lines = new_text
+        .split_inclusive(|c| *c == b'\n')
+        .collect::<Vec<_>>();
+
+    // Find the ranges of lines that were changed by the edits, combining the
+    // edits on adjacent lines.
+    let mut edits = edits.to_vec();
+    edits.sort_unstable_by_key(|edit| edit.start_byte);
+    let mut chunks = Vec::<Chunk>::new();
+    let mut row_delta = 0;
+    for edit in edits {
+        let chunk = Chunk {
+            old_start: edit.start_position.row,
+            old_end: (edit.old_end_position.row + 1).min(old_lines.len()),
+            new_start: offset_row(edit.start_position.row, row_delta),
+            new_end: offset_row(edit.new_end_position.row + 1, row_delta).min(new_lines.len()),
+        };
+        row_delta += edit.new_end_position.row as isize - edit.old_end_position.row as isize;
+        match chunks.last_mut() {
+            Some(last) if chunk.old_start <= last.old_end => {
+                last.old_end = chunk.old_end;
+                last.new_end = chunk.new_end;
+            }
+            _ => chunks.push(chunk),
+        }
+    }
+
+    let mut result = format!("--- a/{path}\n+++ b/{path}\n");
+    let mut hunk_start = 0;
+    while hunk_start < chunks.len() {
+        // Group the chunks whose context lines would overlap into a single hunk.
+        let mut hunk_end = hunk_start + 1;
+        while hunk_end < chunks.len()
+            && chunks[hunk_end].old_start <= chunks[hunk_end - 1].old_end + 2 * DIFF_CONTEXT_LINES
+        {
+            hunk_end += 1;
+        }
+        let hunk = &chunks[hunk_start..hunk_end];
+        let (first, last) = (&hunk[0], &hunk[hunk.len() - 1]);
+        let old_start = first.old_start.saturating_sub(DIFF_CONTEXT_LINES);
+        let old_end = (last.old_end + DIFF_CONTEXT_LINES).min(old_lines.len());
+        let new_start = first.new_start - (first.old_start - old_start);
+        let new_end = last.new_end + (old_end - last.old_end);
+
+        result += &format!(
+            "@@ -{} +{} @@\n",
+            hunk_line_range(old_start, old_end),
+            hunk_line_range(new_start, new_end)
+        );
+        let mut row = old_start;
+        for chunk in hunk {
+            push_lines(&mut result, ' ', &old_lines[row..chunk.old_start]);
+            push_lines(&mut result, '-', &old_lines[chunk.old_start..chunk.old_end]);
+            push_lines(&mut result, '+', &new_lines[chunk.new_start..chunk.new_end]);
+            row = chunk.old_end;
+        }
+        push_lines(&mut result, ' ', &old_lines[row..old_end]);
+        hunk_start = hunk_end;
+    }
+    result
+}
+
+struct Chunk {
+    old_start: usize,
+    old_end: usize,
+    new_start: usize,
+    new_end: usize,
+}
+
+const fn offset_row(row: usize, delta: isize) -> usize {
+    (row as isize + delta) as usize
+}
+
+fn hunk_line_range(start: usize, end: usize) -> String {
+    if start == end {
+        format!("{start},0")
+    } else {
+        format!("{},{}", start + 1, end - start)
+    }
+}
+
+fn push_lines(output: &mut String, prefix: char, lines: &[&[u8]]) {
+    for line in lines {
+        output.push(prefix);
+        output.push_str(&String::from_utf8_lossy(line));
+        if !line.ends_with(b"\n") {
+            output.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use ansi_term::Colour;
+use anyhow::{anyhow, Result};
+use tree_sitter_indent::{IndentConfiguration, Indenter};
+use tree_sitter_loader::{Config, Loader};
+
+use super::{
+    query_testing::{parse_position_comments, Assertion},
+    test::opt_color,
+    util,
+};
+
+#[derive(Debug)]
+pub struct Failure {
+    row: usize,
+    expected_indentation: String,
+    actual_indentation: String,
+    negative: bool,
+}
+
+impl std::error::Error for Failure {}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Failure - row: {}, expected indentation: {}'{}', actual indentation: '{}'",
+            self.row,
+            if self.negative { "not " } else { "" },
+            self.expected_indentation,
+            self.actual_indentation
+        )
+    }
+}
+
+pub fn test_indents(
+    loader: &Loader,
+    loader_config: &Config,
+    indenter: &mut Indenter,
+    directory: &Path,
+    use_color: bool,
+) -> Result<()> {
+    let mut paths = fs::read_dir(directory)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<_>>>()?;
+    paths.sort();
+    test_indent_files(loader, loader_config, indenter, None, &paths, use_color)
+}
+
+pub fn test_indent_files(
+    loader: &Loader,
+    loader_config: &Config,
+    indenter: &mut Indenter,
+    scope: Option<&str>,
+    paths: &[PathBuf],
+    use_color: bool,
+) -> Result<()> {
+    let mut lang = None;
+    if let Some(scope) = scope {
+        lang = loader.language_configuration_for_scope(scope)?;
+        if lang.is_none() {
+            return Err(anyhow!("Unknown scope '{scope}'"));
+        }
+    }
+
+    let mut failed = false;
+    println!("indent:");
+    for test_file_path in paths {
+        let test_file_name = test_file_path
+            .file_name()
+            .unwrap_or(test_file_path.as_os_str())
+            .to_string_lossy();
+        let (language, language_config) = match lang.clone() {
+            Some(v) => v,
+            None => loader
+                .language_configuration_for_file_name(test_file_path)?
+                .ok_or_else(|| {
+                    anyhow!(
+                        "{}",
+                        util::lang_not_found_for_path(test_file_path.as_path(), loader_config)
+                    )
+                })?,
+        };
+        let indent_config = language_config
+            .indent_config(language)?
+            .ok_or_else(|| anyhow!("No indent config found for {:?}", test_file_path))?;
+        match test_indent(
+            loader,
+            indenter,
+            indent_config,
+            fs::read(test_file_path)?.as_slice(),
+        ) {
+            Ok(assertion_count) => {
+                println!(
+                    "  ✓ {} ({assertion_count} assertions)",
+                    opt_color(use_color, Colour::Green, test_file_name.as_ref()),
+                );
+            }
+            Err(e) => {
+                println!(
+                    "  ✗ {}",
+                    opt_color(use_color, Colour::Red, test_file_name.as_ref())
+                );
+                println!("    {e}");
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        Err(anyhow!(""))
+    } else {
+        Ok(())
+    }
+}
+
+pub fn test_indent(
+    loader: &Loader,
+    indenter: &mut Indenter,
+    indent_config: &IndentConfiguration,
+    source: &[u8],
+) -> Result<usize> {
+    let assertions = parse_position_comments(indenter.parser(), &indent_config.language, source)?;
+    let tree = indenter.parser.parse(source, None).unwrap();
+
+    // Each assertion applies to the line that precedes the comment that contains it.
+    for Assertion {
+        position,
+        negative,
+        expected_capture_name: expected_indentation,
+    } in &assertions
+    {
+        let indentation =
+            indenter.indent_for_line(indent_config, &tree, source, position.row, |string| {
+                loader.indent_config_for_injection_string(string)
+            })?;
+        let actual_indentation = indentation.to_string();
+        if (actual_indentation == *expected_indentation) == *negative {
+            return Err(Failure {
+                row: position.row,
+                expected_indentation: expected_indentation.clone(),
+                actual_indentation,
+                negative: *negative,
+            }
+            .into());
+        }
+    }
+
+    Ok(assertions.len())
+}
@@ -6,7 +6,9 @@ use tree_sitter_proc_macro::test_with_seed;
 use super::helpers::{
     allocations,
     edits::{get_random_edit, invert_edit},
-    fixtures::{fixtures_dir, get_language, get_test_language, SCRATCH_BASE_DIR},
+    fixtures::{
+        fixtures_dir, get_language, get_test_language, get_test_rust_language, SCRATCH_BASE_DIR,
+    },
     new_seed,
     random::Rand,
     scope_sequence::ScopeSequence,
@@ -406,6 +408,103 @@ fn test_feature_corpus_files() {
     assert!(failure_count == 0, "{failure_count} corpus tests failed");
 }
 
+#[test]
+fn test_feature_corpus_files_with_rust_parsers() {
+    test_feature_corpus_files_with_render_options(generate::RenderOptions {
+        rust: true,
+        ..Default::default()
+    });
+}
+
+#[test]
+fn test_feature_corpus_files_with_table_driven_lexers() {
+    test_feature_corpus_files_with_render_options(generate::RenderOptions {
+        table_driven_lexer: true,
+        ..Default::default()
+    });
+}
+
+/// Run the corpus tests for the test grammars that don't have external scanners, using
+/// parsers that are generated with the given options.
+fn test_feature_corpus_files_with_render_options(render_options: generate::RenderOptions) {
+    let test_grammars_dir = fixtures_dir().join("test_grammars");
+
+    let mut failure_count = 0;
+    for entry in fs::read_dir(test_grammars_dir).unwrap() {
+        let entry = entry.unwrap();
+        if !entry.metadata().unwrap().is_dir() {
+            continue;
+        }
+        let language_name = entry.file_name();
+        let language_name = language_name.to_str().unwrap();
+
+        if let Some(filter) = LANGUAGE_FILTER.as_ref() {
+            if language_name != filter.as_str() {
+                continue;
+            }
+        }
+
+        // External scanners are written in C, so they can't be built with only `rustc`.
+        // They also refer to the grammar's name, which is changed below.
+        let test_path = entry.path();
+        if test_path.join("expected_error.txt").exists() || test_path.join("scanner.c").exists() {
+            continue;
+        }
+
+        let mut grammar_path = test_path.join("grammar.js");
+        if !grammar_path.exists() {
+            grammar_path = test_path.join("grammar.json");
+        }
+        let grammar_json = generate::load_grammar_file(&grammar_path, None).unwrap();
+
+        let language = if render_options.rust {
+            let (_, rust_code) = generate::generate_parser_for_grammar_with_render_options(
+                &grammar_json,
+                render_options,
+            )
+            .unwrap();
+            get_test_rust_language(language_name, &rust_code)
+        } else {
+            // Give the parser a different name, so that it doesn't replace the parser
+            // that is compiled by `test_feature_corpus_files`.
+            let mut grammar = serde_json::from_str::<serde_json::Value>(&grammar_json).unwrap();
+            let name = format!("{language_name}_with_options");
+            grammar["name"] = name.clone().into();
+            let (_, c_code) = generate::generate_parser_for_grammar_with_render_options(
+                &grammar.to_string(),
+                render_options,
+            )
+            .unwrap();
+            get_test_language(&name, &c_code, None)
+        };
+        let tests = flatten_tests(parse_tests(&test_path.join("corpus.txt")).unwrap());
+
+        if !tests.is_empty() {
+            eprintln!("test language: {language_name:?}");
+        }
+
+        for test in tests {
+            eprintln!("  example: {:?}", test.name);
+
+            let mut parser = Parser::new();
+            parser.set_language(&language).unwrap();
+            let tree = parser.parse(&test.input, None).unwrap();
+            let mut actual_output = tree.root_node().to_sexp();
+            if !test.has_fields {
+                actual_output = strip_sexp_fields(&actual_output);
+            }
+            if actual_output != test.output {
+                print_diff_key();
+                print_diff(&actual_output, &test.output, true);
+                println!();
+                failure_count += 1;
+            }
+        }
+    }
+
+    assert!(failure_count == 0, "{failure_count} corpus tests failed");
+}
+
 fn check_consistent_sizes(tree: &Tree, input: &[u8]) {
     fn check(node: Node, line_offsets: &[usize]) {
         let start_byte = node.start_byte();
@@ -0,0 +1,177 @@
+use tree_sitter::Language;
+use tree_sitter_folds::{Error, Fold, FoldsConfiguration, FoldsContext};
+
+use super::helpers::fixtures::{get_test_grammar_language, get_test_language};
+use crate::generate::generate_parser_for_grammar;
+
+// A language whose blocks contain code in another language.
+const INJECTION_HOST_GRAMMAR: &str = r#"{
+    "name": "folds_injection_host",
+    "extras": [{"type": "PATTERN", "value": "\\s"}],
+    "rules": {
+        "document": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "block"}},
+        "block": {
+            "type": "SEQ",
+            "members": [
+                {"type": "STRING", "value": "<<"},
+                {"type": "SYMBOL", "name": "content"},
+                {"type": "STRING", "value": ">>"}
+            ]
+        },
+        "content": {"type": "PATTERN", "value": "[^<>\\s][^<>]*"}
+    }
+}"#;
+
+fn get_injection_host_language() -> Language {
+    let (grammar_name, parser_code) = generate_parser_for_grammar(INJECTION_HOST_GRAMMAR).unwrap();
+    get_test_language(&grammar_name, &parser_code, None)
+}
+
+fn fold_rows(folds: &[Fold]) -> Vec<(usize, usize, Option<&str>)> {
+    folds
+        .iter()
+        .map(|fold| (fold.start_row, fold.end_row, fold.kind.as_deref()))
+        .collect()
+}
+
+#[test]
+fn test_folds() {
+    let config = FoldsConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @fold (comment)+ @fold.comment",
+        "",
+    )
+    .unwrap();
+    let mut context = FoldsContext::new();
+    context.parser.set_language(&config.language).unwrap();
+
+    let source = [
+        "# one",
+        "# two",
+        "a = f(",
+        "  x,",
+        "  g(",
+        "    y",
+        "  )",
+        ");",
+        "b = h(x,",
+        "y);",
+        "c = i(j(",
+        "z));",
+        "d = k(z);",
+    ]
+    .join("\n");
+    let tree = context.parser.parse(&source, None).unwrap();
+    let folds = context
+        .folds(&config, &tree, source.as_bytes(), None, |_| None)
+        .unwrap();
+    assert_eq!(
+        fold_rows(&folds),
+        &[
+            (0, 1, Some("comment")),
+            (2, 7, None),
+            (4, 6, None),
+            (8, 9, None),
+            // Only the outermost range that starts on a line is kept, and ranges
+            // within a single line are ignored.
+            (10, 11, None),
+        ]
+    );
+}
+
+#[test]
+fn test_folds_merge_overlapping_ranges() {
+    let config = FoldsConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @fold (program (assignment) @fold . (assignment) @fold)",
+        "",
+    )
+    .unwrap();
+    let mut context = FoldsContext::new();
+    context.parser.set_language(&config.language).unwrap();
+
+    let source = "a = f(\nx);\nb = g(\nx);\nc = h(\nx);";
+    let tree = context.parser.parse(source, None).unwrap();
+    let folds = context
+        .folds(&config, &tree, source.as_bytes(), None, |_| None)
+        .unwrap();
+    assert_eq!(
+        fold_rows(&folds),
+        &[(0, 5, None), (2, 3, None), (4, 5, None)]
+    );
+}
+
+#[test]
+fn test_folds_with_injections() {
+    let injected_config = FoldsConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @fold",
+        "",
+    )
+    .unwrap();
+    let config = FoldsConfiguration::new(
+        get_injection_host_language(),
+        "host",
+        "(block) @fold",
+        r#"((content) @injection.content (#set! injection.language "inner"))"#,
+    )
+    .unwrap();
+    let mut context = FoldsContext::new();
+    context.parser.set_language(&config.language).unwrap();
+
+    let source = "<<\na = f(\nx);\n>>";
+    let tree = context.parser.parse(source, None).unwrap();
+    let mut injection_names = Vec::new();
+    let folds = context
+        .folds(&config, &tree, source.as_bytes(), None, |name| {
+            injection_names.push(name.to_string());
+            Some(&injected_config)
+        })
+        .unwrap();
+    assert_eq!(injection_names, &["inner"]);
+    assert_eq!(fold_rows(&folds), &[(0, 3, None), (1, 2, None)]);
+
+    // The parser still parses whole documents in its own language after
+    // parsing an injection.
+    let tree = context.parser.parse(source, None).unwrap();
+    assert_eq!(tree.root_node().kind(), "document");
+    assert_eq!(tree.root_node().byte_range(), 0..source.len());
+
+    // The contents of a combined injection are parsed as a single document.
+    let config = FoldsConfiguration::new(
+        get_injection_host_language(),
+        "host",
+        "",
+        r#"
+        ((content) @injection.content
+          (#set! injection.language "inner")
+          (#set! injection.combined))
+        "#,
+    )
+    .unwrap();
+    let source = "<<a = f(\n>>\n<<x);>>";
+    let tree = context.parser.parse(source, None).unwrap();
+    let folds = context
+        .folds(&config, &tree, source.as_bytes(), None, |_| {
+            Some(&injected_config)
+        })
+        .unwrap();
+    assert_eq!(fold_rows(&folds), &[(0, 2, None)]);
+}
+
+#[test]
+fn test_folds_config_with_invalid_capture() {
+    assert_eq!(
+        FoldsConfiguration::new(
+            get_test_grammar_language("fields_and_supertypes"),
+            "fields_and_supertypes",
+            "(call) @fold (call) @region",
+            "",
+        )
+        .unwrap_err(),
+        Error::InvalidCapture("region".to_string())
+    );
+}
@@ -0,0 +1,157 @@
+use tree_sitter::Point;
+use tree_sitter_format::{Doc, Error, Formatter, FormattingConfiguration, Line};
+
+use super::helpers::fixtures::get_test_grammar_language;
+
+const FORMATTING_QUERY: &str = r#"
+(assignment "=" @prepend_space @append_space ";" @append_newline) @allow_blank_line_before
+
+(call
+  "(" @append_indent_start @append_softline
+  ")" @prepend_indent_end @prepend_softline) @group
+
+(call "," @append_spaced_softline)
+
+(comment) @append_newline @allow_blank_line_before
+"#;
+
+#[test]
+fn test_format() {
+    let config = FormattingConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        FORMATTING_QUERY,
+    )
+    .unwrap();
+    let mut formatter = Formatter::new();
+    formatter.line_width = 30;
+
+    let source = b"
+        a=f(x,y);b   =  2;
+
+
+        # a comment
+        c = ggggggggg(aaaaaaaaaa, bbbbbbbbbbb, h(cccccccc, ddddddd));
+    ";
+    assert_eq!(
+        formatter.format(&config, source, None).unwrap(),
+        [
+            "a = f(x, y);",
+            "b = 2;",
+            "",
+            "# a comment",
+            "c = ggggggggg(",
+            "  aaaaaaaaaa,",
+            "  bbbbbbbbbbb,",
+            "  h(cccccccc, ddddddd)",
+            ");",
+            "",
+        ]
+        .join("\n")
+    );
+
+    // Formatting is idempotent.
+    let formatted = formatter.format(&config, source, None).unwrap();
+    assert_eq!(
+        formatter
+            .format(&config, formatted.as_bytes(), None)
+            .unwrap(),
+        formatted
+    );
+
+    formatter.line_width = 80;
+    formatter.indent = "    ".to_string();
+    assert_eq!(
+        formatter
+            .format(
+                &config,
+                b"c = g(aaaaaaaaaa, h(bbbbbbbbbbb, cccccccc));",
+                None
+            )
+            .unwrap(),
+        "c = g(aaaaaaaaaa, h(bbbbbbbbbbb, cccccccc));\n"
+    );
+}
+
+#[test]
+fn test_format_errors() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+
+    assert_eq!(
+        FormattingConfiguration::new(language.clone(), "(call) @append_tab")
+            .err()
+            .unwrap(),
+        Error::InvalidCapture("append_tab".to_string())
+    );
+
+    // Captures starting with an underscore can be used in predicates.
+    let config = FormattingConfiguration::new(
+        language,
+        r#"((identifier) @_name @delete (#eq? @_name "x"))"#,
+    )
+    .unwrap();
+    let mut formatter = Formatter::new();
+    assert_eq!(
+        formatter.format(&config, b"a = f(x);", None).unwrap(),
+        "a=f();\n"
+    );
+    assert_eq!(
+        formatter.format(&config, b"a = f(x);\nb = ;", None),
+        Err(Error::Syntax(Point::new(1, 3)))
+    );
+    assert_eq!(
+        formatter.format(&config, b"a = f(y);\n# \xff\n", None),
+        Err(Error::InvalidUtf8(Point::new(1, 2)))
+    );
+}
+
+#[test]
+fn test_format_deeply_nested_code() {
+    let config = FormattingConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        r#"(assignment "=" @prepend_space @append_space)"#,
+    )
+    .unwrap();
+    let mut formatter = Formatter::new();
+
+    let depth = 10_000;
+    let source = format!("a={}x{};", "f(".repeat(depth), ")".repeat(depth));
+    assert_eq!(
+        formatter.format(&config, source.as_bytes(), None).unwrap(),
+        format!("a = {}x{};\n", "f(".repeat(depth), ")".repeat(depth))
+    );
+}
+
+#[test]
+fn test_doc_render() {
+    let doc = Doc::Concat(vec![
+        Doc::text("["),
+        Doc::Concat(vec![
+            Doc::Line(Line::Softline),
+            Doc::text("1,"),
+            Doc::Line(Line::SpacedSoftline),
+            Doc::text("2,"),
+            Doc::Line(Line::Space),
+            Doc::Line(Line::Softline),
+            Doc::text("3"),
+        ])
+        .nest(),
+        Doc::Line(Line::Softline),
+        Doc::text("]"),
+    ])
+    .group();
+
+    assert_eq!(doc.render(10, "  "), "[1, 2, 3]\n");
+    assert_eq!(doc.render(8, "\t"), "[\n\t1,\n\t2,\n\t3\n]\n");
+
+    // Leading whitespace is removed, and the widest line between two pieces of
+    // text is printed.
+    let doc = Doc::Concat(vec![
+        Doc::Line(Line::Hardline),
+        Doc::text("a"),
+        Doc::Line(Line::Space),
+        Doc::Line(Line::Blankline),
+        Doc::Line(Line::Hardline),
+        Doc::text("b"),
+    ]);
+    assert_eq!(doc.render(80, "  "), "a\n\nb\n");
+}
@@ -0,0 +1,184 @@
+use std::fs;
+
+use serde_json::Value;
+
+use super::helpers::fixtures::fixtures_dir;
+use crate::generate::load_grammar_file;
+
+#[test]
+fn test_native_runtime_matches_node_for_test_grammars() {
+    for entry in fs::read_dir(fixtures_dir().join("test_grammars")).unwrap() {
+        let grammar_path = entry.unwrap().path().join("grammar.js");
+        if !grammar_path.exists() {
+            continue;
+        }
+        assert_eq!(
+            load_grammar_json(&grammar_path, Some("native")),
+            load_grammar_json(&grammar_path, None),
+            "grammar {grammar_path:?}",
+        );
+    }
+}
+
+#[test]
+fn test_native_runtime_with_helpers_and_modules() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("helpers.js"),
+        r"
+        const PREC = { ADD: 1, MUL: 2, CALL: 3 };
+
+        function commaSep1(rule) {
+          return seq(rule, repeat(seq(',', rule)));
+        }
+
+        module.exports = {
+          PREC,
+          commaSep: (rule) => optional(commaSep1(rule)),
+          commaSep1,
+        };
+        ",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("grammar.js"),
+        r"
+        const { PREC, commaSep } = require('./helpers');
+
+        const operators = [
+          ['+', PREC.ADD],
+          ['-', PREC.ADD],
+          ['*', PREC.MUL],
+          [`/`, PREC.MUL],
+        ];
+
+        module.exports = grammar({
+          name: 'calculator',
+
+          word: $ => $.identifier,
+
+          extras: $ => [/\s/, $.comment],
+
+          externals: $ => [$.string_start, $._string_content],
+
+          inline: $ => [$._expression, $._expression],
+
+          supertypes: $ => [$._expression],
+
+          conflicts: $ => [[$.call, $._expression]],
+
+          precedences: $ => [['call', 'binary']],
+
+          rules: {
+            program: $ => repeat($._expression),
+
+            _expression: $ => choice(
+              $.identifier,
+              $.number,
+              $.binary,
+              $.call,
+              alias($.string_start, $.string),
+            ),
+
+            binary: $ => choice(...operators.map(([operator, precedence]) =>
+              prec.left(precedence, seq(
+                field('left', $._expression),
+                field('operator', operator),
+                field('right', $._expression),
+              )),
+            )),
+
+            call: $ => prec('call', seq(
+              field('function', $.identifier),
+              '(', commaSep($._expression), ')',
+            )),
+
+            number: _ => token(choice(/\d+/, /0[xX][\da-f]+/i)),
+
+            identifier: _ => /[a-z_]\w*/,
+
+            comment: _ => token(seq('#', /.*/)),
+          },
+        });
+        ",
+    )
+    .unwrap();
+
+    let grammar_path = temp_dir.path().join("grammar.js");
+    let native_json = load_grammar_json(&grammar_path, Some("native"));
+    assert_eq!(native_json, load_grammar_json(&grammar_path, None));
+    assert_eq!(native_json["name"], "calculator");
+    assert_eq!(native_json["inline"], serde_json::json!(["_expression"]));
+    assert_eq!(
+        native_json["rules"]["number"]["content"]["members"][1],
+        serde_json::json!({"type": "PATTERN", "value": "0[xX][\\da-f]+", "flags": "i"}),
+    );
+}
+
+#[test]
+fn test_native_runtime_reports_undefined_symbols() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let grammar_path = temp_dir.path().join("grammar.js");
+    fs::write(
+        &grammar_path,
+        r"
+        module.exports = grammar({
+          name: 'test',
+          rules: {
+            program: $ => seq('a', $.missing),
+          },
+        });
+        ",
+    )
+    .unwrap();
+
+    let error = load_grammar_file(&grammar_path, Some("native")).unwrap_err();
+    let message = format!("{error:#}");
+    assert!(
+        message.contains("ReferenceError: Undefined symbol 'missing'"),
+        "unexpected error: {message}"
+    );
+}
+
+#[test]
+fn test_native_runtime_reports_regexp_methods_on_other_values() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let grammar_path = temp_dir.path().join("grammar.js");
+    for (method, call) in [
+        ("test", "/a/.test.call({}, 'x')"),
+        (
+            "exec",
+            "(() => { const { exec } = /a/; return exec('x'); })()",
+        ),
+    ] {
+        fs::write(
+            &grammar_path,
+            format!(
+                "
+                {call};
+                module.exports = grammar({{
+                  name: 'test',
+                  rules: {{
+                    program: $ => 'a',
+                  }},
+                }});
+                "
+            ),
+        )
+        .unwrap();
+
+        let error = load_grammar_file(&grammar_path, Some("native")).unwrap_err();
+        let message = format!("{error:#}");
+        assert!(
+            message.contains(&format!(
+                "TypeError: RegExp.prototype.{method} requires that 'this' be a RegExp"
+            )),
+            "unexpected error: {message}"
+        );
+    }
+}
+
+fn load_grammar_json(grammar_path: &std::path::Path, js_runtime: Option<&str>) -> Value {
+    let json = load_grammar_file(grammar_path, js_runtime).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
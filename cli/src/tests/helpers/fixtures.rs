@@ -1,6 +1,7 @@
 use std::{
     env, fs,
     path::{Path, PathBuf},
+    process::Command,
 };
 
 use anyhow::Context;
@@ -10,7 +11,7 @@ use tree_sitter_highlight::HighlightConfiguration;
 use tree_sitter_loader::{CompileConfig, Loader};
 use tree_sitter_tags::TagsConfiguration;
 
-use crate::generate::ALLOC_HEADER;
+use crate::generate::{generate_parser_for_grammar, load_grammar_file, ALLOC_HEADER};
 
 include!("./dirs.rs");
 
@@ -80,6 +81,13 @@ pub fn get_tags_config(language_name: &str) -> TagsConfiguration {
     TagsConfiguration::new(language, &tags_query, &locals_query).unwrap()
 }
 
+pub fn get_test_grammar_language(name: &str) -> Language {
+    let dir = fixtures_dir().join("test_grammars").join(name);
+    let grammar_json = load_grammar_file(&dir.join("grammar.js"), None).unwrap();
+    let (grammar_name, parser_code) = generate_parser_for_grammar(&grammar_json).unwrap();
+    get_test_language(&grammar_name, &parser_code, Some(&dir))
+}
+
 pub fn get_test_language(name: &str, parser_code: &str, path: Option<&Path>) -> Language {
     let src_dir = scratch_dir().join("src").join(name);
     fs::create_dir_all(&src_dir).unwrap();
@@ -135,3 +143,38 @@ pub fn get_test_language(name: &str, parser_code: &str, path: Option<&Path>) ->
 
     TEST_LOADER.load_language_at_path_with_name(config).unwrap()
 }
+
+/// Compile a parser that was generated as a Rust module into a dynamic library, using
+/// only `rustc`, and load its language.
+pub fn get_test_rust_language(name: &str, parser_code: &str) -> Language {
+    let src_dir = scratch_dir().join("src").join(name);
+    fs::create_dir_all(&src_dir).unwrap();
+
+    let parser_path = src_dir.join("parser.rs");
+    fs::write(&parser_path, parser_code).unwrap();
+
+    let library_path = scratch_dir().join(format!("{name}_rust.{}", env::consts::DLL_EXTENSION));
+    let output = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+        .args(["--edition", "2021", "--crate-type", "cdylib", "-o"])
+        .arg(&library_path)
+        .arg(&parser_path)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "Failed to compile {parser_path:?}:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let library = unsafe { libloading::Library::new(&library_path) }.unwrap();
+    let language = unsafe {
+        let language_fn = library
+            .get::<libloading::Symbol<unsafe extern "C" fn() -> Language>>(
+                format!("tree_sitter_{name}").as_bytes(),
+            )
+            .unwrap();
+        language_fn()
+    };
+    std::mem::forget(library);
+    language
+}
@@ -7,11 +7,16 @@ use std::{
 };
 
 use lazy_static::lazy_static;
+use tree_sitter::{InputEdit, Parser, Point};
 use tree_sitter_highlight::{
-    c, Error, Highlight, HighlightConfiguration, HighlightEvent, Highlighter, HtmlRenderer,
+    c, AnsiRenderer, Color, Error, Highlight, HighlightConfiguration, HighlightEvent,
+    HighlightSession, Highlighter, HtmlRenderer, LatexRenderer, Renderer, RtfRenderer, Style,
+    SvgRenderer,
 };
 
-use super::helpers::fixtures::{get_highlight_config, get_language, get_language_queries_path};
+use super::helpers::fixtures::{
+    get_highlight_config, get_language, get_language_queries_path, get_test_grammar_language,
+};
 
 lazy_static! {
     static ref JS_HIGHLIGHT: HighlightConfiguration =
@@ -683,6 +688,362 @@ fn test_decode_utf8_lossy() {
     assert_eq!(parts, vec!["hello", "\u{fffd}", "\u{fffd}"]);
 }
 
+#[test]
+fn test_highlighting_incrementally() {
+    let mut config = HighlightConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @string (number) @constant (call function: (identifier) @function)",
+        "",
+        "",
+    )
+    .unwrap();
+    config.configure(&HIGHLIGHT_NAMES);
+
+    let mut highlighter = Highlighter::new();
+    let mut session = HighlightSession::new();
+
+    // The first time, the whole document is highlighted.
+    let mut source = "a = f(1);\nb = g(2);\nc = h(3);".to_string();
+    assert_eq!(
+        to_incremental_token_vector(&mut highlighter, &mut session, &config, None, &source),
+        &[
+            ("a = ", vec![]),
+            ("f", vec!["string", "function"]),
+            ("(", vec!["string"]),
+            ("1", vec!["string", "constant"]),
+            (")", vec!["string"]),
+            (";\nb = ", vec![]),
+            ("g", vec!["string", "function"]),
+            ("(", vec!["string"]),
+            ("2", vec!["string", "constant"]),
+            (")", vec!["string"]),
+            (";\nc = ", vec![]),
+            ("h", vec!["string", "function"]),
+            ("(", vec!["string"]),
+            ("3", vec!["string", "constant"]),
+            (")", vec!["string"]),
+            (";", vec![]),
+        ]
+    );
+    let document_range = 0..source.len();
+    assert_eq!(session.changed_ranges(), &[document_range]);
+
+    // After an edit, only the changed text is highlighted again, within the
+    // highlights that enclose it.
+    edit_source(&mut session, &mut source, "2", "23");
+    assert_eq!(
+        to_incremental_token_vector(&mut highlighter, &mut session, &config, None, &source),
+        &[("23", vec!["string", "constant"])]
+    );
+    let edited_range = 16..18;
+    assert_eq!(session.changed_ranges(), &[edited_range]);
+
+    // Changes to the structure of the document are highlighted as well.
+    edit_source(&mut session, &mut source, "(3)", "(i(3))");
+    assert_eq!(
+        to_incremental_token_vector(&mut highlighter, &mut session, &config, None, &source),
+        &[
+            ("(", vec!["string"]),
+            ("i", vec!["string", "string", "function"]),
+            ("(", vec!["string", "string"]),
+            ("3", vec!["string", "string", "constant"]),
+            (")", vec!["string", "string"]),
+            (")", vec!["string"]),
+        ]
+    );
+
+    // Without any edits, nothing is highlighted.
+    assert_eq!(
+        to_incremental_token_vector(&mut highlighter, &mut session, &config, None, &source),
+        &[]
+    );
+    assert!(session.changed_ranges().is_empty());
+
+    let mut parser = Parser::new();
+    parser.set_language(&config.language).unwrap();
+    assert_eq!(
+        session.tree().unwrap().root_node().to_sexp(),
+        parser.parse(&source, None).unwrap().root_node().to_sexp()
+    );
+}
+
+#[test]
+fn test_highlighting_incrementally_with_injections() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut injected_config =
+        HighlightConfiguration::new(language.clone(), "inner", "(number) @constant", "", "")
+            .unwrap();
+    injected_config.configure(&HIGHLIGHT_NAMES);
+    let mut config = HighlightConfiguration::new(
+        language,
+        "outer",
+        "(identifier) @variable",
+        r#"
+        ((assignment left: (identifier) @_name) @injection.content
+          (#eq? @_name "b")
+          (#set! injection.language "inner")
+          (#set! injection.include-children))
+        "#,
+        "",
+    )
+    .unwrap();
+    config.configure(&HIGHLIGHT_NAMES);
+
+    let mut highlighter = Highlighter::new();
+    let mut session = HighlightSession::new();
+    let mut source = "a = 1;\nb = 2;\nc = 3;".to_string();
+    assert_eq!(
+        to_incremental_token_vector(
+            &mut highlighter,
+            &mut session,
+            &config,
+            Some(&injected_config),
+            &source,
+        ),
+        &[
+            ("a", vec!["variable"]),
+            (" = 1;\n", vec![]),
+            ("b", vec!["variable"]),
+            (" = ", vec![]),
+            ("2", vec!["constant"]),
+            (";\n", vec![]),
+            ("c", vec!["variable"]),
+            (" = 3;", vec![]),
+        ]
+    );
+
+    // An edit outside of the injection only highlights the edited text.
+    edit_source(&mut session, &mut source, "3", "d");
+    assert_eq!(
+        to_incremental_token_vector(
+            &mut highlighter,
+            &mut session,
+            &config,
+            Some(&injected_config),
+            &source,
+        ),
+        &[("d", vec!["variable"])]
+    );
+
+    // An edit inside of the injection highlights the edited text with the
+    // injected language.
+    edit_source(&mut session, &mut source, "2", "42");
+    assert_eq!(
+        to_incremental_token_vector(
+            &mut highlighter,
+            &mut session,
+            &config,
+            Some(&injected_config),
+            &source,
+        ),
+        &[("42", vec!["constant"])]
+    );
+
+    // When an injection is added, all of its text is highlighted.
+    edit_source(&mut session, &mut source, "a = 1", "b = 1");
+    assert_eq!(
+        to_incremental_token_vector(
+            &mut highlighter,
+            &mut session,
+            &config,
+            Some(&injected_config),
+            &source,
+        ),
+        &[
+            ("b", vec!["variable"]),
+            (" = ", vec![]),
+            ("1", vec!["constant"]),
+            (";", vec![]),
+        ]
+    );
+}
+
+#[test]
+fn test_highlighting_range() {
+    let mut config = HighlightConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @string (number) @constant (call function: (identifier) @function)",
+        "",
+        "",
+    )
+    .unwrap();
+    config.configure(&HIGHLIGHT_NAMES);
+
+    let source = "a = f(1,\n2);\nb = g(3);\nc = h(4);";
+    let mut highlighter = Highlighter::new();
+
+    // Highlights that start before the range are started at the start of the
+    // range, and highlights that end after the range are ended at its end.
+    let line_range = |row: usize| {
+        let start = source.split_inclusive('\n').take(row).map(str::len).sum();
+        start..start + source.split('\n').nth(row).unwrap().len()
+    };
+    let events = highlighter
+        .highlight_range(&config, source.as_bytes(), line_range(1), None, |_| None)
+        .unwrap();
+    assert_eq!(
+        to_tokens(source, events),
+        &[
+            ("2", vec!["string", "constant"]),
+            (")", vec!["string"]),
+            (";", vec![]),
+        ]
+    );
+
+    let range = line_range(2).start + 5..line_range(3).start + 6;
+    let events = highlighter
+        .highlight_range(&config, source.as_bytes(), range, None, |_| None)
+        .unwrap();
+    assert_eq!(
+        to_tokens(source, events),
+        &[
+            ("(", vec!["string"]),
+            ("3", vec!["string", "constant"]),
+            (")", vec!["string"]),
+            (";\nc = ", vec![]),
+            ("h", vec!["string", "function"]),
+            ("(", vec!["string"]),
+        ]
+    );
+
+    // The full range produces the same events as highlighting the whole document.
+    let events = highlighter
+        .highlight_range(&config, source.as_bytes(), 0..source.len(), None, |_| None)
+        .unwrap();
+    let range_tokens = to_tokens(source, events);
+    let events = highlighter
+        .highlight(&config, source.as_bytes(), None, |_| None)
+        .unwrap();
+    assert_eq!(range_tokens, to_tokens(source, events));
+}
+
+#[test]
+fn test_highlighting_range_with_injections() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut injected_config =
+        HighlightConfiguration::new(language.clone(), "inner", "(number) @constant", "", "")
+            .unwrap();
+    injected_config.configure(&HIGHLIGHT_NAMES);
+    let mut config = HighlightConfiguration::new(
+        language,
+        "outer",
+        "(identifier) @variable",
+        r#"
+        ((assignment left: (identifier) @_name) @injection.content
+          (#eq? @_name "b")
+          (#set! injection.language "inner")
+          (#set! injection.include-children))
+        "#,
+        "",
+    )
+    .unwrap();
+    config.configure(&HIGHLIGHT_NAMES);
+
+    // The injection starts before the range.
+    let source = "a = 1;\nb = 2;\nc = 3;";
+    let start = source.find('2').unwrap();
+    let end = source.find('c').unwrap() + 1;
+    let mut highlighter = Highlighter::new();
+    let events = highlighter
+        .highlight_range(&config, source.as_bytes(), start..end, None, |_| {
+            Some(&injected_config)
+        })
+        .unwrap();
+    assert_eq!(
+        to_tokens(source, events),
+        &[
+            ("2", vec!["constant"]),
+            (";\n", vec![]),
+            ("c", vec!["variable"]),
+        ]
+    );
+}
+
+#[test]
+fn test_rendering_formats() {
+    let mut config = HighlightConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(number) @constant (call function: (identifier) @function)",
+        "",
+        "",
+    )
+    .unwrap();
+    config.configure(&HIGHLIGHT_NAMES);
+    let styles = HIGHLIGHT_NAMES
+        .iter()
+        .map(|name| match name.as_str() {
+            "function" => Style {
+                color: Some(Color::Rgb(0x26, 0xa6, 0x9a)),
+                bold: true,
+                ..Style::default()
+            },
+            "constant" => Style {
+                color: Some(Color::Ansi256(94)),
+                ..Style::default()
+            },
+            _ => Style::default(),
+        })
+        .collect::<Vec<_>>();
+
+    let source = "a = f(1); # {\\} é\n";
+    let mut highlighter = Highlighter::new();
+    let mut render = |renderer: &mut dyn Renderer| {
+        let events = highlighter
+            .highlight(&config, source.as_bytes(), None, |_| None)
+            .unwrap();
+        tree_sitter_highlight::render(renderer, events, source.as_bytes(), &styles).unwrap();
+        String::from_utf8(renderer.output().to_vec()).unwrap()
+    };
+
+    assert_eq!(
+        render(&mut AnsiRenderer::new(true)),
+        "a = \x1b[1;38;2;38;166;154mf\x1b[0m(\x1b[38;5;94m1\x1b[0m); # {\\} é\n"
+    );
+    assert_eq!(
+        render(&mut AnsiRenderer::new(false)),
+        "a = \x1b[1;38;5;36mf\x1b[0m(\x1b[38;5;94m1\x1b[0m); # {\\} é\n"
+    );
+    assert_eq!(
+        render(&mut HtmlRenderer::new()),
+        "a = <span style='font-weight: bold;color: #26a69a'>f</span>(<span style='color: #875f00'>1</span>); # {\\} é\n"
+    );
+    assert_eq!(
+        render(&mut LatexRenderer::new()),
+        [
+            "\\begin{Verbatim}[commandchars=\\\\\\{\\}]",
+            "a = \\textcolor[HTML]{26A69A}{\\textbf{f}}(\\textcolor[HTML]{875F00}{1}); # \\{\\textbackslash{}\\} é",
+            "\\end{Verbatim}",
+            "",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        render(&mut RtfRenderer::new()),
+        [
+            "{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern Courier New;}}",
+            "{\\colortbl ;\\red38\\green166\\blue154;\\red135\\green95\\blue0;}",
+            "\\f0\\fs20",
+            "a = {\\cf1\\b f}({\\cf2 1}); # \\{\\\\\\} \\u233?\\line",
+            "}",
+            "",
+        ]
+        .join("\n")
+    );
+    assert_eq!(
+        render(&mut SvgRenderer::new()),
+        [
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"163\" height=\"40\" font-family=\"monospace\" font-size=\"14\" xml:space=\"preserve\">",
+            "<text x=\"10\" y=\"24\">a = <tspan fill=\"#26a69a\" font-weight=\"bold\">f</tspan>(<tspan fill=\"#875f00\">1</tspan>); # {\\} é</text>",
+            "</svg>",
+            "",
+        ]
+        .join("\n")
+    );
+}
+
 fn c_string(s: &str) -> CString {
     CString::new(s.as_bytes().to_vec()).unwrap()
 }
@@ -767,3 +1128,61 @@ fn to_token_vector<'a>(
     }
     Ok(lines)
 }
+
+fn edit_source(session: &mut HighlightSession, source: &mut String, old: &str, new: &str) {
+    let start_byte = source.find(old).unwrap();
+    let start_position = position_for_byte(source, start_byte);
+    let old_end_position = position_for_byte(source, start_byte + old.len());
+    source.replace_range(start_byte..start_byte + old.len(), new);
+    session.edit(&InputEdit {
+        start_byte,
+        old_end_byte: start_byte + old.len(),
+        new_end_byte: start_byte + new.len(),
+        start_position,
+        old_end_position,
+        new_end_position: position_for_byte(source, start_byte + new.len()),
+    });
+}
+
+fn position_for_byte(source: &str, byte: usize) -> Point {
+    let text = &source[..byte];
+    let row = text.matches('\n').count();
+    let column = byte - text.rfind('\n').map_or(0, |i| i + 1);
+    Point::new(row, column)
+}
+
+fn to_incremental_token_vector<'a>(
+    highlighter: &mut Highlighter,
+    session: &mut HighlightSession,
+    config: &HighlightConfiguration,
+    injected_config: Option<&HighlightConfiguration>,
+    source: &'a str,
+) -> Vec<(&'a str, Vec<&'static str>)> {
+    let events = highlighter
+        .highlight_incremental(session, config, source.as_bytes(), None, |_| {
+            injected_config
+        })
+        .unwrap();
+    to_tokens(source, events)
+}
+
+fn to_tokens(
+    source: &str,
+    events: impl Iterator<Item = Result<HighlightEvent, Error>>,
+) -> Vec<(&str, Vec<&'static str>)> {
+    let mut tokens = Vec::new();
+    let mut highlights = Vec::new();
+    for event in events {
+        match event.unwrap() {
+            HighlightEvent::HighlightStart(s) => highlights.push(HIGHLIGHT_NAMES[s.0].as_str()),
+            HighlightEvent::HighlightEnd => {
+                highlights.pop().unwrap();
+            }
+            HighlightEvent::Source { start, end } => {
+                tokens.push((&source[start..end], highlights.clone()));
+            }
+        }
+    }
+    assert_eq!(highlights, Vec::<&str>::new());
+    tokens
+}
@@ -0,0 +1,232 @@
+use std::path::PathBuf;
+
+use tree_sitter::Language;
+use tree_sitter_indent::{Error, IndentConfiguration, Indentation, Indenter};
+use tree_sitter_loader::Loader;
+
+use super::helpers::fixtures::{get_test_grammar_language, get_test_language};
+use crate::{generate::generate_parser_for_grammar, test_indent::test_indent};
+
+// A language whose blocks contain code in another language.
+const INJECTION_HOST_GRAMMAR: &str = r#"{
+    "name": "indent_injection_host",
+    "extras": [{"type": "PATTERN", "value": "\\s"}],
+    "rules": {
+        "document": {"type": "REPEAT", "content": {"type": "SYMBOL", "name": "block"}},
+        "block": {
+            "type": "SEQ",
+            "members": [
+                {"type": "STRING", "value": "<<"},
+                {"type": "SYMBOL", "name": "content"},
+                {"type": "STRING", "value": ">>"}
+            ]
+        },
+        "content": {"type": "PATTERN", "value": "[^<>\\s][^<>]*"}
+    }
+}"#;
+
+fn get_injection_host_language() -> Language {
+    let (grammar_name, parser_code) = generate_parser_for_grammar(INJECTION_HOST_GRAMMAR).unwrap();
+    get_test_language(&grammar_name, &parser_code, None)
+}
+
+fn indentations(config: &IndentConfiguration, source: &str) -> Vec<String> {
+    let mut indenter = Indenter::new();
+    indenter.parser.set_language(&config.language).unwrap();
+    let tree = indenter.parser.parse(source, None).unwrap();
+    (0..source.lines().count())
+        .map(|row| {
+            indenter
+                .indent_for_line(config, &tree, source.as_bytes(), row, |_| None)
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn test_indent_and_outdent() {
+    let config = IndentConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        r#"(call) @indent (call ")" @outdent)"#,
+        "",
+    )
+    .unwrap();
+
+    let source = ["a = f(", "x,", "g(", "y),", "", "z", ");"].join("\n");
+    assert_eq!(
+        indentations(&config, &source),
+        &["0", "1", "1", "2", "1", "1", "0"]
+    );
+
+    // Nodes that start on the same line only indent the lines within them once.
+    assert_eq!(indentations(&config, "a = f(g(\nx));"), &["0", "1"]);
+}
+
+#[test]
+fn test_indent_always() {
+    let config = IndentConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @indent.always",
+        "",
+    )
+    .unwrap();
+    assert_eq!(indentations(&config, "a = f(g(\nx));"), &["0", "2"]);
+}
+
+#[test]
+fn test_align() {
+    let config = IndentConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @align",
+        "",
+    )
+    .unwrap();
+
+    assert_eq!(
+        indentations(&config, "a = f(x,\ny,\nz);"),
+        &["0", "column.6", "column.6"]
+    );
+
+    // Without an argument on the first line, the lines are indented instead.
+    assert_eq!(indentations(&config, "a = f(\nx,\ny);"), &["0", "1", "1"]);
+
+    // The innermost aligned node takes precedence.
+    assert_eq!(
+        indentations(&config, "a = f(x, g(y,\nz));"),
+        &["0", "column.11"]
+    );
+
+    let indentation = Indentation {
+        align_column: Some(6),
+        level: 1,
+    };
+    assert_eq!(indentation.to_string(), "column.6.1");
+    assert_eq!(indentation.whitespace("\t"), "      \t");
+}
+
+#[test]
+fn test_indent_at_byte() {
+    let config = IndentConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @indent",
+        "",
+    )
+    .unwrap();
+    let source = b"a = f(\n  x);";
+    let mut indenter = Indenter::new();
+    indenter.parser.set_language(&config.language).unwrap();
+    let tree = indenter.parser.parse(source, None).unwrap();
+
+    let indentation = indenter
+        .indent_at_byte(&config, &tree, source, 9, |_| None)
+        .unwrap();
+    assert_eq!(
+        indentation,
+        Indentation {
+            align_column: None,
+            level: 1
+        }
+    );
+    assert_eq!(indentation.whitespace("  "), "  ");
+}
+
+#[test]
+fn test_indent_with_injections() {
+    let injected_config = IndentConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        "(call) @indent",
+        "",
+    )
+    .unwrap();
+    let config = IndentConfiguration::new(
+        get_injection_host_language(),
+        "host",
+        r#"(block) @indent (block ">>" @outdent)"#,
+        r#"((content) @injection.content (#set! injection.language "inner"))"#,
+    )
+    .unwrap();
+
+    let source = "<<\na = f(\nx);\n>>";
+    let mut indenter = Indenter::new();
+    indenter.parser.set_language(&config.language).unwrap();
+    let tree = indenter.parser.parse(source, None).unwrap();
+
+    // The indentation within the injection is added to the indentation of the
+    // injection itself.
+    let mut injection_names = Vec::new();
+    let indentation = indenter
+        .indent_for_line(&config, &tree, source.as_bytes(), 2, |name| {
+            injection_names.push(name.to_string());
+            Some(&injected_config)
+        })
+        .unwrap();
+    assert_eq!(injection_names, &["inner"]);
+    assert_eq!(indentation.to_string(), "2");
+    let indentation = indenter
+        .indent_for_line(&config, &tree, source.as_bytes(), 1, |_| {
+            Some(&injected_config)
+        })
+        .unwrap();
+    assert_eq!(indentation.to_string(), "1");
+
+    // Lines outside of the injection are not affected by it.
+    let indentation = indenter
+        .indent_for_line(&config, &tree, source.as_bytes(), 3, |_| {
+            panic!("Unexpected injection")
+        })
+        .unwrap();
+    assert_eq!(indentation.to_string(), "0");
+
+    // The parser still parses whole documents in its own language after
+    // parsing an injection.
+    let tree = indenter.parser.parse(source, None).unwrap();
+    assert_eq!(tree.root_node().kind(), "document");
+    assert_eq!(tree.root_node().byte_range(), 0..source.len());
+}
+
+#[test]
+fn test_indent_config_with_invalid_capture() {
+    assert_eq!(
+        IndentConfiguration::new(
+            get_test_grammar_language("fields_and_supertypes"),
+            "fields_and_supertypes",
+            "(call) @indent (call) @dedent",
+            "",
+        )
+        .unwrap_err(),
+        Error::InvalidCapture("dedent".to_string())
+    );
+}
+
+#[test]
+fn test_indent_test_with_assertions() {
+    let config = IndentConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        r#"(call) @indent (call ")" @outdent)"#,
+        "",
+    )
+    .unwrap();
+    let loader = Loader::with_parser_lib_path(PathBuf::new());
+    let mut indenter = Indenter::new();
+
+    let source = ["a = f(", "  x,", "# <- 1", "  y", "# <- !0", ");", "# <- 0"].join("\n");
+    assert_eq!(
+        test_indent(&loader, &mut indenter, &config, source.as_bytes()).unwrap(),
+        3
+    );
+
+    let source = ["a = f(", "x);", "# <- 2"].join("\n");
+    assert_eq!(
+        test_indent(&loader, &mut indenter, &config, source.as_bytes())
+            .unwrap_err()
+            .to_string(),
+        "Failure - row: 1, expected indentation: '2', actual indentation: '1'"
+    );
+}
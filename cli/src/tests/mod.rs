@@ -1,18 +1,28 @@
 mod async_context_test;
 mod corpus_test;
 mod detect_language;
+mod folds_test;
+mod format_test;
+mod grammar_dsl_test;
 mod helpers;
 mod highlight_test;
+mod indent_test;
 mod language_test;
 mod node_test;
 mod parser_hang_test;
 mod parser_test;
 mod pathological_test;
+mod query_macro_test;
 mod query_test;
+mod rewrite_test;
+mod rust_ast_test;
+mod semantic_tokens_test;
 mod tags_test;
 mod test_highlight_test;
 mod test_tags_test;
 mod text_provider_test;
+mod theme_test;
+mod tree_diff_test;
 mod tree_test;
 
 #[cfg(feature = "wasm")]
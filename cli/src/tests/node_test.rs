@@ -1,4 +1,5 @@
-use tree_sitter::{Node, Parser, Point, Tree};
+use serde_json::json;
+use tree_sitter::{Node, Parser, Point, SerializedTree, Tree};
 
 use super::helpers::{
     edits::get_random_edit,
@@ -945,6 +946,200 @@ fn test_node_numeric_symbols_respect_simple_aliases() {
     assert_eq!(unary_minus_node.kind_id(), binary_minus_node.kind_id());
 }
 
+#[test]
+fn test_node_serialize() {
+    let (parser_name, parser_code) = generate_parser_for_grammar(
+        r##"
+        {
+            "name": "test_grammar_for_serialization",
+            "extras": [
+                {"type": "PATTERN", "value": "\\s+"},
+                {"type": "SYMBOL", "name": "comment"}
+            ],
+            "rules": {
+                "assignment": {
+                    "type": "SEQ",
+                    "members": [
+                        {
+                            "type": "FIELD",
+                            "name": "left",
+                            "content": {"type": "SYMBOL", "name": "identifier"}
+                        },
+                        {"type": "STRING", "value": "="},
+                        {
+                            "type": "FIELD",
+                            "name": "right",
+                            "content": {"type": "SYMBOL", "name": "identifier"}
+                        }
+                    ]
+                },
+                "identifier": {"type": "PATTERN", "value": "[a-z]+"},
+                "comment": {"type": "PATTERN", "value": "#.*"}
+            }
+        }
+    "##,
+    )
+    .unwrap();
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&get_test_language(&parser_name, &parser_code, None))
+        .unwrap();
+
+    let source = "a = b # c";
+    let tree = parser.parse(source, None).unwrap();
+    let range = |start: usize, end: usize| {
+        json!({
+            "start_byte": start,
+            "end_byte": end,
+            "start_point": {"row": 0, "column": start},
+            "end_point": {"row": 0, "column": end},
+        })
+    };
+
+    let json =
+        serde_json::to_value(tree.root_node().serializable().with_text(source.as_bytes())).unwrap();
+    assert_eq!(
+        json,
+        json!([
+            {
+                "kind": "assignment",
+                "named": true,
+                "extra": false,
+                "missing": false,
+                "error": false,
+                "range": range(0, 9),
+            },
+            {
+                "kind": "identifier",
+                "field": "left",
+                "parent": 0,
+                "named": true,
+                "extra": false,
+                "missing": false,
+                "error": false,
+                "range": range(0, 1),
+                "text": "a",
+            },
+            {
+                "kind": "=",
+                "parent": 0,
+                "named": false,
+                "extra": false,
+                "missing": false,
+                "error": false,
+                "range": range(2, 3),
+                "text": "=",
+            },
+            {
+                "kind": "identifier",
+                "field": "right",
+                "parent": 0,
+                "named": true,
+                "extra": false,
+                "missing": false,
+                "error": false,
+                "range": range(4, 5),
+                "text": "b",
+            },
+            {
+                "kind": "comment",
+                "parent": 0,
+                "named": true,
+                "extra": true,
+                "missing": false,
+                "error": false,
+                "range": range(6, 9),
+                "text": "# c",
+            },
+        ])
+    );
+
+    // Without the source, the text of the leaves is omitted.
+    let json = serde_json::to_value(tree.root_node()).unwrap();
+    assert_eq!(json[1].get("text"), None);
+
+    // The serialized tree can be loaded back into owned nodes.
+    let serialized_tree = serde_json::from_value::<SerializedTree>(json).unwrap();
+    let root = serialized_tree.root().unwrap();
+    assert_eq!(root.kind, "assignment");
+    assert_eq!(root.range, tree.root_node().range());
+    let children = serialized_tree
+        .children(0)
+        .map(|(_, child)| child)
+        .collect::<Vec<_>>();
+    assert_eq!(children[2].field.as_deref(), Some("right"));
+    assert!(children[3].extra);
+    assert_eq!(
+        serde_json::to_value(&serialized_tree).unwrap(),
+        serde_json::to_value(tree.root_node()).unwrap()
+    );
+
+    // Trees with errors record the error and missing nodes.
+    let tree = parser.parse("a =", None).unwrap();
+    let serialized_tree =
+        serde_json::from_str::<SerializedTree>(&serde_json::to_string(&tree.root_node()).unwrap())
+            .unwrap();
+    assert_eq!(serialized_tree.nodes[3].kind, "identifier");
+    assert!(serialized_tree.nodes[3].missing);
+}
+
+#[test]
+fn test_node_serialize_deeply_nested_tree() {
+    let (parser_name, parser_code) = generate_parser_for_grammar(
+        r#"
+        {
+            "name": "test_grammar_for_nested_serialization",
+            "rules": {
+                "expression": {
+                    "type": "CHOICE",
+                    "members": [
+                        {"type": "STRING", "value": "x"},
+                        {
+                            "type": "SEQ",
+                            "members": [
+                                {"type": "STRING", "value": "("},
+                                {"type": "SYMBOL", "name": "expression"},
+                                {"type": "STRING", "value": ")"}
+                            ]
+                        }
+                    ]
+                }
+            }
+        }
+    "#,
+    )
+    .unwrap();
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&get_test_language(&parser_name, &parser_code, None))
+        .unwrap();
+
+    let depth = 1000;
+    let source = format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+    let tree = parser.parse(&source, None).unwrap();
+    let json = serde_json::to_string(&tree.root_node().serializable().with_text(source.as_bytes()))
+        .unwrap();
+    let serialized_tree = serde_json::from_str::<SerializedTree>(&json).unwrap();
+    assert_eq!(
+        serialized_tree.nodes.len(),
+        tree.root_node().descendant_count()
+    );
+    assert_eq!(serde_json::to_string(&serialized_tree).unwrap(), json);
+
+    // Each expression is the second child of the one that contains it.
+    let mut index = 0;
+    for _ in 0..depth {
+        let children = serialized_tree.children(index).collect::<Vec<_>>();
+        assert_eq!(children.len(), 3);
+        index = children[1].0;
+        assert_eq!(serialized_tree.nodes[index].kind, "expression");
+    }
+    let (_, leaf) = serialized_tree.children(index).next().unwrap();
+    assert_eq!(leaf.text.as_deref(), Some("x"));
+}
+
 fn get_all_nodes(tree: &Tree) -> Vec<Node> {
     let mut result = Vec::new();
     let mut visited_children = false;
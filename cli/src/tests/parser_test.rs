@@ -12,8 +12,13 @@ use super::helpers::{
     fixtures::{get_language, get_test_language},
 };
 use crate::{
-    generate::{generate_parser_for_grammar, load_grammar_file},
+    choice,
+    generate::{
+        builder::{field, pattern, prec_left, repeat, sym, GrammarBuilder},
+        generate_parser_for_grammar, generate_parser_for_input_grammar, load_grammar_file,
+    },
     parse::{perform_edit, Edit},
+    seq,
     tests::helpers::fixtures::fixtures_dir,
 };
 
@@ -1312,6 +1317,38 @@ fn test_parsing_with_included_ranges_and_missing_tokens() {
     assert_eq!(root.child(3).unwrap().start_byte(), 4);
 }
 
+#[test]
+fn test_parsing_with_a_grammar_built_in_rust() {
+    let grammar = GrammarBuilder::new("test_grammar_built_in_rust")
+        .rule("program", repeat(sym("_expression")))
+        .rule("_expression", choice![sym("number"), sym("sum")])
+        .rule(
+            "sum",
+            prec_left(
+                1,
+                seq![
+                    field("left", sym("_expression")),
+                    "+",
+                    field("right", sym("_expression")),
+                ],
+            ),
+        )
+        .rule("number", pattern(r"\d+"))
+        .build()
+        .unwrap();
+    let parser_code = generate_parser_for_input_grammar(&grammar).unwrap().c_code;
+
+    let mut parser = Parser::new();
+    parser
+        .set_language(&get_test_language(&grammar.name, &parser_code, None))
+        .unwrap();
+    let tree = parser.parse("1 + 2 + 3", None).unwrap();
+    assert_eq!(
+        tree.root_node().to_sexp(),
+        "(program (sum left: (sum left: (number) right: (number)) right: (number)))"
+    );
+}
+
 #[test]
 fn test_grammars_that_can_hang_on_eof() {
     let (parser_name, parser_code) = generate_parser_for_grammar(
@@ -0,0 +1,128 @@
+use std::fs;
+
+use tree_sitter::{Parser, QueryCursor};
+use tree_sitter_query_macro::query;
+
+use super::helpers::fixtures::{fixtures_dir, get_test_grammar_language};
+use crate::generate::{
+    generate_parser_for_input_grammar, load_grammar_file, parse_grammar::parse_grammar,
+};
+
+query! {
+    struct AssignmentQuery {
+        node_types: "../test/fixtures/test_grammars/fields_and_supertypes/node-types.json",
+        query: r#"
+            (program (assignment)+ @assignments)
+
+            (assignment
+              left: (identifier) @name
+              right: (call
+                function: (identifier) @function
+                argument: (number)? @number))
+
+            (assignment
+              right: [
+                (number) @value
+                (call argument: (number) @value)
+              ])
+
+            ((comment) @comment (#match? @comment "^#"))
+        "#,
+    }
+}
+
+query! {
+    struct QuantifierQuery {
+        node_types: "../test/fixtures/test_grammars/fields_and_supertypes/node-types.json",
+        query: r#"
+            (program (assignment left: (identifier) @left)* @assignments)
+
+            (call argument: [(number) @number (identifier) @identifier]+)
+
+            (call
+              function: (identifier) @function
+              ((number) @first . (identifier)? @second)?)
+
+            ((assignment) @first . [(assignment) (comment)]? @next)
+
+            [
+              (assignment left: (identifier) @name right: (number) @value)
+              (call function: (identifier) @name argument: (_)* @value)
+            ]
+        "#,
+    }
+}
+
+#[test]
+fn test_query_macro_quantifiers_match_the_query() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let query = QuantifierQuery::new(&language).unwrap();
+    let source = "a = f(1, b);\nc = 2;\n";
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+
+    let mut cursor = QueryCursor::new();
+    let match_count = query
+        .matches(&mut cursor, tree.root_node(), source.as_bytes())
+        .count();
+    assert_eq!(
+        match_count,
+        cursor
+            .matches(query.query(), tree.root_node(), source.as_bytes())
+            .count()
+    );
+}
+
+#[test]
+fn test_query_macro_captures() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let query = AssignmentQuery::new(&language).unwrap();
+    let source = "a = f(1, b);\nc = 2;\nd = g();\n# comment\n";
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse(source, None).unwrap();
+    let text = |node: tree_sitter::Node| node.utf8_text(source.as_bytes()).unwrap();
+
+    let mut cursor = QueryCursor::new();
+    let matches = query
+        .matches(&mut cursor, tree.root_node(), source.as_bytes())
+        .map(|query_match| match query_match {
+            AssignmentQueryMatch::Pattern0(pattern) => {
+                format!("{} assignments", pattern.assignments.len())
+            }
+            AssignmentQueryMatch::Pattern1(pattern) => format!(
+                "{} = {}({})",
+                text(pattern.name),
+                text(pattern.function),
+                pattern.number.map_or("", text)
+            ),
+            AssignmentQueryMatch::Pattern2(pattern) => format!("value {}", text(pattern.value)),
+            AssignmentQueryMatch::Pattern3(pattern) => format!("comment {}", text(pattern.comment)),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        matches,
+        [
+            "a = f(1)",
+            "value 1",
+            "value 2",
+            "d = g()",
+            "3 assignments",
+            "comment # comment",
+        ]
+    );
+}
+
+#[test]
+fn test_query_macro_node_types_fixture_is_up_to_date() {
+    let grammar_dir = fixtures_dir()
+        .join("test_grammars")
+        .join("fields_and_supertypes");
+    let grammar_json = load_grammar_file(&grammar_dir.join("grammar.js"), None).unwrap();
+    let parser = generate_parser_for_input_grammar(&parse_grammar(&grammar_json).unwrap()).unwrap();
+    assert_eq!(
+        parser.node_types_json,
+        fs::read_to_string(grammar_dir.join("node-types.json")).unwrap()
+    );
+}
@@ -0,0 +1,189 @@
+use tree_sitter::{Parser, Query, RewriteError, Rewriter};
+
+use super::helpers::fixtures::get_test_grammar_language;
+use crate::rewrite::unified_diff;
+
+#[test]
+fn test_rewrite() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let query = Query::new(
+        &language,
+        r#"
+        ((call function: (identifier) @function argument: (_) @argument) @call
+         (#eq? @function "f"))
+        "#,
+    )
+    .unwrap();
+    let rewriter = Rewriter::new(&query, "g(@argument, @argument)").unwrap();
+
+    let source = b"a = f(x);\nb = h(y);\nc = f(f(z));\n";
+    let tree = parser.parse(source, None).unwrap();
+
+    // Only the outermost of the overlapping matches is replaced.
+    let replacements = rewriter.replacements(tree.root_node(), source);
+    assert_eq!(
+        replacements
+            .iter()
+            .map(|r| (r.range.start_byte, r.range.end_byte, r.text.as_slice()))
+            .collect::<Vec<_>>(),
+        [
+            (4, 8, b"g(x, x)".as_slice()),
+            (24, 31, b"g(f(z), f(z))".as_slice())
+        ]
+    );
+
+    let output = rewriter.rewrite(&mut parser, &tree, source).unwrap();
+    assert_eq!(
+        String::from_utf8(output.source.clone()).unwrap(),
+        "a = g(x, x);\nb = h(y);\nc = g(f(z), f(z));\n"
+    );
+    assert_eq!(output.edits.len(), 2);
+    assert_eq!(output.edits[0].start_byte, 24);
+    assert_eq!(output.edits[1].start_byte, 4);
+
+    // The incrementally parsed tree matches a fresh parse of the rewritten code.
+    let new_tree = parser.parse(&output.source, None).unwrap();
+    assert_eq!(
+        output.tree.root_node().to_sexp(),
+        new_tree.root_node().to_sexp()
+    );
+
+    // The edits can be used to reparse the original tree.
+    let mut edited_tree = tree.clone();
+    for edit in &output.edits {
+        edited_tree.edit(edit);
+    }
+    let new_tree = parser.parse(&output.source, Some(&edited_tree)).unwrap();
+    assert_eq!(
+        output.tree.root_node().to_sexp(),
+        new_tree.root_node().to_sexp()
+    );
+}
+
+#[test]
+fn test_rewrite_template_captures() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let query = Query::new(
+        &language,
+        "(assignment left: (identifier) @assignment.left right: (number)? @value) @assignment",
+    )
+    .unwrap();
+
+    // Punctuation after a capture name is not part of the name, and `@@` is a literal `@`.
+    let rewriter = Rewriter::new(&query, "@assignment.left = 1; # @@@assignment.left.").unwrap();
+    let source = b"a = 2;";
+    let tree = parser.parse(source, None).unwrap();
+    let replacements = rewriter.replacements(tree.root_node(), source);
+    assert_eq!(replacements.len(), 1);
+    assert_eq!(replacements[0].text, b"a = 1; # @a.");
+
+    // Captures that match no nodes are replaced with nothing.
+    let rewriter = Rewriter::new(&query, "@assignment.left = @value;").unwrap();
+    let source = b"b = c;";
+    let tree = parser.parse(source, None).unwrap();
+    let output = rewriter.rewrite(&mut parser, &tree, source);
+    assert_eq!(
+        output.unwrap_err(),
+        RewriteError::Syntax(vec![tree_sitter::Range {
+            start_byte: 3,
+            end_byte: 3,
+            start_point: tree_sitter::Point::new(0, 3),
+            end_point: tree_sitter::Point::new(0, 3),
+        }])
+    );
+
+    assert_eq!(
+        Rewriter::new(&query, "@assignment = @val;").err().unwrap(),
+        RewriteError::Capture {
+            offset: 14,
+            name: "val".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_rewrite_with_existing_syntax_errors() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    // Errors that are moved by the edits are still allowed.
+    let query = Query::new(
+        &language,
+        r#"
+        ((assignment left: (identifier) @left right: (number) @value) @assignment
+         (#eq? @left "b"))
+        "#,
+    )
+    .unwrap();
+    let rewriter = Rewriter::new(&query, "@left = f(@value);").unwrap();
+    let source = b"b = 2;\na = 1 1;";
+    let tree = parser.parse(source, None).unwrap();
+    let output = rewriter.rewrite(&mut parser, &tree, source).unwrap();
+    assert_eq!(output.source, b"b = f(2);\na = 1 1;");
+
+    // Removing one error doesn't allow another one to be added.
+    let query = Query::new(
+        &language,
+        "(assignment left: (identifier) @left right: (number)? @value) @assignment",
+    )
+    .unwrap();
+    let rewriter = Rewriter::new(&query, "@left = @value;").unwrap();
+    let source = b"a = 1 1;\nb = c;";
+    let tree = parser.parse(source, None).unwrap();
+    let output = rewriter.rewrite(&mut parser, &tree, source);
+    assert_eq!(
+        output.unwrap_err(),
+        RewriteError::Syntax(vec![tree_sitter::Range {
+            start_byte: 10,
+            end_byte: 10,
+            start_point: tree_sitter::Point::new(1, 3),
+            end_point: tree_sitter::Point::new(1, 3),
+        }])
+    );
+}
+
+#[test]
+fn test_rewrite_unified_diff() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let query = Query::new(&language, "(assignment right: (number) @number)").unwrap();
+    let rewriter = Rewriter::new(&query, "f(@number)").unwrap();
+    let source = b"a = 1;\nb = c;\nd = e;\nf = g;\nh = i;\nj = k;\nl = m;\nn = o;\np = 2;\nq = 3;";
+    let tree = parser.parse(source, None).unwrap();
+    let output = rewriter.rewrite(&mut parser, &tree, source).unwrap();
+
+    assert_eq!(
+        unified_diff("test.txt", source, &output.source, &output.edits),
+        [
+            "--- a/test.txt",
+            "+++ b/test.txt",
+            "@@ -1,4 +1,4 @@",
+            "-a = 1;",
+            "+a = f(1);",
+            " b = c;",
+            " d = e;",
+            " f = g;",
+            "@@ -6,5 +6,5 @@",
+            " j = k;",
+            " l = m;",
+            " n = o;",
+            "-p = 2;",
+            "-q = 3;",
+            "\\ No newline at end of file",
+            "+p = f(2);",
+            "+q = f(3);",
+            "\\ No newline at end of file",
+            "",
+        ]
+        .join("\n")
+    );
+}
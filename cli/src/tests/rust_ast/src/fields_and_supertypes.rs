@@ -0,0 +1,217 @@
+// Typed syntax nodes for the `fields_and_supertypes` grammar, generated from its node types.
+
+#![allow(dead_code, clippy::all)]
+
+use tree_sitter::Node;
+
+/// A typed view of a syntax node.
+pub trait AstNode<'tree>: Sized {
+    /// Returns a typed view of the given node, if the node has this type.
+    fn cast(node: Node<'tree>) -> Option<Self>;
+
+    /// Returns the underlying syntax node.
+    fn node(&self) -> Node<'tree>;
+}
+
+/// Iterates over the children of a node, along with the names of their fields.
+fn children_with_field_names<'tree>(
+    node: Node<'tree>,
+) -> impl Iterator<Item = (Option<&'static str>, Node<'tree>)> {
+    let mut cursor = node.walk();
+    let mut has_next = cursor.goto_first_child();
+    core::iter::from_fn(move || {
+        if !has_next {
+            return None;
+        }
+        let item = (cursor.field_name(), cursor.node());
+        has_next = cursor.goto_next_sibling();
+        Some(item)
+    })
+}
+
+/// Iterates over the children of a node that are stored in the given field.
+fn field_children<'tree>(
+    node: Node<'tree>,
+    field_name: &'static str,
+) -> impl Iterator<Item = Node<'tree>> {
+    children_with_field_names(node)
+        .filter(move |(name, _)| *name == Some(field_name))
+        .map(|(_, child)| child)
+}
+
+/// Iterates over the named children of a node that are not stored in any field.
+fn children_without_fields<'tree>(node: Node<'tree>) -> impl Iterator<Item = Node<'tree>> {
+    children_with_field_names(node)
+        .filter(|(name, child)| name.is_none() && child.is_named() && !child.is_extra())
+        .map(|(_, child)| child)
+}
+/// A node of one of the subtypes of `_expression`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Expression<'tree> {
+    Call(Call<'tree>),
+    Identifier(Identifier<'tree>),
+    Number(Number<'tree>),
+}
+
+impl<'tree> AstNode<'tree> for Expression<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        if let Some(node) = Call::cast(node) {
+            return Some(Self::Call(node));
+        }
+        if let Some(node) = Identifier::cast(node) {
+            return Some(Self::Identifier(node));
+        }
+        if let Some(node) = Number::cast(node) {
+            return Some(Self::Number(node));
+        }
+        None
+    }
+
+    fn node(&self) -> Node<'tree> {
+        match self {
+            Self::Call(node) => AstNode::node(node),
+            Self::Identifier(node) => AstNode::node(node),
+            Self::Number(node) => AstNode::node(node),
+        }
+    }
+}
+
+/// A `assignment` node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Assignment<'tree>(Node<'tree>);
+
+impl<'tree> Assignment<'tree> {
+    pub const KIND: &'static str = "assignment";
+
+    /// The `left` field of `assignment` nodes.
+    ///
+    /// This is always present, unless the node contains errors.
+    pub fn left(&self) -> Option<Identifier<'tree>> {
+        self.0.child_by_field_name("left").and_then(Identifier::cast)
+    }
+
+    /// The `right` field of `assignment` nodes.
+    ///
+    /// This is always present, unless the node contains errors.
+    pub fn right(&self) -> Option<Expression<'tree>> {
+        self.0.child_by_field_name("right").and_then(Expression::cast)
+    }
+}
+
+impl<'tree> AstNode<'tree> for Assignment<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }
+
+    fn node(&self) -> Node<'tree> {
+        self.0
+    }
+}
+
+/// A `call` node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Call<'tree>(Node<'tree>);
+
+impl<'tree> Call<'tree> {
+    pub const KIND: &'static str = "call";
+
+    /// The `argument` field of `call` nodes.
+    pub fn argument(&self) -> impl Iterator<Item = Expression<'tree>> + 'tree {
+        field_children(self.0, "argument").filter_map(Expression::cast)
+    }
+
+    /// The `function` field of `call` nodes.
+    ///
+    /// This is always present, unless the node contains errors.
+    pub fn function(&self) -> Option<Identifier<'tree>> {
+        self.0.child_by_field_name("function").and_then(Identifier::cast)
+    }
+}
+
+impl<'tree> AstNode<'tree> for Call<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }
+
+    fn node(&self) -> Node<'tree> {
+        self.0
+    }
+}
+
+/// A `program` node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Program<'tree>(Node<'tree>);
+
+impl<'tree> Program<'tree> {
+    pub const KIND: &'static str = "program";
+
+    /// The named children of `program` nodes that are not in any field.
+    pub fn children(&self) -> impl Iterator<Item = Assignment<'tree>> + 'tree {
+        children_without_fields(self.0).filter_map(Assignment::cast)
+    }
+}
+
+impl<'tree> AstNode<'tree> for Program<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }
+
+    fn node(&self) -> Node<'tree> {
+        self.0
+    }
+}
+
+/// A `comment` node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Comment<'tree>(Node<'tree>);
+
+impl<'tree> Comment<'tree> {
+    pub const KIND: &'static str = "comment";
+}
+
+impl<'tree> AstNode<'tree> for Comment<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }
+
+    fn node(&self) -> Node<'tree> {
+        self.0
+    }
+}
+
+/// A `identifier` node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Identifier<'tree>(Node<'tree>);
+
+impl<'tree> Identifier<'tree> {
+    pub const KIND: &'static str = "identifier";
+}
+
+impl<'tree> AstNode<'tree> for Identifier<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }
+
+    fn node(&self) -> Node<'tree> {
+        self.0
+    }
+}
+
+/// A `number` node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Number<'tree>(Node<'tree>);
+
+impl<'tree> Number<'tree> {
+    pub const KIND: &'static str = "number";
+}
+
+impl<'tree> AstNode<'tree> for Number<'tree> {
+    fn cast(node: Node<'tree>) -> Option<Self> {
+        (node.is_named() && node.kind() == Self::KIND).then_some(Self(node))
+    }
+
+    fn node(&self) -> Node<'tree> {
+        self.0
+    }
+}
+
@@ -0,0 +1,5 @@
+//! The typed syntax trees that `tree-sitter generate --rust-ast` writes for some of the
+//! test grammars, so that the generated code is compiled along with the tests.
+
+#[rustfmt::skip]
+pub mod fields_and_supertypes;
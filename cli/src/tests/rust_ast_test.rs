@@ -0,0 +1,38 @@
+use tree_sitter::Parser;
+use tree_sitter_rust_ast::fields_and_supertypes::{AstNode, Expression, Program};
+
+use super::helpers::fixtures::get_test_grammar_language;
+
+#[test]
+fn test_generated_rust_ast() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source = "a = b;\nc = f(1, g(d));\n";
+    let tree = parser.parse(source, None).unwrap();
+    let program = Program::cast(tree.root_node()).unwrap();
+    let assignments = program.children().collect::<Vec<_>>();
+    assert_eq!(assignments.len(), 2);
+
+    assert_eq!(text(assignments[0].left().unwrap(), source), "a");
+    assert!(matches!(
+        assignments[0].right(),
+        Some(Expression::Identifier(_))
+    ));
+
+    let Some(Expression::Call(call)) = assignments[1].right() else {
+        panic!("expected a call");
+    };
+    assert_eq!(text(call.function().unwrap(), source), "f");
+    let arguments = call.argument().collect::<Vec<_>>();
+    assert!(matches!(
+        arguments[..],
+        [Expression::Number(_), Expression::Call(_)]
+    ));
+    assert!(Program::cast(call.node()).is_none());
+}
+
+fn text<'a>(node: impl AstNode<'a>, source: &'a str) -> &'a str {
+    node.node().utf8_text(source.as_bytes()).unwrap()
+}
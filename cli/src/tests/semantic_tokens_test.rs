@@ -0,0 +1,195 @@
+use tree_sitter_highlight::{
+    HighlightConfiguration, Highlighter, SemanticToken, SemanticTokensEdit, SemanticTokensLegend,
+    SemanticTokensRenderer,
+};
+
+use super::helpers::fixtures::get_test_grammar_language;
+
+const HIGHLIGHT_NAMES: &[&str] = &[
+    "comment",
+    "function",
+    "function.builtin",
+    "number",
+    "punctuation",
+    "string",
+    "variable",
+    "variable.parameter",
+];
+
+fn highlight_config(highlights_query: &str) -> HighlightConfiguration {
+    let mut config = HighlightConfiguration::new(
+        get_test_grammar_language("fields_and_supertypes"),
+        "fields_and_supertypes",
+        highlights_query,
+        "",
+        "",
+    )
+    .unwrap();
+    config.configure(HIGHLIGHT_NAMES);
+    config
+}
+
+fn semantic_tokens(config: &HighlightConfiguration, source: &str) -> SemanticTokensRenderer {
+    let mut highlighter = Highlighter::new();
+    let events = highlighter
+        .highlight(config, source.as_bytes(), None, |_| None)
+        .unwrap();
+    let mut renderer =
+        SemanticTokensRenderer::new(&SemanticTokensLegend::standard(), HIGHLIGHT_NAMES);
+    renderer.render(events, source.as_bytes()).unwrap();
+    renderer
+}
+
+#[test]
+fn test_semantic_token_legend() {
+    let legend = SemanticTokensLegend::standard();
+    let token_type = |name: &str| legend.token_types.iter().position(|t| t == name).unwrap() as u32;
+    let token_modifier = |name: &str| {
+        1 << legend
+            .token_modifiers
+            .iter()
+            .position(|m| m == name)
+            .unwrap()
+    };
+
+    assert_eq!(
+        legend.token_for_highlight_name("function.method.builtin"),
+        Some(SemanticToken {
+            token_type: token_type("method"),
+            token_modifiers: token_modifier("defaultLibrary"),
+        })
+    );
+    assert_eq!(
+        legend.token_for_highlight_name("constant.builtin"),
+        Some(SemanticToken {
+            token_type: token_type("variable"),
+            token_modifiers: token_modifier("readonly") | token_modifier("defaultLibrary"),
+        })
+    );
+    assert_eq!(
+        legend.token_for_highlight_name("comment.documentation"),
+        Some(SemanticToken {
+            token_type: token_type("comment"),
+            token_modifiers: token_modifier("documentation"),
+        })
+    );
+    assert_eq!(legend.token_for_highlight_name("punctuation.bracket"), None);
+
+    let mut legend = SemanticTokensLegend::new(&["keyword", "function"], &["builtin"]);
+    legend.add_alias("method", "function");
+    assert_eq!(
+        legend.token_for_highlight_name("function.method.builtin"),
+        Some(SemanticToken {
+            token_type: 1,
+            token_modifiers: 1,
+        })
+    );
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let config = highlight_config(
+        r#"
+        (comment) @comment
+        (number) @number
+        (call function: (identifier) @function)
+        (call function: (identifier) @function.builtin (#eq? @function.builtin "print"))
+        (call argument: (identifier) @variable.parameter)
+        (assignment left: (identifier) @variable)
+        "#,
+    );
+
+    // Columns and lengths are measured in UTF-16 code units.
+    let source = "# é😀 x\na = f(1, b);\ncc = print(2);";
+    let renderer = semantic_tokens(&config, source);
+    assert_eq!(
+        renderer.data,
+        &[
+            0, 0, 7, 17, 0, //
+            1, 0, 1, 8, 0, //
+            0, 4, 1, 12, 0, //
+            0, 2, 1, 19, 0, //
+            0, 3, 1, 7, 0, //
+            1, 0, 2, 8, 0, //
+            0, 5, 5, 12, 512, //
+            0, 6, 1, 19, 0, //
+        ]
+    );
+
+    // The positions of the tokens in a range are relative to the start of the
+    // document.
+    let mut highlighter = Highlighter::new();
+    let events = highlighter
+        .highlight(&config, source.as_bytes(), None, |_| None)
+        .unwrap();
+    let mut range_renderer =
+        SemanticTokensRenderer::new(&SemanticTokensLegend::standard(), HIGHLIGHT_NAMES);
+    let line_start = source.rfind('\n').unwrap() + 1;
+    range_renderer
+        .render_range(events, source.as_bytes(), line_start..source.len())
+        .unwrap();
+    assert_eq!(
+        range_renderer.data,
+        &[
+            2, 0, 2, 8, 0, //
+            0, 5, 5, 12, 512, //
+            0, 6, 1, 19, 0, //
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_tokens_with_nested_and_multiline_highlights() {
+    let config = highlight_config(
+        r#"
+        (call function: (identifier) @function)
+        (call) @string
+        "," @punctuation
+        "#,
+    );
+
+    // Tokens are split at line breaks, and highlights without a token type use
+    // the token type of the enclosing highlight.
+    let renderer = semantic_tokens(&config, "a = f(1,\n2);");
+    assert_eq!(
+        renderer.data,
+        &[
+            0, 4, 1, 12, 0, //
+            0, 1, 3, 18, 0, //
+            1, 0, 2, 18, 0, //
+        ]
+    );
+}
+
+#[test]
+fn test_semantic_tokens_edits() {
+    let config = highlight_config(
+        r#"
+        (number) @number
+        (call function: (identifier) @function)
+        "#,
+    );
+
+    let old_renderer = semantic_tokens(&config, "a = f(1);\nb = g(2);\nc = h(3);");
+    assert_eq!(old_renderer.edits(&old_renderer.data), &[]);
+
+    let renderer = semantic_tokens(&config, "a = f(1);\nb = g(22);\nc = h(3);");
+    assert_eq!(
+        renderer.edits(&old_renderer.data),
+        &[SemanticTokensEdit {
+            start: 15,
+            delete_count: 5,
+            data: vec![0, 2, 2, 19, 0],
+        }]
+    );
+
+    let renderer = semantic_tokens(&config, "a = f(1);\nc = h(3);");
+    assert_eq!(
+        renderer.edits(&old_renderer.data),
+        &[SemanticTokensEdit {
+            start: 20,
+            delete_count: 10,
+            data: vec![],
+        }]
+    );
+}
@@ -0,0 +1,266 @@
+use std::fs;
+
+use tree_sitter_theme::{Color, Error, Style, Theme};
+
+fn style(theme: &Theme, name: &str) -> Option<Style> {
+    let index = theme.highlight_names.iter().position(|n| n == name)?;
+    Some(theme.styles[index])
+}
+
+fn color_style(red: u8, green: u8, blue: u8) -> Style {
+    Style {
+        color: Some(Color::Rgb(red, green, blue)),
+        ..Style::default()
+    }
+}
+
+#[test]
+fn test_theme_json() {
+    let theme = Theme::from_json(
+        r##"{
+          "comment": {"color": 245, "italic": true},
+          "function": "#26A69A",
+          "keyword": "purple",
+          "variable.parameter": {"underline": true},
+          "embedded": null
+        }"##,
+    )
+    .unwrap();
+
+    assert_eq!(
+        style(&theme, "comment"),
+        Some(Style {
+            color: Some(Color::Ansi256(245)),
+            italic: true,
+            ..Style::default()
+        })
+    );
+    assert_eq!(style(&theme, "function"), Some(color_style(38, 166, 154)));
+    assert_eq!(
+        style(&theme, "keyword").unwrap().color,
+        Some(Color::Ansi256(5))
+    );
+    assert_eq!(style(&theme, "embedded"), Some(Style::default()));
+    assert_eq!(
+        theme.style_for_highlight_name("function.builtin"),
+        theme.style_for_highlight_name("function")
+    );
+    assert_eq!(theme.style_for_highlight_name("string"), None);
+
+    // Colors that are not in the xterm palette are approximated by the ANSI
+    // renderer when the terminal doesn't support them.
+    let function_color = style(&theme, "function").unwrap().color.unwrap();
+    assert_eq!(function_color.to_hex(), "#26a69a");
+    assert_eq!(function_color.to_ansi256(), 36);
+    assert_eq!(Color::Rgb(0x00, 0xaf, 0x87).to_ansi256(), 36);
+
+    let json = serde_json::to_value(&theme).unwrap();
+    assert_eq!(json["function"], "#26a69a");
+    assert_eq!(json["keyword"], 5);
+    assert_eq!(json["variable.parameter"]["underline"], true);
+    let theme_again = serde_json::from_value::<Theme>(json).unwrap();
+    assert_eq!(style(&theme_again, "comment"), style(&theme, "comment"));
+}
+
+#[test]
+fn test_theme_from_vscode_json() {
+    let theme = Theme::from_json(
+        r##"{
+          // Comments and trailing commas are allowed.
+          "name": "Example // not a comment",
+          "colors": {"editor.background": "#1e1e1e"},
+          "tokenColors": [
+            {"settings": {"foreground": "#d4d4d4"}},
+            {
+              "scope": "comment",
+              "settings": {"foreground": "#6A9955", "fontStyle": "italic"},
+            },
+            {
+              "scope": ["entity.name.function", "support.function"],
+              "settings": {"foreground": "#DCDCAA"}
+            },
+            /* The font style of a more specific rule is combined with the
+               color of a less specific one. */
+            {"scope": "support.function.builtin", "settings": {"fontStyle": "bold"}},
+            {"scope": "support.function", "settings": {"fontStyle": "bold"}},
+            {"scope": "keyword, storage.type", "settings": {"foreground": "#569cd6"}},
+            {"scope": "keyword.operator", "settings": {"foreground": "#d4d4d4"}},
+            {"scope": "string", "settings": {"foreground": "#ce9178"}},
+          ],
+        }"##,
+    )
+    .unwrap();
+
+    assert_eq!(
+        style(&theme, "comment"),
+        Some(Style {
+            color: Some(Color::Rgb(0x6a, 0x99, 0x55)),
+            italic: true,
+            ..Style::default()
+        })
+    );
+    assert_eq!(
+        style(&theme, "function"),
+        Some(color_style(0xdc, 0xdc, 0xaa))
+    );
+    assert_eq!(
+        style(&theme, "function.builtin"),
+        Some(Style {
+            color: Some(Color::Rgb(0xdc, 0xdc, 0xaa)),
+            bold: true,
+            ..Style::default()
+        })
+    );
+    assert_eq!(
+        style(&theme, "keyword"),
+        Some(color_style(0x56, 0x9c, 0xd6))
+    );
+    assert_eq!(
+        style(&theme, "operator"),
+        Some(color_style(0xd4, 0xd4, 0xd4))
+    );
+
+    // Highlight names fall back to the styles of less specific scopes.
+    assert_eq!(style(&theme, "function.method"), style(&theme, "function"));
+    assert_eq!(
+        style(&theme, "string.special.symbol"),
+        Some(color_style(0xce, 0x91, 0x78))
+    );
+    assert_eq!(style(&theme, "variable"), None);
+}
+
+#[test]
+fn test_theme_from_tmtheme() {
+    let theme = Theme::from_tmtheme(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+  <key>name</key>
+  <string>Example &amp; Co</string>
+  <key>settings</key>
+  <array>
+    <dict>
+      <key>settings</key>
+      <dict>
+        <key>background</key>
+        <string>#FFFFFF</string>
+      </dict>
+    </dict>
+    <!-- A comment -->
+    <dict>
+      <key>scope</key>
+      <string>comment</string>
+      <key>settings</key>
+      <dict>
+        <key>foreground</key>
+        <string>#6A737D</string>
+        <key>fontStyle</key>
+        <string>italic underline</string>
+      </dict>
+    </dict>
+    <dict>
+      <key>scope</key>
+      <string>entity.name.tag, source.js entity.name.function</string>
+      <key>settings</key>
+      <dict>
+        <key>foreground</key>
+        <string>#22863A</string>
+      </dict>
+    </dict>
+    <dict>
+      <key>scope</key>
+      <string><![CDATA[string]]></string>
+      <key>settings</key>
+      <dict>
+        <key>foreground</key>
+        <string>#032F62FF</string>
+      </dict>
+    </dict>
+    <dict>
+      <key>scope</key>
+      <string>constant.character.escape</string>
+      <key>settings</key>
+      <dict>
+        <key>foreground</key>
+        <string>#<![CDATA[f00]]></string>
+        <key>fontStyle</key>
+        <string></string>
+      </dict>
+    </dict>
+  </array>
+</dict>
+</plist>
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        style(&theme, "comment"),
+        Some(Style {
+            color: Some(Color::Rgb(0x6a, 0x73, 0x7d)),
+            italic: true,
+            underline: true,
+            ..Style::default()
+        })
+    );
+    assert_eq!(style(&theme, "tag"), Some(color_style(0x22, 0x86, 0x3a)));
+
+    // Selectors that only apply within other scopes are skipped.
+    assert_eq!(style(&theme, "function"), None);
+
+    // The text of an element can contain CDATA sections.
+    assert_eq!(style(&theme, "string"), Some(color_style(0x03, 0x2f, 0x62)));
+    assert_eq!(style(&theme, "string.escape"), Some(color_style(255, 0, 0)));
+    assert_eq!(style(&theme, "string.regexp"), style(&theme, "string"));
+
+    assert!(matches!(
+        Theme::from_tmtheme("<plist><dict><key>settings</key><array><dict></array></dict></plist>"),
+        Err(Error::InvalidPlist(_))
+    ));
+    assert!(matches!(
+        Theme::from_tmtheme(
+            "<plist><dict><key>settings</key><string><![CDATA[</string></dict></plist>"
+        ),
+        Err(Error::InvalidPlist(_))
+    ));
+}
+
+#[test]
+fn test_theme_load() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let tmtheme_path = dir.path().join("example.tmTheme");
+    fs::write(
+        &tmtheme_path,
+        "<plist><dict><key>settings</key><array><dict>\
+         <key>scope</key><string>keyword</string>\
+         <key>settings</key><dict><key>foreground</key><string>#0000ff</string></dict>\
+         </dict></array></dict></plist>",
+    )
+    .unwrap();
+    let theme = Theme::load(&tmtheme_path).unwrap();
+    assert_eq!(style(&theme, "keyword"), Some(color_style(0, 0, 255)));
+
+    let json_path = dir.path().join("example.json");
+    fs::write(&json_path, r#"{"keyword": {"color": 56, "bold": true}}"#).unwrap();
+    let theme = Theme::load(&json_path).unwrap();
+    assert_eq!(
+        style(&theme, "keyword"),
+        Some(Style {
+            color: Some(Color::Ansi256(56)),
+            bold: true,
+            ..Style::default()
+        })
+    );
+
+    fs::write(&json_path, "[]").unwrap();
+    assert!(matches!(
+        Theme::load(&json_path),
+        Err(Error::InvalidTheme(_))
+    ));
+    assert!(matches!(
+        Theme::load(&dir.path().join("missing.json")),
+        Err(Error::Io(_))
+    ));
+}
@@ -0,0 +1,105 @@
+use tree_sitter::{DiffAction, Node, Parser, TreeDiff};
+
+use super::helpers::fixtures::get_test_grammar_language;
+
+#[test]
+fn test_tree_diff() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let old_source = b"a = f(x, 2);\nb = 3;\nc = 4;\n";
+    let new_source = b"c = 4;\na = g(2, x);\nb = 30;\nd = 5;\n";
+    let old_tree = parser.parse(old_source, None).unwrap();
+    let new_tree = parser.parse(new_source, None).unwrap();
+
+    let diff = TreeDiff::new(&old_tree, old_source, &new_tree, new_source);
+    let actions = diff
+        .actions()
+        .iter()
+        .map(|action| match action {
+            DiffAction::Insert { node } => format!("insert {}", describe(*node, new_source)),
+            DiffAction::Delete { node } => format!("delete {}", describe(*node, old_source)),
+            DiffAction::Update { old, new } => format!(
+                "update {} -> {}",
+                describe(*old, old_source),
+                describe(*new, new_source)
+            ),
+            DiffAction::Move { old, new } => format!(
+                "move {} -> {}",
+                describe(*old, old_source),
+                describe(*new, new_source)
+            ),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        actions,
+        [
+            "move assignment `c = 4;` -> assignment `c = 4;`",
+            "update identifier `f` -> identifier `g`",
+            "move number `2` -> number `2`",
+            "move , `,` -> , `,`",
+            "update number `3` -> number `30`",
+            "insert assignment `d = 5;`",
+        ]
+    );
+
+    // Every node of the old tree has a counterpart in the new tree.
+    let old_root = old_tree.root_node();
+    assert_eq!(diff.new_node(old_root), Some(new_tree.root_node()));
+    assert_eq!(diff.matches().count(), old_root.descendant_count());
+
+    let old_x = old_root.named_descendant_for_byte_range(6, 7).unwrap();
+    let new_x = diff.new_node(old_x).unwrap();
+    assert_eq!(new_x.utf8_text(new_source).unwrap(), "x");
+    assert_eq!(diff.old_node(new_x), Some(old_x));
+
+    // The inserted assignment has no counterpart in the old tree.
+    let new_d = new_tree.root_node().named_child(3).unwrap();
+    assert_eq!(diff.old_node(new_d), None);
+}
+
+#[test]
+fn test_tree_diff_with_deletions() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let old_source = b"a = f(x, y);\nb = 1;\n";
+    let new_source = b"a = f(x);\nb = 1;\n";
+    let old_tree = parser.parse(old_source, None).unwrap();
+    let new_tree = parser.parse(new_source, None).unwrap();
+
+    let diff = TreeDiff::new(&old_tree, old_source, &new_tree, new_source);
+    let deleted = diff
+        .actions()
+        .iter()
+        .map(|action| match action {
+            DiffAction::Delete { node } => describe(*node, old_source),
+            _ => panic!("unexpected action {action:?}"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(deleted, [", `,`", "identifier `y`"]);
+}
+
+#[test]
+fn test_tree_diff_of_identical_trees() {
+    let language = get_test_grammar_language("fields_and_supertypes");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let source = b"a = f(1, g(2));\nb = a;\n";
+    let old_tree = parser.parse(source, None).unwrap();
+    let new_tree = parser.parse(source, None).unwrap();
+
+    let diff = TreeDiff::new(&old_tree, source, &new_tree, source);
+    assert!(diff.actions().is_empty());
+    for (old_node, new_node) in diff.matches() {
+        assert_eq!(old_node.kind(), new_node.kind());
+        assert_eq!(old_node.byte_range(), new_node.byte_range());
+    }
+}
+
+fn describe(node: Node, source: &[u8]) -> String {
+    format!("{} `{}`", node.kind(), node.utf8_text(source).unwrap())
+}
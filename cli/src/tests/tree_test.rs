@@ -1,9 +1,18 @@
-use std::str;
-
-use tree_sitter::{InputEdit, Parser, Point, Range, Tree};
-
-use super::helpers::{edits::invert_edit, fixtures::get_language};
-use crate::parse::{perform_edit, Edit};
+use std::{collections::HashMap, str, thread};
+
+use tree_sitter::{
+    InputEdit, Node, NodeMap, NodeMatchKind, NodeRef, Parser, Point, Range, SyncTree, Tree,
+    TreeDeserializeError,
+};
+
+use super::helpers::{
+    edits::invert_edit,
+    fixtures::{get_language, get_test_grammar_language, get_test_language},
+};
+use crate::{
+    generate::generate_parser_for_grammar,
+    parse::{perform_edit, Edit},
+};
 
 #[test]
 fn test_tree_edit() {
@@ -702,6 +711,366 @@ fn test_consistency_with_mid_codepoint_edit() {
     assert_eq!(tree3.root_node().to_sexp(), tree.root_node().to_sexp());
 }
 
+#[test]
+fn test_tree_serialize_and_deserialize() {
+    let language = get_test_grammar_language("external_tokens");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let mut source_code = b"%{sup {} #{x + y} {} scanner?}".to_vec();
+    let mut tree = parser.parse(&source_code, None).unwrap();
+    let bytes = tree.serialize();
+
+    let mut loaded_tree = Tree::deserialize(&language, &bytes).unwrap();
+    assert_eq!(
+        loaded_tree.root_node().to_sexp(),
+        tree.root_node().to_sexp()
+    );
+    assert_eq!(loaded_tree.included_ranges(), tree.included_ranges());
+    assert_eq!(
+        serde_json::to_value(loaded_tree.root_node()).unwrap(),
+        serde_json::to_value(tree.root_node()).unwrap()
+    );
+    assert_eq!(loaded_tree.serialize(), bytes);
+
+    // A loaded tree can be reused when reparsing after an edit.
+    let edit = Edit {
+        position: index_of(&source_code, "y"),
+        deleted_length: 1,
+        inserted_text: b"{z}".to_vec(),
+    };
+    perform_edit(&mut tree, &mut source_code.clone(), &edit).unwrap();
+    perform_edit(&mut loaded_tree, &mut source_code, &edit).unwrap();
+    let new_tree = parser.parse(&source_code, Some(&tree)).unwrap();
+    let new_loaded_tree = parser.parse(&source_code, Some(&loaded_tree)).unwrap();
+    assert_eq!(
+        new_loaded_tree.root_node().to_sexp(),
+        new_tree.root_node().to_sexp()
+    );
+    assert_eq!(
+        loaded_tree
+            .changed_ranges(&new_loaded_tree)
+            .collect::<Vec<_>>(),
+        tree.changed_ranges(&new_tree).collect::<Vec<_>>()
+    );
+    assert!(Tree::deserialize(&language, &tree.serialize())
+        .unwrap()
+        .root_node()
+        .has_changes());
+}
+
+#[test]
+fn test_tree_deserialize_errors() {
+    let language = get_test_grammar_language("external_tokens");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let tree = parser.parse("x + %(y)", None).unwrap();
+    let bytes = tree.serialize();
+
+    let (grammar_name, parser_code) = generate_parser_for_grammar(
+        r#"{
+            "name": "test_tree_deserialize_errors",
+            "rules": {
+                "x": {"type": "STRING", "value": "x"}
+            }
+        }"#,
+    )
+    .unwrap();
+    let other_language = get_test_language(&grammar_name, &parser_code, None);
+    assert_eq!(
+        Tree::deserialize(&other_language, &bytes).unwrap_err(),
+        TreeDeserializeError::Language
+    );
+
+    let mut bytes_with_other_version = bytes.clone();
+    bytes_with_other_version[4] += 1;
+    assert_eq!(
+        Tree::deserialize(&language, &bytes_with_other_version).unwrap_err(),
+        TreeDeserializeError::Version
+    );
+
+    // The number of included ranges is stored after the header, followed by
+    // six integers for each range.
+    let range_count = u32::from_le_bytes(bytes[20..24].try_into().unwrap()) as usize;
+    let bytes_with_too_many_ranges = [
+        &bytes[..20],
+        &u32::MAX.to_le_bytes(),
+        &bytes[24 + range_count * 24..],
+    ]
+    .concat();
+    assert_eq!(
+        Tree::deserialize(&language, &bytes_with_too_many_ranges).unwrap_err(),
+        TreeDeserializeError::Format
+    );
+
+    for invalid_bytes in [
+        &b""[..],
+        b"not a tree",
+        &bytes[..bytes.len() - 1],
+        &[bytes.as_slice(), &[0]].concat(),
+    ] {
+        assert_eq!(
+            Tree::deserialize(&language, invalid_bytes).unwrap_err(),
+            TreeDeserializeError::Format
+        );
+    }
+}
+
+#[test]
+fn test_tree_deserialize_corrupted_subtrees() {
+    let (grammar_name, parser_code) = generate_parser_for_grammar(
+        r#"{
+            "name": "test_tree_deserialize_corrupted_subtrees",
+            "extras": [{"type": "PATTERN", "value": "\\s"}],
+            "rules": {
+                "pair": {
+                    "type": "SEQ",
+                    "members": [
+                        {"type": "FIELD", "name": "key", "content": {"type": "SYMBOL", "name": "word"}},
+                        {"type": "STRING", "value": "="},
+                        {"type": "SYMBOL", "name": "word"}
+                    ]
+                },
+                "word": {"type": "PATTERN", "value": "[a-z]+"}
+            }
+        }"#,
+    )
+    .unwrap();
+    let language = get_test_language(&grammar_name, &parser_code, None);
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let bytes = parser.parse("a = b", None).unwrap().serialize();
+
+    // A node with a production id can't have more children than the longest
+    // production, because its children's aliases are looked up by position.
+    // The root node's children are followed by an extra leaf that marks the
+    // end of the input.
+    let records = subtree_records(&bytes);
+    assert_eq!(records.len(), 5);
+    let (word, pair) = (records[2].clone(), records[4].clone());
+    let mut pair_with_extra_child = bytes[pair.clone()].to_vec();
+    pair_with_extra_child[38..42].copy_from_slice(&5u32.to_le_bytes());
+    let subtree_count_offset = records[0].start - 4;
+    let bytes_with_extra_child = [
+        &bytes[..subtree_count_offset],
+        &6u32.to_le_bytes(),
+        &bytes[records[0].start..word.end],
+        &bytes[word.clone()],
+        &bytes[word.end..pair.start],
+        &pair_with_extra_child,
+    ]
+    .concat();
+    assert_eq!(
+        Tree::deserialize(&language, &bytes_with_extra_child).unwrap_err(),
+        TreeDeserializeError::Format
+    );
+
+    // The properties of a node that are derived from its children are
+    // computed again, rather than loaded.
+    let language = get_test_grammar_language("external_tokens");
+    parser.set_language(&language).unwrap();
+    let mut source_code = b"x + y".to_vec();
+    let tree = parser.parse(&source_code, None).unwrap();
+    let bytes = tree.serialize();
+    let sum = language.id_for_node_kind("sum", true);
+    let sum_record = subtree_records(&bytes)
+        .into_iter()
+        .find(|record| u16::from_le_bytes(bytes[record.start..][..2].try_into().unwrap()) == sum)
+        .unwrap();
+    let mut corrupted_bytes = bytes.clone();
+    let corrupted_sum = &mut corrupted_bytes[sum_record];
+    let has_external_tokens = 1 << 6;
+    let depends_on_column = 1 << 8;
+    let flags = u16::from_le_bytes(corrupted_sum[4..6].try_into().unwrap());
+    let corrupted_flags = flags | has_external_tokens | depends_on_column;
+    corrupted_sum[4..6].copy_from_slice(&corrupted_flags.to_le_bytes());
+    corrupted_sum[18..22].copy_from_slice(&u32::MAX.to_le_bytes());
+    corrupted_sum[34..38].copy_from_slice(&u32::MAX.to_le_bytes());
+    let mut loaded_tree = Tree::deserialize(&language, &corrupted_bytes).unwrap();
+    assert_eq!(loaded_tree.serialize(), bytes);
+
+    let edit = Edit {
+        position: index_of(&source_code, "y"),
+        deleted_length: 1,
+        inserted_text: b"%(z)".to_vec(),
+    };
+    perform_edit(&mut loaded_tree, &mut source_code, &edit).unwrap();
+    let new_tree = parser.parse(&source_code, Some(&loaded_tree)).unwrap();
+    assert_eq!(
+        new_tree.root_node().to_sexp(),
+        "(expression (sum (expression (identifier)) (expression (string))))"
+    );
+}
+
+// Get the byte ranges of the subtrees in a serialized tree, which follow the
+// header and the included ranges. Each subtree is stored as its symbol, parse
+// state, flags, padding, size, lookahead bytes, error cost and child count,
+// followed by the dynamic precedence and production id of an internal node.
+// This only supports leaves without any external scanner state or lookahead
+// character.
+fn subtree_records(bytes: &[u8]) -> Vec<std::ops::Range<usize>> {
+    let read_u32 = |offset: usize| u32::from_le_bytes(bytes[offset..][..4].try_into().unwrap());
+    let range_count = read_u32(20) as usize;
+    let mut offset = 24 + range_count * 24;
+    let subtree_count = read_u32(offset);
+    offset += 4;
+    let records = (0..subtree_count)
+        .map(|_| {
+            let start = offset;
+            offset += if read_u32(start + 38) > 0 { 48 } else { 42 };
+            start..offset
+        })
+        .collect();
+    assert_eq!(offset, bytes.len());
+    records
+}
+
+#[test]
+fn test_sync_tree_node_refs() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SyncTree>();
+    assert_send_sync::<NodeRef>();
+
+    let language = get_test_grammar_language("external_tokens");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+    let source_code = "x + y + z";
+    let tree = SyncTree::new(parser.parse(source_code, None).unwrap());
+
+    // Node references outlive the borrows of the tree that produced them.
+    let identifiers = {
+        let mut result = Vec::new();
+        let mut stack = vec![tree.root_node_ref()];
+        while let Some(node_ref) = stack.pop() {
+            let node = node_ref.node();
+            if node.kind() == "identifier" {
+                result.push(tree.node_ref(node).unwrap());
+            }
+            let mut cursor = node.walk();
+            stack.extend(
+                node.children(&mut cursor)
+                    .map(|child| node_ref.tree().node_ref(child).unwrap()),
+            );
+        }
+        result
+    };
+    drop(tree);
+
+    let texts = thread::scope(|scope| {
+        let handles = identifiers
+            .iter()
+            .cloned()
+            .map(|node_ref| {
+                scope.spawn(move || {
+                    let node = node_ref.node();
+                    (
+                        node_ref.id(),
+                        node.utf8_text(source_code.as_bytes()).unwrap(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect::<HashMap<_, _>>()
+    });
+    assert_eq!(texts.len(), 3);
+    assert_eq!(texts[&identifiers[0].id()], "z");
+    assert_eq!(texts[&identifiers[2].id()], "x");
+
+    // Node references are equal if they refer to the same node.
+    let tree = identifiers[0].tree().clone();
+    assert_eq!(
+        tree.node_ref(identifiers[0].node()).unwrap(),
+        identifiers[0]
+    );
+    assert_ne!(identifiers[0], identifiers[1]);
+
+    // Nodes from other trees are rejected.
+    let other_tree = parser.parse(source_code, None).unwrap();
+    assert_eq!(tree.node_ref(other_tree.root_node()), None);
+
+    // Editing a copy of the tree does not affect the shared tree.
+    let mut edited_tree = tree.to_tree();
+    edited_tree.edit(&InputEdit {
+        start_byte: 0,
+        old_end_byte: 0,
+        new_end_byte: 1,
+        start_position: Point::new(0, 0),
+        old_end_position: Point::new(0, 0),
+        new_end_position: Point::new(0, 1),
+    });
+    assert!(edited_tree.root_node().has_changes());
+    assert!(!tree.root_node().has_changes());
+    assert_eq!(identifiers[2].node().start_byte(), 0);
+}
+
+#[test]
+fn test_node_map() {
+    let language = get_test_grammar_language("external_tokens");
+    let mut parser = Parser::new();
+    parser.set_language(&language).unwrap();
+
+    let mut source_code = b"x + y + %(s)".to_vec();
+    let old_tree = parser.parse(&source_code, None).unwrap();
+
+    // Replace `y` with `zz`.
+    let mut tree = old_tree.clone();
+    let edit = Edit {
+        position: index_of(&source_code, "y"),
+        deleted_length: 1,
+        inserted_text: b"zz".to_vec(),
+    };
+    let input_edit = perform_edit(&mut tree, &mut source_code, &edit).unwrap();
+    let new_tree = parser.parse(&source_code, Some(&tree)).unwrap();
+
+    let old_text = b"x + y + %(s)";
+
+    let node_map = NodeMap::new(&old_tree, &new_tree, &[input_edit]);
+    assert_eq!(node_map.len(), 12);
+
+    // Nodes before and after the edit are reused, even though their positions have changed.
+    let old_x = find_node(&old_tree, old_text, "identifier", "x");
+    let new_x = find_node(&new_tree, &source_code, "identifier", "x");
+    assert_eq!(node_map.new_node(old_x), Some(new_x));
+    assert_eq!(node_map.old_node(new_x), Some(old_x));
+    assert_eq!(node_map.match_kind(old_x), Some(NodeMatchKind::Reused));
+
+    let old_string = find_node(&old_tree, old_text, "string", "%(s)");
+    let new_string = find_node(&new_tree, &source_code, "string", "%(s)");
+    assert_eq!(node_map.new_node(old_string), Some(new_string));
+    assert_eq!(node_map.match_kind(old_string), Some(NodeMatchKind::Reused));
+    assert_eq!(new_string.start_byte(), old_string.start_byte() + 1);
+
+    // The edited node and its ancestors are mapped to the nodes that replace them.
+    let old_y = find_node(&old_tree, old_text, "identifier", "y");
+    let new_zz = find_node(&new_tree, &source_code, "identifier", "zz");
+    assert_eq!(node_map.new_node(old_y), Some(new_zz));
+    assert_eq!(node_map.match_kind(old_y), Some(NodeMatchKind::Equivalent));
+    assert_eq!(
+        node_map.new_node(old_tree.root_node()),
+        Some(new_tree.root_node())
+    );
+    assert_eq!(
+        node_map.match_kind(old_tree.root_node()),
+        Some(NodeMatchKind::Equivalent)
+    );
+
+    // Passing a tree that has already been edited gives the same mapping.
+    let edited_node_map = NodeMap::new(&tree, &new_tree, &[]);
+    assert_eq!(
+        edited_node_map
+            .iter()
+            .map(|(_, new_node, kind)| (new_node, kind))
+            .collect::<Vec<_>>(),
+        node_map
+            .iter()
+            .map(|(_, new_node, kind)| (new_node, kind))
+            .collect::<Vec<_>>(),
+    );
+}
+
 fn index_of(text: &[u8], substring: &str) -> usize {
     str::from_utf8(text).unwrap().find(substring).unwrap()
 }
@@ -729,3 +1098,11 @@ fn get_changed_ranges(
     *tree = new_tree;
     result
 }
+
+fn find_node<'a>(tree: &'a Tree, text: &[u8], kind: &str, substring: &str) -> Node<'a> {
+    let range = range_of(text, substring);
+    tree.root_node()
+        .descendant_for_byte_range(range.start_byte, range.end_byte)
+        .filter(|node| node.kind() == kind)
+        .unwrap()
+}
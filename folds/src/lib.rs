@@ -0,0 +1,307 @@
+#![doc = include_str!("../README.md")]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use thiserror::Error;
+use tree_sitter::{Language, Node, Parser, Point, Query, QueryCursor, QueryError, Range, Tree};
+use tree_sitter_highlight::{InjectionPatterns, Injections};
+
+const CANCELLATION_CHECK_INTERVAL: usize = 100;
+
+/// Contains the data needed to compute the foldable ranges of code written in
+/// a particular language.
+#[derive(Debug)]
+pub struct FoldsConfiguration {
+    pub language: Language,
+    pub language_name: String,
+    pub query: Query,
+    injection_patterns: InjectionPatterns,
+    fold_captures: Vec<(u32, Option<String>)>,
+}
+
+/// Computes the foldable ranges of source code, according to a
+/// [`FoldsConfiguration`].
+///
+/// You need one of these for each thread that you're using for folding.
+pub struct FoldsContext {
+    pub parser: Parser,
+    cursor: QueryCursor,
+}
+
+/// A range of lines that can be folded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fold {
+    /// The row on which the range starts, which remains visible when the range
+    /// is folded.
+    pub start_row: usize,
+    /// The last row of the range.
+    pub end_row: usize,
+    /// The kind of the range, given by the name of the capture after `fold.`,
+    /// such as `comment` for `@fold.comment`.
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Invalid language")]
+    InvalidLanguage,
+    #[error("Invalid capture @{0}. Expected @fold, @fold.<kind>, @injection.content, or @injection.language.")]
+    InvalidCapture(String),
+}
+
+impl FoldsConfiguration {
+    /// Create a folding configuration from a query whose captures mark the
+    /// nodes that can be folded, and a query that describes the injected
+    /// languages, which has the same format as the one used for highlighting.
+    ///
+    /// Nodes are marked with `@fold`, or with `@fold.<kind>` to give their
+    /// ranges a kind, such as `comment` for `@fold.comment`. Other captures are
+    /// an error, unless they are reserved by [`InjectionPatterns`].
+    ///
+    /// The language's name is used by the injections with `injection.self` and
+    /// `injection.parent`.
+    pub fn new(
+        language: Language,
+        name: impl Into<String>,
+        folds_query: &str,
+        injection_query: &str,
+    ) -> Result<Self, Error> {
+        let (query, injection_patterns) =
+            InjectionPatterns::new_query(&language, folds_query, injection_query)?;
+
+        let mut fold_captures = Vec::new();
+        for (i, name) in query.capture_names().iter().enumerate() {
+            if InjectionPatterns::is_reserved_capture_name(name) {
+                continue;
+            }
+            let kind = if *name == "fold" {
+                None
+            } else {
+                let kind = name
+                    .strip_prefix("fold.")
+                    .ok_or_else(|| Error::InvalidCapture((*name).to_string()))?;
+                Some(kind.to_string())
+            };
+            fold_captures.push((i as u32, kind));
+        }
+
+        Ok(Self {
+            language,
+            language_name: name.into(),
+            query,
+            injection_patterns,
+            fold_captures,
+        })
+    }
+}
+
+impl Default for FoldsContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FoldsContext {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            cursor: QueryCursor::new(),
+        }
+    }
+
+    pub fn parser(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Compute the foldable ranges of a syntax tree, sorted by their start row.
+    ///
+    /// Only one range is returned for each start row, and ranges that overlap
+    /// without one containing the other are merged. The `injection_callback`
+    /// is used to find the configurations of languages that are injected into
+    /// the tree.
+    pub fn folds<'a>(
+        &mut self,
+        config: &'a FoldsConfiguration,
+        tree: &Tree,
+        source: &[u8],
+        cancellation_flag: Option<&AtomicUsize>,
+        mut injection_callback: impl FnMut(&str) -> Option<&'a FoldsConfiguration>,
+    ) -> Result<Vec<Fold>, Error> {
+        let mut folds = Vec::new();
+        self.collect_folds(
+            config,
+            None,
+            tree.root_node(),
+            &tree.included_ranges(),
+            source,
+            cancellation_flag,
+            &mut injection_callback,
+            &mut folds,
+        )?;
+        Ok(merge_folds(folds))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn collect_folds<'a, F>(
+        &mut self,
+        config: &'a FoldsConfiguration,
+        parent_name: Option<&'a str>,
+        root: Node,
+        ranges: &[Range],
+        source: &[u8],
+        cancellation_flag: Option<&AtomicUsize>,
+        injection_callback: &mut F,
+        folds: &mut Vec<Fold>,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&str) -> Option<&'a FoldsConfiguration>,
+    {
+        let mut injections = Injections::default();
+        for (i, m) in self.cursor.matches(&config.query, root, source).enumerate() {
+            if i % CANCELLATION_CHECK_INTERVAL == 0
+                && cancellation_flag.is_some_and(|flag| flag.load(Ordering::Relaxed) != 0)
+            {
+                return Err(Error::Cancelled);
+            }
+
+            if config.injection_patterns.contains(m.pattern_index) {
+                injections.add_match(
+                    &config.injection_patterns,
+                    &config.language_name,
+                    parent_name,
+                    &config.query,
+                    &m,
+                    source,
+                );
+                continue;
+            }
+
+            // A capture that matches several nodes, such as a sequence of comments,
+            // is folded from the start of its first node to the end of its last node.
+            for (capture_index, kind) in &config.fold_captures {
+                let mut nodes = m.nodes_for_capture_index(*capture_index);
+                if let Some(first) = nodes.next() {
+                    let last = nodes.last().unwrap_or(first);
+                    if let Some(fold) =
+                        Fold::new(first.start_position(), last.end_position(), kind.clone())
+                    {
+                        folds.push(fold);
+                    }
+                }
+            }
+        }
+
+        for injection in injections.into_injections() {
+            let Some(injected_config) = injection_callback(injection.language_name) else {
+                continue;
+            };
+            let injection_ranges = injection.ranges(ranges);
+            if injection_ranges.is_empty() {
+                continue;
+            }
+            let language = self.parser.language();
+            self.parser
+                .set_language(&injected_config.language)
+                .map_err(|_| Error::InvalidLanguage)?;
+            let injected_tree = if self.parser.set_included_ranges(&injection_ranges).is_ok() {
+                unsafe { self.parser.set_cancellation_flag(cancellation_flag) };
+                let tree = self.parser.parse(source, None);
+                unsafe { self.parser.set_cancellation_flag(None) };
+                Some(tree)
+            } else {
+                None
+            };
+
+            // Let the parser parse entire documents in its own language again.
+            self.parser.set_included_ranges(&[]).unwrap();
+            if let Some(language) = language {
+                self.parser
+                    .set_language(&language)
+                    .map_err(|_| Error::InvalidLanguage)?;
+            }
+            let Some(injected_tree) = injected_tree else {
+                continue;
+            };
+            let injected_tree = injected_tree.ok_or(Error::Cancelled)?;
+            self.collect_folds(
+                injected_config,
+                Some(&config.language_name),
+                injected_tree.root_node(),
+                &injection_ranges,
+                source,
+                cancellation_flag,
+                injection_callback,
+                folds,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Fold {
+    // A range that ends at the start of a line does not include that line, and
+    // a range within a single line cannot be folded.
+    fn new(start: Point, end: Point, kind: Option<String>) -> Option<Self> {
+        let end_row = if end.column == 0 && end.row > start.row {
+            end.row - 1
+        } else {
+            end.row
+        };
+        (end_row > start.row).then_some(Self {
+            start_row: start.row,
+            end_row,
+            kind,
+        })
+    }
+}
+
+fn merge_folds(mut folds: Vec<Fold>) -> Vec<Fold> {
+    folds.sort_by(|a, b| {
+        a.start_row
+            .cmp(&b.start_row)
+            .then_with(|| b.end_row.cmp(&a.end_row))
+    });
+
+    // Keep a stack of the folds that contain the current row. A fold that ends
+    // on the row where another fold starts does not overlap it.
+    let mut result = Vec::<Fold>::with_capacity(folds.len());
+    let mut stack = Vec::<usize>::new();
+    for fold in folds {
+        if result
+            .last()
+            .is_some_and(|last| last.start_row == fold.start_row)
+        {
+            continue;
+        }
+        while stack
+            .last()
+            .is_some_and(|i| result[*i].end_row <= fold.start_row)
+        {
+            stack.pop();
+        }
+
+        // A fold that ends after the folds that contain its start is merged into
+        // them, extending them to its end.
+        if stack
+            .last()
+            .is_some_and(|i| result[*i].end_row < fold.end_row)
+        {
+            for i in stack.iter().rev() {
+                if result[*i].end_row >= fold.end_row {
+                    break;
+                }
+                result[*i].end_row = fold.end_row;
+            }
+            continue;
+        }
+
+        stack.push(result.len());
+        result.push(fold);
+    }
+    result
+}
@@ -0,0 +1,230 @@
+/// A line break, or a space, between two pieces of text in a [`Doc`].
+///
+/// When several lines occur between the same two pieces of text, only the
+/// widest of them is printed, so a space followed by a newline produces a
+/// single newline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Line {
+    /// A single space.
+    Space,
+    /// A newline if the enclosing group does not fit on one line, and nothing
+    /// otherwise.
+    Softline,
+    /// A newline if the enclosing group does not fit on one line, and a space
+    /// otherwise.
+    SpacedSoftline,
+    /// A newline.
+    Hardline,
+    /// Two newlines, leaving an empty line.
+    Blankline,
+}
+
+/// A document to be laid out by a pretty printer, as described in Philip
+/// Wadler's "A prettier printer".
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Doc {
+    Text(String),
+    Line(Line),
+    Concat(Vec<Doc>),
+    /// A document in which every newline is followed by one more level of
+    /// indentation.
+    Nest(Box<Doc>),
+    /// A document that is printed on a single line if it fits within the line
+    /// width, and otherwise has all of its soft lines broken.
+    Group(Box<Doc>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Whitespace {
+    None,
+    Space,
+    Newline,
+    Blankline,
+}
+
+#[derive(Clone, Copy)]
+struct Command<'a> {
+    indent_level: usize,
+    mode: Mode,
+    doc: &'a Doc,
+}
+
+impl Doc {
+    #[must_use]
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    #[must_use]
+    pub fn nest(self) -> Self {
+        Self::Nest(Box::new(self))
+    }
+
+    #[must_use]
+    pub fn group(self) -> Self {
+        Self::Group(Box::new(self))
+    }
+
+    /// Lay out the document, breaking the groups that do not fit within the
+    /// given line width, and indenting nested lines with the given string.
+    ///
+    /// Leading whitespace is removed, and the output ends with a single newline
+    /// unless it is empty.
+    #[must_use]
+    pub fn render(&self, line_width: usize, indent: &str) -> String {
+        let mut output = String::new();
+        let mut column = 0;
+        let mut pending = Whitespace::None;
+        let mut stack = vec![Command {
+            indent_level: 0,
+            mode: Mode::Break,
+            doc: self,
+        }];
+
+        while let Some(command) = stack.pop() {
+            match command.doc {
+                Doc::Text(text) => {
+                    if text.is_empty() {
+                        continue;
+                    }
+                    if !output.is_empty() {
+                        match pending {
+                            Whitespace::None => {}
+                            Whitespace::Space => {
+                                output.push(' ');
+                                column += 1;
+                            }
+                            Whitespace::Newline | Whitespace::Blankline => {
+                                if pending == Whitespace::Blankline {
+                                    output.push('\n');
+                                }
+                                output.push('\n');
+                                column = 0;
+                                for _ in 0..command.indent_level {
+                                    output += indent;
+                                    column += indent.chars().count();
+                                }
+                            }
+                        }
+                    }
+                    pending = Whitespace::None;
+                    output += text;
+                    match text.rfind('\n') {
+                        Some(index) => column = text[index + 1..].chars().count(),
+                        None => column += text.chars().count(),
+                    }
+                }
+                Doc::Line(line) => {
+                    let whitespace = match (line, command.mode) {
+                        (Line::Space, _) | (Line::SpacedSoftline, Mode::Flat) => Whitespace::Space,
+                        (Line::Softline, Mode::Flat) => Whitespace::None,
+                        (Line::Softline | Line::SpacedSoftline | Line::Hardline, _) => {
+                            Whitespace::Newline
+                        }
+                        (Line::Blankline, _) => Whitespace::Blankline,
+                    };
+                    pending = pending.max(whitespace);
+                }
+                Doc::Concat(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| Command { doc, ..command }));
+                }
+                Doc::Nest(doc) => stack.push(Command {
+                    indent_level: command.indent_level + 1,
+                    doc,
+                    ..command
+                }),
+                Doc::Group(doc) => {
+                    // Account for the whitespace that will be printed before the group.
+                    let start_column = match pending {
+                        Whitespace::None => column,
+                        Whitespace::Space => column + 1,
+                        Whitespace::Newline | Whitespace::Blankline => {
+                            command.indent_level * indent.chars().count()
+                        }
+                    };
+                    let width = line_width as isize - start_column as isize;
+                    let mode = if command.mode == Mode::Flat || fits(doc, width, &stack) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push(Command {
+                        mode,
+                        doc,
+                        ..command
+                    });
+                }
+            }
+        }
+
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output
+    }
+}
+
+// Deeply nested documents would overflow the stack if they were dropped
+// recursively, so their descendants are moved into a list and dropped one by one.
+impl Drop for Doc {
+    fn drop(&mut self) {
+        let mut descendants = Vec::new();
+        self.take_children(&mut descendants);
+        while let Some(mut doc) = descendants.pop() {
+            doc.take_children(&mut descendants);
+        }
+    }
+}
+
+impl Doc {
+    fn take_children(&mut self, children: &mut Vec<Self>) {
+        match self {
+            Self::Concat(docs) => children.append(docs),
+            Self::Nest(doc) | Self::Group(doc) => {
+                children.push(std::mem::replace(doc, Self::Concat(Vec::new())));
+            }
+            Self::Text(_) | Self::Line(_) => {}
+        }
+    }
+}
+
+// Check if the given document fits in the given width when printed on a single
+// line, along with the rest of the current line.
+fn fits(doc: &Doc, mut width: isize, rest: &[Command]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![(Mode::Flat, doc)];
+    loop {
+        if width < 0 {
+            return false;
+        }
+        let (mode, doc) = match stack.pop() {
+            Some(entry) => entry,
+            None => match rest.next() {
+                Some(command) => (command.mode, command.doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => {
+                if text.contains('\n') {
+                    return mode == Mode::Break;
+                }
+                width -= text.chars().count() as isize;
+            }
+            Doc::Line(line) => match (line, mode) {
+                (Line::Space, _) | (Line::SpacedSoftline, Mode::Flat) => width -= 1,
+                (Line::Softline, Mode::Flat) => {}
+                (Line::Hardline | Line::Blankline, Mode::Flat) => return false,
+                (_, Mode::Break) => return true,
+            },
+            Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+            Doc::Nest(doc) | Doc::Group(doc) => stack.push((mode, doc)),
+        }
+    }
+}
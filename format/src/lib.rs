@@ -0,0 +1,382 @@
+#![doc = include_str!("../README.md")]
+
+mod doc;
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub use doc::{Doc, Line};
+use thiserror::Error;
+use tree_sitter::{Language, Node, Parser, Point, Query, QueryCursor, QueryError, Tree};
+
+const CANCELLATION_CHECK_INTERVAL: usize = 100;
+const DEFAULT_LINE_WIDTH: usize = 80;
+const DEFAULT_INDENT: &str = "  ";
+
+/// Contains the data needed to format code written in a particular language.
+#[derive(Debug)]
+pub struct FormattingConfiguration {
+    pub language: Language,
+    pub query: Query,
+    capture_kinds: Vec<Option<CaptureKind>>,
+}
+
+/// Formats source code, laying it out according to a [`FormattingConfiguration`].
+///
+/// You need one of these for each thread that you're using for formatting.
+pub struct Formatter {
+    pub parser: Parser,
+    /// The maximum width of a line. Groups of nodes that do not fit on a line
+    /// are broken over several lines.
+    pub line_width: usize,
+    /// The string used for each level of indentation.
+    pub indent: String,
+    cursor: QueryCursor,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Invalid language")]
+    InvalidLanguage,
+    #[error("Invalid capture @{0}. Expected one of: @leaf, @delete, @group, @indent, @append_indent_start, @prepend_indent_end, @allow_blank_line_before, or @(prepend|append)_(space|softline|spaced_softline|newline).")]
+    InvalidCapture(String),
+    #[error("Cannot format code with syntax errors. The first error is at {}:{}", .0.row + 1, .0.column + 1)]
+    Syntax(Point),
+    #[error("Cannot format code that is not valid UTF-8. The first invalid byte is at {}:{}", .0.row + 1, .0.column + 1)]
+    InvalidUtf8(Point),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaptureKind {
+    Leaf,
+    Delete,
+    Group,
+    Indent,
+    IndentStart,
+    IndentEnd,
+    AllowBlankLineBefore,
+    Prepend(Line),
+    Append(Line),
+}
+
+// The formatting that the query assigns to a particular node.
+#[derive(Debug, Default)]
+struct NodeFormatting {
+    is_leaf: bool,
+    is_deleted: bool,
+    is_group: bool,
+    is_indented: bool,
+    starts_indent: bool,
+    ends_indent: bool,
+    allows_blank_line_before: bool,
+    before: Vec<Line>,
+    after: Vec<Line>,
+}
+
+impl FormattingConfiguration {
+    /// Create a formatting configuration from a query whose captures describe
+    /// how each node should be laid out.
+    ///
+    /// Captures whose names start with an underscore are ignored, so that they
+    /// can be used in predicates.
+    pub fn new(language: Language, formatting_query: &str) -> Result<Self, Error> {
+        let query = Query::new(&language, formatting_query)?;
+        let capture_kinds = query
+            .capture_names()
+            .iter()
+            .map(|name| {
+                if name.starts_with('_') {
+                    return Ok(None);
+                }
+                let kind = match *name {
+                    "leaf" => CaptureKind::Leaf,
+                    "delete" => CaptureKind::Delete,
+                    "group" => CaptureKind::Group,
+                    "indent" => CaptureKind::Indent,
+                    "append_indent_start" => CaptureKind::IndentStart,
+                    "prepend_indent_end" => CaptureKind::IndentEnd,
+                    "allow_blank_line_before" => CaptureKind::AllowBlankLineBefore,
+                    _ => {
+                        let (line, is_append) = name
+                            .strip_prefix("prepend_")
+                            .map(|line| (line, false))
+                            .or_else(|| name.strip_prefix("append_").map(|line| (line, true)))
+                            .ok_or_else(|| Error::InvalidCapture((*name).to_string()))?;
+                        let line = match line {
+                            "space" => Line::Space,
+                            "softline" => Line::Softline,
+                            "spaced_softline" => Line::SpacedSoftline,
+                            "newline" => Line::Hardline,
+                            _ => return Err(Error::InvalidCapture((*name).to_string())),
+                        };
+                        if is_append {
+                            CaptureKind::Append(line)
+                        } else {
+                            CaptureKind::Prepend(line)
+                        }
+                    }
+                };
+                Ok(Some(kind))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            language,
+            query,
+            capture_kinds,
+        })
+    }
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            line_width: DEFAULT_LINE_WIDTH,
+            indent: DEFAULT_INDENT.to_string(),
+            cursor: QueryCursor::new(),
+        }
+    }
+
+    pub fn parser(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Parse and format the given source code.
+    pub fn format(
+        &mut self,
+        config: &FormattingConfiguration,
+        source: &[u8],
+        cancellation_flag: Option<&AtomicUsize>,
+    ) -> Result<String, Error> {
+        self.parser
+            .set_language(&config.language)
+            .map_err(|_| Error::InvalidLanguage)?;
+        self.parser.reset();
+        unsafe { self.parser.set_cancellation_flag(cancellation_flag) };
+        let tree = self.parser.parse(source, None);
+        unsafe { self.parser.set_cancellation_flag(None) };
+        let tree = tree.ok_or(Error::Cancelled)?;
+        let doc = self.document(config, &tree, source, cancellation_flag)?;
+        Ok(doc.render(self.line_width, &self.indent))
+    }
+
+    /// Build the document that describes the layout of the given syntax tree,
+    /// without printing it.
+    pub fn document(
+        &mut self,
+        config: &FormattingConfiguration,
+        tree: &Tree,
+        source: &[u8],
+        cancellation_flag: Option<&AtomicUsize>,
+    ) -> Result<Doc, Error> {
+        let root = tree.root_node();
+        if root.has_error() {
+            return Err(Error::Syntax(first_error(root).start_position()));
+        }
+
+        let mut formatting = HashMap::<usize, NodeFormatting>::new();
+        for (i, m) in self.cursor.matches(&config.query, root, source).enumerate() {
+            if i % CANCELLATION_CHECK_INTERVAL == 0
+                && cancellation_flag.is_some_and(|flag| flag.load(Ordering::Relaxed) != 0)
+            {
+                return Err(Error::Cancelled);
+            }
+            for capture in m.captures {
+                let Some(kind) = config.capture_kinds[capture.index as usize] else {
+                    continue;
+                };
+                let node_formatting = formatting.entry(capture.node.id()).or_default();
+                match kind {
+                    CaptureKind::Leaf => node_formatting.is_leaf = true,
+                    CaptureKind::Delete => node_formatting.is_deleted = true,
+                    CaptureKind::Group => node_formatting.is_group = true,
+                    CaptureKind::Indent => node_formatting.is_indented = true,
+                    CaptureKind::IndentStart => node_formatting.starts_indent = true,
+                    CaptureKind::IndentEnd => node_formatting.ends_indent = true,
+                    CaptureKind::AllowBlankLineBefore => {
+                        node_formatting.allows_blank_line_before = true;
+                    }
+                    CaptureKind::Prepend(line) => node_formatting.before.push(line),
+                    CaptureKind::Append(line) => node_formatting.after.push(line),
+                }
+            }
+        }
+
+        node_document(root, &formatting, source)
+    }
+}
+
+// The document of a node whose children are being visited.
+struct PartialDocument<'a> {
+    formatting: &'a NodeFormatting,
+    before: Vec<Doc>,
+    // Each child that starts an indented section is followed by a nested
+    // document, which ends at the child that ends the indented section.
+    sections: Vec<Vec<Doc>>,
+}
+
+impl<'a> PartialDocument<'a> {
+    fn new(node: Node, formatting: &'a NodeFormatting, source: &[u8]) -> Self {
+        let mut before = Vec::new();
+        if formatting.allows_blank_line_before && has_blank_line_before(node, source) {
+            before.push(Doc::Line(Line::Blankline));
+        }
+        before.extend(formatting.before.iter().copied().map(Doc::Line));
+        Self {
+            formatting,
+            before,
+            sections: vec![Vec::new()],
+        }
+    }
+
+    fn push_child(&mut self, child: Doc, child_formatting: &NodeFormatting) {
+        if child_formatting.ends_indent && self.sections.len() > 1 {
+            self.end_section();
+        }
+        self.sections.last_mut().unwrap().push(child);
+        if child_formatting.starts_indent {
+            self.sections.push(Vec::new());
+        }
+    }
+
+    fn end_section(&mut self) {
+        let section = self.sections.pop().unwrap();
+        self.sections
+            .last_mut()
+            .unwrap()
+            .push(Doc::Concat(section).nest());
+    }
+
+    fn finish(mut self) -> Doc {
+        while self.sections.len() > 1 {
+            self.end_section();
+        }
+        let content = Doc::Concat(self.sections.pop().unwrap());
+        self.finish_with_content(content)
+    }
+
+    fn finish_with_content(self, mut content: Doc) -> Doc {
+        if self.formatting.is_indented {
+            content = content.nest();
+        }
+        if self.formatting.is_group {
+            content = content.group();
+        }
+        let mut result = self.before;
+        result.push(content);
+        result.extend(self.formatting.after.iter().copied().map(Doc::Line));
+        Doc::Concat(result)
+    }
+}
+
+// Build the document of a node by walking its descendants with a cursor,
+// rather than recursively, so that deeply nested code can be formatted.
+fn node_document(
+    node: Node,
+    formatting: &HashMap<usize, NodeFormatting>,
+    source: &[u8],
+) -> Result<Doc, Error> {
+    let default_formatting = NodeFormatting::default();
+    let formatting_of = |node: Node| formatting.get(&node.id()).unwrap_or(&default_formatting);
+
+    let mut ancestors = Vec::<PartialDocument>::new();
+    let mut cursor = node.walk();
+    loop {
+        let node = cursor.node();
+        let node_formatting = formatting_of(node);
+        let mut doc = if node_formatting.is_deleted {
+            Doc::Concat(Vec::new())
+        } else {
+            let document = PartialDocument::new(node, node_formatting, source);
+
+            // Nodes that contain text outside of their children, such as strings
+            // whose contents are not represented by nodes, are printed as they are.
+            if node_formatting.is_leaf || has_text_between_children(node, source) {
+                document.finish_with_content(Doc::text(node_text(node, source)?))
+            } else if cursor.goto_first_child() {
+                ancestors.push(document);
+                continue;
+            } else {
+                document.finish()
+            }
+        };
+
+        // Add the node's document to its parent, finishing the documents of
+        // any ancestors whose last child this is.
+        loop {
+            let Some(parent) = ancestors.last_mut() else {
+                return Ok(doc);
+            };
+            parent.push_child(doc, formatting_of(cursor.node()));
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            cursor.goto_parent();
+            doc = ancestors.pop().unwrap().finish();
+        }
+    }
+}
+
+fn node_text<'a>(node: Node, source: &'a [u8]) -> Result<&'a str, Error> {
+    std::str::from_utf8(&source[node.byte_range()]).map_err(|error| {
+        let offset = node.start_byte() + error.valid_up_to();
+        let row_start = source[..offset]
+            .iter()
+            .rposition(|c| *c == b'\n')
+            .map_or(0, |i| i + 1);
+        let row = source[..row_start].iter().filter(|c| **c == b'\n').count();
+        Error::InvalidUtf8(Point::new(row, offset - row_start))
+    })
+}
+
+fn has_text_between_children(node: Node, source: &[u8]) -> bool {
+    let mut position = node.start_byte();
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if !is_whitespace(&source[position..child.start_byte()]) {
+            return true;
+        }
+        position = child.end_byte();
+    }
+    !is_whitespace(&source[position..node.end_byte()])
+}
+
+fn has_blank_line_before(node: Node, source: &[u8]) -> bool {
+    source[..node.start_byte()]
+        .iter()
+        .rev()
+        .take_while(|c| c.is_ascii_whitespace())
+        .filter(|c| **c == b'\n')
+        .nth(1)
+        .is_some()
+}
+
+fn is_whitespace(text: &[u8]) -> bool {
+    text.iter().all(u8::is_ascii_whitespace)
+}
+
+fn first_error(node: Node) -> Node {
+    let mut cursor = node.walk();
+    let mut node = node;
+    while !node.is_error() && !node.is_missing() {
+        match node.children(&mut cursor).find(|child| child.has_error()) {
+            Some(child) => node = child,
+            None => break,
+        }
+    }
+    node
+}
@@ -0,0 +1,334 @@
+use tree_sitter::{Language, Node, Point, Query, QueryError, QueryMatch, Range};
+
+/// Describes the patterns of a query that find injected languages.
+///
+/// The injection query has the same format for every kind of query that is
+/// combined with it, such as highlighting, indentation and folding queries.
+/// Its patterns come first in the combined query, and the captures named
+/// `injection.content` and `injection.language` belong to them. Captures whose
+/// names start with an underscore are not used by either query, so that they
+/// can be used in predicates.
+#[derive(Debug)]
+pub struct InjectionPatterns {
+    pattern_count: usize,
+    content_capture_index: Option<u32>,
+    language_capture_index: Option<u32>,
+}
+
+/// A language that is injected into a layer of a document.
+#[derive(Clone, Debug)]
+pub struct Injection<'a> {
+    pub language_name: &'a str,
+    /// The nodes whose text is parsed as the injected language. There is more
+    /// than one for the patterns marked with `injection.combined`, whose
+    /// matches in a layer are all parsed as a single document.
+    pub content_nodes: Vec<Node<'a>>,
+    /// Whether the children of the content nodes are parsed as part of the
+    /// injected language, which is set with `injection.include-children`.
+    pub include_children: bool,
+    /// The pattern of the combined query that found the injection.
+    pub pattern_index: usize,
+    pub combined: bool,
+}
+
+/// Collects the languages that are injected into a layer of a document, from
+/// the matches of a query's injection patterns.
+#[derive(Debug, Default)]
+pub struct Injections<'a> {
+    injections: Vec<PartialInjection<'a>>,
+}
+
+#[derive(Debug)]
+struct PartialInjection<'a> {
+    language_name: Option<&'a str>,
+    content_nodes: Vec<Node<'a>>,
+    include_children: bool,
+    pattern_index: usize,
+    combined: bool,
+}
+
+impl InjectionPatterns {
+    /// Create a query whose patterns are those of an injection query followed
+    /// by those of the given query.
+    pub fn new_query(
+        language: &Language,
+        query: &str,
+        injection_query: &str,
+    ) -> Result<(Query, Self), QueryError> {
+        let combined_query = Query::new(language, &format!("{injection_query}{query}"))?;
+        let query_offset = injection_query.len();
+        let pattern_count = (0..combined_query.pattern_count())
+            .take_while(|i| combined_query.start_byte_for_pattern(*i) < query_offset)
+            .count();
+        let injection_patterns = Self::for_query(&combined_query, pattern_count);
+        Ok((combined_query, injection_patterns))
+    }
+
+    // Describe the injection patterns of a query whose first patterns are
+    // those of an injection query.
+    pub(crate) fn for_query(query: &Query, pattern_count: usize) -> Self {
+        let mut content_capture_index = None;
+        let mut language_capture_index = None;
+        for (i, name) in query.capture_names().iter().enumerate() {
+            match *name {
+                "injection.content" => content_capture_index = Some(i as u32),
+                "injection.language" => language_capture_index = Some(i as u32),
+                _ => {}
+            }
+        }
+        Self {
+            pattern_count,
+            content_capture_index,
+            language_capture_index,
+        }
+    }
+
+    /// Check if a capture name is used by the injection patterns, or is only
+    /// used in predicates. The other queries' captures are all other names.
+    #[must_use]
+    pub fn is_reserved_capture_name(name: &str) -> bool {
+        matches!(name, "injection.content" | "injection.language") || name.starts_with('_')
+    }
+
+    /// Check if a pattern of the combined query is an injection pattern.
+    #[must_use]
+    pub const fn contains(&self, pattern_index: usize) -> bool {
+        pattern_index < self.pattern_count
+    }
+
+    /// Get the language name, the content node and whether the content node's
+    /// children are included, as described by a match of an injection pattern.
+    ///
+    /// The `language_name` is the name of the layer's language, which is
+    /// injected by the patterns with `injection.self`, and the `parent_name` is
+    /// the name of the language that the layer is injected into, which is
+    /// injected by the patterns with `injection.parent`.
+    #[must_use]
+    pub fn injection_for_match<'a>(
+        &self,
+        language_name: &'a str,
+        parent_name: Option<&'a str>,
+        query: &'a Query,
+        query_match: &QueryMatch<'_, 'a>,
+        source: &'a [u8],
+    ) -> (Option<&'a str>, Option<Node<'a>>, bool) {
+        let layer_name = language_name;
+        let mut language_name = None;
+        let mut content_node = None;
+
+        for capture in query_match.captures {
+            let index = Some(capture.index);
+            if index == self.language_capture_index {
+                language_name = capture.node.utf8_text(source).ok();
+            } else if index == self.content_capture_index {
+                content_node = Some(capture.node);
+            }
+        }
+
+        let mut include_children = false;
+        for prop in query.property_settings(query_match.pattern_index) {
+            match prop.key.as_ref() {
+                // In addition to specifying the language name via the text of a
+                // captured node, it can also be hard-coded via a `#set!` predicate
+                // that sets the injection.language key.
+                "injection.language" => {
+                    if language_name.is_none() {
+                        language_name = prop.value.as_ref().map(std::convert::AsRef::as_ref);
+                    }
+                }
+
+                // Setting the `injection.self` key can be used to specify that the
+                // language name should be the same as the language of the current
+                // layer.
+                "injection.self" => {
+                    if language_name.is_none() {
+                        language_name = Some(layer_name);
+                    }
+                }
+
+                // Setting the `injection.parent` key can be used to specify that
+                // the language name should be the same as the language of the
+                // parent layer
+                "injection.parent" => {
+                    if language_name.is_none() {
+                        language_name = parent_name;
+                    }
+                }
+
+                // By default, injections do not include the *children* of an
+                // `injection.content` node - only the ranges that belong to the
+                // node itself. This can be changed using a `#set!` predicate that
+                // sets the `injection.include-children` key.
+                "injection.include-children" => include_children = true,
+                _ => {}
+            }
+        }
+
+        (language_name, content_node, include_children)
+    }
+}
+
+impl<'a> Injections<'a> {
+    /// Add the injection that is described by a match of an injection pattern.
+    ///
+    /// The matches of a pattern with `injection.combined` are added to a single
+    /// injection, which takes its language name from any of them.
+    pub fn add_match(
+        &mut self,
+        patterns: &InjectionPatterns,
+        language_name: &'a str,
+        parent_name: Option<&'a str>,
+        query: &'a Query,
+        query_match: &QueryMatch<'_, 'a>,
+        source: &'a [u8],
+    ) {
+        let (language_name, content_node, include_children) =
+            patterns.injection_for_match(language_name, parent_name, query, query_match, source);
+        let pattern_index = query_match.pattern_index;
+        let combined = query
+            .property_settings(pattern_index)
+            .iter()
+            .any(|prop| &*prop.key == "injection.combined");
+
+        let existing = self
+            .injections
+            .iter_mut()
+            .find(|injection| combined && injection.pattern_index == pattern_index);
+        let injection = if let Some(injection) = existing {
+            injection
+        } else {
+            self.injections.push(PartialInjection {
+                language_name: None,
+                content_nodes: Vec::new(),
+                include_children,
+                pattern_index,
+                combined,
+            });
+            self.injections.last_mut().unwrap()
+        };
+        if language_name.is_some() {
+            injection.language_name = language_name;
+        }
+        injection.content_nodes.extend(content_node);
+        injection.include_children = include_children;
+    }
+
+    /// Get the injections that have both a language name and content nodes.
+    pub fn into_injections(self) -> impl Iterator<Item = Injection<'a>> {
+        self.injections.into_iter().filter_map(|injection| {
+            if injection.content_nodes.is_empty() {
+                return None;
+            }
+            Some(Injection {
+                language_name: injection.language_name?,
+                content_nodes: injection.content_nodes,
+                include_children: injection.include_children,
+                pattern_index: injection.pattern_index,
+                combined: injection.combined,
+            })
+        })
+    }
+}
+
+impl Injection<'_> {
+    /// Get the ranges of the document that the injected language is parsed
+    /// from, which are within the ranges of the layer that it's injected into.
+    #[must_use]
+    pub fn ranges(&self, parent_ranges: &[Range]) -> Vec<Range> {
+        intersect_ranges(parent_ranges, &self.content_nodes, self.include_children)
+    }
+}
+
+// Compute the ranges that should be included when parsing an injection.
+// This takes into account three things:
+// * `parent_ranges` - The ranges must all fall within the *current* layer's ranges.
+// * `nodes` - Every injection takes place within a set of nodes. The injection ranges are the
+//   ranges of those nodes.
+// * `includes_children` - For some injections, the content nodes' children should be excluded
+//   from the nested document, so that only the content nodes' *own* content is reparsed. For
+//   other injections, the content nodes' entire ranges should be reparsed, including the ranges
+//   of their children.
+pub(crate) fn intersect_ranges(
+    parent_ranges: &[Range],
+    nodes: &[Node],
+    includes_children: bool,
+) -> Vec<Range> {
+    let mut cursor = nodes[0].walk();
+    let mut result = Vec::new();
+    let mut parent_range_iter = parent_ranges.iter();
+    let mut parent_range = parent_range_iter
+        .next()
+        .expect("Layers should only be constructed with non-empty ranges vectors");
+    for node in nodes {
+        let mut preceding_range = Range {
+            start_byte: 0,
+            start_point: Point::new(0, 0),
+            end_byte: node.start_byte(),
+            end_point: node.start_position(),
+        };
+        let following_range = Range {
+            start_byte: node.end_byte(),
+            start_point: node.end_position(),
+            end_byte: usize::MAX,
+            end_point: Point::new(usize::MAX, usize::MAX),
+        };
+
+        for excluded_range in node
+            .children(&mut cursor)
+            .filter_map(|child| {
+                if includes_children {
+                    None
+                } else {
+                    Some(child.range())
+                }
+            })
+            .chain(std::iter::once(following_range))
+        {
+            let mut range = Range {
+                start_byte: preceding_range.end_byte,
+                start_point: preceding_range.end_point,
+                end_byte: excluded_range.start_byte,
+                end_point: excluded_range.start_point,
+            };
+            preceding_range = excluded_range;
+
+            if range.end_byte < parent_range.start_byte {
+                continue;
+            }
+
+            while parent_range.start_byte <= range.end_byte {
+                if parent_range.end_byte > range.start_byte {
+                    if range.start_byte < parent_range.start_byte {
+                        range.start_byte = parent_range.start_byte;
+                        range.start_point = parent_range.start_point;
+                    }
+
+                    if parent_range.end_byte < range.end_byte {
+                        if range.start_byte < parent_range.end_byte {
+                            result.push(Range {
+                                start_byte: range.start_byte,
+                                start_point: range.start_point,
+                                end_byte: parent_range.end_byte,
+                                end_point: parent_range.end_point,
+                            });
+                        }
+                        range.start_byte = parent_range.end_byte;
+                        range.start_point = parent_range.end_point;
+                    } else {
+                        if range.start_byte < range.end_byte {
+                            result.push(range);
+                        }
+                        break;
+                    }
+                }
+
+                if let Some(next_range) = parent_range_iter.next() {
+                    parent_range = next_range;
+                } else {
+                    return result;
+                }
+            }
+        }
+    }
+    result
+}
@@ -1,18 +1,30 @@
 #![doc = include_str!("../README.md")]
 
 pub mod c_lib;
+mod injections;
+mod render;
+mod semantic_tokens;
+mod session;
 use std::{
-    collections::HashSet,
-    iter, mem, ops, str,
+    collections::{HashSet, VecDeque},
+    iter, mem, ops, slice, str,
     sync::atomic::{AtomicUsize, Ordering},
 };
 
 pub use c_lib as c;
+pub use injections::{Injection, InjectionPatterns, Injections};
 use lazy_static::lazy_static;
+pub use render::{
+    render, AnsiRenderer, Color, LatexRenderer, Renderer, RtfRenderer, Style, SvgRenderer,
+};
+pub use semantic_tokens::{
+    SemanticToken, SemanticTokensEdit, SemanticTokensLegend, SemanticTokensRenderer,
+    STANDARD_TOKEN_MODIFIERS, STANDARD_TOKEN_TYPES,
+};
+pub use session::HighlightSession;
 use thiserror::Error;
 use tree_sitter::{
-    Language, LossyUtf8, Node, Parser, Point, Query, QueryCaptures, QueryCursor, QueryError,
-    QueryMatch, Range, Tree,
+    Language, LossyUtf8, Parser, Point, Query, QueryCaptures, QueryCursor, QueryError, Range, Tree,
 };
 
 const CANCELLATION_CHECK_INTERVAL: usize = 100;
@@ -113,8 +125,7 @@ pub struct HighlightConfiguration {
     highlights_pattern_index: usize,
     highlight_indices: Vec<Option<Highlight>>,
     non_local_variable_patterns: Vec<bool>,
-    injection_content_capture_index: Option<u32>,
-    injection_language_capture_index: Option<u32>,
+    injection_patterns: InjectionPatterns,
     local_scope_capture_index: Option<u32>,
     local_def_capture_index: Option<u32>,
     local_def_value_capture_index: Option<u32>,
@@ -166,6 +177,24 @@ where
     iter_count: usize,
     next_event: Option<HighlightEvent>,
     last_highlight_range: Option<(usize, usize, usize)>,
+    byte_range: ops::Range<usize>,
+    session: Option<&'a mut HighlightSession>,
+}
+
+// Yields the events of a highlighting iterator that fall within a given range,
+// or within the changed ranges of its session, restarting the enclosing
+// highlights at the start of each range.
+struct ClippedHighlightIter<'a, F>
+where
+    F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+{
+    iter: HighlightIter<'a, F>,
+    range: Option<ops::Range<usize>>,
+    highlights: Vec<Highlight>,
+    open_count: usize,
+    last_end: usize,
+    queue: VecDeque<HighlightEvent>,
+    done: bool,
 }
 
 struct HighlightIterLayer<'a> {
@@ -204,8 +233,84 @@ impl Highlighter {
         config: &'a HighlightConfiguration,
         source: &'a [u8],
         cancellation_flag: Option<&'a AtomicUsize>,
-        mut injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+        injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+    ) -> Result<impl Iterator<Item = Result<HighlightEvent, Error>> + 'a, Error> {
+        self.highlight_iter(
+            config,
+            source,
+            0..usize::MAX,
+            cancellation_flag,
+            injection_callback,
+            None,
+        )
+    }
+
+    /// Iterate over the highlighted regions within a byte range of a given slice of
+    /// source code.
+    ///
+    /// The queries are only run over the nodes that intersect the range, in the
+    /// document and in any injected languages. Highlights that enclose the start of
+    /// the range are started at the start of the range, and any highlights that are
+    /// still open at the end of the range are ended there. Local variables that are
+    /// defined before the range are not tracked.
+    pub fn highlight_range<'a>(
+        &'a mut self,
+        config: &'a HighlightConfiguration,
+        source: &'a [u8],
+        range: ops::Range<usize>,
+        cancellation_flag: Option<&'a AtomicUsize>,
+        injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+    ) -> Result<impl Iterator<Item = Result<HighlightEvent, Error>> + 'a, Error> {
+        let iter = self.highlight_iter(
+            config,
+            source,
+            range.clone(),
+            cancellation_flag,
+            injection_callback,
+            None,
+        )?;
+        Ok(ClippedHighlightIter::new(iter, Some(range)))
+    }
+
+    /// Iterate over the highlighted regions of a document that have changed since
+    /// it was last highlighted with the given session.
+    ///
+    /// The document and its injected languages are reparsed incrementally, using
+    /// the syntax trees from the previous call and the edits that were passed to
+    /// [`HighlightSession::edit`]. Only the events within the session's
+    /// [`changed_ranges`](HighlightSession::changed_ranges) are emitted, and any
+    /// highlights that enclose the start of a changed range are started again
+    /// there. The first time that a session is used, the whole document is
+    /// highlighted.
+    pub fn highlight_incremental<'a>(
+        &'a mut self,
+        session: &'a mut HighlightSession,
+        config: &'a HighlightConfiguration,
+        source: &'a [u8],
+        cancellation_flag: Option<&'a AtomicUsize>,
+        injection_callback: impl FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
     ) -> Result<impl Iterator<Item = Result<HighlightEvent, Error>> + 'a, Error> {
+        session.start();
+        let iter = self.highlight_iter(
+            config,
+            source,
+            0..usize::MAX,
+            cancellation_flag,
+            injection_callback,
+            Some(session),
+        )?;
+        Ok(ClippedHighlightIter::new(iter, None))
+    }
+
+    fn highlight_iter<'a, F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a>(
+        &'a mut self,
+        config: &'a HighlightConfiguration,
+        source: &'a [u8],
+        byte_range: ops::Range<usize>,
+        cancellation_flag: Option<&'a AtomicUsize>,
+        mut injection_callback: F,
+        mut session: Option<&'a mut HighlightSession>,
+    ) -> Result<HighlightIter<'a, F>, Error> {
         let layers = HighlightIterLayer::new(
             source,
             None,
@@ -220,6 +325,8 @@ impl Highlighter {
                 start_point: Point::new(0, 0),
                 end_point: Point::new(usize::MAX, usize::MAX),
             }],
+            byte_range.clone(),
+            session.as_deref_mut(),
         )?;
         assert_ne!(layers.len(), 0);
         let mut result = HighlightIter {
@@ -233,6 +340,8 @@ impl Highlighter {
             layers,
             next_event: None,
             last_highlight_range: None,
+            byte_range,
+            session,
         };
         result.sort_layers();
         Ok(result)
@@ -317,8 +426,6 @@ impl HighlightConfiguration {
             .collect();
 
         // Store the numeric ids for all of the special captures.
-        let mut injection_content_capture_index = None;
-        let mut injection_language_capture_index = None;
         let mut local_def_capture_index = None;
         let mut local_def_value_capture_index = None;
         let mut local_ref_capture_index = None;
@@ -326,8 +433,6 @@ impl HighlightConfiguration {
         for (i, name) in query.capture_names().iter().enumerate() {
             let i = Some(i as u32);
             match *name {
-                "injection.content" => injection_content_capture_index = i,
-                "injection.language" => injection_language_capture_index = i,
                 "local.definition" => local_def_capture_index = i,
                 "local.definition-value" => local_def_value_capture_index = i,
                 "local.reference" => local_ref_capture_index = i,
@@ -336,6 +441,7 @@ impl HighlightConfiguration {
             }
         }
 
+        let injection_patterns = InjectionPatterns::for_query(&query, locals_pattern_index);
         let highlight_indices = vec![None; query.capture_names().len()];
         Ok(Self {
             language,
@@ -346,8 +452,7 @@ impl HighlightConfiguration {
             highlights_pattern_index,
             highlight_indices,
             non_local_variable_patterns,
-            injection_content_capture_index,
-            injection_language_capture_index,
+            injection_patterns,
             local_def_capture_index,
             local_def_value_capture_index,
             local_ref_capture_index,
@@ -434,6 +539,8 @@ impl<'a> HighlightIterLayer<'a> {
         mut config: &'a HighlightConfiguration,
         mut depth: usize,
         mut ranges: Vec<Range>,
+        byte_range: ops::Range<usize>,
+        mut session: Option<&mut HighlightSession>,
     ) -> Result<Vec<Self>, Error> {
         let mut result = Vec::with_capacity(1);
         let mut queue = Vec::new();
@@ -444,49 +551,49 @@ impl<'a> HighlightIterLayer<'a> {
                     .set_language(&config.language)
                     .map_err(|_| Error::InvalidLanguage)?;
 
+                // Reuse the tree that was parsed for this layer the last time that the
+                // document was highlighted, if there is one.
+                let previous_layer = session
+                    .as_deref_mut()
+                    .and_then(|session| session.take_layer(config, depth, &ranges));
+
                 unsafe { highlighter.parser.set_cancellation_flag(cancellation_flag) };
                 let tree = highlighter
                     .parser
-                    .parse(source, None)
-                    .ok_or(Error::Cancelled)?;
+                    .parse(source, previous_layer.as_ref().map(|layer| &layer.tree));
                 unsafe { highlighter.parser.set_cancellation_flag(None) };
+                let Some(tree) = tree else {
+                    if let (Some(session), Some(layer)) = (session, previous_layer) {
+                        session.restore_layer(layer);
+                    }
+                    return Err(Error::Cancelled);
+                };
+                if let Some(session) = session.as_deref_mut() {
+                    session.add_layer(config, depth, &ranges, &tree, previous_layer, source.len());
+                }
                 let mut cursor = highlighter.cursors.pop().unwrap_or_default();
+                cursor.set_byte_range(0..usize::MAX);
 
                 // Process combined injections.
                 if let Some(combined_injections_query) = &config.combined_injections_query {
-                    let mut injections_by_pattern_index =
-                        vec![(None, Vec::new(), false); combined_injections_query.pattern_count()];
+                    let mut injections = Injections::default();
                     let matches =
                         cursor.matches(combined_injections_query, tree.root_node(), source);
                     for mat in matches {
-                        let entry = &mut injections_by_pattern_index[mat.pattern_index];
-                        let (language_name, content_node, include_children) = injection_for_match(
-                            config,
+                        injections.add_match(
+                            &config.injection_patterns,
+                            &config.language_name,
                             parent_name,
                             combined_injections_query,
                             &mat,
                             source,
                         );
-                        if language_name.is_some() {
-                            entry.0 = language_name;
-                        }
-                        if let Some(content_node) = content_node {
-                            entry.1.push(content_node);
-                        }
-                        entry.2 = include_children;
                     }
-                    for (lang_name, content_nodes, includes_children) in injections_by_pattern_index
-                    {
-                        if let (Some(lang_name), false) = (lang_name, content_nodes.is_empty()) {
-                            if let Some(next_config) = (injection_callback)(lang_name) {
-                                let ranges = Self::intersect_ranges(
-                                    &ranges,
-                                    &content_nodes,
-                                    includes_children,
-                                );
-                                if !ranges.is_empty() {
-                                    queue.push((next_config, depth + 1, ranges));
-                                }
+                    for injection in injections.into_injections() {
+                        if let Some(next_config) = (injection_callback)(injection.language_name) {
+                            let ranges = injection.ranges(&ranges);
+                            if !ranges.is_empty() {
+                                queue.push((next_config, depth + 1, ranges));
                             }
                         }
                     }
@@ -498,6 +605,7 @@ impl<'a> HighlightIterLayer<'a> {
                 let tree_ref = unsafe { mem::transmute::<_, &'static Tree>(&tree) };
                 let cursor_ref =
                     unsafe { mem::transmute::<_, &'static mut QueryCursor>(&mut cursor) };
+                cursor_ref.set_byte_range(byte_range.clone());
                 let captures = cursor_ref
                     .captures(&config.query, tree_ref.root_node(), source)
                     .peekable();
@@ -531,100 +639,6 @@ impl<'a> HighlightIterLayer<'a> {
         Ok(result)
     }
 
-    // Compute the ranges that should be included when parsing an injection.
-    // This takes into account three things:
-    // * `parent_ranges` - The ranges must all fall within the *current* layer's ranges.
-    // * `nodes` - Every injection takes place within a set of nodes. The injection ranges are the
-    //   ranges of those nodes.
-    // * `includes_children` - For some injections, the content nodes' children should be excluded
-    //   from the nested document, so that only the content nodes' *own* content is reparsed. For
-    //   other injections, the content nodes' entire ranges should be reparsed, including the ranges
-    //   of their children.
-    fn intersect_ranges(
-        parent_ranges: &[Range],
-        nodes: &[Node],
-        includes_children: bool,
-    ) -> Vec<Range> {
-        let mut cursor = nodes[0].walk();
-        let mut result = Vec::new();
-        let mut parent_range_iter = parent_ranges.iter();
-        let mut parent_range = parent_range_iter
-            .next()
-            .expect("Layers should only be constructed with non-empty ranges vectors");
-        for node in nodes {
-            let mut preceding_range = Range {
-                start_byte: 0,
-                start_point: Point::new(0, 0),
-                end_byte: node.start_byte(),
-                end_point: node.start_position(),
-            };
-            let following_range = Range {
-                start_byte: node.end_byte(),
-                start_point: node.end_position(),
-                end_byte: usize::MAX,
-                end_point: Point::new(usize::MAX, usize::MAX),
-            };
-
-            for excluded_range in node
-                .children(&mut cursor)
-                .filter_map(|child| {
-                    if includes_children {
-                        None
-                    } else {
-                        Some(child.range())
-                    }
-                })
-                .chain(std::iter::once(following_range))
-            {
-                let mut range = Range {
-                    start_byte: preceding_range.end_byte,
-                    start_point: preceding_range.end_point,
-                    end_byte: excluded_range.start_byte,
-                    end_point: excluded_range.start_point,
-                };
-                preceding_range = excluded_range;
-
-                if range.end_byte < parent_range.start_byte {
-                    continue;
-                }
-
-                while parent_range.start_byte <= range.end_byte {
-                    if parent_range.end_byte > range.start_byte {
-                        if range.start_byte < parent_range.start_byte {
-                            range.start_byte = parent_range.start_byte;
-                            range.start_point = parent_range.start_point;
-                        }
-
-                        if parent_range.end_byte < range.end_byte {
-                            if range.start_byte < parent_range.end_byte {
-                                result.push(Range {
-                                    start_byte: range.start_byte,
-                                    start_point: range.start_point,
-                                    end_byte: parent_range.end_byte,
-                                    end_point: parent_range.end_point,
-                                });
-                            }
-                            range.start_byte = parent_range.end_byte;
-                            range.start_point = parent_range.end_point;
-                        } else {
-                            if range.start_byte < range.end_byte {
-                                result.push(range);
-                            }
-                            break;
-                        }
-                    }
-
-                    if let Some(next_range) = parent_range_iter.next() {
-                        parent_range = next_range;
-                    } else {
-                        return result;
-                    }
-                }
-            }
-        }
-        result
-    }
-
     // First, sort scope boundaries by their byte offset in the document. At a
     // given position, emit scope endings before scope beginnings. Finally, emit
     // scope boundaries from deeper layers first.
@@ -787,13 +801,14 @@ where
 
             // If this capture represents an injection, then process the injection.
             if match_.pattern_index < layer.config.locals_pattern_index {
-                let (language_name, content_node, include_children) = injection_for_match(
-                    layer.config,
-                    Some(self.language_name),
-                    &layer.config.query,
-                    &match_,
-                    self.source,
-                );
+                let (language_name, content_node, include_children) =
+                    layer.config.injection_patterns.injection_for_match(
+                        &layer.config.language_name,
+                        Some(self.language_name),
+                        &layer.config.query,
+                        &match_,
+                        self.source,
+                    );
 
                 // Explicitly remove this match so that none of its other captures will remain
                 // in the stream of captures.
@@ -803,7 +818,7 @@ where
                 // to the highlighted document.
                 if let (Some(language_name), Some(content_node)) = (language_name, content_node) {
                     if let Some(config) = (self.injection_callback)(language_name) {
-                        let ranges = HighlightIterLayer::intersect_ranges(
+                        let ranges = injections::intersect_ranges(
                             &self.layers[0].ranges,
                             &[content_node],
                             include_children,
@@ -818,6 +833,8 @@ where
                                 config,
                                 self.layers[0].depth + 1,
                                 ranges,
+                                self.byte_range.clone(),
+                                self.session.as_deref_mut(),
                             ) {
                                 Ok(layers) => {
                                     for layer in layers {
@@ -980,6 +997,111 @@ where
     }
 }
 
+impl<'a, F> ClippedHighlightIter<'a, F>
+where
+    F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+{
+    fn new(iter: HighlightIter<'a, F>, range: Option<ops::Range<usize>>) -> Self {
+        Self {
+            iter,
+            range,
+            highlights: Vec::new(),
+            open_count: 0,
+            last_end: 0,
+            queue: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn close_highlights(&mut self) {
+        for _ in 0..self.open_count {
+            self.queue.push_back(HighlightEvent::HighlightEnd);
+        }
+        self.open_count = 0;
+    }
+
+    fn finish(&mut self) {
+        self.close_highlights();
+        self.done = true;
+        if let Some(session) = self.iter.session.as_deref_mut() {
+            session.finish();
+        }
+    }
+}
+
+impl<'a, F> Iterator for ClippedHighlightIter<'a, F>
+where
+    F: FnMut(&str) -> Option<&'a HighlightConfiguration> + 'a,
+{
+    type Item = Result<HighlightEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.queue.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.done {
+                return None;
+            }
+
+            match self.iter.next() {
+                None => self.finish(),
+                Some(Err(e)) => return Some(Err(e)),
+                Some(Ok(HighlightEvent::HighlightStart(highlight))) => {
+                    self.highlights.push(highlight);
+                }
+                Some(Ok(HighlightEvent::HighlightEnd)) => {
+                    self.highlights.pop();
+                    if self.open_count > self.highlights.len() {
+                        self.open_count = self.highlights.len();
+                        self.queue.push_back(HighlightEvent::HighlightEnd);
+                    }
+                }
+                Some(Ok(HighlightEvent::Source { start, end })) => {
+                    let ranges = match (&self.range, self.iter.session.as_deref()) {
+                        (Some(range), _) => slice::from_ref(range),
+                        (None, Some(session)) => session.changed_ranges(),
+                        (None, None) => &[],
+                    };
+                    for range in ranges {
+                        if range.end <= start {
+                            continue;
+                        }
+                        if range.start >= end {
+                            break;
+                        }
+
+                        // Close the highlights that were open at the end of the
+                        // previous range, and start the enclosing highlights again.
+                        let start = start.max(range.start);
+                        if start != self.last_end {
+                            for _ in 0..self.open_count {
+                                self.queue.push_back(HighlightEvent::HighlightEnd);
+                            }
+                            self.open_count = 0;
+                        }
+                        for highlight in &self.highlights[self.open_count..] {
+                            self.queue
+                                .push_back(HighlightEvent::HighlightStart(*highlight));
+                        }
+                        self.open_count = self.highlights.len();
+
+                        let end = end.min(range.end);
+                        self.queue.push_back(HighlightEvent::Source { start, end });
+                        self.last_end = end;
+                    }
+
+                    // A fixed range can't grow, so there is nothing more to emit
+                    // once the end of the range has been reached.
+                    if self.range.as_ref().is_some_and(|range| end >= range.end) {
+                        self.finish();
+                    }
+                }
+            }
+        }
+    }
+}
+
 impl Default for HtmlRenderer {
     fn default() -> Self {
         Self::new()
@@ -1136,70 +1258,6 @@ impl HtmlRenderer {
     }
 }
 
-fn injection_for_match<'a>(
-    config: &'a HighlightConfiguration,
-    parent_name: Option<&'a str>,
-    query: &'a Query,
-    query_match: &QueryMatch<'a, 'a>,
-    source: &'a [u8],
-) -> (Option<&'a str>, Option<Node<'a>>, bool) {
-    let content_capture_index = config.injection_content_capture_index;
-    let language_capture_index = config.injection_language_capture_index;
-
-    let mut language_name = None;
-    let mut content_node = None;
-
-    for capture in query_match.captures {
-        let index = Some(capture.index);
-        if index == language_capture_index {
-            language_name = capture.node.utf8_text(source).ok();
-        } else if index == content_capture_index {
-            content_node = Some(capture.node);
-        }
-    }
-
-    let mut include_children = false;
-    for prop in query.property_settings(query_match.pattern_index) {
-        match prop.key.as_ref() {
-            // In addition to specifying the language name via the text of a
-            // captured node, it can also be hard-coded via a `#set!` predicate
-            // that sets the injection.language key.
-            "injection.language" => {
-                if language_name.is_none() {
-                    language_name = prop.value.as_ref().map(std::convert::AsRef::as_ref);
-                }
-            }
-
-            // Setting the `injection.self` key can be used to specify that the
-            // language name should be the same as the language of the current
-            // layer.
-            "injection.self" => {
-                if language_name.is_none() {
-                    language_name = Some(config.language_name.as_str());
-                }
-            }
-
-            // Setting the `injection.parent` key can be used to specify that
-            // the language name should be the same as the language of the
-            // parent layer
-            "injection.parent" => {
-                if language_name.is_none() {
-                    language_name = parent_name;
-                }
-            }
-
-            // By default, injections do not include the *children* of an
-            // `injection.content` node - only the ranges that belong to the
-            // node itself. This can be changed using a `#set!` predicate that
-            // sets the `injection.include-children` key.
-            "injection.include-children" => include_children = true,
-            _ => {}
-        }
-    }
-
-    (language_name, content_node, include_children)
-}
-
 fn shrink_and_clear<T>(vec: &mut Vec<T>, capacity: usize) {
     if vec.len() > capacity {
         vec.truncate(capacity);
@@ -0,0 +1,598 @@
+use std::fmt::Write;
+
+use tree_sitter::LossyUtf8;
+
+use crate::{Error, HighlightEvent, HtmlRenderer};
+
+// The standard colors of the xterm 256-color palette, which precede the 6x6x6
+// color cube and the grayscale ramp.
+const ANSI_STANDARD_COLORS: [(u8, u8, u8); 16] = [
+    (0x00, 0x00, 0x00),
+    (0x80, 0x00, 0x00),
+    (0x00, 0x80, 0x00),
+    (0x80, 0x80, 0x00),
+    (0x00, 0x00, 0x80),
+    (0x80, 0x00, 0x80),
+    (0x00, 0x80, 0x80),
+    (0xc0, 0xc0, 0xc0),
+    (0x80, 0x80, 0x80),
+    (0xff, 0x00, 0x00),
+    (0x00, 0xff, 0x00),
+    (0xff, 0xff, 0x00),
+    (0x00, 0x00, 0xff),
+    (0xff, 0x00, 0xff),
+    (0x00, 0xff, 0xff),
+    (0xff, 0xff, 0xff),
+];
+const ANSI_CUBE_LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+
+const SVG_FONT_SIZE: usize = 14;
+const SVG_LINE_HEIGHT: usize = 20;
+const SVG_CHAR_WIDTH: f64 = 8.4;
+const SVG_PADDING: usize = 10;
+
+/// A color that a highlight is rendered with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Color {
+    /// A color from the xterm 256-color palette.
+    Ansi256(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// The visual style of a highlight.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+/// Converts highlighted text into an output format.
+///
+/// Renderers are driven by [`render`], which passes them each piece of text
+/// along with the style of its innermost highlight. The text that is passed to
+/// [`add_text`](Self::add_text) never contains line breaks.
+pub trait Renderer {
+    /// Add a piece of text with the given style.
+    fn add_text(&mut self, text: &str, style: &Style);
+
+    /// Start a new line.
+    fn add_line_break(&mut self);
+
+    /// Complete the output, once all of the text has been added.
+    fn finish(&mut self) {}
+
+    /// Get the rendered output.
+    fn output(&self) -> &[u8];
+
+    /// Clear the output, so that the renderer can be reused.
+    fn reset(&mut self);
+}
+
+/// Renders terminal output using ANSI escape sequences.
+pub struct AnsiRenderer {
+    pub output: Vec<u8>,
+    truecolor: bool,
+}
+
+/// Renders a `Verbatim` environment for LaTeX, in the same style as `minted`.
+///
+/// The output requires the `fancyvrb` and `xcolor` packages.
+pub struct LatexRenderer {
+    pub output: Vec<u8>,
+    body: String,
+}
+
+/// Renders a Rich Text Format document, which can be pasted into word processors.
+pub struct RtfRenderer {
+    pub output: Vec<u8>,
+    body: String,
+    colors: Vec<(u8, u8, u8)>,
+}
+
+/// Renders a standalone SVG image.
+pub struct SvgRenderer {
+    pub output: Vec<u8>,
+    body: String,
+    line: usize,
+    column: usize,
+    max_column: usize,
+    line_started: bool,
+}
+
+/// Render the events of a highlighting iterator. The style of each highlight
+/// is found by its index in `styles`.
+pub fn render<R: Renderer + ?Sized>(
+    renderer: &mut R,
+    highlighter: impl Iterator<Item = Result<HighlightEvent, Error>>,
+    source: &[u8],
+    styles: &[Style],
+) -> Result<(), Error> {
+    let default_style = Style::default();
+    let mut highlights = Vec::new();
+    for event in highlighter {
+        match event? {
+            HighlightEvent::HighlightStart(highlight) => highlights.push(highlight),
+            HighlightEvent::HighlightEnd => {
+                highlights.pop();
+            }
+            HighlightEvent::Source { start, end } => {
+                let style = highlights
+                    .last()
+                    .and_then(|highlight| styles.get(highlight.0))
+                    .unwrap_or(&default_style);
+                let text = LossyUtf8::new(&source[start..end]).collect::<String>();
+                for (i, line) in text.split('\n').enumerate() {
+                    if i > 0 {
+                        renderer.add_line_break();
+                    }
+                    let line = line.strip_suffix('\r').unwrap_or(line);
+                    if !line.is_empty() {
+                        renderer.add_text(line, style);
+                    }
+                }
+            }
+        }
+    }
+    renderer.finish();
+    Ok(())
+}
+
+impl Color {
+    /// Get the red, green, and blue components of the color.
+    #[must_use]
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Rgb(red, green, blue) => (red, green, blue),
+            Self::Ansi256(n @ 0..=15) => ANSI_STANDARD_COLORS[n as usize],
+            Self::Ansi256(n @ 16..=231) => {
+                let n = (n - 16) as usize;
+                (
+                    ANSI_CUBE_LEVELS[n / 36],
+                    ANSI_CUBE_LEVELS[n / 6 % 6],
+                    ANSI_CUBE_LEVELS[n % 6],
+                )
+            }
+            Self::Ansi256(n) => {
+                let level = 8 + (n - 232) * 10;
+                (level, level, level)
+            }
+        }
+    }
+
+    /// Get the index of the closest color in the xterm 256-color palette.
+    #[must_use]
+    pub fn to_ansi256(self) -> u8 {
+        match self {
+            Self::Ansi256(n) => n,
+            // Find the color with the minimum Euclidean distance to this one.
+            Self::Rgb(red, green, blue) => (0..=255)
+                .min_by_key(|n| {
+                    let (r, g, b) = Self::Ansi256(*n).to_rgb();
+                    u32::from(r.abs_diff(red)).pow(2)
+                        + u32::from(g.abs_diff(green)).pow(2)
+                        + u32::from(b.abs_diff(blue)).pow(2)
+                })
+                .unwrap(),
+        }
+    }
+
+    /// Get the color as a hexadecimal string, such as `#5f87af`.
+    #[must_use]
+    pub fn to_hex(self) -> String {
+        let (red, green, blue) = self.to_rgb();
+        format!("#{red:02x}{green:02x}{blue:02x}")
+    }
+}
+
+impl Style {
+    /// Get the style as the value of a CSS `style` attribute.
+    #[must_use]
+    pub fn to_css(&self) -> String {
+        let mut result = String::new();
+        if self.underline {
+            result += "text-decoration: underline;";
+        }
+        if self.bold {
+            result += "font-weight: bold;";
+        }
+        if self.italic {
+            result += "font-style: italic;";
+        }
+        if let Some(color) = self.color {
+            write!(&mut result, "color: {}", color.to_hex()).unwrap();
+        }
+        result
+    }
+}
+
+impl Default for AnsiRenderer {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl AnsiRenderer {
+    /// Create a renderer. Unless `truecolor` is set, RGB colors are
+    /// approximated with the closest colors in the 256-color palette.
+    #[must_use]
+    pub const fn new(truecolor: bool) -> Self {
+        Self {
+            output: Vec::new(),
+            truecolor,
+        }
+    }
+}
+
+impl Renderer for AnsiRenderer {
+    fn add_text(&mut self, text: &str, style: &Style) {
+        let mut codes = Vec::new();
+        if style.bold {
+            codes.push("1".to_string());
+        }
+        if style.italic {
+            codes.push("3".to_string());
+        }
+        if style.underline {
+            codes.push("4".to_string());
+        }
+        match style.color {
+            Some(Color::Rgb(red, green, blue)) if self.truecolor => {
+                codes.push(format!("38;2;{red};{green};{blue}"));
+            }
+            Some(color) => codes.push(format!("38;5;{}", color.to_ansi256())),
+            None => {}
+        }
+
+        if codes.is_empty() {
+            self.output.extend(text.as_bytes());
+        } else {
+            self.output.extend(b"\x1b[");
+            self.output.extend(codes.join(";").as_bytes());
+            self.output.push(b'm');
+            self.output.extend(text.as_bytes());
+            self.output.extend(b"\x1b[0m");
+        }
+    }
+
+    fn add_line_break(&mut self) {
+        self.output.push(b'\n');
+    }
+
+    fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    fn reset(&mut self) {
+        self.output.clear();
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn add_text(&mut self, text: &str, style: &Style) {
+        let css = style.to_css();
+        if css.is_empty() {
+            self.html.extend(escape_xml(text).as_bytes());
+        } else {
+            self.html
+                .extend(format!("<span style='{css}'>{}</span>", escape_xml(text)).as_bytes());
+        }
+    }
+
+    fn add_line_break(&mut self) {
+        self.html.push(b'\n');
+        self.line_offsets.push(self.html.len() as u32);
+    }
+
+    fn finish(&mut self) {
+        if self.html.last() != Some(&b'\n') {
+            self.html.push(b'\n');
+        }
+        if self.line_offsets.last() == Some(&(self.html.len() as u32)) {
+            self.line_offsets.pop();
+        }
+    }
+
+    fn output(&self) -> &[u8] {
+        &self.html
+    }
+
+    fn reset(&mut self) {
+        Self::reset(self);
+    }
+}
+
+impl Default for LatexRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatexRenderer {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            body: String::new(),
+        }
+    }
+}
+
+impl Renderer for LatexRenderer {
+    fn add_text(&mut self, text: &str, style: &Style) {
+        let mut suffix = String::new();
+        if let Some(color) = style.color {
+            let (red, green, blue) = color.to_rgb();
+            write!(
+                &mut self.body,
+                "\\textcolor[HTML]{{{red:02X}{green:02X}{blue:02X}}}{{"
+            )
+            .unwrap();
+            suffix.push('}');
+        }
+        for (enabled, command) in [
+            (style.bold, "\\textbf{"),
+            (style.italic, "\\textit{"),
+            (style.underline, "\\underline{"),
+        ] {
+            if enabled {
+                self.body += command;
+                suffix.push('}');
+            }
+        }
+        for c in text.chars() {
+            match c {
+                '\\' => self.body += "\\textbackslash{}",
+                '{' => self.body += "\\{",
+                '}' => self.body += "\\}",
+                _ => self.body.push(c),
+            }
+        }
+        self.body += &suffix;
+    }
+
+    fn add_line_break(&mut self) {
+        self.body.push('\n');
+    }
+
+    fn finish(&mut self) {
+        if !self.body.is_empty() && !self.body.ends_with('\n') {
+            self.body.push('\n');
+        }
+        self.output.clear();
+        self.output
+            .extend(b"\\begin{Verbatim}[commandchars=\\\\\\{\\}]\n");
+        self.output.extend(self.body.as_bytes());
+        self.output.extend(b"\\end{Verbatim}\n");
+    }
+
+    fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    fn reset(&mut self) {
+        self.output.clear();
+        self.body.clear();
+    }
+}
+
+impl Default for RtfRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RtfRenderer {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            body: String::new(),
+            colors: Vec::new(),
+        }
+    }
+}
+
+impl Renderer for RtfRenderer {
+    fn add_text(&mut self, text: &str, style: &Style) {
+        let mut control_words = String::new();
+        if let Some(color) = style.color {
+            let color = color.to_rgb();
+            let index = self
+                .colors
+                .iter()
+                .position(|c| *c == color)
+                .unwrap_or_else(|| {
+                    self.colors.push(color);
+                    self.colors.len() - 1
+                });
+            // The first entry of the color table is the default color.
+            write!(&mut control_words, "\\cf{}", index + 1).unwrap();
+        }
+        if style.bold {
+            control_words += "\\b";
+        }
+        if style.italic {
+            control_words += "\\i";
+        }
+        if style.underline {
+            control_words += "\\ul";
+        }
+
+        if !control_words.is_empty() {
+            self.body.push('{');
+            self.body += &control_words;
+            self.body.push(' ');
+        }
+        for c in text.chars() {
+            match c {
+                '\\' | '{' | '}' => {
+                    self.body.push('\\');
+                    self.body.push(c);
+                }
+                '\t' => self.body += "\\tab ",
+                ' '..='~' => self.body.push(c),
+                _ => {
+                    let mut units = [0; 2];
+                    for unit in c.encode_utf16(&mut units) {
+                        write!(&mut self.body, "\\u{}?", *unit as i16).unwrap();
+                    }
+                }
+            }
+        }
+        if !control_words.is_empty() {
+            self.body.push('}');
+        }
+    }
+
+    fn add_line_break(&mut self) {
+        self.body += "\\line\n";
+    }
+
+    fn finish(&mut self) {
+        self.output.clear();
+        self.output
+            .extend(b"{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0\\fmodern Courier New;}}\n");
+        self.output.extend(b"{\\colortbl ;");
+        for (red, green, blue) in &self.colors {
+            self.output
+                .extend(format!("\\red{red}\\green{green}\\blue{blue};").as_bytes());
+        }
+        self.output.extend(b"}\n\\f0\\fs20\n");
+        self.output.extend(self.body.as_bytes());
+        self.output.extend(b"}\n");
+    }
+
+    fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    fn reset(&mut self) {
+        self.output.clear();
+        self.body.clear();
+        self.colors.clear();
+    }
+}
+
+impl Default for SvgRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SvgRenderer {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            body: String::new(),
+            line: 0,
+            column: 0,
+            max_column: 0,
+            line_started: false,
+        }
+    }
+}
+
+impl Renderer for SvgRenderer {
+    fn add_text(&mut self, text: &str, style: &Style) {
+        if !self.line_started {
+            write!(
+                &mut self.body,
+                "<text x=\"{SVG_PADDING}\" y=\"{}\">",
+                SVG_PADDING + self.line * SVG_LINE_HEIGHT + SVG_FONT_SIZE
+            )
+            .unwrap();
+            self.line_started = true;
+        }
+
+        let mut attributes = String::new();
+        if let Some(color) = style.color {
+            write!(&mut attributes, " fill=\"{}\"", color.to_hex()).unwrap();
+        }
+        if style.bold {
+            attributes += " font-weight=\"bold\"";
+        }
+        if style.italic {
+            attributes += " font-style=\"italic\"";
+        }
+        if style.underline {
+            attributes += " text-decoration=\"underline\"";
+        }
+        if attributes.is_empty() {
+            self.body += &escape_xml(text);
+        } else {
+            write!(
+                &mut self.body,
+                "<tspan{attributes}>{}</tspan>",
+                escape_xml(text)
+            )
+            .unwrap();
+        }
+
+        self.column += text.chars().count();
+        self.max_column = self.max_column.max(self.column);
+    }
+
+    fn add_line_break(&mut self) {
+        if self.line_started {
+            self.body += "</text>\n";
+            self.line_started = false;
+        }
+        self.line += 1;
+        self.column = 0;
+    }
+
+    fn finish(&mut self) {
+        if self.line_started {
+            self.body += "</text>\n";
+            self.line_started = false;
+        }
+        let line_count = if self.column > 0 {
+            self.line + 1
+        } else {
+            self.line
+        };
+        let width = (self.max_column as f64 * SVG_CHAR_WIDTH).ceil() as usize + 2 * SVG_PADDING;
+        let height = line_count * SVG_LINE_HEIGHT + 2 * SVG_PADDING;
+        self.output.clear();
+        self.output.extend(
+            format!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+                 font-family=\"monospace\" font-size=\"{SVG_FONT_SIZE}\" xml:space=\"preserve\">\n"
+            )
+            .as_bytes(),
+        );
+        self.output.extend(self.body.as_bytes());
+        self.output.extend(b"</svg>\n");
+    }
+
+    fn output(&self) -> &[u8] {
+        &self.output
+    }
+
+    fn reset(&mut self) {
+        self.output.clear();
+        self.body.clear();
+        self.line = 0;
+        self.column = 0;
+        self.max_column = 0;
+        self.line_started = false;
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '>' => result += "&gt;",
+            '<' => result += "&lt;",
+            '&' => result += "&amp;",
+            '\'' => result += "&#39;",
+            '"' => result += "&quot;",
+            _ => result.push(c),
+        }
+    }
+    result
+}
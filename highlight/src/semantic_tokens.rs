@@ -0,0 +1,344 @@
+use std::{collections::HashMap, ops};
+
+use crate::{Error, Highlight, HighlightEvent};
+
+/// The token types and modifiers that are defined by the Language Server
+/// Protocol.
+pub const STANDARD_TOKEN_TYPES: &[&str] = &[
+    "namespace",
+    "type",
+    "class",
+    "enum",
+    "interface",
+    "struct",
+    "typeParameter",
+    "parameter",
+    "variable",
+    "property",
+    "enumMember",
+    "event",
+    "function",
+    "method",
+    "macro",
+    "keyword",
+    "modifier",
+    "comment",
+    "string",
+    "number",
+    "regexp",
+    "operator",
+    "decorator",
+];
+
+pub const STANDARD_TOKEN_MODIFIERS: &[&str] = &[
+    "declaration",
+    "definition",
+    "readonly",
+    "static",
+    "deprecated",
+    "abstract",
+    "async",
+    "modification",
+    "documentation",
+    "defaultLibrary",
+];
+
+// The parts of the standard highlight names that have different names in the
+// Language Server Protocol.
+const STANDARD_ALIASES: &[(&str, &str)] = &[
+    ("attribute", "decorator"),
+    ("builtin", "defaultLibrary"),
+    ("constant", "variable.readonly"),
+    ("constructor", "class"),
+    ("member", "property"),
+    ("module", "namespace"),
+];
+
+/// The semantic token type and modifiers of a highlight.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SemanticToken {
+    /// The index of the token type in the legend.
+    pub token_type: u32,
+    /// A bit set of the indices of the token modifiers in the legend.
+    pub token_modifiers: u32,
+}
+
+/// The token types and modifiers that a language server reports to its
+/// clients, and the mapping from highlight names to them.
+///
+/// Each part of a dot-separated highlight name is either a token type or a
+/// token modifier. The last part that is a token type is used, along with all
+/// of the parts that are modifiers, so `function.method.defaultLibrary` is
+/// mapped to the `method` type with the `defaultLibrary` modifier. Highlight
+/// names without a token type are not reported.
+#[derive(Clone, Debug, Default)]
+pub struct SemanticTokensLegend {
+    pub token_types: Vec<String>,
+    pub token_modifiers: Vec<String>,
+    aliases: HashMap<String, String>,
+}
+
+/// An edit to the data of a previous set of semantic tokens, used to update a
+/// client's tokens incrementally.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SemanticTokensEdit {
+    pub start: u32,
+    pub delete_count: u32,
+    pub data: Vec<u32>,
+}
+
+/// Converts a general-purpose syntax highlighting iterator into the semantic
+/// tokens of the Language Server Protocol.
+///
+/// The tokens are encoded as five integers each: the line, relative to the
+/// previous token, the start column, in UTF-16 code units and relative to the
+/// previous token if it is on the same line, the length, the token type, and
+/// the token modifiers. When highlights are nested, the innermost one that has
+/// a token type is used, and tokens that span several lines are split.
+pub struct SemanticTokensRenderer {
+    pub data: Vec<u32>,
+    highlight_tokens: Vec<Option<SemanticToken>>,
+}
+
+// A token whose position is absolute.
+#[derive(Debug)]
+struct PositionedToken {
+    line: u32,
+    start: u32,
+    length: u32,
+    token: SemanticToken,
+}
+
+impl SemanticTokensLegend {
+    /// Create a legend from the given token types and modifiers.
+    #[must_use]
+    pub fn new(token_types: &[impl AsRef<str>], token_modifiers: &[impl AsRef<str>]) -> Self {
+        Self {
+            token_types: token_types.iter().map(|t| t.as_ref().to_string()).collect(),
+            token_modifiers: token_modifiers
+                .iter()
+                .map(|m| m.as_ref().to_string())
+                .collect(),
+            aliases: HashMap::new(),
+        }
+    }
+
+    /// Create a legend with the standard token types and modifiers, which maps
+    /// the standard highlight names to them.
+    #[must_use]
+    pub fn standard() -> Self {
+        let mut result = Self::new(STANDARD_TOKEN_TYPES, STANDARD_TOKEN_MODIFIERS);
+        for (part, replacement) in STANDARD_ALIASES {
+            result.add_alias(part, replacement);
+        }
+        result
+    }
+
+    /// Replace a part of highlight names with one or more dot-separated token
+    /// types or modifiers before mapping them. For example, an alias from
+    /// `builtin` to `defaultLibrary` maps `function.builtin` to the `function`
+    /// type with the `defaultLibrary` modifier.
+    pub fn add_alias(&mut self, part: &str, replacement: &str) {
+        self.aliases
+            .insert(part.to_string(), replacement.to_string());
+    }
+
+    /// Get the semantic token type and modifiers of a highlight name.
+    #[must_use]
+    pub fn token_for_highlight_name(&self, name: &str) -> Option<SemanticToken> {
+        let mut token_type = None;
+        let mut token_modifiers = 0;
+        let parts = name.split('.').flat_map(|part| {
+            self.aliases
+                .get(part)
+                .map_or(part, String::as_str)
+                .split('.')
+        });
+        for part in parts {
+            if let Some(index) = self.token_types.iter().position(|t| t == part) {
+                token_type = Some(index as u32);
+            } else if let Some(index) = self.token_modifiers.iter().position(|m| m == part) {
+                token_modifiers |= 1 << index;
+            }
+        }
+        Some(SemanticToken {
+            token_type: token_type?,
+            token_modifiers,
+        })
+    }
+}
+
+impl SemanticTokensRenderer {
+    /// Create a renderer for highlights whose indices refer to the given list
+    /// of highlight names, which is the list that the
+    /// [`HighlightConfiguration`](crate::HighlightConfiguration) was configured
+    /// with.
+    #[must_use]
+    pub fn new(legend: &SemanticTokensLegend, highlight_names: &[impl AsRef<str>]) -> Self {
+        Self {
+            data: Vec::new(),
+            highlight_tokens: highlight_names
+                .iter()
+                .map(|name| legend.token_for_highlight_name(name.as_ref()))
+                .collect(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.data.clear();
+    }
+
+    /// Render the tokens of an entire document.
+    pub fn render(
+        &mut self,
+        highlighter: impl Iterator<Item = Result<HighlightEvent, Error>>,
+        source: &[u8],
+    ) -> Result<(), Error> {
+        self.render_range(highlighter, source, 0..source.len())
+    }
+
+    /// Render the tokens that intersect the given byte range of a document.
+    pub fn render_range(
+        &mut self,
+        highlighter: impl Iterator<Item = Result<HighlightEvent, Error>>,
+        source: &[u8],
+        range: ops::Range<usize>,
+    ) -> Result<(), Error> {
+        let mut tokens = Vec::<PositionedToken>::new();
+        let mut highlights = Vec::<Highlight>::new();
+        let mut offset = 0;
+        let mut line = 0;
+        let mut column = 0;
+        for event in highlighter {
+            let (start, end) = match event? {
+                HighlightEvent::HighlightStart(highlight) => {
+                    highlights.push(highlight);
+                    continue;
+                }
+                HighlightEvent::HighlightEnd => {
+                    highlights.pop();
+                    continue;
+                }
+                HighlightEvent::Source { start, end } => (start, end),
+            };
+            if start < offset {
+                (offset, line, column) = (0, 0, 0);
+            }
+            advance(&source[offset..start], &mut line, &mut column);
+            offset = end;
+            if start >= range.end {
+                break;
+            }
+
+            let token = highlights
+                .iter()
+                .rev()
+                .find_map(|highlight| self.highlight_tokens.get(highlight.0).copied().flatten());
+            let mut line_start = start;
+            for text in source[start..end].split_inclusive(|c| *c == b'\n') {
+                let line_end = line_start + text.len();
+                let content = text.strip_suffix(b"\n").unwrap_or(text);
+                let content = content.strip_suffix(b"\r").unwrap_or(content);
+                let length = utf16_len(content);
+                if let Some(token) = token {
+                    if length > 0
+                        && line_start < range.end
+                        && line_start + content.len() > range.start
+                    {
+                        match tokens.last_mut() {
+                            // Join the pieces of a token that were split by nested highlights.
+                            Some(last)
+                                if last.line == line
+                                    && last.start + last.length == column
+                                    && last.token == token =>
+                            {
+                                last.length += length;
+                            }
+                            _ => tokens.push(PositionedToken {
+                                line,
+                                start: column,
+                                length,
+                                token,
+                            }),
+                        }
+                    }
+                }
+                advance(text, &mut line, &mut column);
+                line_start = line_end;
+            }
+        }
+
+        self.data.clear();
+        let mut previous_line = 0;
+        let mut previous_start = 0;
+        for token in tokens {
+            let delta_line = token.line - previous_line;
+            let delta_start = if delta_line == 0 {
+                token.start - previous_start
+            } else {
+                token.start
+            };
+            self.data.extend([
+                delta_line,
+                delta_start,
+                token.length,
+                token.token.token_type,
+                token.token.token_modifiers,
+            ]);
+            previous_line = token.line;
+            previous_start = token.start;
+        }
+        Ok(())
+    }
+
+    /// Compute the edits that turn the data of a previous set of tokens into
+    /// the current data. The edits do not split any token.
+    #[must_use]
+    pub fn edits(&self, previous_data: &[u32]) -> Vec<SemanticTokensEdit> {
+        let data = &self.data;
+        let prefix_len = data
+            .chunks(5)
+            .zip(previous_data.chunks(5))
+            .take_while(|(a, b)| a == b)
+            .count()
+            * 5;
+        if prefix_len == data.len() && prefix_len == previous_data.len() {
+            return Vec::new();
+        }
+        let suffix_len = data[prefix_len..]
+            .rchunks(5)
+            .zip(previous_data[prefix_len..].rchunks(5))
+            .take_while(|(a, b)| a == b)
+            .count()
+            * 5;
+        vec![SemanticTokensEdit {
+            start: prefix_len as u32,
+            delete_count: (previous_data.len() - prefix_len - suffix_len) as u32,
+            data: data[prefix_len..data.len() - suffix_len].to_vec(),
+        }]
+    }
+}
+
+// Advance a position past the given text, counting columns in UTF-16 code
+// units.
+fn advance(text: &[u8], line: &mut u32, column: &mut u32) {
+    for (i, text) in text.split(|c| *c == b'\n').enumerate() {
+        if i > 0 {
+            *line += 1;
+            *column = 0;
+        }
+        *column += utf16_len(text);
+    }
+}
+
+// Count the UTF-16 code units of some UTF-8 text. Characters that take four
+// bytes in UTF-8 take two code units in UTF-16.
+fn utf16_len(text: &[u8]) -> u32 {
+    text.iter()
+        .map(|c| match c {
+            0x80..=0xBF => 0,
+            0xF0..=0xFF => 2,
+            _ => 1,
+        })
+        .sum()
+}
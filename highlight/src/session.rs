@@ -0,0 +1,218 @@
+use std::{mem, ops};
+
+use tree_sitter::{InputEdit, Point, Range, Tree};
+
+use crate::HighlightConfiguration;
+
+/// Stores the syntax trees of a document between highlighting calls, so that
+/// the document and its injected languages can be reparsed incrementally after
+/// it is edited.
+///
+/// A session is used with
+/// [`Highlighter::highlight_incremental`](crate::Highlighter::highlight_incremental),
+/// and is informed of every change to the document with [`edit`](Self::edit).
+/// A separate session is needed for each document.
+#[derive(Default)]
+pub struct HighlightSession {
+    layers: Vec<SessionLayer>,
+    previous_layers: Vec<SessionLayer>,
+    edited_ranges: Vec<ops::Range<usize>>,
+    changed_ranges: Vec<ops::Range<usize>>,
+}
+
+// The syntax tree of one language layer of a document, along with the ranges
+// of the document that it was parsed from.
+pub(crate) struct SessionLayer {
+    language_name: String,
+    depth: usize,
+    ranges: Vec<Range>,
+    pub(crate) tree: Tree,
+}
+
+impl HighlightSession {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inform the session of an edit to the document, so that its syntax
+    /// trees can be reused when the document is highlighted again.
+    pub fn edit(&mut self, edit: &InputEdit) {
+        for layer in self.layers.iter_mut().chain(&mut self.previous_layers) {
+            layer.tree.edit(edit);
+            for range in &mut layer.ranges {
+                edit_range(range, edit);
+            }
+        }
+        for range in mem::take(&mut self.edited_ranges) {
+            let start = edit_byte(range.start, edit, false);
+            let end = edit_byte(range.end, edit, true);
+            add_range(&mut self.edited_ranges, start..end);
+        }
+        add_range(&mut self.edited_ranges, edit.start_byte..edit.new_end_byte);
+    }
+
+    /// Discard the syntax trees of the document, so that it is parsed and
+    /// highlighted entirely the next time.
+    pub fn reset(&mut self) {
+        self.layers.clear();
+        self.previous_layers.clear();
+        self.edited_ranges.clear();
+        self.changed_ranges.clear();
+    }
+
+    /// Get the sorted, disjoint byte ranges of the document that were
+    /// highlighted by the last call to `highlight_incremental`. These are the
+    /// ranges whose text or syntax changed, along with the ranges of any
+    /// injected languages that were parsed for the first time. They are
+    /// complete once all of the highlighting events have been consumed.
+    #[must_use]
+    pub fn changed_ranges(&self) -> &[ops::Range<usize>] {
+        &self.changed_ranges
+    }
+
+    /// Get the syntax tree of the document, from the last time that it was
+    /// highlighted.
+    #[must_use]
+    pub fn tree(&self) -> Option<&Tree> {
+        self.layers
+            .iter()
+            .find(|layer| layer.depth == 0)
+            .map(|layer| &layer.tree)
+    }
+
+    pub(crate) fn start(&mut self) {
+        let layers = mem::take(&mut self.layers);
+        self.previous_layers.extend(layers);
+        self.changed_ranges.clone_from(&self.edited_ranges);
+    }
+
+    pub(crate) fn finish(&mut self) {
+        self.previous_layers.clear();
+        self.edited_ranges.clear();
+    }
+
+    // Find the previous layer that corresponds to a new layer. Layers are
+    // identified by their language, their depth, and the position where
+    // they start.
+    pub(crate) fn take_layer(
+        &mut self,
+        config: &HighlightConfiguration,
+        depth: usize,
+        ranges: &[Range],
+    ) -> Option<SessionLayer> {
+        let index = self.previous_layers.iter().position(|layer| {
+            layer.depth == depth
+                && layer.language_name == config.language_name
+                && layer.ranges.first().map(|r| r.start_byte)
+                    == ranges.first().map(|r| r.start_byte)
+        })?;
+        Some(self.previous_layers.swap_remove(index))
+    }
+
+    pub(crate) fn restore_layer(&mut self, layer: SessionLayer) {
+        self.previous_layers.push(layer);
+    }
+
+    pub(crate) fn add_layer(
+        &mut self,
+        config: &HighlightConfiguration,
+        depth: usize,
+        ranges: &[Range],
+        tree: &Tree,
+        previous_layer: Option<SessionLayer>,
+        source_len: usize,
+    ) {
+        match previous_layer {
+            Some(previous_layer) if previous_layer.ranges == ranges => {
+                for range in previous_layer.tree.changed_ranges(tree) {
+                    add_range(
+                        &mut self.changed_ranges,
+                        range.start_byte..range.end_byte.min(source_len),
+                    );
+                }
+            }
+            _ => {
+                if let (Some(first), Some(last)) = (ranges.first(), ranges.last()) {
+                    add_range(
+                        &mut self.changed_ranges,
+                        first.start_byte..last.end_byte.min(source_len),
+                    );
+                }
+            }
+        }
+        self.layers.push(SessionLayer {
+            language_name: config.language_name.clone(),
+            depth,
+            ranges: ranges.to_vec(),
+            tree: tree.clone(),
+        });
+    }
+}
+
+// Insert a range into a sorted list of disjoint ranges, merging it with any
+// ranges that it overlaps or touches.
+fn add_range(ranges: &mut Vec<ops::Range<usize>>, mut range: ops::Range<usize>) {
+    if range.start >= range.end {
+        return;
+    }
+    let start_index = ranges.partition_point(|r| r.end < range.start);
+    let end_index = ranges.partition_point(|r| r.start <= range.end);
+    if start_index < end_index {
+        range.start = range.start.min(ranges[start_index].start);
+        range.end = range.end.max(ranges[end_index - 1].end);
+    }
+    ranges.splice(start_index..end_index, [range]);
+}
+
+// Adjust a range of the document for an edit, in the same way as the included
+// ranges of a syntax tree.
+fn edit_range(range: &mut Range, edit: &InputEdit) {
+    if range.end_byte != usize::MAX {
+        range.end_byte = edit_byte(range.end_byte, edit, true);
+        range.end_point = edit_point(range.end_point, edit, true);
+    }
+    range.start_byte = edit_byte(range.start_byte, edit, false);
+    range.start_point = edit_point(range.start_point, edit, false);
+}
+
+// Positions after the edit are moved with the end of the edit. Positions within
+// the replaced text are moved to the start of the edit, or to the end of the new
+// text if they are the end of a range.
+fn edit_byte(byte: usize, edit: &InputEdit, is_end: bool) -> usize {
+    if byte >= edit.old_end_byte {
+        byte - edit.old_end_byte + edit.new_end_byte
+    } else if byte > edit.start_byte {
+        if is_end {
+            edit.new_end_byte
+        } else {
+            edit.start_byte
+        }
+    } else {
+        byte
+    }
+}
+
+fn edit_point(point: Point, edit: &InputEdit, is_end: bool) -> Point {
+    if point >= edit.old_end_position {
+        if point.row > edit.old_end_position.row {
+            Point::new(
+                point.row - edit.old_end_position.row + edit.new_end_position.row,
+                point.column,
+            )
+        } else {
+            Point::new(
+                edit.new_end_position.row,
+                point.column - edit.old_end_position.column + edit.new_end_position.column,
+            )
+        }
+    } else if point > edit.start_position {
+        if is_end {
+            edit.new_end_position
+        } else {
+            edit.start_position
+        }
+    } else {
+        point
+    }
+}
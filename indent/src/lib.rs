@@ -0,0 +1,401 @@
+#![doc = include_str!("../README.md")]
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use thiserror::Error;
+use tree_sitter::{Language, Node, Parser, Query, QueryCursor, QueryError, Range, Tree};
+use tree_sitter_highlight::{InjectionPatterns, Injections};
+
+/// Contains the data needed to compute the indentation of code written in a
+/// particular language.
+#[derive(Debug)]
+pub struct IndentConfiguration {
+    pub language: Language,
+    pub language_name: String,
+    pub query: Query,
+    injection_patterns: InjectionPatterns,
+    capture_kinds: Vec<Option<CaptureKind>>,
+}
+
+/// Computes the indentation of lines of source code, according to an
+/// [`IndentConfiguration`].
+///
+/// You need one of these for each thread that you're using for indentation.
+pub struct Indenter {
+    pub parser: Parser,
+    cursor: QueryCursor,
+}
+
+/// The desired indentation of a line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Indentation {
+    /// The column, in bytes, of the node that the line is aligned with, if
+    /// any.
+    pub align_column: Option<usize>,
+    /// The number of levels by which the line is indented, after the alignment
+    /// column.
+    pub level: usize,
+}
+
+#[derive(Debug, Error, PartialEq)]
+pub enum Error {
+    #[error(transparent)]
+    Query(#[from] QueryError),
+    #[error("Cancelled")]
+    Cancelled,
+    #[error("Invalid language")]
+    InvalidLanguage,
+    #[error("Invalid capture @{0}. Expected one of: @indent, @indent.always, @outdent, @align, @injection.content, or @injection.language.")]
+    InvalidCapture(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaptureKind {
+    Indent,
+    IndentAlways,
+    Outdent,
+    Align,
+}
+
+// The indentation rules that the query assigns to a particular node.
+#[derive(Debug, Default)]
+struct NodeIndents {
+    indent: bool,
+    indent_always: bool,
+    outdent: bool,
+    align: bool,
+}
+
+impl IndentConfiguration {
+    /// Create an indentation configuration from a query whose captures mark the
+    /// nodes that affect indentation, and a query that describes the injected
+    /// languages, which has the same format as the one used for highlighting.
+    ///
+    /// Nodes are marked with `@indent` to indent the lines within them, with
+    /// `@indent.always` to indent them even if another node on the same line
+    /// does, with `@outdent` to outdent the line on which they start, or with
+    /// `@align` to align the lines within them with their opening delimiter.
+    /// Other captures are an error, unless they are reserved by
+    /// [`InjectionPatterns`].
+    ///
+    /// The language's name is used by the injections with `injection.self` and
+    /// `injection.parent`.
+    pub fn new(
+        language: Language,
+        name: impl Into<String>,
+        indents_query: &str,
+        injection_query: &str,
+    ) -> Result<Self, Error> {
+        let (query, injection_patterns) =
+            InjectionPatterns::new_query(&language, indents_query, injection_query)?;
+        let capture_kinds = query
+            .capture_names()
+            .iter()
+            .map(|name| match *name {
+                "indent" => Ok(Some(CaptureKind::Indent)),
+                "indent.always" => Ok(Some(CaptureKind::IndentAlways)),
+                "outdent" => Ok(Some(CaptureKind::Outdent)),
+                "align" => Ok(Some(CaptureKind::Align)),
+                _ if InjectionPatterns::is_reserved_capture_name(name) => Ok(None),
+                _ => Err(Error::InvalidCapture((*name).to_string())),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            language,
+            language_name: name.into(),
+            query,
+            injection_patterns,
+            capture_kinds,
+        })
+    }
+}
+
+impl Indentation {
+    /// Get the whitespace that produces this indentation, using the given
+    /// string for each level of indentation.
+    #[must_use]
+    pub fn whitespace(&self, indent: &str) -> String {
+        let mut result = " ".repeat(self.align_column.unwrap_or(0));
+        result += &indent.repeat(self.level);
+        result
+    }
+}
+
+impl fmt::Display for Indentation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.align_column, self.level) {
+            (None, level) => write!(f, "{level}"),
+            (Some(column), 0) => write!(f, "column.{column}"),
+            (Some(column), level) => write!(f, "column.{column}.{level}"),
+        }
+    }
+}
+
+impl Default for Indenter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Indenter {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::new(),
+            cursor: QueryCursor::new(),
+        }
+    }
+
+    pub fn parser(&mut self) -> &mut Parser {
+        &mut self.parser
+    }
+
+    /// Compute the desired indentation of the given line of a syntax tree.
+    ///
+    /// The indentation is determined by the nodes that contain the first
+    /// non-whitespace character of the line, or the end of the line if it is
+    /// blank. The `injection_callback` is used to find the configurations of
+    /// languages that are injected into the tree.
+    pub fn indent_for_line<'a>(
+        &mut self,
+        config: &'a IndentConfiguration,
+        tree: &Tree,
+        source: &[u8],
+        row: usize,
+        mut injection_callback: impl FnMut(&str) -> Option<&'a IndentConfiguration>,
+    ) -> Result<Indentation, Error> {
+        let line_start = source
+            .split_inclusive(|c| *c == b'\n')
+            .take(row)
+            .map(<[u8]>::len)
+            .sum::<usize>();
+        let position = source[line_start..]
+            .iter()
+            .position(|c| !matches!(c, b' ' | b'\t'))
+            .map_or(source.len(), |offset| line_start + offset);
+        self.indentation(
+            config,
+            None,
+            tree.root_node(),
+            &tree.included_ranges(),
+            source,
+            row,
+            position,
+            &mut injection_callback,
+        )
+    }
+
+    /// Compute the desired indentation of the line that contains the given
+    /// byte offset.
+    pub fn indent_at_byte<'a>(
+        &mut self,
+        config: &'a IndentConfiguration,
+        tree: &Tree,
+        source: &[u8],
+        byte: usize,
+        injection_callback: impl FnMut(&str) -> Option<&'a IndentConfiguration>,
+    ) -> Result<Indentation, Error> {
+        let row = source[..byte.min(source.len())]
+            .iter()
+            .filter(|c| **c == b'\n')
+            .count();
+        self.indent_for_line(config, tree, source, row, injection_callback)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn indentation<'a, F>(
+        &mut self,
+        config: &'a IndentConfiguration,
+        parent_name: Option<&'a str>,
+        root: Node,
+        ranges: &[Range],
+        source: &[u8],
+        row: usize,
+        position: usize,
+        injection_callback: &mut F,
+    ) -> Result<Indentation, Error>
+    where
+        F: FnMut(&str) -> Option<&'a IndentConfiguration>,
+    {
+        let target = root
+            .descendant_for_byte_range(position, position)
+            .unwrap_or(root);
+
+        // Every node that contains the position intersects this range, even if
+        // it is empty.
+        self.cursor
+            .set_byte_range(position.saturating_sub(1)..position + 1);
+        let mut indents = HashMap::<usize, NodeIndents>::new();
+        let mut injections = Injections::default();
+        for m in self.cursor.matches(&config.query, root, source) {
+            if config.injection_patterns.contains(m.pattern_index) {
+                injections.add_match(
+                    &config.injection_patterns,
+                    &config.language_name,
+                    parent_name,
+                    &config.query,
+                    &m,
+                    source,
+                );
+                continue;
+            }
+
+            for capture in m.captures {
+                let Some(kind) = config.capture_kinds[capture.index as usize] else {
+                    continue;
+                };
+                let node_indents = indents.entry(capture.node.id()).or_default();
+                match kind {
+                    CaptureKind::Indent => node_indents.indent = true,
+                    CaptureKind::IndentAlways => node_indents.indent_always = true,
+                    CaptureKind::Outdent => node_indents.outdent = true,
+                    CaptureKind::Align => node_indents.align = true,
+                }
+            }
+        }
+
+        // Use the innermost injection whose content contains the position.
+        let injection = injections
+            .into_injections()
+            .filter_map(|injection| {
+                let content_node = injection
+                    .content_nodes
+                    .iter()
+                    .find(|node| node.start_byte() <= position && position < node.end_byte())?;
+                Some((content_node.byte_range().len(), injection))
+            })
+            .min_by_key(|(len, _)| *len)
+            .map(|(_, injection)| injection);
+
+        // Walk outward from the node at the position. Nodes that start on an
+        // earlier line indent it, but several nodes that start on the same line
+        // only indent it once, unless they are marked with `@indent.always`.
+        let mut level = 0_isize;
+        let mut align_column = None;
+        let mut indented_rows = HashSet::new();
+        let mut node = Some(target);
+        while let Some(current) = node {
+            node = current.parent();
+            let Some(node_indents) = indents.get(&current.id()) else {
+                continue;
+            };
+            let start = current.start_position();
+            if node_indents.outdent && current.start_byte() == position {
+                level -= 1;
+            }
+            if start.row >= row {
+                continue;
+            }
+            if node_indents.align {
+                if let Some(column) = alignment_column(current) {
+                    align_column = Some(column);
+                    break;
+                }
+            }
+            if node_indents.indent_always {
+                indented_rows.insert(start.row);
+                level += 1;
+            } else if (node_indents.indent || node_indents.align) && indented_rows.insert(start.row)
+            {
+                level += 1;
+            }
+        }
+        let indentation = Indentation {
+            align_column,
+            level: level.max(0) as usize,
+        };
+
+        // Add the indentation within an injected language to the indentation of
+        // the injection itself.
+        let Some(mut injection) = injection else {
+            return Ok(indentation);
+        };
+        let Some(injected_config) = injection_callback(injection.language_name) else {
+            return Ok(indentation);
+        };
+
+        // A combined injection is parsed from the content of all of its pattern's
+        // matches, not only the ones near the position.
+        if injection.combined {
+            let mut combined_injections = Injections::default();
+            self.cursor.set_byte_range(0..usize::MAX);
+            for m in self.cursor.matches(&config.query, root, source) {
+                if m.pattern_index == injection.pattern_index {
+                    combined_injections.add_match(
+                        &config.injection_patterns,
+                        &config.language_name,
+                        parent_name,
+                        &config.query,
+                        &m,
+                        source,
+                    );
+                }
+            }
+            if let Some(combined_injection) = combined_injections.into_injections().next() {
+                injection = combined_injection;
+            }
+        }
+
+        let injection_ranges = injection.ranges(ranges);
+        if injection_ranges.is_empty() {
+            return Ok(indentation);
+        }
+        let language = self.parser.language();
+        self.parser
+            .set_language(&injected_config.language)
+            .map_err(|_| Error::InvalidLanguage)?;
+        let injected_tree = self
+            .parser
+            .set_included_ranges(&injection_ranges)
+            .is_ok()
+            .then(|| self.parser.parse(source, None));
+
+        // Let the parser parse entire documents in its own language again.
+        self.parser.set_included_ranges(&[]).unwrap();
+        if let Some(language) = language {
+            self.parser
+                .set_language(&language)
+                .map_err(|_| Error::InvalidLanguage)?;
+        }
+        let Some(injected_tree) = injected_tree else {
+            return Ok(indentation);
+        };
+        let injected_tree = injected_tree.ok_or(Error::Cancelled)?;
+        let injected_indentation = self.indentation(
+            injected_config,
+            Some(&config.language_name),
+            injected_tree.root_node(),
+            &injection_ranges,
+            source,
+            row,
+            position,
+            injection_callback,
+        )?;
+        if injected_indentation.align_column.is_some() {
+            Ok(injected_indentation)
+        } else {
+            Ok(Indentation {
+                align_column: indentation.align_column,
+                level: indentation.level + injected_indentation.level,
+            })
+        }
+    }
+}
+
+// A node marked with `@align` aligns the lines within it with the child that
+// follows its first anonymous child, which is typically an opening delimiter,
+// if that child starts on the node's first line.
+fn alignment_column(node: Node) -> Option<usize> {
+    let mut cursor = node.walk();
+    let anchor = node
+        .children(&mut cursor)
+        .filter(|child| !child.is_extra())
+        .skip_while(Node::is_named)
+        .nth(1)?;
+    let start = anchor.start_position();
+    (start.row == node.start_position().row).then_some(start.column)
+}
@@ -147,6 +147,11 @@ pub const TSQueryErrorCapture: TSQueryError = 4;
 pub const TSQueryErrorStructure: TSQueryError = 5;
 pub const TSQueryErrorLanguage: TSQueryError = 6;
 pub type TSQueryError = ::std::os::raw::c_uint;
+pub const TSTreeDeserializeErrorNone: TSTreeDeserializeError = 0;
+pub const TSTreeDeserializeErrorFormat: TSTreeDeserializeError = 1;
+pub const TSTreeDeserializeErrorVersion: TSTreeDeserializeError = 2;
+pub const TSTreeDeserializeErrorLanguage: TSTreeDeserializeError = 3;
+pub type TSTreeDeserializeError = ::std::os::raw::c_uint;
 extern "C" {
     #[doc = " Create a new parser."]
     pub fn ts_parser_new() -> *mut TSParser;
@@ -278,6 +283,20 @@ extern "C" {
     #[doc = " Write a DOT graph describing the syntax tree to the given file."]
     pub fn ts_tree_print_dot_graph(self_: *const TSTree, file_descriptor: ::std::os::raw::c_int);
 }
+extern "C" {
+    #[doc = " Serialize the syntax tree into a binary format that can be loaded again\n using [`ts_tree_deserialize`].\n\n The returned buffer is allocated using `malloc` and the caller is responsible\n for freeing it using `free`. The length of the buffer will be written to the\n given `length` pointer."]
+    pub fn ts_tree_serialize(self_: *const TSTree, length: *mut u32)
+        -> *mut ::std::os::raw::c_char;
+}
+extern "C" {
+    #[doc = " Load a syntax tree that was serialized using [`ts_tree_serialize`].\n\n The tree must have been serialized with the same version of the same\n language. The loaded tree can be edited and passed to the parser as an\n old tree, just like one that was produced by the parser itself.\n\n If the data is invalid, this returns `NULL`, and writes the kind of problem\n to the `error` parameter."]
+    pub fn ts_tree_deserialize(
+        language: *const TSLanguage,
+        data: *const ::std::os::raw::c_char,
+        length: u32,
+        error: *mut TSTreeDeserializeError,
+    ) -> *mut TSTree;
+}
 extern "C" {
     #[doc = " Get the node's type as a null-terminated string."]
     pub fn ts_node_type(self_: TSNode) -> *const ::std::os::raw::c_char;
@@ -1,6 +1,10 @@
 #![doc = include_str!("./README.md")]
 
 pub mod ffi;
+mod node_map;
+mod rewrite;
+mod sync_tree;
+mod tree_diff;
 mod util;
 
 #[cfg(any(unix, target_os = "wasi"))]
@@ -22,8 +26,16 @@ use std::{
     sync::atomic::AtomicUsize,
 };
 
+#[cfg(feature = "serde")]
+mod serialization;
 #[cfg(feature = "wasm")]
 mod wasm_language;
+pub use node_map::{NodeMap, NodeMatchKind};
+pub use rewrite::{Replacement, RewriteError, RewriteOutput, Rewriter};
+#[cfg(feature = "serde")]
+pub use serialization::*;
+pub use sync_tree::{NodeRef, SyncTree};
+pub use tree_diff::{DiffAction, TreeDiff};
 #[cfg(feature = "wasm")]
 pub use wasm_language::*;
 
@@ -46,6 +58,18 @@ pub const MIN_COMPATIBLE_LANGUAGE_VERSION: usize =
 pub const ARRAY_HEADER: &str = include_str!("../src/array.h");
 pub const PARSER_HEADER: &str = include_str!("../src/parser.h");
 
+/// The keywords that can't be used as identifiers in Rust code, unless they are
+/// written as raw identifiers. This is used by the tools that generate Rust code
+/// from a grammar's node types.
+#[doc(hidden)]
+pub const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
 /// An opaque object that defines how to parse a particular language. The code
 /// for each `Language` is generated by the Tree-sitter CLI.
 #[doc(alias = "TSLanguage")]
@@ -63,6 +87,7 @@ pub struct Tree(NonNull<ffi::TSTree>);
 ///
 /// Rows and columns are zero-based.
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub row: usize,
     pub column: usize,
@@ -71,6 +96,7 @@ pub struct Point {
 /// A range of positions in a multi-line text document, both in terms of bytes
 /// and of rows and columns.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Range {
     pub start_byte: usize,
     pub end_byte: usize,
@@ -242,6 +268,17 @@ pub struct LanguageError {
 #[derive(Debug, PartialEq, Eq)]
 pub struct IncludedRangesError(pub usize);
 
+/// An error that occurred in [`Tree::deserialize`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum TreeDeserializeError {
+    /// The data is not a serialized tree, or it is truncated or corrupted.
+    Format,
+    /// The tree was serialized by an incompatible version of the library.
+    Version,
+    /// The tree was serialized with a different version of the language.
+    Language,
+}
+
 /// An error that occurred when trying to create a [`Query`].
 #[derive(Debug, PartialEq, Eq)]
 pub struct QueryError {
@@ -957,6 +994,47 @@ impl Tree {
             unsafe { ffi::ts_tree_print_dot_graph(self.0.as_ptr(), handle as i32) }
         }
     }
+
+    /// Serialize the tree into a binary format that can be loaded again
+    /// with [`Tree::deserialize`].
+    ///
+    /// The serialized tree records the ABI version of its language and a hash
+    /// of the language's parse tables, so that it can only be loaded with the
+    /// same version of the same language.
+    #[doc(alias = "ts_tree_serialize")]
+    #[must_use]
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut length = 0u32;
+        unsafe {
+            let ptr = ffi::ts_tree_serialize(self.0.as_ptr(), std::ptr::addr_of_mut!(length));
+            let result = slice::from_raw_parts(ptr.cast::<u8>(), length as usize).to_vec();
+            (FREE_FN)(ptr.cast::<c_void>());
+            result
+        }
+    }
+
+    /// Load a tree that was serialized with [`Tree::serialize`].
+    ///
+    /// The loaded tree can be edited and passed to [`Parser::parse`] as the
+    /// old tree, just like a tree that was produced by the parser.
+    #[doc(alias = "ts_tree_deserialize")]
+    pub fn deserialize(language: &Language, bytes: &[u8]) -> Result<Self, TreeDeserializeError> {
+        let length = u32::try_from(bytes.len()).map_err(|_| TreeDeserializeError::Format)?;
+        let mut error = ffi::TSTreeDeserializeErrorNone;
+        let ptr = unsafe {
+            ffi::ts_tree_deserialize(
+                language.0,
+                bytes.as_ptr().cast::<c_char>(),
+                length,
+                std::ptr::addr_of_mut!(error),
+            )
+        };
+        NonNull::new(ptr).map(Self).ok_or(match error {
+            ffi::TSTreeDeserializeErrorVersion => TreeDeserializeError::Version,
+            ffi::TSTreeDeserializeErrorLanguage => TreeDeserializeError::Language,
+            _ => TreeDeserializeError::Format,
+        })
+    }
 }
 
 impl fmt::Debug for Tree {
@@ -2873,6 +2951,16 @@ impl fmt::Display for IncludedRangesError {
     }
 }
 
+impl fmt::Display for TreeDeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Format => write!(f, "Invalid serialized tree"),
+            Self::Version => write!(f, "Unsupported serialized tree version"),
+            Self::Language => write!(f, "Serialized tree was produced by a different language"),
+        }
+    }
+}
+
 impl fmt::Display for LanguageError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -3043,6 +3131,7 @@ pub unsafe fn set_allocator(
 
 impl error::Error for IncludedRangesError {}
 impl error::Error for LanguageError {}
+impl error::Error for TreeDeserializeError {}
 impl error::Error for QueryError {}
 
 unsafe impl Send for Language {}
@@ -3051,6 +3140,9 @@ unsafe impl Sync for Language {}
 unsafe impl Send for Node<'_> {}
 unsafe impl Sync for Node<'_> {}
 
+unsafe impl Send for NodeRef {}
+unsafe impl Sync for NodeRef {}
+
 unsafe impl Send for LookaheadIterator {}
 unsafe impl Sync for LookaheadIterator {}
 
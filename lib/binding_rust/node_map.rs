@@ -0,0 +1,170 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{InputEdit, Node, Range, Tree, TreeCursor};
+
+/// How a node of an old tree corresponds to a node of a new tree.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeMatchKind {
+    /// The node was not affected by the edits, and its syntactic structure is
+    /// unchanged in the new tree.
+    Reused,
+    /// The node has the same type and position in the new tree, but it
+    /// contains edited text or its structure may have changed.
+    Equivalent,
+}
+
+/// A mapping from the nodes of an old syntax tree to the corresponding nodes
+/// of a new tree that was parsed from it after some edits.
+///
+/// [`Node::id`] identifies the subtree that a node points to, so a node only
+/// keeps its id when the parser reuses its subtree. The ancestors of an edit
+/// and any nodes that are parsed again get new ids, even if they end up with
+/// the same structure. A `NodeMap` instead matches up nodes that have the same
+/// type and the same position, after accounting for the edits, so that data
+/// attached to the nodes of the old tree can be carried over to the new tree.
+pub struct NodeMap<'old, 'new> {
+    matches: Vec<(Node<'old>, Node<'new>, NodeMatchKind)>,
+    old_indices: HashMap<Node<'old>, usize>,
+    new_indices: HashMap<Node<'new>, usize>,
+}
+
+struct OldNode<'old> {
+    node: Node<'old>,
+    has_changes: bool,
+}
+
+impl<'old, 'new> NodeMap<'old, 'new> {
+    /// Match up the nodes of `old_tree` with those of `new_tree`.
+    ///
+    /// `old_tree` is the tree as it was before the given `edits` were applied.
+    /// If the edits have already been applied to it using [`Tree::edit`], pass
+    /// an empty slice instead.
+    #[must_use]
+    pub fn new(old_tree: &'old Tree, new_tree: &'new Tree, edits: &[InputEdit]) -> Self {
+        let mut edited_tree = old_tree.clone();
+        for edit in edits {
+            edited_tree.edit(edit);
+        }
+        let changed_ranges = edited_tree.changed_ranges(new_tree).collect::<Vec<_>>();
+
+        // Index the old nodes by their type and their position after the edits. The
+        // edited tree has the same structure as the old tree, so the two can be
+        // traversed in lockstep.
+        let mut old_nodes = HashMap::<_, VecDeque<OldNode>>::new();
+        let mut old_cursor = old_tree.walk();
+        let mut edited_cursor = edited_tree.walk();
+        loop {
+            let edited_node = edited_cursor.node();
+            old_nodes
+                .entry((edited_node.kind_id(), edited_node.byte_range()))
+                .or_default()
+                .push_back(OldNode {
+                    node: old_cursor.node(),
+                    has_changes: edited_node.has_changes(),
+                });
+            if !goto_next_node(&mut edited_cursor) {
+                break;
+            }
+            goto_next_node(&mut old_cursor);
+        }
+
+        let mut result = Self {
+            matches: Vec::new(),
+            old_indices: HashMap::new(),
+            new_indices: HashMap::new(),
+        };
+        let mut new_cursor = new_tree.walk();
+        loop {
+            let new_node = new_cursor.node();
+            if let Some(old_node) = old_nodes
+                .get_mut(&(new_node.kind_id(), new_node.byte_range()))
+                .and_then(VecDeque::pop_front)
+            {
+                let kind = if old_node.has_changes || overlaps(&changed_ranges, new_node) {
+                    NodeMatchKind::Equivalent
+                } else {
+                    NodeMatchKind::Reused
+                };
+                let index = result.matches.len();
+                result.matches.push((old_node.node, new_node, kind));
+                result.old_indices.insert(old_node.node, index);
+                result.new_indices.insert(new_node, index);
+            }
+            if !goto_next_node(&mut new_cursor) {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Get the node of the new tree that corresponds to the given node of the
+    /// old tree.
+    #[must_use]
+    pub fn new_node(&self, old_node: Node<'old>) -> Option<Node<'new>> {
+        self.old_indices
+            .get(&old_node)
+            .map(|index| self.matches[*index].1)
+    }
+
+    /// Get the node of the old tree that corresponds to the given node of the
+    /// new tree.
+    #[must_use]
+    pub fn old_node(&self, new_node: Node<'new>) -> Option<Node<'old>> {
+        self.new_indices
+            .get(&new_node)
+            .map(|index| self.matches[*index].0)
+    }
+
+    /// Get how the given node of the old tree corresponds to its node in the
+    /// new tree, if it has one.
+    #[must_use]
+    pub fn match_kind(&self, old_node: Node<'old>) -> Option<NodeMatchKind> {
+        self.old_indices
+            .get(&old_node)
+            .map(|index| self.matches[*index].2)
+    }
+
+    /// Iterate over the matched pairs of old and new nodes, in the order of the
+    /// new tree.
+    pub fn iter(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (Node<'old>, Node<'new>, NodeMatchKind)> + '_ {
+        self.matches.iter().copied()
+    }
+
+    /// Get the number of matched nodes.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Check if no nodes were matched.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+}
+
+// Move the cursor to the next node in a pre-order traversal.
+fn goto_next_node(cursor: &mut TreeCursor) -> bool {
+    if cursor.goto_first_child() {
+        return true;
+    }
+    loop {
+        if cursor.goto_next_sibling() {
+            return true;
+        }
+        if !cursor.goto_parent() {
+            return false;
+        }
+    }
+}
+
+// Check if the given node intersects any of the given sorted ranges.
+fn overlaps(ranges: &[Range], node: Node) -> bool {
+    let (start, end) = (node.start_byte(), node.end_byte());
+    let index = ranges.partition_point(|range| range.end_byte <= start);
+    ranges.get(index).map_or(false, |range| {
+        range.start_byte < end || range.start_byte <= start
+    })
+}
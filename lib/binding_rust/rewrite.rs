@@ -0,0 +1,330 @@
+use std::{cmp::Reverse, error, fmt};
+
+use crate::{InputEdit, Node, Parser, Point, Query, QueryCursor, Range, Tree};
+
+/// A structural search-and-replace operation, which replaces each match of a
+/// [`Query`] with an instance of a template.
+///
+/// The template is ordinary text in which `@name` is replaced with the text of
+/// the capture called `name`, and `@@` stands for a literal `@`. Each match
+/// replaces the text that its captures span, so patterns typically capture
+/// their outermost node:
+///
+/// ```text
+/// query:    (call_expression function: (_) @function arguments: (_) @args) @call
+/// template: invoke(@function, @args)
+/// ```
+///
+/// A capture that matches several nodes is replaced with the text from the
+/// start of its first node to the end of its last node, and a capture that
+/// matches no nodes is replaced with nothing.
+pub struct Rewriter<'query> {
+    query: &'query Query,
+    template: Vec<TemplatePart>,
+}
+
+/// A replacement of a range of the source code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replacement {
+    pub range: Range,
+    pub text: Vec<u8>,
+}
+
+/// The result of applying a [`Rewriter`] to a syntax tree.
+#[derive(Debug)]
+pub struct RewriteOutput {
+    /// The rewritten source code.
+    pub source: Vec<u8>,
+    /// The syntax tree of the rewritten source code.
+    pub tree: Tree,
+    /// The edits that were made to the source code, in the order in which
+    /// they were applied.
+    pub edits: Vec<InputEdit>,
+}
+
+/// An error that occurred when creating or applying a [`Rewriter`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum RewriteError {
+    /// The template refers to a capture that does not exist in the query.
+    Capture { offset: usize, name: String },
+    /// The rewritten source code could not be parsed, because the parser has
+    /// no language or parsing was cancelled.
+    Parse,
+    /// The rewritten source code contains syntax errors that are not in the
+    /// original source code. The ranges of those errors in the rewritten code
+    /// are given.
+    Syntax(Vec<Range>),
+}
+
+#[derive(Debug)]
+enum TemplatePart {
+    Text(String),
+    Capture(u32),
+}
+
+impl<'query> Rewriter<'query> {
+    /// Create a rewriter that replaces the matches of the given query with the
+    /// given template.
+    pub fn new(query: &'query Query, template: &str) -> Result<Self, RewriteError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(index) = rest.find('@') {
+            text.push_str(&rest[..index]);
+            rest = &rest[index + 1..];
+            if let Some(stripped) = rest.strip_prefix('@') {
+                text.push('@');
+                rest = stripped;
+                continue;
+            }
+
+            let name_len = rest
+                .find(|c: char| !is_capture_name_char(c))
+                .unwrap_or(rest.len());
+            if name_len == 0 {
+                text.push('@');
+                continue;
+            }
+
+            // Capture names may contain `.` and `-`, so use the longest prefix of the
+            // name that is a capture, to allow for punctuation after the name.
+            let name = &rest[..name_len];
+            let (capture_index, len) = (1..=name_len)
+                .rev()
+                .find_map(|len| {
+                    let index = query.capture_index_for_name(&name[..len])?;
+                    Some((index, len))
+                })
+                .ok_or_else(|| RewriteError::Capture {
+                    offset: template.len() - rest.len() - 1,
+                    name: name.to_string(),
+                })?;
+            if !text.is_empty() {
+                parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+            }
+            parts.push(TemplatePart::Capture(capture_index));
+            rest = &rest[len..];
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            parts.push(TemplatePart::Text(text));
+        }
+
+        Ok(Self {
+            query,
+            template: parts,
+        })
+    }
+
+    /// Get the query whose matches are replaced.
+    #[must_use]
+    pub const fn query(&self) -> &'query Query {
+        self.query
+    }
+
+    /// Compute the replacements for the matches of the query within the given
+    /// node, sorted by their position.
+    ///
+    /// When matches overlap, only the one that starts first is replaced, or the
+    /// outermost one if they start at the same position.
+    #[must_use]
+    pub fn replacements(&self, node: Node, source: &[u8]) -> Vec<Replacement> {
+        let mut replacements = Vec::new();
+        let mut cursor = QueryCursor::new();
+        for m in cursor.matches(self.query, node, source) {
+            let first = m
+                .captures
+                .iter()
+                .map(|c| c.node)
+                .min_by_key(Node::start_byte);
+            let last = m.captures.iter().map(|c| c.node).max_by_key(Node::end_byte);
+            let range = match (first, last) {
+                (Some(first), Some(last)) => Range {
+                    start_byte: first.start_byte(),
+                    end_byte: last.end_byte(),
+                    start_point: first.start_position(),
+                    end_point: last.end_position(),
+                },
+                _ => continue,
+            };
+
+            let mut text = Vec::new();
+            for part in &self.template {
+                match part {
+                    TemplatePart::Text(s) => text.extend_from_slice(s.as_bytes()),
+                    TemplatePart::Capture(index) => {
+                        let mut nodes = m.nodes_for_capture_index(*index);
+                        if let Some(first) = nodes.next() {
+                            let last = nodes.last().unwrap_or(first);
+                            text.extend_from_slice(&source[first.start_byte()..last.end_byte()]);
+                        }
+                    }
+                }
+            }
+            replacements.push(Replacement { range, text });
+        }
+
+        replacements.sort_by_key(|r| (r.range.start_byte, Reverse(r.range.end_byte)));
+        let mut result = Vec::<Replacement>::with_capacity(replacements.len());
+        for replacement in replacements {
+            if result.last().map_or(true, |last| {
+                last.range.end_byte <= replacement.range.start_byte
+            }) {
+                result.push(replacement);
+            }
+        }
+        result
+    }
+
+    /// Replace the matches of the query within the given syntax tree, and
+    /// reparse the rewritten source code incrementally.
+    ///
+    /// Returns an error if the rewritten code contains syntax errors that are
+    /// not in the original code.
+    pub fn rewrite(
+        &self,
+        parser: &mut Parser,
+        tree: &Tree,
+        source: &[u8],
+    ) -> Result<RewriteOutput, RewriteError> {
+        // Apply the replacements from last to first, so that the positions of the
+        // remaining replacements are not affected.
+        let mut new_source = source.to_vec();
+        let mut edited_tree = tree.clone();
+        let mut edits = Vec::new();
+        for replacement in self
+            .replacements(tree.root_node(), source)
+            .into_iter()
+            .rev()
+        {
+            let range = replacement.range;
+            let edit = InputEdit {
+                start_byte: range.start_byte,
+                old_end_byte: range.end_byte,
+                new_end_byte: range.start_byte + replacement.text.len(),
+                start_position: range.start_point,
+                old_end_position: range.end_point,
+                new_end_position: advance(range.start_point, &replacement.text),
+            };
+            new_source.splice(range.start_byte..range.end_byte, replacement.text);
+            edited_tree.edit(&edit);
+            edits.push(edit);
+        }
+
+        let new_tree = parser
+            .parse(&new_source, Some(&edited_tree))
+            .ok_or(RewriteError::Parse)?;
+
+        // Every error in the rewritten code must correspond to an error in the
+        // original code, after the original error's position is adjusted for the
+        // edits.
+        let old_errors = error_ranges(tree)
+            .into_iter()
+            .map(|range| {
+                edits
+                    .iter()
+                    .fold(range.start_byte..range.end_byte, |range, edit| {
+                        edit_byte(range.start, edit, false)..edit_byte(range.end, edit, true)
+                    })
+            })
+            .collect::<Vec<_>>();
+        let new_errors = error_ranges(&new_tree)
+            .into_iter()
+            .filter(|new| {
+                !old_errors
+                    .iter()
+                    .any(|old| old.start <= new.end_byte && new.start_byte <= old.end)
+            })
+            .collect::<Vec<_>>();
+        if !new_errors.is_empty() {
+            return Err(RewriteError::Syntax(new_errors));
+        }
+
+        Ok(RewriteOutput {
+            source: new_source,
+            tree: new_tree,
+            edits,
+        })
+    }
+}
+
+impl fmt::Display for RewriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Capture { offset, name } => {
+                write!(
+                    f,
+                    "Invalid capture name @{name} in template at offset {offset}"
+                )
+            }
+            Self::Parse => write!(f, "Failed to parse the rewritten code"),
+            Self::Syntax(ranges) => {
+                write!(f, "Rewritten code contains syntax errors at")?;
+                for (i, range) in ranges.iter().enumerate() {
+                    let separator = if i == 0 { " " } else { ", " };
+                    let point = range.start_point;
+                    write!(f, "{separator}{}:{}", point.row + 1, point.column + 1)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl error::Error for RewriteError {}
+
+const fn is_capture_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+fn advance(mut point: Point, text: &[u8]) -> Point {
+    for c in text {
+        if *c == b'\n' {
+            point.row += 1;
+            point.column = 0;
+        } else {
+            point.column += 1;
+        }
+    }
+    point
+}
+
+// Adjust a byte offset for an edit. Offsets within the replaced text are moved
+// to the start of the edit, or to the end of the new text if they are the end of
+// a range.
+const fn edit_byte(byte: usize, edit: &InputEdit, is_end: bool) -> usize {
+    if byte >= edit.old_end_byte {
+        byte - edit.old_end_byte + edit.new_end_byte
+    } else if byte > edit.start_byte {
+        if is_end {
+            edit.new_end_byte
+        } else {
+            edit.start_byte
+        }
+    } else {
+        byte
+    }
+}
+
+fn error_ranges(tree: &Tree) -> Vec<Range> {
+    let mut result = Vec::new();
+    let mut cursor = tree.walk();
+    loop {
+        let node = cursor.node();
+        if node.is_error() || node.is_missing() {
+            result.push(node.range());
+        }
+        // Only descend into nodes that contain errors.
+        if node.has_error() && !node.is_error() && cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return result;
+            }
+        }
+    }
+}
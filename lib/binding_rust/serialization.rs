@@ -0,0 +1,177 @@
+use serde::{
+    ser::{SerializeSeq, SerializeStruct},
+    Deserialize, Serialize, Serializer,
+};
+
+use crate::{Node, Range};
+
+/// A [`Node`] and its descendants, prepared for serialization with [`serde`].
+///
+/// The nodes are serialized as a flat sequence in pre-order, rather than as
+/// nested structures, so that deeply nested trees don't exceed the recursion
+/// limits of formats like JSON. Each node is a structure with the same shape
+/// as [`SerializedNode`], which records the index of its parent within the
+/// sequence, so the output of any serde format can be loaded back into a
+/// [`SerializedTree`].
+#[derive(Clone, Copy)]
+pub struct SerializableNode<'a, 'tree> {
+    node: Node<'tree>,
+    source: Option<&'a [u8]>,
+}
+
+/// An owned syntax node, as produced by serializing a [`SerializableNode`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializedNode {
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// The index of the node's parent, which is `None` for the first node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<usize>,
+    pub named: bool,
+    pub extra: bool,
+    pub missing: bool,
+    pub error: bool,
+    pub range: Range,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// The owned nodes of a serialized [`SerializableNode`], in pre-order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SerializedTree {
+    pub nodes: Vec<SerializedNode>,
+}
+
+impl<'tree> Node<'tree> {
+    /// Prepare this node and its descendants for serialization.
+    ///
+    /// The node's text is omitted unless the source code is provided with
+    /// [`SerializableNode::with_text`].
+    #[must_use]
+    pub const fn serializable(self) -> SerializableNode<'static, 'tree> {
+        SerializableNode {
+            node: self,
+            source: None,
+        }
+    }
+}
+
+impl<'a, 'tree> SerializableNode<'a, 'tree> {
+    /// Include the text of each leaf node, taken from the given source code.
+    #[must_use]
+    pub const fn with_text<'b>(self, source: &'b [u8]) -> SerializableNode<'b, 'tree> {
+        SerializableNode {
+            node: self.node,
+            source: Some(source),
+        }
+    }
+
+    /// Get the node that will be serialized.
+    #[must_use]
+    pub const fn node(&self) -> Node<'tree> {
+        self.node
+    }
+}
+
+impl SerializedTree {
+    /// Get the node that was serialized, whose descendants follow it.
+    #[must_use]
+    pub fn root(&self) -> Option<&SerializedNode> {
+        self.nodes.first()
+    }
+
+    /// Iterate over the children of the node at the given index, along with
+    /// their own indices.
+    pub fn children(&self, index: usize) -> impl Iterator<Item = (usize, &SerializedNode)> + '_ {
+        // The descendants of a node directly follow it, and the first node
+        // after them has one of the node's ancestors as its parent.
+        self.nodes
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .take_while(move |(_, node)| matches!(node.parent, Some(parent) if parent >= index))
+            .filter(move |(_, node)| node.parent == Some(index))
+    }
+}
+
+impl Serialize for Node<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serializable().serialize(serializer)
+    }
+}
+
+impl Serialize for SerializableNode<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.node.descendant_count()))?;
+        let mut cursor = self.node.walk();
+        let mut parents = Vec::new();
+        let mut index = 0;
+        loop {
+            seq.serialize_element(&FlatNode {
+                node: cursor.node(),
+                field_name: cursor.field_name(),
+                parent: parents.last().copied(),
+                source: self.source,
+            })?;
+            if cursor.goto_first_child() {
+                parents.push(index);
+            } else {
+                while !cursor.goto_next_sibling() {
+                    if !cursor.goto_parent() {
+                        return seq.end();
+                    }
+                    parents.pop();
+                }
+            }
+            index += 1;
+        }
+    }
+}
+
+struct FlatNode<'a, 'tree> {
+    node: Node<'tree>,
+    field_name: Option<&'static str>,
+    parent: Option<usize>,
+    source: Option<&'a [u8]>,
+}
+
+impl Serialize for FlatNode<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.node;
+        let text = self
+            .source
+            .filter(|_| node.child_count() == 0)
+            .and_then(|source| source.get(node.byte_range()))
+            .map(String::from_utf8_lossy);
+
+        let len = 6
+            + usize::from(self.field_name.is_some())
+            + usize::from(self.parent.is_some())
+            + usize::from(text.is_some());
+        let mut state = serializer.serialize_struct("SerializedNode", len)?;
+        state.serialize_field("kind", node.kind())?;
+        if let Some(field_name) = self.field_name {
+            state.serialize_field("field", field_name)?;
+        } else {
+            state.skip_field("field")?;
+        }
+        if let Some(parent) = self.parent {
+            state.serialize_field("parent", &parent)?;
+        } else {
+            state.skip_field("parent")?;
+        }
+        state.serialize_field("named", &node.is_named())?;
+        state.serialize_field("extra", &node.is_extra())?;
+        state.serialize_field("missing", &node.is_missing())?;
+        state.serialize_field("error", &node.is_error())?;
+        state.serialize_field("range", &node.range())?;
+        if let Some(text) = text {
+            state.serialize_field("text", &text)?;
+        } else {
+            state.skip_field("text")?;
+        }
+        state.end()
+    }
+}
@@ -0,0 +1,130 @@
+use std::{fmt, hash, marker::PhantomData, ops::Deref, sync::Arc};
+
+use crate::{ffi, Node, Tree};
+
+/// A read-only syntax tree that can be shared between threads.
+///
+/// A `SyncTree` is a reference-counted handle to a [`Tree`], so cloning it is
+/// cheap. Unlike a [`Node`], which borrows its tree, a [`NodeRef`] obtained
+/// from a `SyncTree` holds its own handle to the tree. Node references can
+/// therefore be stored in long-lived data structures and sent to other threads.
+///
+/// # Thread safety
+///
+/// A syntax tree never changes after it has been parsed, except through
+/// [`Tree::edit`], which requires exclusive access to the tree. The subtrees
+/// that make up a tree are reference-counted atomically, which is what makes
+/// copying a tree with [`Tree::clone`] cheap and safe from any thread. Any
+/// number of threads can therefore read a `SyncTree` and its nodes at the same
+/// time. To edit the tree, make a copy of it with [`SyncTree::to_tree`].
+#[derive(Clone)]
+pub struct SyncTree(Arc<Tree>);
+
+/// An owned reference to a node within a [`SyncTree`].
+///
+/// A `NodeRef` keeps its tree alive, and can be turned back into a [`Node`]
+/// using [`NodeRef::node`].
+#[derive(Clone)]
+pub struct NodeRef {
+    tree: SyncTree,
+    node: ffi::TSNode,
+}
+
+impl SyncTree {
+    /// Create a shareable handle to the given tree.
+    #[must_use]
+    pub fn new(tree: Tree) -> Self {
+        Self(Arc::new(tree))
+    }
+
+    /// Get a reference to the root node of the tree.
+    #[must_use]
+    pub fn root_node_ref(&self) -> NodeRef {
+        NodeRef {
+            tree: self.clone(),
+            node: self.root_node().0,
+        }
+    }
+
+    /// Get a reference to the given node, which can outlive the borrow of
+    /// this tree.
+    ///
+    /// Returns `None` if the node does not belong to this tree.
+    #[must_use]
+    pub fn node_ref(&self, node: Node) -> Option<NodeRef> {
+        (node.0.tree == self.0 .0.as_ptr()).then(|| NodeRef {
+            tree: self.clone(),
+            node: node.0,
+        })
+    }
+
+    /// Create an editable copy of the tree.
+    ///
+    /// The copy shares its nodes with this tree, so this is a cheap operation.
+    #[must_use]
+    pub fn to_tree(&self) -> Tree {
+        self.0.as_ref().clone()
+    }
+}
+
+impl Deref for SyncTree {
+    type Target = Tree;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Tree> for SyncTree {
+    fn from(tree: Tree) -> Self {
+        Self::new(tree)
+    }
+}
+
+impl fmt::Debug for SyncTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{SyncTree {:?}}}", self.root_node())
+    }
+}
+
+impl NodeRef {
+    /// Get the referenced node.
+    #[must_use]
+    pub fn node(&self) -> Node<'_> {
+        Node(self.node, PhantomData)
+    }
+
+    /// Get the tree that contains the referenced node.
+    #[must_use]
+    pub const fn tree(&self) -> &SyncTree {
+        &self.tree
+    }
+
+    /// Get the referenced node's numerical id.
+    ///
+    /// This is the same as the [`Node::id`] of the referenced node.
+    #[must_use]
+    pub fn id(&self) -> usize {
+        self.node().id()
+    }
+}
+
+impl PartialEq for NodeRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.node() == other.node()
+    }
+}
+
+impl Eq for NodeRef {}
+
+impl hash::Hash for NodeRef {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        self.node().hash(state);
+    }
+}
+
+impl fmt::Debug for NodeRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{NodeRef {:?}}}", self.node())
+    }
+}
@@ -0,0 +1,633 @@
+use std::{
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, BinaryHeap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::{Node, Tree};
+
+// Subtrees smaller than this are only matched in the bottom-up phase.
+const MIN_HEIGHT: usize = 2;
+
+// The fraction of common descendants that two nodes must have in order to be
+// matched in the bottom-up phase.
+const MIN_DICE: f64 = 0.5;
+
+// The largest subtrees whose unmatched descendants are matched when their roots
+// are matched in the bottom-up phase.
+const MAX_RECOVERY_SIZE: usize = 1000;
+
+/// An action in the edit script that transforms one syntax tree into another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffAction<'old, 'new> {
+    /// A node of the new tree has no counterpart in the old tree.
+    ///
+    /// The node's descendants that have no counterpart either are inserted
+    /// along with it, and are not reported separately.
+    Insert { node: Node<'new> },
+    /// A node of the old tree has no counterpart in the new tree.
+    ///
+    /// The node's descendants that have no counterpart either are deleted
+    /// along with it, and are not reported separately.
+    Delete { node: Node<'old> },
+    /// A leaf node's text changed.
+    Update { old: Node<'old>, new: Node<'new> },
+    /// A node was moved to a different parent, or to a different position
+    /// among its siblings.
+    Move { old: Node<'old>, new: Node<'new> },
+}
+
+/// A structural diff between two syntax trees of the same language.
+///
+/// The nodes of the two trees are matched using the GumTree algorithm: first,
+/// identical subtrees are matched from the largest to the smallest, and then
+/// the remaining nodes are matched with the nodes of the same type that share
+/// most of their matched descendants. The resulting edit script describes the
+/// inserted, deleted, updated and moved nodes.
+pub struct TreeDiff<'old, 'new> {
+    old_nodes: Vec<Node<'old>>,
+    new_nodes: Vec<Node<'new>>,
+    old_indices: HashMap<Node<'old>, usize>,
+    new_indices: HashMap<Node<'new>, usize>,
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+    actions: Vec<DiffAction<'old, 'new>>,
+}
+
+// The nodes of a tree in pre-order, so that the descendants of the node at
+// index `i` are the nodes at indices `i + 1..i + sizes[i]`.
+struct DiffTree<'tree, 'source> {
+    nodes: Vec<Node<'tree>>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    sizes: Vec<usize>,
+    heights: Vec<usize>,
+    hashes: Vec<u64>,
+    values: Vec<Option<&'source [u8]>>,
+}
+
+struct Matcher<'a, 'old, 'new, 'source> {
+    old: &'a DiffTree<'old, 'source>,
+    new: &'a DiffTree<'new, 'source>,
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+}
+
+// A priority queue of subtrees, ordered by height.
+struct HeightQueue<'a, 'tree, 'source> {
+    tree: &'a DiffTree<'tree, 'source>,
+    heap: BinaryHeap<(usize, Reverse<usize>)>,
+}
+
+impl<'old, 'new> TreeDiff<'old, 'new> {
+    /// Compute the differences between two syntax trees, along with the
+    /// source code that they were parsed from.
+    ///
+    /// The trees must have been parsed with the same language.
+    #[must_use]
+    pub fn new(
+        old_tree: &'old Tree,
+        old_source: &[u8],
+        new_tree: &'new Tree,
+        new_source: &[u8],
+    ) -> Self {
+        let old = DiffTree::new(old_tree, old_source);
+        let new = DiffTree::new(new_tree, new_source);
+
+        let mut matcher = Matcher {
+            old: &old,
+            new: &new,
+            old_to_new: vec![None; old.len()],
+            new_to_old: vec![None; new.len()],
+        };
+        matcher.match_top_down();
+        matcher.match_bottom_up();
+        matcher.match_by_parent();
+        let actions = matcher.actions();
+        let Matcher {
+            old_to_new,
+            new_to_old,
+            ..
+        } = matcher;
+
+        Self {
+            old_indices: index_nodes(&old.nodes),
+            new_indices: index_nodes(&new.nodes),
+            old_nodes: old.nodes,
+            new_nodes: new.nodes,
+            old_to_new,
+            new_to_old,
+            actions,
+        }
+    }
+
+    /// Get the edit script that transforms the old tree into the new tree.
+    ///
+    /// Deletions are listed first, in the order of the old tree, followed by
+    /// the other actions in the order of the new tree.
+    #[must_use]
+    pub fn actions(&self) -> &[DiffAction<'old, 'new>] {
+        &self.actions
+    }
+
+    /// Iterate over the pairs of matching nodes, in the order of the old tree.
+    pub fn matches(&self) -> impl Iterator<Item = (Node<'old>, Node<'new>)> + '_ {
+        self.old_to_new
+            .iter()
+            .enumerate()
+            .filter_map(|(old_index, new_index)| {
+                Some((self.old_nodes[old_index], self.new_nodes[(*new_index)?]))
+            })
+    }
+
+    /// Get the node of the new tree that matches the given node of the old
+    /// tree.
+    #[must_use]
+    pub fn new_node(&self, old_node: Node<'old>) -> Option<Node<'new>> {
+        let index = self.old_to_new[*self.old_indices.get(&old_node)?]?;
+        Some(self.new_nodes[index])
+    }
+
+    /// Get the node of the old tree that matches the given node of the new
+    /// tree.
+    #[must_use]
+    pub fn old_node(&self, new_node: Node<'new>) -> Option<Node<'old>> {
+        let index = self.new_to_old[*self.new_indices.get(&new_node)?]?;
+        Some(self.old_nodes[index])
+    }
+}
+
+impl<'tree, 'source> DiffTree<'tree, 'source> {
+    fn new(tree: &'tree Tree, source: &'source [u8]) -> Self {
+        let mut result = Self {
+            nodes: Vec::new(),
+            parents: Vec::new(),
+            children: Vec::new(),
+            sizes: Vec::new(),
+            heights: Vec::new(),
+            hashes: Vec::new(),
+            values: Vec::new(),
+        };
+
+        let mut cursor = tree.walk();
+        let mut stack = Vec::<usize>::new();
+        'nodes: loop {
+            let node = cursor.node();
+            let index = result.nodes.len();
+            let parent = stack.last().copied();
+            if let Some(parent) = parent {
+                result.children[parent].push(index);
+            }
+            result.nodes.push(node);
+            result.parents.push(parent);
+            result.children.push(Vec::new());
+            result.values.push(
+                (node.child_count() == 0)
+                    .then(|| source.get(node.byte_range()).unwrap_or_default()),
+            );
+
+            if cursor.goto_first_child() {
+                stack.push(index);
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    break 'nodes;
+                }
+                stack.pop();
+            }
+        }
+
+        // Compute the size, height and hash of each subtree, visiting every node
+        // after its descendants.
+        let len = result.nodes.len();
+        result.sizes = vec![1; len];
+        result.heights = vec![1; len];
+        result.hashes = vec![0; len];
+        for index in (0..len).rev() {
+            let mut hasher = DefaultHasher::new();
+            result.nodes[index].kind_id().hash(&mut hasher);
+            result.values[index].hash(&mut hasher);
+            for &child in &result.children[index] {
+                result.sizes[index] += result.sizes[child];
+                result.heights[index] = result.heights[index].max(result.heights[child] + 1);
+                result.hashes[child].hash(&mut hasher);
+            }
+            result.hashes[index] = hasher.finish();
+        }
+
+        result
+    }
+
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn kind_id(&self, index: usize) -> u16 {
+        self.nodes[index].kind_id()
+    }
+
+    fn descendants(&self, index: usize) -> std::ops::Range<usize> {
+        index + 1..index + self.sizes[index]
+    }
+
+    fn is_descendant(&self, ancestor: usize, index: usize) -> bool {
+        self.descendants(ancestor).contains(&index)
+    }
+
+    fn is_isomorphic(&self, index: usize, other: &DiffTree, other_index: usize) -> bool {
+        self.hashes[index] == other.hashes[other_index]
+            && self.sizes[index] == other.sizes[other_index]
+    }
+}
+
+impl<'a, 'tree, 'source> HeightQueue<'a, 'tree, 'source> {
+    fn new(tree: &'a DiffTree<'tree, 'source>) -> Self {
+        let mut result = Self {
+            tree,
+            heap: BinaryHeap::new(),
+        };
+        if tree.len() > 0 {
+            result.push(0);
+        }
+        result
+    }
+
+    fn push(&mut self, index: usize) {
+        self.heap.push((self.tree.heights[index], Reverse(index)));
+    }
+
+    fn open(&mut self, index: usize) {
+        for &child in &self.tree.children[index] {
+            self.push(child);
+        }
+    }
+
+    fn peek_height(&self) -> usize {
+        self.heap.peek().map_or(0, |(height, _)| *height)
+    }
+
+    fn pop(&mut self) -> Vec<usize> {
+        let height = self.peek_height();
+        let mut result = Vec::new();
+        while self.peek_height() == height && height > 0 {
+            let (_, Reverse(index)) = self.heap.pop().unwrap();
+            result.push(index);
+        }
+        result
+    }
+}
+
+impl<'a, 'old, 'new, 'source> Matcher<'a, 'old, 'new, 'source> {
+    fn add_match(&mut self, old_index: usize, new_index: usize) {
+        self.old_to_new[old_index] = Some(new_index);
+        self.new_to_old[new_index] = Some(old_index);
+    }
+
+    fn add_subtree_matches(&mut self, old_index: usize, new_index: usize) {
+        for offset in 0..self.old.sizes[old_index] {
+            self.add_match(old_index + offset, new_index + offset);
+        }
+    }
+
+    fn is_subtree_unmatched(&self, old_index: usize, new_index: usize) -> bool {
+        (old_index..old_index + self.old.sizes[old_index]).all(|i| self.old_to_new[i].is_none())
+            && (new_index..new_index + self.new.sizes[new_index])
+                .all(|i| self.new_to_old[i].is_none())
+    }
+
+    // The fraction of the descendants of two nodes that are matched with each
+    // other.
+    fn dice(&self, old_index: usize, new_index: usize) -> f64 {
+        let total = self.old.sizes[old_index] + self.new.sizes[new_index] - 2;
+        if total == 0 {
+            return 0.0;
+        }
+        let common = self
+            .old
+            .descendants(old_index)
+            .filter(|i| self.old_to_new[*i].map_or(false, |m| self.new.is_descendant(new_index, m)))
+            .count();
+        2.0 * common as f64 / total as f64
+    }
+
+    // Match identical subtrees, starting with the tallest ones.
+    fn match_top_down(&mut self) {
+        let mut old_queue = HeightQueue::new(self.old);
+        let mut new_queue = HeightQueue::new(self.new);
+        let mut ambiguous_matches = Vec::new();
+
+        loop {
+            let old_height = old_queue.peek_height();
+            let new_height = new_queue.peek_height();
+            if old_height.max(new_height) < MIN_HEIGHT {
+                break;
+            }
+            if old_height > new_height {
+                for index in old_queue.pop() {
+                    old_queue.open(index);
+                }
+                continue;
+            }
+            if new_height > old_height {
+                for index in new_queue.pop() {
+                    new_queue.open(index);
+                }
+                continue;
+            }
+
+            let old_indices = old_queue.pop();
+            let new_indices = new_queue.pop();
+            let mut new_indices_by_hash = HashMap::<u64, Vec<usize>>::new();
+            for &new_index in &new_indices {
+                new_indices_by_hash
+                    .entry(self.new.hashes[new_index])
+                    .or_default()
+                    .push(new_index);
+            }
+            let mut old_indices_by_hash = HashMap::<u64, Vec<usize>>::new();
+            for &old_index in &old_indices {
+                old_indices_by_hash
+                    .entry(self.old.hashes[old_index])
+                    .or_default()
+                    .push(old_index);
+            }
+
+            let mut matched_new_indices = HashSet::new();
+            for &old_index in &old_indices {
+                let candidates = new_indices_by_hash
+                    .get(&self.old.hashes[old_index])
+                    .map_or(&[][..], Vec::as_slice)
+                    .iter()
+                    .copied()
+                    .filter(|&new_index| self.old.is_isomorphic(old_index, self.new, new_index))
+                    .collect::<Vec<_>>();
+                if candidates.is_empty() {
+                    old_queue.open(old_index);
+                    continue;
+                }
+                matched_new_indices.extend(candidates.iter().copied());
+                if candidates.len() == 1
+                    && old_indices_by_hash[&self.old.hashes[old_index]].len() == 1
+                {
+                    self.add_subtree_matches(old_index, candidates[0]);
+                } else {
+                    ambiguous_matches.extend(candidates.iter().map(|&c| (old_index, c)));
+                }
+            }
+            for new_index in new_indices {
+                if !matched_new_indices.contains(&new_index) {
+                    new_queue.open(new_index);
+                }
+            }
+        }
+
+        // Among identical subtrees that occur several times, prefer the ones whose
+        // parents are most similar, and then the ones that appear in the same order.
+        let mut ambiguous_matches = ambiguous_matches
+            .into_iter()
+            .map(|(old_index, new_index)| {
+                let dice = match (self.old.parents[old_index], self.new.parents[new_index]) {
+                    (Some(old_parent), Some(new_parent)) => self.dice(old_parent, new_parent),
+                    _ => 0.0,
+                };
+                (dice, old_index, new_index)
+            })
+            .collect::<Vec<_>>();
+        ambiguous_matches.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| a.1.abs_diff(a.2).cmp(&b.1.abs_diff(b.2)))
+                .then_with(|| (a.1, a.2).cmp(&(b.1, b.2)))
+        });
+        for (_, old_index, new_index) in ambiguous_matches {
+            if self.is_subtree_unmatched(old_index, new_index) {
+                self.add_subtree_matches(old_index, new_index);
+            }
+        }
+    }
+
+    // Match the remaining nodes with nodes of the same type that contain many of
+    // the same descendants.
+    fn match_bottom_up(&mut self) {
+        if self.old.len() == 0 || self.new.len() == 0 {
+            return;
+        }
+
+        for old_index in (1..self.old.len()).rev() {
+            if self.old_to_new[old_index].is_some() || self.old.children[old_index].is_empty() {
+                continue;
+            }
+
+            let kind_id = self.old.kind_id(old_index);
+            let mut visited = HashSet::new();
+            let mut candidates = Vec::new();
+            for descendant in self.old.descendants(old_index) {
+                let mut ancestor = self.old_to_new[descendant].and_then(|m| self.new.parents[m]);
+                while let Some(index) = ancestor {
+                    if !visited.insert(index) {
+                        break;
+                    }
+                    if self.new_to_old[index].is_none() && self.new.kind_id(index) == kind_id {
+                        candidates.push(index);
+                    }
+                    ancestor = self.new.parents[index];
+                }
+            }
+
+            let best_candidate = candidates
+                .into_iter()
+                .map(|new_index| (self.dice(old_index, new_index), new_index))
+                .filter(|(dice, _)| *dice > MIN_DICE)
+                .max_by(|a, b| a.0.total_cmp(&b.0).then_with(|| b.1.cmp(&a.1)));
+            if let Some((_, new_index)) = best_candidate {
+                self.add_match(old_index, new_index);
+                self.recover_matches(old_index, new_index);
+            }
+        }
+
+        if self.old_to_new[0].is_none() && self.new_to_old[0].is_none() {
+            self.add_match(0, 0);
+            self.recover_matches(0, 0);
+        }
+    }
+
+    // Match the unmatched descendants of two matched nodes that are identical,
+    // or that have the same type and text.
+    fn recover_matches(&mut self, old_index: usize, new_index: usize) {
+        if self.old.sizes[old_index].max(self.new.sizes[new_index]) > MAX_RECOVERY_SIZE {
+            return;
+        }
+
+        let mut new_subtrees = HashMap::<u64, Vec<usize>>::new();
+        let mut new_nodes = HashMap::<(u16, Option<&[u8]>), Vec<usize>>::new();
+        for index in self.new.descendants(new_index).rev() {
+            if self.new_to_old[index].is_none() {
+                new_subtrees
+                    .entry(self.new.hashes[index])
+                    .or_default()
+                    .push(index);
+                new_nodes
+                    .entry((self.new.kind_id(index), self.new.values[index]))
+                    .or_default()
+                    .push(index);
+            }
+        }
+
+        for index in self.old.descendants(old_index) {
+            if self.old_to_new[index].is_some() {
+                continue;
+            }
+            if let Some(candidates) = new_subtrees.get_mut(&self.old.hashes[index]) {
+                while let Some(candidate) = candidates.pop() {
+                    if self.old.is_isomorphic(index, self.new, candidate)
+                        && self.is_subtree_unmatched(index, candidate)
+                    {
+                        self.add_subtree_matches(index, candidate);
+                        break;
+                    }
+                }
+            }
+        }
+
+        for index in self.old.descendants(old_index) {
+            if self.old_to_new[index].is_some() {
+                continue;
+            }
+            let key = (self.old.kind_id(index), self.old.values[index]);
+            if let Some(candidates) = new_nodes.get_mut(&key) {
+                while let Some(candidate) = candidates.pop() {
+                    if self.new_to_old[candidate].is_none() {
+                        self.add_match(index, candidate);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // Match the remaining nodes with unmatched siblings of the same type in the
+    // matching parent node, so that changed leaves are reported as updates.
+    fn match_by_parent(&mut self) {
+        for old_index in 0..self.old.len() {
+            if self.old_to_new[old_index].is_some() {
+                continue;
+            }
+            let new_parent = match self.old.parents[old_index].and_then(|p| self.old_to_new[p]) {
+                Some(new_parent) => new_parent,
+                None => continue,
+            };
+            let kind_id = self.old.kind_id(old_index);
+            if let Some(&new_index) = self.new.children[new_parent]
+                .iter()
+                .find(|&&c| self.new_to_old[c].is_none() && self.new.kind_id(c) == kind_id)
+            {
+                self.add_match(old_index, new_index);
+            }
+        }
+    }
+
+    fn actions(&self) -> Vec<DiffAction<'old, 'new>> {
+        let mut result = Vec::new();
+
+        for old_index in 0..self.old.len() {
+            if self.old_to_new[old_index].is_none()
+                && self.old.parents[old_index].map_or(true, |p| self.old_to_new[p].is_some())
+            {
+                result.push(DiffAction::Delete {
+                    node: self.old.nodes[old_index],
+                });
+            }
+        }
+
+        // Within each pair of matching parents, the children that are not part of
+        // the longest sequence of children appearing in the same order have moved.
+        let mut reordered = HashSet::new();
+        for (new_parent, children) in self.new.children.iter().enumerate() {
+            let old_parent = match self.new_to_old[new_parent] {
+                Some(old_parent) => old_parent,
+                None => continue,
+            };
+            let moved_children = children
+                .iter()
+                .filter_map(|&c| {
+                    let old_child = self.new_to_old[c]?;
+                    (self.old.parents[old_child] == Some(old_parent)).then_some((c, old_child))
+                })
+                .collect::<Vec<_>>();
+            let in_order = longest_increasing_subsequence(
+                &moved_children.iter().map(|(_, o)| *o).collect::<Vec<_>>(),
+            );
+            for (i, (new_child, _)) in moved_children.into_iter().enumerate() {
+                if !in_order.contains(&i) {
+                    reordered.insert(new_child);
+                }
+            }
+        }
+
+        for new_index in 0..self.new.len() {
+            let new_node = self.new.nodes[new_index];
+            let new_parent = self.new.parents[new_index];
+            let old_index = match self.new_to_old[new_index] {
+                Some(old_index) => old_index,
+                None => {
+                    if new_parent.map_or(true, |p| self.new_to_old[p].is_some()) {
+                        result.push(DiffAction::Insert { node: new_node });
+                    }
+                    continue;
+                }
+            };
+            let old_node = self.old.nodes[old_index];
+            if self.old.values[old_index] != self.new.values[new_index] {
+                result.push(DiffAction::Update {
+                    old: old_node,
+                    new: new_node,
+                });
+            }
+            let old_parent = self.old.parents[old_index];
+            if reordered.contains(&new_index)
+                || old_parent.and_then(|p| self.old_to_new[p]) != new_parent
+            {
+                result.push(DiffAction::Move {
+                    old: old_node,
+                    new: new_node,
+                });
+            }
+        }
+
+        result
+    }
+}
+
+fn index_nodes<'tree>(nodes: &[Node<'tree>]) -> HashMap<Node<'tree>, usize> {
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (*node, index))
+        .collect()
+}
+
+// Get the positions of the elements that make up a longest increasing
+// subsequence of the given values.
+fn longest_increasing_subsequence(values: &[usize]) -> HashSet<usize> {
+    let mut tails = Vec::<usize>::new();
+    let mut predecessors = vec![None; values.len()];
+    for (i, value) in values.iter().enumerate() {
+        let position = tails.partition_point(|&t| values[t] < *value);
+        if position > 0 {
+            predecessors[i] = Some(tails[position - 1]);
+        }
+        if position == tails.len() {
+            tails.push(i);
+        } else {
+            tails[position] = i;
+        }
+    }
+
+    let mut result = HashSet::new();
+    let mut index = tails.last().copied();
+    while let Some(i) = index {
+        result.insert(i);
+        index = predecessors[i];
+    }
+    result
+}
@@ -149,6 +149,13 @@ typedef enum TSQueryError {
   TSQueryErrorLanguage,
 } TSQueryError;
 
+typedef enum TSTreeDeserializeError {
+  TSTreeDeserializeErrorNone = 0,
+  TSTreeDeserializeErrorFormat,
+  TSTreeDeserializeErrorVersion,
+  TSTreeDeserializeErrorLanguage,
+} TSTreeDeserializeError;
+
 /********************/
 /* Section - Parser */
 /********************/
@@ -435,6 +442,33 @@ TSRange *ts_tree_get_changed_ranges(
  */
 void ts_tree_print_dot_graph(const TSTree *self, int file_descriptor);
 
+/**
+ * Serialize the syntax tree into a binary format that can be loaded again
+ * using [`ts_tree_deserialize`].
+ *
+ * The returned buffer is allocated using `malloc` and the caller is responsible
+ * for freeing it using `free`. The length of the buffer will be written to the
+ * given `length` pointer.
+ */
+char *ts_tree_serialize(const TSTree *self, uint32_t *length);
+
+/**
+ * Load a syntax tree that was serialized using [`ts_tree_serialize`].
+ *
+ * The tree must have been serialized with the same version of the same
+ * language. The loaded tree can be edited and passed to the parser as an
+ * old tree, just like one that was produced by the parser itself.
+ *
+ * If the data is invalid, this returns `NULL`, and writes the kind of problem
+ * to the `error` parameter.
+ */
+TSTree *ts_tree_deserialize(
+  const TSLanguage *language,
+  const char *data,
+  uint32_t length,
+  TSTreeDeserializeError *error
+);
+
 /******************/
 /* Section - Node */
 /******************/
@@ -7,6 +7,7 @@
 #include "./node.c"
 #include "./parser.c"
 #include "./query.c"
+#include "./serialization.c"
 #include "./stack.c"
 #include "./subtree.c"
 #include "./tree_cursor.c"
@@ -91,6 +91,23 @@ typedef struct {
   int32_t end;
 } TSCharacterRange;
 
+typedef struct {
+  uint32_t range_index;
+  uint16_t range_count;
+  TSStateId state;
+  bool skip;
+} TSLexTransition;
+
+typedef struct {
+  uint32_t transition_index;
+  uint16_t transition_count;
+  TSStateId eof_state;
+  TSSymbol accept_symbol;
+  bool accepts;
+} TSLexTableState;
+
+#define TS_LEX_NO_STATE ((TSStateId)-1)
+
 struct TSLanguage {
   uint32_t version;
   uint32_t symbol_count;
@@ -148,6 +165,51 @@ static inline bool set_contains(TSCharacterRange *ranges, uint32_t len, int32_t
   return (lookahead >= range->start && lookahead <= range->end);
 }
 
+/*
+ *  Interpret a lexer that was generated as a table of transitions, rather than
+ *  as a function. A transition without any character ranges always applies.
+ */
+static inline bool ts_lex_with_table(
+  TSLexer *lexer,
+  TSStateId state,
+  const TSLexTableState *states,
+  uint32_t state_count,
+  const TSLexTransition *transitions,
+  TSCharacterRange *ranges
+) {
+  bool result = false;
+  for (;;) {
+    if (state >= state_count) return result;
+    const TSLexTableState *entry = &states[state];
+    if (entry->accepts) {
+      result = true;
+      lexer->result_symbol = entry->accept_symbol;
+      lexer->mark_end(lexer);
+    }
+
+    int32_t lookahead = lexer->lookahead;
+    bool eof = lexer->eof(lexer);
+    const TSLexTransition *transition = NULL;
+    if (eof && entry->eof_state != TS_LEX_NO_STATE) {
+      state = entry->eof_state;
+    } else {
+      for (uint32_t i = 0; i < entry->transition_count; i++) {
+        const TSLexTransition *candidate = &transitions[entry->transition_index + i];
+        if (
+          candidate->range_count == 0 ||
+          (!eof && set_contains(&ranges[candidate->range_index], candidate->range_count, lookahead))
+        ) {
+          transition = candidate;
+          break;
+        }
+      }
+      if (!transition) return result;
+      state = transition->state;
+    }
+    lexer->advance(lexer, transition && transition->skip);
+  }
+}
+
 /*
  *  Lexer Macros
  */
@@ -0,0 +1,496 @@
+#include <string.h>
+#include "tree_sitter/api.h"
+#include "./alloc.h"
+#include "./array.h"
+#include "./language.h"
+#include "./length.h"
+#include "./subtree.h"
+#include "./tree.h"
+
+// The binary format of a serialized tree consists of a header, followed by the
+// tree's subtrees in post-order, so that every subtree is preceded by all of its
+// children. All integers are stored in little-endian byte order.
+//
+// The header records the ABI version of the language and a hash of its parse
+// tables, so that a tree is never loaded with a different grammar than the one
+// that produced it.
+
+#define TREE_SERIALIZATION_MAGIC "TSTR"
+#define TREE_SERIALIZATION_MAGIC_LENGTH 4
+#define TREE_SERIALIZATION_VERSION 1
+
+#define FNV_OFFSET_BASIS 0xcbf29ce484222325ull
+#define FNV_PRIME 0x100000001b3ull
+
+typedef enum {
+  SubtreeFlagVisible = 1 << 0,
+  SubtreeFlagNamed = 1 << 1,
+  SubtreeFlagExtra = 1 << 2,
+  SubtreeFlagFragileLeft = 1 << 3,
+  SubtreeFlagFragileRight = 1 << 4,
+  SubtreeFlagHasChanges = 1 << 5,
+  SubtreeFlagHasExternalTokens = 1 << 6,
+  SubtreeFlagHasExternalScannerStateChange = 1 << 7,
+  SubtreeFlagDependsOnColumn = 1 << 8,
+  SubtreeFlagIsMissing = 1 << 9,
+  SubtreeFlagIsKeyword = 1 << 10,
+} SubtreeFlag;
+
+typedef Array(uint8_t) SerializationBuffer;
+
+typedef struct {
+  Subtree tree;
+  uint32_t child_index;
+} SerializationStackEntry;
+
+typedef struct {
+  const uint8_t *data;
+  uint32_t length;
+  uint32_t offset;
+  bool valid;
+} SerializationReader;
+
+typedef struct {
+  TSSymbol symbol;
+  TSStateId parse_state;
+  uint16_t flags;
+  Length padding;
+  Length size;
+  uint32_t lookahead_bytes;
+  uint32_t error_cost;
+  uint32_t child_count;
+  int32_t dynamic_precedence;
+  uint16_t production_id;
+  int32_t lookahead_char;
+  const char *external_scanner_state;
+  uint32_t external_scanner_state_length;
+} SerializedSubtree;
+
+/*
+ *  Grammar hash
+ */
+
+static inline void ts_serialization__hash_u16(uint64_t *hash, uint16_t value) {
+  for (unsigned i = 0; i < 2; i++) {
+    *hash = (*hash ^ ((value >> (8 * i)) & 0xFF)) * FNV_PRIME;
+  }
+}
+
+static inline void ts_serialization__hash_u32(uint64_t *hash, uint32_t value) {
+  for (unsigned i = 0; i < 4; i++) {
+    *hash = (*hash ^ ((value >> (8 * i)) & 0xFF)) * FNV_PRIME;
+  }
+}
+
+static inline void ts_serialization__hash_string(uint64_t *hash, const char *string) {
+  if (string) {
+    for (const char *c = string; *c; c++) {
+      *hash = (*hash ^ (uint8_t)*c) * FNV_PRIME;
+    }
+  }
+  *hash = *hash * FNV_PRIME;
+}
+
+// Compute a hash of the parts of a language that determine the structure of
+// the trees that it produces.
+static uint64_t ts_serialization__language_hash(const TSLanguage *self) {
+  uint64_t hash = FNV_OFFSET_BASIS;
+  ts_serialization__hash_u32(&hash, self->symbol_count);
+  ts_serialization__hash_u32(&hash, self->alias_count);
+  ts_serialization__hash_u32(&hash, self->token_count);
+  ts_serialization__hash_u32(&hash, self->external_token_count);
+  ts_serialization__hash_u32(&hash, self->state_count);
+  ts_serialization__hash_u32(&hash, self->large_state_count);
+  ts_serialization__hash_u32(&hash, self->production_id_count);
+  ts_serialization__hash_u32(&hash, self->field_count);
+  ts_serialization__hash_u16(&hash, self->max_alias_sequence_length);
+
+  for (uint32_t i = 0; i < self->symbol_count + self->alias_count; i++) {
+    TSSymbolMetadata metadata = self->symbol_metadata[i];
+    ts_serialization__hash_string(&hash, self->symbol_names[i]);
+    ts_serialization__hash_u16(&hash, metadata.visible | metadata.named << 1 | metadata.supertype << 2);
+  }
+  for (uint32_t i = 1; i <= self->field_count; i++) {
+    ts_serialization__hash_string(&hash, self->field_names[i]);
+  }
+  if (self->alias_sequences) {
+    uint32_t alias_sequence_length = self->production_id_count * self->max_alias_sequence_length;
+    for (uint32_t i = 0; i < alias_sequence_length; i++) {
+      ts_serialization__hash_u16(&hash, self->alias_sequences[i]);
+    }
+  }
+
+  for (uint32_t state = 0; state < self->state_count; state++) {
+    if (state < self->large_state_count) {
+      const uint16_t *data = &self->parse_table[state * self->symbol_count];
+      for (uint32_t i = 0; i < self->symbol_count; i++) {
+        ts_serialization__hash_u16(&hash, data[i]);
+      }
+    } else {
+      uint32_t index = self->small_parse_table_map[state - self->large_state_count];
+      const uint16_t *data = &self->small_parse_table[index];
+      uint16_t group_count = *(data++);
+      ts_serialization__hash_u16(&hash, group_count);
+      for (unsigned i = 0; i < group_count; i++) {
+        uint16_t section_value = *(data++);
+        uint16_t symbol_count = *(data++);
+        ts_serialization__hash_u16(&hash, section_value);
+        ts_serialization__hash_u16(&hash, symbol_count);
+        for (unsigned j = 0; j < symbol_count; j++) {
+          ts_serialization__hash_u16(&hash, *(data++));
+        }
+      }
+    }
+    if (self->lex_modes) {
+      ts_serialization__hash_u16(&hash, self->lex_modes[state].lex_state);
+      ts_serialization__hash_u16(&hash, self->lex_modes[state].external_lex_state);
+    }
+  }
+
+  return hash;
+}
+
+/*
+ *  Serialization
+ */
+
+static inline void ts_serialization__write_u16(SerializationBuffer *self, uint16_t value) {
+  uint8_t bytes[2] = {value & 0xFF, value >> 8};
+  array_extend(self, 2, bytes);
+}
+
+static inline void ts_serialization__write_u32(SerializationBuffer *self, uint32_t value) {
+  uint8_t bytes[4] = {value & 0xFF, (value >> 8) & 0xFF, (value >> 16) & 0xFF, value >> 24};
+  array_extend(self, 4, bytes);
+}
+
+static inline void ts_serialization__write_u64(SerializationBuffer *self, uint64_t value) {
+  ts_serialization__write_u32(self, (uint32_t)value);
+  ts_serialization__write_u32(self, (uint32_t)(value >> 32));
+}
+
+static inline void ts_serialization__write_length(SerializationBuffer *self, Length length) {
+  ts_serialization__write_u32(self, length.bytes);
+  ts_serialization__write_u32(self, length.extent.row);
+  ts_serialization__write_u32(self, length.extent.column);
+}
+
+static void ts_serialization__write_subtree(SerializationBuffer *self, Subtree subtree) {
+  uint32_t child_count = ts_subtree_child_count(subtree);
+  uint16_t flags =
+    (ts_subtree_visible(subtree) ? SubtreeFlagVisible : 0) |
+    (ts_subtree_named(subtree) ? SubtreeFlagNamed : 0) |
+    (ts_subtree_extra(subtree) ? SubtreeFlagExtra : 0) |
+    (ts_subtree_fragile_left(subtree) ? SubtreeFlagFragileLeft : 0) |
+    (ts_subtree_fragile_right(subtree) ? SubtreeFlagFragileRight : 0) |
+    (ts_subtree_has_changes(subtree) ? SubtreeFlagHasChanges : 0) |
+    (ts_subtree_has_external_tokens(subtree) ? SubtreeFlagHasExternalTokens : 0) |
+    (ts_subtree_has_external_scanner_state_change(subtree) ? SubtreeFlagHasExternalScannerStateChange : 0) |
+    (ts_subtree_depends_on_column(subtree) ? SubtreeFlagDependsOnColumn : 0) |
+    (ts_subtree_missing(subtree) ? SubtreeFlagIsMissing : 0) |
+    (ts_subtree_is_keyword(subtree) ? SubtreeFlagIsKeyword : 0);
+
+  ts_serialization__write_u16(self, ts_subtree_symbol(subtree));
+  ts_serialization__write_u16(self, ts_subtree_parse_state(subtree));
+  ts_serialization__write_u16(self, flags);
+  ts_serialization__write_length(self, ts_subtree_padding(subtree));
+  ts_serialization__write_length(self, ts_subtree_size(subtree));
+  ts_serialization__write_u32(self, ts_subtree_lookahead_bytes(subtree));
+  ts_serialization__write_u32(self, subtree.data.is_inline ? 0 : subtree.ptr->error_cost);
+  ts_serialization__write_u32(self, child_count);
+
+  if (child_count > 0) {
+    ts_serialization__write_u32(self, (uint32_t)subtree.ptr->dynamic_precedence);
+    ts_serialization__write_u16(self, subtree.ptr->production_id);
+  } else if (ts_subtree_is_error(subtree)) {
+    ts_serialization__write_u32(self, (uint32_t)subtree.ptr->lookahead_char);
+  } else if (ts_subtree_has_external_tokens(subtree)) {
+    const ExternalScannerState *state = &subtree.ptr->external_scanner_state;
+    ts_serialization__write_u32(self, state->length);
+    array_extend(self, state->length, (const uint8_t *)ts_external_scanner_state_data(state));
+  }
+}
+
+char *ts_tree_serialize(const TSTree *self, uint32_t *length) {
+  SerializationBuffer buffer = array_new();
+  array_extend(&buffer, TREE_SERIALIZATION_MAGIC_LENGTH, (const uint8_t *)TREE_SERIALIZATION_MAGIC);
+  ts_serialization__write_u32(&buffer, TREE_SERIALIZATION_VERSION);
+  ts_serialization__write_u32(&buffer, self->language->version);
+  ts_serialization__write_u64(&buffer, ts_serialization__language_hash(self->language));
+
+  ts_serialization__write_u32(&buffer, self->included_range_count);
+  for (unsigned i = 0; i < self->included_range_count; i++) {
+    TSRange *range = &self->included_ranges[i];
+    ts_serialization__write_u32(&buffer, range->start_point.row);
+    ts_serialization__write_u32(&buffer, range->start_point.column);
+    ts_serialization__write_u32(&buffer, range->end_point.row);
+    ts_serialization__write_u32(&buffer, range->end_point.column);
+    ts_serialization__write_u32(&buffer, range->start_byte);
+    ts_serialization__write_u32(&buffer, range->end_byte);
+  }
+
+  // Reserve space for the subtree count, which is filled in at the end.
+  uint32_t subtree_count_offset = buffer.size;
+  uint32_t subtree_count = 0;
+  ts_serialization__write_u32(&buffer, 0);
+
+  Array(SerializationStackEntry) stack = array_new();
+  array_push(&stack, ((SerializationStackEntry) {self->root, 0}));
+  while (stack.size > 0) {
+    SerializationStackEntry *entry = array_back(&stack);
+    if (entry->child_index < ts_subtree_child_count(entry->tree)) {
+      Subtree child = ts_subtree_children(entry->tree)[entry->child_index++];
+      array_push(&stack, ((SerializationStackEntry) {child, 0}));
+    } else {
+      ts_serialization__write_subtree(&buffer, entry->tree);
+      subtree_count++;
+      stack.size--;
+    }
+  }
+  array_delete(&stack);
+
+  for (unsigned i = 0; i < 4; i++) {
+    buffer.contents[subtree_count_offset + i] = (subtree_count >> (8 * i)) & 0xFF;
+  }
+
+  *length = buffer.size;
+  return (char *)buffer.contents;
+}
+
+/*
+ *  Deserialization
+ */
+
+static inline bool ts_serialization__can_read(SerializationReader *self, uint32_t count) {
+  if (self->valid && self->length - self->offset >= count) return true;
+  self->valid = false;
+  return false;
+}
+
+static inline uint16_t ts_serialization__read_u16(SerializationReader *self) {
+  if (!ts_serialization__can_read(self, 2)) return 0;
+  const uint8_t *data = &self->data[self->offset];
+  self->offset += 2;
+  return (uint16_t)(data[0] | data[1] << 8);
+}
+
+static inline uint32_t ts_serialization__read_u32(SerializationReader *self) {
+  if (!ts_serialization__can_read(self, 4)) return 0;
+  const uint8_t *data = &self->data[self->offset];
+  self->offset += 4;
+  return (uint32_t)data[0] | (uint32_t)data[1] << 8 | (uint32_t)data[2] << 16 | (uint32_t)data[3] << 24;
+}
+
+static inline uint64_t ts_serialization__read_u64(SerializationReader *self) {
+  uint64_t low = ts_serialization__read_u32(self);
+  uint64_t high = ts_serialization__read_u32(self);
+  return low | high << 32;
+}
+
+static inline Length ts_serialization__read_length(SerializationReader *self) {
+  Length result;
+  result.bytes = ts_serialization__read_u32(self);
+  result.extent.row = ts_serialization__read_u32(self);
+  result.extent.column = ts_serialization__read_u32(self);
+  return result;
+}
+
+static bool ts_serialization__read_subtree(
+  SerializationReader *self,
+  const TSLanguage *language,
+  SerializedSubtree *result
+) {
+  *result = (SerializedSubtree) {0};
+  result->symbol = ts_serialization__read_u16(self);
+  result->parse_state = ts_serialization__read_u16(self);
+  result->flags = ts_serialization__read_u16(self);
+  result->padding = ts_serialization__read_length(self);
+  result->size = ts_serialization__read_length(self);
+  result->lookahead_bytes = ts_serialization__read_u32(self);
+  result->error_cost = ts_serialization__read_u32(self);
+  result->child_count = ts_serialization__read_u32(self);
+
+  if (result->child_count > 0) {
+    result->dynamic_precedence = (int32_t)ts_serialization__read_u32(self);
+    result->production_id = ts_serialization__read_u16(self);
+  } else if (result->symbol == ts_builtin_sym_error) {
+    result->lookahead_char = (int32_t)ts_serialization__read_u32(self);
+  } else if (result->flags & SubtreeFlagHasExternalTokens) {
+    result->external_scanner_state_length = ts_serialization__read_u32(self);
+    if (ts_serialization__can_read(self, result->external_scanner_state_length)) {
+      result->external_scanner_state = (const char *)&self->data[self->offset];
+      self->offset += result->external_scanner_state_length;
+    }
+  }
+
+  return
+    self->valid &&
+    (
+      result->symbol < language->symbol_count ||
+      result->symbol == ts_builtin_sym_error ||
+      result->symbol == ts_builtin_sym_error_repeat
+    ) &&
+    (result->parse_state < language->state_count || result->parse_state == TS_TREE_STATE_NONE) &&
+    (result->production_id == 0 || result->production_id < language->production_id_count);
+}
+
+// Restore the properties of a subtree that are not derived from its symbol
+// and children. The rest of an internal node's properties are computed from
+// its children by `ts_subtree_summarize_children`, so that they are consistent
+// with the children even when the serialized data is not.
+static void ts_serialization__restore_subtree(MutableSubtree *self, const SerializedSubtree *record) {
+  uint16_t flags = record->flags;
+  if (self->data.is_inline) {
+    self->data.extra = flags & SubtreeFlagExtra;
+    self->data.has_changes = flags & SubtreeFlagHasChanges;
+    self->data.is_missing = flags & SubtreeFlagIsMissing;
+  } else {
+    self->ptr->extra = flags & SubtreeFlagExtra;
+    self->ptr->has_changes = flags & SubtreeFlagHasChanges;
+    self->ptr->is_missing = flags & SubtreeFlagIsMissing;
+    self->ptr->parse_state = record->parse_state;
+    if (flags & SubtreeFlagFragileLeft) self->ptr->fragile_left = true;
+    if (flags & SubtreeFlagFragileRight) self->ptr->fragile_right = true;
+    if (record->child_count > 0) {
+      self->ptr->dynamic_precedence = record->dynamic_precedence;
+    } else {
+      self->ptr->has_external_scanner_state_change = flags & SubtreeFlagHasExternalScannerStateChange;
+      self->ptr->error_cost = record->error_cost;
+    }
+  }
+}
+
+// The aliases of a node's children are looked up by their index among the
+// children that aren't extras, so a node with a production id can't have
+// more of those children than the language's longest production.
+static bool ts_serialization__check_children(
+  const TSLanguage *language,
+  const SerializedSubtree *record,
+  const Subtree *children
+) {
+  if (record->production_id == 0) return true;
+  uint32_t structural_child_count = 0;
+  for (uint32_t i = 0; i < record->child_count; i++) {
+    if (!ts_subtree_extra(children[i])) structural_child_count++;
+  }
+  return structural_child_count <= language->max_alias_sequence_length;
+}
+
+TSTree *ts_tree_deserialize(
+  const TSLanguage *language,
+  const char *data,
+  uint32_t length,
+  TSTreeDeserializeError *error
+) {
+  SerializationReader reader = {(const uint8_t *)data, length, 0, true};
+  if (
+    length < TREE_SERIALIZATION_MAGIC_LENGTH ||
+    memcmp(data, TREE_SERIALIZATION_MAGIC, TREE_SERIALIZATION_MAGIC_LENGTH) != 0
+  ) {
+    *error = TSTreeDeserializeErrorFormat;
+    return NULL;
+  }
+  reader.offset = TREE_SERIALIZATION_MAGIC_LENGTH;
+
+  uint32_t format_version = ts_serialization__read_u32(&reader);
+  if (!reader.valid || format_version != TREE_SERIALIZATION_VERSION) {
+    *error = reader.valid ? TSTreeDeserializeErrorVersion : TSTreeDeserializeErrorFormat;
+    return NULL;
+  }
+
+  uint32_t language_version = ts_serialization__read_u32(&reader);
+  uint64_t language_hash = ts_serialization__read_u64(&reader);
+  if (!reader.valid) {
+    *error = TSTreeDeserializeErrorFormat;
+    return NULL;
+  }
+  if (
+    language_version != language->version ||
+    language_hash != ts_serialization__language_hash(language)
+  ) {
+    *error = TSTreeDeserializeErrorLanguage;
+    return NULL;
+  }
+
+  Array(TSRange) included_ranges = array_new();
+  SubtreeArray stack = array_new();
+  SubtreePool pool = ts_subtree_pool_new(0);
+
+  uint32_t included_range_count = ts_serialization__read_u32(&reader);
+  if (reader.valid && included_range_count <= (length - reader.offset) / (6 * sizeof(uint32_t))) {
+    array_reserve(&included_ranges, included_range_count);
+    for (unsigned i = 0; i < included_range_count; i++) {
+      TSRange range;
+      range.start_point.row = ts_serialization__read_u32(&reader);
+      range.start_point.column = ts_serialization__read_u32(&reader);
+      range.end_point.row = ts_serialization__read_u32(&reader);
+      range.end_point.column = ts_serialization__read_u32(&reader);
+      range.start_byte = ts_serialization__read_u32(&reader);
+      range.end_byte = ts_serialization__read_u32(&reader);
+      array_push(&included_ranges, range);
+    }
+  } else {
+    reader.valid = false;
+  }
+
+  uint32_t subtree_count = ts_serialization__read_u32(&reader);
+  for (uint32_t i = 0; i < subtree_count && reader.valid; i++) {
+    SerializedSubtree record;
+    if (
+      !ts_serialization__read_subtree(&reader, language, &record) ||
+      record.child_count > stack.size ||
+      !ts_serialization__check_children(
+        language, &record, &stack.contents[stack.size - record.child_count]
+      )
+    ) {
+      reader.valid = false;
+      break;
+    }
+
+    MutableSubtree subtree;
+    if (record.child_count > 0) {
+      SubtreeArray children = array_new();
+      array_extend(&children, record.child_count, &stack.contents[stack.size - record.child_count]);
+      stack.size -= record.child_count;
+      subtree = ts_subtree_new_node(record.symbol, &children, record.production_id, language);
+    } else if (record.symbol == ts_builtin_sym_error) {
+      subtree = ts_subtree_to_mut_unsafe(ts_subtree_new_error(
+        &pool, record.lookahead_char, record.padding, record.size,
+        record.lookahead_bytes, record.parse_state, language
+      ));
+    } else {
+      bool has_external_tokens = record.flags & SubtreeFlagHasExternalTokens;
+      subtree = ts_subtree_to_mut_unsafe(ts_subtree_new_leaf(
+        &pool, record.symbol, record.padding, record.size,
+        record.lookahead_bytes, record.parse_state, has_external_tokens,
+        record.flags & SubtreeFlagDependsOnColumn,
+        record.flags & SubtreeFlagIsKeyword, language
+      ));
+      if (has_external_tokens) {
+        ts_external_scanner_state_init(
+          &subtree.ptr->external_scanner_state,
+          record.external_scanner_state,
+          record.external_scanner_state_length
+        );
+      }
+    }
+    ts_serialization__restore_subtree(&subtree, &record);
+    array_push(&stack, ts_subtree_from_mut(subtree));
+  }
+
+  TSTree *result = NULL;
+  if (reader.valid && reader.offset == length && stack.size == 1) {
+    result = ts_tree_new(stack.contents[0], language, included_ranges.contents, included_ranges.size);
+    *error = TSTreeDeserializeErrorNone;
+  } else {
+    for (uint32_t i = 0; i < stack.size; i++) {
+      ts_subtree_release(&pool, stack.contents[i]);
+    }
+    *error = TSTreeDeserializeErrorFormat;
+  }
+
+  array_delete(&stack);
+  array_delete(&included_ranges);
+  ts_subtree_pool_delete(&pool);
+  return result;
+}
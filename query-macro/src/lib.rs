@@ -0,0 +1,337 @@
+#![doc = include_str!("../README.md")]
+
+mod node_types;
+mod query;
+
+use std::{collections::HashSet, env, fs, path::PathBuf};
+
+use node_types::NodeTypes;
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use query::{parse_query, Pattern, Quantifier};
+use quote::{format_ident, quote};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    parse_macro_input, Attribute, Error, Ident, LitStr, Token, Visibility,
+};
+use tree_sitter::RUST_KEYWORDS;
+
+struct QueryInput {
+    attrs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    node_types: LitStr,
+    query: LitStr,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![struct]>()?;
+        let name = input.parse::<Ident>()?;
+
+        let content;
+        braced!(content in input);
+        let mut node_types = None;
+        let mut query = None;
+        while !content.is_empty() {
+            let key = content.parse::<Ident>()?;
+            content.parse::<Token![:]>()?;
+            match key.to_string().as_str() {
+                "node_types" => node_types = Some(content.parse()?),
+                "query" => query = Some(content.parse()?),
+                _ => return Err(Error::new(key.span(), "expected `node_types` or `query`")),
+            }
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Self {
+            attrs,
+            vis,
+            node_types: node_types
+                .ok_or_else(|| Error::new(name.span(), "missing `node_types` path"))?,
+            query: query.ok_or_else(|| Error::new(name.span(), "missing `query` source"))?,
+            name,
+        })
+    }
+}
+
+/// Defines a struct that wraps a `tree_sitter::Query`, along with a typed struct for the
+/// captures of each of the query's patterns.
+///
+/// The query is checked at compile time against the language's `node-types.json` file,
+/// whose path is relative to the crate's manifest directory:
+///
+/// ```ignore
+/// tree_sitter_query_macro::query! {
+///     pub struct Calls {
+///         node_types: "src/node-types.json",
+///         query: "(call function: (identifier) @function arguments: (_)* @arguments)",
+///     }
+/// }
+///
+/// let calls = Calls::new(&language)?;
+/// let mut cursor = QueryCursor::new();
+/// for CallsMatch::Pattern0(call) in calls.matches(&mut cursor, tree.root_node(), source) {
+///     let function: Node = call.function;
+///     let arguments: Vec<Node> = call.arguments;
+/// }
+/// ```
+#[proc_macro]
+pub fn query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as QueryInput);
+    expand_query(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_query(input: &QueryInput) -> syn::Result<TokenStream2> {
+    let node_types_path = env::var_os("CARGO_MANIFEST_DIR")
+        .map_or_else(PathBuf::new, PathBuf::from)
+        .join(input.node_types.value());
+    let node_types_json = fs::read_to_string(&node_types_path).map_err(|e| {
+        Error::new(
+            input.node_types.span(),
+            format!("Failed to read {}: {e}", node_types_path.display()),
+        )
+    })?;
+    let node_types = NodeTypes::parse(&node_types_json).map_err(|e| {
+        Error::new(
+            input.node_types.span(),
+            format!("Failed to parse {}: {e}", node_types_path.display()),
+        )
+    })?;
+    let source = input.query.value();
+    let patterns = parse_query(&source, &node_types)
+        .map_err(|e| Error::new(input.query.span(), e.display(&source)))?;
+
+    let QueryInput {
+        attrs, vis, name, ..
+    } = input;
+    let match_name = format_ident!("{name}Match");
+    let node_types_path = node_types_path.to_string_lossy();
+
+    let mut capture_names = Vec::<&str>::new();
+    for pattern in &patterns {
+        for (capture_name, _) in &pattern.captures {
+            if !capture_names.contains(&capture_name.as_str()) {
+                capture_names.push(capture_name);
+            }
+        }
+    }
+    let capture_count = capture_names.len();
+
+    let mut pattern_structs = Vec::new();
+    let mut variants = Vec::new();
+    let mut match_arms = Vec::new();
+    let mut quantifiers = Vec::new();
+    for (pattern_index, pattern) in patterns.iter().enumerate() {
+        let pattern_name = format_ident!("{name}Pattern{pattern_index}");
+        let variant = format_ident!("Pattern{pattern_index}");
+        let (mut fields, mut values) =
+            pattern_fields(pattern_index, pattern, &capture_names, input.query.span())?;
+        for (capture_name, quantifier) in &pattern.captures {
+            let index = capture_names
+                .iter()
+                .position(|name| name == capture_name)
+                .unwrap();
+            let quantifier = format_ident!("{quantifier:?}");
+            quantifiers.push(quote! {
+                (#pattern_index, #index, #capture_name, ::tree_sitter::CaptureQuantifier::#quantifier)
+            });
+        }
+        if fields.is_empty() {
+            fields.push(quote! { _tree: ::core::marker::PhantomData<::tree_sitter::Node<'tree>> });
+            values.push(quote! { _tree: ::core::marker::PhantomData });
+        }
+        let doc = format!(" The captures of the pattern at index {pattern_index} of [`{name}`].");
+        pattern_structs.push(quote! {
+            #[doc = #doc]
+            #[derive(Clone, Debug)]
+            #vis struct #pattern_name<'tree> {
+                #(#fields,)*
+            }
+        });
+        match_arms.push(quote! {
+            #pattern_index => Some(#match_name::#variant(#pattern_name { #(#values,)* })),
+        });
+        variants.push(quote! { #variant(#pattern_name<'tree>) });
+    }
+
+    let quantifier_count = quantifiers.len();
+    let match_doc = format!(" A match of one of the patterns of [`{name}`].");
+    Ok(quote! {
+        #(#attrs)*
+        #vis struct #name {
+            query: ::tree_sitter::Query,
+            capture_indices: [u32; #capture_count],
+        }
+
+        impl #name {
+            /// The source of the query.
+            pub const SOURCE: &'static str = #source;
+
+            /// Creates the query for the given language.
+            ///
+            /// Returns an error if the captures of the query don't occur as many times
+            /// as they did when the query was checked against the node types, which
+            /// means that the node types are out of date.
+            pub fn new(
+                language: &::tree_sitter::Language,
+            ) -> ::core::result::Result<Self, ::tree_sitter::QueryError> {
+                // Rebuild the query when the node types change.
+                const _: &[u8] = include_bytes!(#node_types_path);
+                let query = ::tree_sitter::Query::new(language, Self::SOURCE)?;
+                let capture_indices = [#(
+                    query.capture_index_for_name(#capture_names).unwrap_or(u32::MAX)
+                ),*];
+                const QUANTIFIERS: [
+                    (usize, usize, &str, ::tree_sitter::CaptureQuantifier);
+                    #quantifier_count
+                ] = [#(#quantifiers),*];
+                for (pattern_index, index, capture_name, quantifier) in QUANTIFIERS {
+                    let query_quantifier = query
+                        .capture_quantifiers(pattern_index)
+                        .get(capture_indices[index] as usize)
+                        .copied()
+                        .unwrap_or(::tree_sitter::CaptureQuantifier::Zero);
+                    if query_quantifier != quantifier {
+                        let offset = query.start_byte_for_pattern(pattern_index);
+                        let before = &Self::SOURCE[..offset];
+                        return Err(::tree_sitter::QueryError {
+                            row: before.matches('\n').count(),
+                            column: offset - before.rfind('\n').map_or(0, |i| i + 1),
+                            offset,
+                            message: format!(
+                                "{capture_name}, which occurs {query_quantifier:?} in pattern \
+                                 {pattern_index}, instead of {quantifier:?}"
+                            ),
+                            kind: ::tree_sitter::QueryErrorKind::Capture,
+                        });
+                    }
+                }
+                Ok(Self {
+                    query,
+                    capture_indices,
+                })
+            }
+
+            /// Returns the underlying query.
+            pub fn query(&self) -> &::tree_sitter::Query {
+                &self.query
+            }
+
+            /// Returns the typed captures of the given match of this query.
+            pub fn get_match<'tree>(
+                &self,
+                query_match: &::tree_sitter::QueryMatch<'_, 'tree>,
+            ) -> Option<#match_name<'tree>> {
+                match query_match.pattern_index {
+                    #(#match_arms)*
+                    _ => None,
+                }
+            }
+
+            /// Iterates over the typed matches of this query within the given node.
+            pub fn matches<'query, 'tree: 'query, T, I>(
+                &'query self,
+                cursor: &'query mut ::tree_sitter::QueryCursor,
+                node: ::tree_sitter::Node<'tree>,
+                text_provider: T,
+            ) -> impl Iterator<Item = #match_name<'tree>> + 'query
+            where
+                T: ::tree_sitter::TextProvider<I> + 'query,
+                I: AsRef<[u8]> + 'query,
+            {
+                cursor
+                    .matches(&self.query, node, text_provider)
+                    .filter_map(move |query_match| self.get_match(&query_match))
+            }
+        }
+
+        #[doc = #match_doc]
+        #[derive(Clone, Debug)]
+        #vis enum #match_name<'tree> {
+            #(#variants,)*
+        }
+
+        #(#pattern_structs)*
+    })
+}
+
+/// Returns the fields of the struct for the given pattern, along with the expressions
+/// that compute those fields from a `QueryMatch`.
+fn pattern_fields(
+    pattern_index: usize,
+    pattern: &Pattern,
+    capture_names: &[&str],
+    span: Span,
+) -> syn::Result<(Vec<TokenStream2>, Vec<TokenStream2>)> {
+    let mut fields = Vec::new();
+    let mut values = Vec::new();
+    let mut field_names = HashSet::new();
+    for (capture_name, quantifier) in &pattern.captures {
+        let field_name = field_name(capture_name);
+        if !field_names.insert(field_name.to_string()) {
+            return Err(Error::new(
+                span,
+                format!("Capture @{capture_name} has the same field name as another capture"),
+            ));
+        }
+        let index = capture_names
+            .iter()
+            .position(|name| name == capture_name)
+            .unwrap();
+        let nodes = quote! {
+            query_match.nodes_for_capture_index(self.capture_indices[#index])
+        };
+        let doc = format!(" The nodes captured by `@{capture_name}`.");
+        // `new` checks that the capture always occurs in matches of the pattern.
+        let missing = format!("A match of pattern {pattern_index} has no @{capture_name} capture");
+        let (field_type, value) = match quantifier {
+            Quantifier::One => (
+                quote! { ::tree_sitter::Node<'tree> },
+                quote! { #nodes.next().expect(#missing) },
+            ),
+            Quantifier::ZeroOrOne => (
+                quote! { Option<::tree_sitter::Node<'tree>> },
+                quote! { #nodes.next() },
+            ),
+            Quantifier::ZeroOrMore | Quantifier::OneOrMore => (
+                quote! { Vec<::tree_sitter::Node<'tree>> },
+                quote! { #nodes.collect() },
+            ),
+            Quantifier::Zero => continue,
+        };
+        fields.push(quote! {
+            #[doc = #doc]
+            pub #field_name: #field_type
+        });
+        values.push(quote! { #field_name: #value });
+    }
+    Ok((fields, values))
+}
+
+/// Converts a capture name such as `definition.function` into a field name such as
+/// `definition_function`.
+fn field_name(capture_name: &str) -> Ident {
+    let mut name = capture_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if name.starts_with(|c: char| c.is_ascii_digit()) || name == "_" {
+        name.insert(0, '_');
+    }
+    if matches!(name.as_str(), "crate" | "self" | "Self" | "super") {
+        name.push('_');
+    }
+    if RUST_KEYWORDS.contains(&name.as_str()) {
+        Ident::new_raw(&name, Span::call_site())
+    } else {
+        Ident::new(&name, Span::call_site())
+    }
+}
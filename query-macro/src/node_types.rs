@@ -0,0 +1,47 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct NodeInfoJSON {
+    #[serde(rename = "type")]
+    kind: String,
+    named: bool,
+    #[serde(default)]
+    fields: BTreeMap<String, serde_json::Value>,
+}
+
+/// The node types and field names of a language, as listed in its `node-types.json` file.
+#[derive(Default)]
+pub struct NodeTypes {
+    named_nodes: HashSet<String>,
+    anonymous_nodes: HashSet<String>,
+    fields: HashSet<String>,
+}
+
+impl NodeTypes {
+    pub fn parse(json: &str) -> serde_json::Result<Self> {
+        let mut result = Self::default();
+        for node in serde_json::from_str::<Vec<NodeInfoJSON>>(json)? {
+            result.fields.extend(node.fields.into_keys());
+            if node.named {
+                result.named_nodes.insert(node.kind);
+            } else {
+                result.anonymous_nodes.insert(node.kind);
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn has_named_node(&self, kind: &str) -> bool {
+        kind == "ERROR" || self.named_nodes.contains(kind)
+    }
+
+    pub fn has_anonymous_node(&self, kind: &str) -> bool {
+        self.anonymous_nodes.contains(kind)
+    }
+
+    pub fn has_field(&self, name: &str) -> bool {
+        self.fields.contains(name)
+    }
+}
@@ -0,0 +1,613 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::node_types::NodeTypes;
+
+/// The number of times that a capture can occur in a single match of a pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantifier {
+    Zero,
+    ZeroOrOne,
+    ZeroOrMore,
+    One,
+    OneOrMore,
+}
+
+/// The captures of a single pattern, in the order in which they first appear.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Pattern {
+    pub captures: Vec<(String, Quantifier)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum QueryErrorKind {
+    Syntax,
+    NodeType,
+    Field,
+    Capture,
+}
+
+/// An error in a query, at a given byte offset. These mirror the errors that
+/// `tree_sitter::Query::new` would report for the same query.
+#[derive(Debug, PartialEq, Eq)]
+pub struct QueryError {
+    pub offset: usize,
+    pub kind: QueryErrorKind,
+    pub message: String,
+}
+
+/// The quantifiers of the captures within a part of a pattern, keyed by capture name.
+/// Captures that don't appear in that part have the quantifier `Zero`.
+#[derive(Clone, Default)]
+struct CaptureQuantifiers(BTreeMap<String, Quantifier>);
+
+struct Parser<'a> {
+    source: &'a str,
+    offset: usize,
+    node_types: &'a NodeTypes,
+    capture_names: Vec<String>,
+    pattern_capture_names: Vec<String>,
+}
+
+/// Parses a query, checking its node types and fields against the given node types, and
+/// returns the captures of each of its patterns.
+///
+/// This follows the structure of `ts_query__parse_pattern` in the runtime, so that the
+/// quantifiers of the captures match those that the runtime computes.
+pub fn parse_query(source: &str, node_types: &NodeTypes) -> Result<Vec<Pattern>, QueryError> {
+    let mut parser = Parser {
+        source,
+        offset: 0,
+        node_types,
+        capture_names: Vec::new(),
+        pattern_capture_names: Vec::new(),
+    };
+    let mut patterns = Vec::new();
+    parser.skip_whitespace();
+    while parser.offset < source.len() {
+        let mut quantifiers = CaptureQuantifiers::default();
+        match parser.parse_pattern(&mut quantifiers) {
+            Ok(true) => {}
+            Ok(false) => return Err(parser.error(QueryErrorKind::Syntax, parser.offset)),
+            Err(error) => return Err(error),
+        }
+        let captures = std::mem::take(&mut parser.pattern_capture_names)
+            .into_iter()
+            .filter_map(|name| {
+                let quantifier = quantifiers.get(&name);
+                (quantifier != Quantifier::Zero).then_some((name, quantifier))
+            })
+            .collect();
+        patterns.push(Pattern { captures });
+    }
+    Ok(patterns)
+}
+
+impl<'a> Parser<'a> {
+    fn next(&self) -> Option<char> {
+        self.source[self.offset..].chars().next()
+    }
+
+    fn advance(&mut self) {
+        if let Some(c) = self.next() {
+            self.offset += c.len_utf8();
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.next() {
+            if c.is_whitespace() {
+                self.advance();
+            } else if c == ';' {
+                while self.next().is_some_and(|c| c != '\n') {
+                    self.advance();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn is_identifier_start(&self) -> bool {
+        self.next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    }
+
+    fn scan_identifier(&mut self) -> &'a str {
+        let start = self.offset;
+        self.advance();
+        while self
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '?' | '!'))
+        {
+            self.advance();
+        }
+        &self.source[start..self.offset]
+    }
+
+    fn parse_string_literal(&mut self) -> Result<String, QueryError> {
+        let start = self.offset;
+        let mut result = String::new();
+        self.advance();
+        loop {
+            match self.next() {
+                Some('\\') => {
+                    self.advance();
+                    match self.next() {
+                        Some('n') => result.push('\n'),
+                        Some('r') => result.push('\r'),
+                        Some('t') => result.push('\t'),
+                        Some('0') => result.push('\0'),
+                        Some(c) => result.push(c),
+                        None => return Err(self.error(QueryErrorKind::Syntax, start)),
+                    }
+                }
+                Some('"') => {
+                    self.advance();
+                    return Ok(result);
+                }
+                Some('\n') | None => return Err(self.error(QueryErrorKind::Syntax, start)),
+                Some(c) => result.push(c),
+            }
+            self.advance();
+        }
+    }
+
+    fn parse_predicate(&mut self) -> Result<(), QueryError> {
+        if !self.is_identifier_start() {
+            return Err(self.error(QueryErrorKind::Syntax, self.offset));
+        }
+        self.scan_identifier();
+        self.skip_whitespace();
+        loop {
+            match self.next() {
+                Some(')') => {
+                    self.advance();
+                    self.skip_whitespace();
+                    return Ok(());
+                }
+                Some('@') => {
+                    self.advance();
+                    if !self.is_identifier_start() {
+                        return Err(self.error(QueryErrorKind::Syntax, self.offset));
+                    }
+                    let start = self.offset;
+                    let name = self.scan_identifier();
+                    if !self.capture_names.iter().any(|n| n == name) {
+                        return Err(self.error(QueryErrorKind::Capture, start));
+                    }
+                }
+                Some('"') => {
+                    self.parse_string_literal()?;
+                }
+                _ if self.is_identifier_start() => {
+                    self.scan_identifier();
+                }
+                _ => return Err(self.error(QueryErrorKind::Syntax, self.offset)),
+            }
+            self.skip_whitespace();
+        }
+    }
+
+    /// Parses a single pattern, adding the quantifiers of its captures to the given
+    /// quantifiers. Returns `false` if the parent pattern ends before this pattern.
+    fn parse_pattern(&mut self, quantifiers: &mut CaptureQuantifiers) -> Result<bool, QueryError> {
+        let Some(next) = self.next() else {
+            return Err(self.error(QueryErrorKind::Syntax, self.offset));
+        };
+        if next == ')' || next == ']' {
+            return Ok(false);
+        }
+
+        // An alternation. The quantifiers of the branches are joined.
+        if next == '[' {
+            self.advance();
+            self.skip_whitespace();
+            let mut branch_count = 0;
+            loop {
+                let mut branch_quantifiers = CaptureQuantifiers::default();
+                if !self.parse_pattern(&mut branch_quantifiers)? {
+                    if self.next() == Some(']') && branch_count > 0 {
+                        self.advance();
+                        break;
+                    }
+                    return Err(self.error(QueryErrorKind::Syntax, self.offset));
+                }
+                if branch_count == 0 {
+                    *quantifiers = branch_quantifiers;
+                } else {
+                    quantifiers.join_all(&branch_quantifiers);
+                }
+                branch_count += 1;
+            }
+        }
+        // A grouped sequence, a predicate, or a named node.
+        else if next == '(' {
+            self.advance();
+            self.skip_whitespace();
+            match self.next() {
+                Some('(' | '"' | '[') => loop {
+                    if self.next() == Some('.') {
+                        self.advance();
+                        self.skip_whitespace();
+                    }
+                    let mut child_quantifiers = CaptureQuantifiers::default();
+                    if !self.parse_pattern(&mut child_quantifiers)? {
+                        if self.next() == Some(')') {
+                            self.advance();
+                            break;
+                        }
+                        return Err(self.error(QueryErrorKind::Syntax, self.offset));
+                    }
+                    quantifiers.add_all(&child_quantifiers);
+                },
+                Some('.' | '#') => {
+                    self.advance();
+                    self.parse_predicate()?;
+                    return Ok(true);
+                }
+                _ => self.parse_named_node(quantifiers)?,
+            }
+        }
+        // A wildcard.
+        else if next == '_' {
+            self.advance();
+        }
+        // An anonymous node.
+        else if next == '"' {
+            let start = self.offset;
+            let kind = self.parse_string_literal()?;
+            if !self.node_types.has_anonymous_node(&kind) {
+                return Err(QueryError {
+                    message: kind,
+                    ..self.error(QueryErrorKind::NodeType, start + 1)
+                });
+            }
+        }
+        // A field-prefixed pattern.
+        else if self.is_identifier_start() {
+            let start = self.offset;
+            let field_name = self.scan_identifier();
+            self.skip_whitespace();
+            if self.next() != Some(':') {
+                return Err(self.error(QueryErrorKind::Syntax, start));
+            }
+            self.advance();
+            self.skip_whitespace();
+            let mut field_quantifiers = CaptureQuantifiers::default();
+            if !self.parse_pattern(&mut field_quantifiers)? {
+                return Err(self.error(QueryErrorKind::Syntax, self.offset));
+            }
+            if !self.node_types.has_field(field_name) {
+                return Err(self.error(QueryErrorKind::Field, start));
+            }
+            quantifiers.add_all(&field_quantifiers);
+        } else {
+            return Err(self.error(QueryErrorKind::Syntax, self.offset));
+        }
+
+        self.skip_whitespace();
+
+        // Parse the suffixes of the pattern.
+        let mut quantifier = Quantifier::One;
+        loop {
+            match self.next() {
+                Some('+') => quantifier = Quantifier::OneOrMore.join(quantifier),
+                Some('*') => quantifier = Quantifier::ZeroOrMore.join(quantifier),
+                Some('?') => quantifier = Quantifier::ZeroOrOne.join(quantifier),
+                Some('@') => {
+                    self.advance();
+                    if !self.is_identifier_start() {
+                        return Err(self.error(QueryErrorKind::Syntax, self.offset));
+                    }
+                    let name = self.scan_identifier();
+                    if !self.capture_names.iter().any(|n| n == name) {
+                        self.capture_names.push(name.to_string());
+                    }
+                    if !self.pattern_capture_names.iter().any(|n| n == name) {
+                        self.pattern_capture_names.push(name.to_string());
+                    }
+                    quantifiers.add(name, Quantifier::One);
+                    self.skip_whitespace();
+                    continue;
+                }
+                _ => break,
+            }
+            self.advance();
+            self.skip_whitespace();
+        }
+        quantifiers.mul(quantifier);
+        Ok(true)
+    }
+
+    fn parse_named_node(&mut self, quantifiers: &mut CaptureQuantifiers) -> Result<(), QueryError> {
+        if !self.is_identifier_start() {
+            return Err(self.error(QueryErrorKind::Syntax, self.offset));
+        }
+        let start = self.offset;
+        let kind = self.scan_identifier();
+        if kind != "_" && !self.node_types.has_named_node(kind) {
+            return Err(self.error(QueryErrorKind::NodeType, start));
+        }
+        self.skip_whitespace();
+
+        if self.next() == Some('/') {
+            self.advance();
+            if !self.is_identifier_start() {
+                return Err(self.error(QueryErrorKind::Syntax, self.offset));
+            }
+            let start = self.offset;
+            let subtype = self.scan_identifier();
+            if !self.node_types.has_named_node(subtype) {
+                return Err(self.error(QueryErrorKind::NodeType, start));
+            }
+            self.skip_whitespace();
+        }
+
+        let mut child_is_immediate = false;
+        let mut has_children = false;
+        loop {
+            if self.next() == Some('!') {
+                self.advance();
+                self.skip_whitespace();
+                if !self.is_identifier_start() {
+                    return Err(self.error(QueryErrorKind::Syntax, self.offset));
+                }
+                let start = self.offset;
+                let field_name = self.scan_identifier();
+                self.skip_whitespace();
+                if !self.node_types.has_field(field_name) {
+                    return Err(self.error(QueryErrorKind::Field, start));
+                }
+                continue;
+            }
+
+            if self.next() == Some('.') {
+                child_is_immediate = true;
+                self.advance();
+                self.skip_whitespace();
+            }
+
+            let mut child_quantifiers = CaptureQuantifiers::default();
+            if !self.parse_pattern(&mut child_quantifiers)? {
+                if self.next() == Some(')') {
+                    if child_is_immediate && !has_children {
+                        return Err(self.error(QueryErrorKind::Syntax, self.offset));
+                    }
+                    self.advance();
+                    return Ok(());
+                }
+                return Err(self.error(QueryErrorKind::Syntax, self.offset));
+            }
+            quantifiers.add_all(&child_quantifiers);
+            has_children = true;
+            child_is_immediate = false;
+        }
+    }
+
+    fn error(&self, kind: QueryErrorKind, offset: usize) -> QueryError {
+        let message = match kind {
+            QueryErrorKind::Syntax => {
+                let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+                self.source[line_start..].lines().next().map_or_else(
+                    || "Unexpected EOF".to_string(),
+                    |line| format!("{line}\n{}^", " ".repeat(offset - line_start)),
+                )
+            }
+            _ => {
+                let mut parser = Parser {
+                    source: self.source,
+                    offset,
+                    node_types: self.node_types,
+                    capture_names: Vec::new(),
+                    pattern_capture_names: Vec::new(),
+                };
+                parser.scan_identifier().to_string()
+            }
+        };
+        QueryError {
+            offset,
+            kind,
+            message,
+        }
+    }
+}
+
+impl QueryError {
+    /// Formats the error in the same way as `tree_sitter::QueryError`.
+    pub fn display(&self, source: &str) -> String {
+        let before = &source[..self.offset];
+        let row = before.matches('\n').count();
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+        format!("Query error at {}:{}. {self}", row + 1, column + 1)
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let prefix = match self.kind {
+            QueryErrorKind::Syntax => "Invalid syntax:\n",
+            QueryErrorKind::NodeType => "Invalid node type ",
+            QueryErrorKind::Field => "Invalid field name ",
+            QueryErrorKind::Capture => "Invalid capture name ",
+        };
+        write!(f, "{prefix}{}", self.message)
+    }
+}
+
+impl CaptureQuantifiers {
+    fn get(&self, name: &str) -> Quantifier {
+        self.0.get(name).copied().unwrap_or(Quantifier::Zero)
+    }
+
+    fn add(&mut self, name: &str, quantifier: Quantifier) {
+        let own = self.get(name);
+        self.0.insert(name.to_string(), own.add(quantifier));
+    }
+
+    fn add_all(&mut self, other: &Self) {
+        for (name, quantifier) in &other.0 {
+            self.add(name, *quantifier);
+        }
+    }
+
+    fn join_all(&mut self, other: &Self) {
+        let names = self
+            .0
+            .keys()
+            .chain(other.0.keys())
+            .cloned()
+            .collect::<Vec<_>>();
+        for name in names {
+            let quantifier = self.get(&name).join(other.get(&name));
+            self.0.insert(name, quantifier);
+        }
+    }
+
+    fn mul(&mut self, quantifier: Quantifier) {
+        for own in self.0.values_mut() {
+            *own = own.mul(quantifier);
+        }
+    }
+}
+
+impl Quantifier {
+    /// The quantifier of a capture within a repeated pattern.
+    const fn mul(self, other: Self) -> Self {
+        use Quantifier::{One, OneOrMore, Zero, ZeroOrMore, ZeroOrOne};
+        match (self, other) {
+            (Zero, _) | (_, Zero) => Zero,
+            (One, other) => other,
+            (ZeroOrOne, ZeroOrOne | One) => ZeroOrOne,
+            (OneOrMore, One | OneOrMore) => OneOrMore,
+            _ => ZeroOrMore,
+        }
+    }
+
+    /// The quantifier of a capture that appears in one of two alternatives.
+    const fn join(self, other: Self) -> Self {
+        use Quantifier::{One, OneOrMore, Zero, ZeroOrMore, ZeroOrOne};
+        match (self, other) {
+            (Zero, Zero) => Zero,
+            (One, One) => One,
+            (One | OneOrMore, One | OneOrMore) => OneOrMore,
+            (Zero | ZeroOrOne | One, Zero | ZeroOrOne | One) => ZeroOrOne,
+            _ => ZeroOrMore,
+        }
+    }
+
+    /// The quantifier of a capture that appears in two parts of a sequence.
+    const fn add(self, other: Self) -> Self {
+        use Quantifier::{OneOrMore, Zero, ZeroOrMore, ZeroOrOne};
+        match (self, other) {
+            (Zero, other) => other,
+            (own, Zero) => own,
+            (ZeroOrOne | ZeroOrMore, ZeroOrOne | ZeroOrMore) => ZeroOrMore,
+            _ => OneOrMore,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE_TYPES: &str = r#"[
+        {
+            "type": "expression",
+            "named": true,
+            "subtypes": [{"type": "call", "named": true}, {"type": "identifier", "named": true}]
+        },
+        {
+            "type": "call",
+            "named": true,
+            "fields": {
+                "function": {"multiple": false, "required": true, "types": [{"type": "identifier", "named": true}]},
+                "argument": {"multiple": true, "required": false, "types": [{"type": "expression", "named": true}]}
+            }
+        },
+        {"type": "identifier", "named": true},
+        {"type": "(", "named": false},
+        {"type": ")", "named": false}
+    ]"#;
+
+    #[test]
+    fn test_capture_quantifiers() {
+        let node_types = NodeTypes::parse(NODE_TYPES).unwrap();
+        let patterns = parse_query(
+            r#"
+            ; A comment
+            (call function: (identifier) @function argument: (_)* @argument) @call
+            (call argument: (identifier)? @first . argument: (_) @second)
+            [(identifier) @name (call function: (_) @name)]
+            [(identifier) @name (call) @call]
+            ((call "(" @open (identifier)+ @name) (#eq? @name "x"))
+            (expression/identifier) @name @other
+            "#,
+            &node_types,
+        )
+        .unwrap();
+        assert_eq!(
+            patterns,
+            [
+                vec![
+                    ("function", Quantifier::One),
+                    ("argument", Quantifier::ZeroOrMore),
+                    ("call", Quantifier::One),
+                ],
+                vec![
+                    ("first", Quantifier::ZeroOrOne),
+                    ("second", Quantifier::One)
+                ],
+                vec![("name", Quantifier::One)],
+                vec![
+                    ("name", Quantifier::ZeroOrOne),
+                    ("call", Quantifier::ZeroOrOne)
+                ],
+                vec![("open", Quantifier::One), ("name", Quantifier::OneOrMore)],
+                vec![("name", Quantifier::One), ("other", Quantifier::One)],
+            ]
+            .into_iter()
+            .map(|captures| Pattern {
+                captures: captures
+                    .into_iter()
+                    .map(|(name, quantifier)| (name.to_string(), quantifier))
+                    .collect()
+            })
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_query_errors() {
+        let node_types = NodeTypes::parse(NODE_TYPES).unwrap();
+        let error = |source| {
+            parse_query(source, &node_types)
+                .unwrap_err()
+                .display(source)
+        };
+        assert_eq!(
+            error("(call)\n(statement)"),
+            "Query error at 2:2. Invalid node type statement"
+        );
+        assert_eq!(
+            error("(call body: (_))"),
+            "Query error at 1:7. Invalid field name body"
+        );
+        assert_eq!(
+            error("(call \"+\")"),
+            "Query error at 1:8. Invalid node type +"
+        );
+        assert_eq!(
+            error("((call) @a (#eq? @b \"x\"))"),
+            "Query error at 1:19. Invalid capture name b"
+        );
+        assert_eq!(
+            error("(call (identifier)"),
+            "Query error at 1:19. Invalid syntax:\n(call (identifier)\n                  ^"
+        );
+        assert_eq!(
+            error("(call (identifier) @)"),
+            "Query error at 1:21. Invalid syntax:\n(call (identifier) @)\n                    ^"
+        );
+    }
+}
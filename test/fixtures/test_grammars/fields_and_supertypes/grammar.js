@@ -0,0 +1,45 @@
+module.exports = grammar({
+  name: 'fields_and_supertypes',
+
+  extras: $ => [
+    /\s/,
+    $.comment,
+  ],
+
+  supertypes: $ => [
+    $._expression,
+  ],
+
+  rules: {
+    program: $ => repeat($.assignment),
+
+    assignment: $ => seq(
+      field('left', $.identifier),
+      '=',
+      field('right', $._expression),
+      ';',
+    ),
+
+    _expression: $ => choice(
+      $.identifier,
+      $.number,
+      $.call,
+    ),
+
+    call: $ => seq(
+      field('function', $.identifier),
+      '(',
+      optional(seq(
+        field('argument', $._expression),
+        repeat(seq(',', field('argument', $._expression))),
+      )),
+      ')',
+    ),
+
+    identifier: _ => /[a-z]+/,
+
+    number: _ => /\d+/,
+
+    comment: _ => /#.*/,
+  },
+});
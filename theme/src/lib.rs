@@ -0,0 +1,280 @@
+#![doc = include_str!("../README.md")]
+
+mod plist;
+mod textmate;
+
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::{ser::SerializeMap, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+pub use tree_sitter_highlight::{Color, Style};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid property list: {0}")]
+    InvalidPlist(String),
+    #[error("Invalid theme: {0}")]
+    InvalidTheme(String),
+}
+
+/// The styles that are used to render each highlight name.
+///
+/// In JSON, a theme is an object whose keys are highlight names. Each value is
+/// a color, or an object with an optional `color` and optional `bold`,
+/// `italic`, and `underline` flags. A color is either an index in the xterm
+/// 256-color palette, a hexadecimal string such as `#5f87af`, or the name of
+/// one of the first eight colors of the palette.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub styles: Vec<Style>,
+    pub highlight_names: Vec<String>,
+}
+
+impl Theme {
+    /// Load a theme from a file, which is either a theme in the format above,
+    /// a VS Code color theme, or a TextMate `.tmTheme` file.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)?;
+        let is_tmtheme = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("tmTheme"))
+            || contents.trim_start().starts_with('<');
+        if is_tmtheme {
+            Self::from_tmtheme(&contents)
+        } else {
+            Self::from_json(&contents)
+        }
+    }
+
+    /// Parse a theme from JSON, which is either a theme in the format above or
+    /// a VS Code color theme. Comments and trailing commas are allowed, as
+    /// they are in VS Code.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let value = serde_json::from_str::<Value>(&strip_json_comments(json))?;
+        if value.get("tokenColors").is_some() {
+            textmate::theme_from_vscode(&value)
+        } else if value.is_object() {
+            Ok(serde_json::from_value(value)?)
+        } else {
+            Err(Error::InvalidTheme("expected an object".to_string()))
+        }
+    }
+
+    /// Parse a TextMate theme, which is an XML property list.
+    ///
+    /// The theme's rules are matched against the TextMate scopes that
+    /// correspond to the standard highlight names, such as
+    /// `entity.name.function` for `function`. When no rule matches a highlight
+    /// name, the scopes of its dot-separated prefixes are used instead.
+    pub fn from_tmtheme(xml: &str) -> Result<Self, Error> {
+        textmate::theme_from_tmtheme(xml)
+    }
+
+    /// Get the style of a highlight name, falling back to the style of its
+    /// longest dot-separated prefix that has one.
+    #[must_use]
+    pub fn style_for_highlight_name(&self, name: &str) -> Option<&Style> {
+        let mut name = name;
+        loop {
+            if let Some(index) = self.highlight_names.iter().position(|n| n == name) {
+                return self.styles.get(index);
+            }
+            name = &name[..name.rfind('.')?];
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Theme {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut styles = Vec::new();
+        let mut highlight_names = Vec::new();
+        if let Ok(colors) = HashMap::<String, Value>::deserialize(deserializer) {
+            highlight_names.reserve(colors.len());
+            styles.reserve(colors.len());
+            for (name, style_value) in colors {
+                highlight_names.push(name);
+                styles.push(parse_style(style_value));
+            }
+        }
+        Ok(Self {
+            styles,
+            highlight_names,
+        })
+    }
+}
+
+impl Serialize for Theme {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.styles.len()))?;
+        for (name, style) in self.highlight_names.iter().zip(&self.styles) {
+            let color = style.color.map(|color| match color {
+                Color::Ansi256(n) => json!(n),
+                Color::Rgb(..) => json!(color.to_hex()),
+            });
+            if style.bold || style.italic || style.underline {
+                let mut style_json = Map::new();
+                if let Some(color) = color {
+                    style_json.insert("color".to_string(), color);
+                }
+                if style.bold {
+                    style_json.insert("bold".to_string(), Value::Bool(true));
+                }
+                if style.italic {
+                    style_json.insert("italic".to_string(), Value::Bool(true));
+                }
+                if style.underline {
+                    style_json.insert("underline".to_string(), Value::Bool(true));
+                }
+                map.serialize_entry(&name, &style_json)?;
+            } else if let Some(color) = color {
+                map.serialize_entry(&name, &color)?;
+            } else {
+                map.serialize_entry(&name, &Value::Null)?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        serde_json::from_value(json!({
+              "attribute": {"color": 124, "italic": true},
+              "comment": {"color": 245, "italic": true},
+              "constant.builtin": {"color": 94, "bold": true},
+              "constant": 94,
+              "constructor": 136,
+              "embedded": null,
+              "function.builtin": {"color": 26, "bold": true},
+              "function": 26,
+              "keyword": 56,
+              "number": {"color": 94, "bold": true},
+              "module": 136,
+              "property": 124,
+              "operator": {"color": 239, "bold": true},
+              "punctuation.bracket": 239,
+              "punctuation.delimiter": 239,
+              "string.special": 30,
+              "string": 28,
+              "tag": 18,
+              "type": 23,
+              "type.builtin": {"color": 23, "bold": true},
+              "variable.builtin": {"bold": true},
+              "variable.parameter": {"underline": true}
+        }))
+        .unwrap()
+    }
+}
+
+fn parse_style(json: Value) -> Style {
+    let mut style = Style::default();
+    if let Value::Object(entries) = json {
+        for (property_name, value) in entries {
+            match property_name.as_str() {
+                "bold" => style.bold = value == Value::Bool(true),
+                "italic" => style.italic = value == Value::Bool(true),
+                "underline" => style.underline = value == Value::Bool(true),
+                "color" => style.color = parse_color(&value),
+                _ => {}
+            }
+        }
+    } else {
+        style.color = parse_color(&json);
+    }
+    style
+}
+
+fn parse_color(json: &Value) -> Option<Color> {
+    match json {
+        Value::Number(n) => n.as_u64().map(|n| Color::Ansi256(n as u8)),
+        Value::String(s) => match s.to_lowercase().as_str() {
+            "black" => Some(Color::Ansi256(0)),
+            "red" => Some(Color::Ansi256(1)),
+            "green" => Some(Color::Ansi256(2)),
+            "yellow" => Some(Color::Ansi256(3)),
+            "blue" => Some(Color::Ansi256(4)),
+            "purple" => Some(Color::Ansi256(5)),
+            "cyan" => Some(Color::Ansi256(6)),
+            "white" => Some(Color::Ansi256(7)),
+            s => textmate::parse_color(s),
+        },
+        _ => None,
+    }
+}
+
+// Remove the comments and trailing commas that VS Code allows in its JSON
+// files, leaving the contents of strings alone.
+fn strip_json_comments(json: &str) -> String {
+    let mut result = String::with_capacity(json.len());
+    let mut chars = json.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                result.push(c);
+                while let Some(c) = chars.next() {
+                    result.push(c);
+                    match c {
+                        '\\' => result.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|c| *c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                result.push(' ');
+            }
+            _ => result.push(c),
+        }
+    }
+
+    // Remove the commas that are followed only by whitespace before the end of
+    // an object or array.
+    let mut in_string = false;
+    let mut is_escaped = false;
+    let mut last_comma = None;
+    let mut output = String::with_capacity(result.len());
+    for c in result.chars() {
+        if in_string {
+            if is_escaped {
+                is_escaped = false;
+            } else if c == '\\' {
+                is_escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if !c.is_whitespace() {
+            if let Some(index) = last_comma.take() {
+                if c == '}' || c == ']' {
+                    output.remove(index);
+                }
+            }
+            match c {
+                ',' => last_comma = Some(output.len()),
+                '"' => in_string = true,
+                _ => {}
+            }
+        }
+        output.push(c);
+    }
+    output
+}
@@ -0,0 +1,249 @@
+use crate::Error;
+
+/// A value in an XML property list, which is the format of TextMate themes.
+/// Numbers, dates, and data are kept as strings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value {
+    String(String),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Dictionary(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(crate) fn get(&self, key: &str) -> Option<&Self> {
+        match self {
+            Self::Dictionary(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Self]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Parse the XML form of a property list. Only the parts of XML that property
+/// lists use are supported: elements without attributes other than the root,
+/// character references, CDATA sections, comments, and the prolog.
+pub(crate) fn parse(xml: &str) -> Result<Value, Error> {
+    let mut parser = Parser {
+        input: xml,
+        offset: 0,
+    };
+    parser.skip_misc();
+    let result = match parser.read_tag()? {
+        Tag::Start(name) if name == "plist" => {
+            let value = parser.read_value()?;
+            parser.expect_end_tag("plist")?;
+            value
+        }
+        // Tolerate documents that omit the `plist` element.
+        Tag::Start(name) => parser.read_value_with_tag(&name)?,
+        Tag::Empty(name) => parser.read_value_with_tag(&format!("{name}/"))?,
+        Tag::End(name) => return Err(parser.error(&format!("unexpected </{name}>"))),
+    };
+    parser.skip_misc();
+    if parser.offset < parser.input.len() {
+        return Err(parser.error("unexpected content after the root element"));
+    }
+    Ok(result)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    offset: usize,
+}
+
+enum Tag {
+    Start(String),
+    End(String),
+    Empty(String),
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.offset..]
+    }
+
+    fn error(&self, message: &str) -> Error {
+        let row = self.input[..self.offset].matches('\n').count() + 1;
+        Error::InvalidPlist(format!("{message} at line {row}"))
+    }
+
+    // Skip whitespace, comments, processing instructions, and the document
+    // type declaration.
+    fn skip_misc(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.offset += rest.len() - trimmed.len();
+            let terminator = if trimmed.starts_with("<!--") {
+                "-->"
+            } else if trimmed.starts_with("<?") {
+                "?>"
+            } else if trimmed.starts_with("<!") {
+                ">"
+            } else {
+                return;
+            };
+            match trimmed.find(terminator) {
+                Some(index) => self.offset += index + terminator.len(),
+                None => self.offset = self.input.len(),
+            }
+        }
+    }
+
+    fn read_tag(&mut self) -> Result<Tag, Error> {
+        let rest = self.rest();
+        if !rest.starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        let Some(end) = rest.find('>') else {
+            return Err(self.error("unterminated element"));
+        };
+        let content = &rest[1..end];
+        self.offset += end + 1;
+        if let Some(name) = content.strip_prefix('/') {
+            return Ok(Tag::End(name.trim().to_string()));
+        }
+        let (content, is_empty) = match content.strip_suffix('/') {
+            Some(content) => (content, true),
+            None => (content, false),
+        };
+        let name = content.split_whitespace().next().unwrap_or("").to_string();
+        Ok(if is_empty {
+            Tag::Empty(name)
+        } else {
+            Tag::Start(name)
+        })
+    }
+
+    fn expect_end_tag(&mut self, name: &str) -> Result<(), Error> {
+        self.skip_misc();
+        match self.read_tag()? {
+            Tag::End(end_name) if end_name == name => Ok(()),
+            _ => Err(self.error(&format!("expected </{name}>"))),
+        }
+    }
+
+    fn read_value(&mut self) -> Result<Value, Error> {
+        self.skip_misc();
+        match self.read_tag()? {
+            Tag::Start(name) => self.read_value_with_tag(&name),
+            Tag::Empty(name) => self.read_value_with_tag(&format!("{name}/")),
+            Tag::End(name) => Err(self.error(&format!("unexpected </{name}>"))),
+        }
+    }
+
+    // Read the rest of a value whose start tag has been read. Empty elements
+    // are identified by a trailing slash.
+    fn read_value_with_tag(&mut self, tag: &str) -> Result<Value, Error> {
+        match tag {
+            "true/" => Ok(Value::Boolean(true)),
+            "false/" => Ok(Value::Boolean(false)),
+            "dict/" => Ok(Value::Dictionary(Vec::new())),
+            "array/" => Ok(Value::Array(Vec::new())),
+            "string/" | "key/" | "data/" => Ok(Value::String(String::new())),
+            "dict" => {
+                let mut entries = Vec::new();
+                loop {
+                    self.skip_misc();
+                    match self.read_tag()? {
+                        Tag::End(name) if name == "dict" => break,
+                        Tag::Start(name) if name == "key" => {
+                            let key = self.read_text("key")?;
+                            entries.push((key, self.read_value()?));
+                        }
+                        Tag::Empty(name) if name == "key" => {
+                            entries.push((String::new(), self.read_value()?));
+                        }
+                        _ => return Err(self.error("expected <key>")),
+                    }
+                }
+                Ok(Value::Dictionary(entries))
+            }
+            "array" => {
+                let mut values = Vec::new();
+                loop {
+                    self.skip_misc();
+                    if self.rest().starts_with("</") {
+                        self.expect_end_tag("array")?;
+                        break;
+                    }
+                    values.push(self.read_value()?);
+                }
+                Ok(Value::Array(values))
+            }
+            "string" | "integer" | "real" | "date" | "data" => {
+                Ok(Value::String(self.read_text(tag)?))
+            }
+            _ => Err(self.error(&format!("unexpected <{tag}>"))),
+        }
+    }
+
+    // Read the text of an element, which may contain CDATA sections, whose
+    // contents are not unescaped.
+    fn read_text(&mut self, tag: &str) -> Result<String, Error> {
+        let mut text = String::new();
+        loop {
+            let rest = self.rest();
+            let Some(end) = rest.find('<') else {
+                return Err(self.error(&format!("unterminated <{tag}>")));
+            };
+            text += &unescape(&rest[..end]).ok_or_else(|| self.error("invalid entity"))?;
+            self.offset += end;
+
+            let Some(cdata) = self.rest().strip_prefix("<![CDATA[") else {
+                break;
+            };
+            let Some(cdata_end) = cdata.find("]]>") else {
+                return Err(self.error("unterminated CDATA section"));
+            };
+            text += &cdata[..cdata_end];
+            self.offset += "<![CDATA[".len() + cdata_end + "]]>".len();
+        }
+        self.expect_end_tag(tag)?;
+        Ok(text)
+    }
+}
+
+fn unescape(text: &str) -> Option<String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('&') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        let end = rest.find(';')?;
+        let entity = &rest[..end];
+        rest = &rest[end + 1..];
+        let c = match entity {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()?
+                } else {
+                    entity.strip_prefix('#')?.parse().ok()?
+                };
+                char::from_u32(code)?
+            }
+        };
+        result.push(c);
+    }
+    result.push_str(rest);
+    Some(result)
+}
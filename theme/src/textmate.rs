@@ -0,0 +1,277 @@
+use serde_json::Value;
+
+use crate::{plist, Color, Error, Style, Theme};
+
+// The TextMate scopes that correspond to the standard highlight names, in
+// order of preference. The prefixes of these names that are not listed, such
+// as `markup`, are looked up as scopes themselves.
+const SCOPES_BY_HIGHLIGHT_NAME: &[(&str, &[&str])] = &[
+    ("attribute", &["entity.other.attribute-name"]),
+    ("boolean", &["constant.language.boolean"]),
+    ("comment", &["comment"]),
+    ("comment.documentation", &["comment.block.documentation"]),
+    ("constant", &["variable.other.constant", "constant"]),
+    (
+        "constant.builtin",
+        &["constant.language", "support.constant"],
+    ),
+    (
+        "constructor",
+        &["entity.name.function.constructor", "entity.name.class"],
+    ),
+    ("embedded", &["meta.embedded"]),
+    ("escape", &["constant.character.escape"]),
+    ("function", &["entity.name.function"]),
+    ("function.builtin", &["support.function"]),
+    ("function.macro", &["entity.name.function.macro"]),
+    ("function.method", &["entity.name.function.member"]),
+    ("keyword", &["keyword", "storage"]),
+    ("label", &["entity.name.label"]),
+    ("markup.bold", &["markup.bold"]),
+    ("markup.heading", &["markup.heading"]),
+    ("markup.italic", &["markup.italic"]),
+    ("markup.link", &["markup.underline.link"]),
+    ("markup.list", &["markup.list"]),
+    ("markup.quote", &["markup.quote"]),
+    ("markup.raw", &["markup.raw", "markup.inline.raw"]),
+    ("markup.strikethrough", &["markup.strikethrough"]),
+    ("module", &["entity.name.namespace", "entity.name.module"]),
+    ("number", &["constant.numeric"]),
+    ("operator", &["keyword.operator"]),
+    (
+        "property",
+        &["variable.other.property", "support.type.property-name"],
+    ),
+    ("punctuation", &["punctuation"]),
+    (
+        "punctuation.bracket",
+        &["punctuation.section", "meta.brace"],
+    ),
+    (
+        "punctuation.delimiter",
+        &["punctuation.separator", "punctuation.terminator"],
+    ),
+    (
+        "punctuation.special",
+        &["punctuation.definition.template-expression"],
+    ),
+    ("string", &["string"]),
+    ("string.escape", &["constant.character.escape"]),
+    ("string.regexp", &["string.regexp"]),
+    ("string.special", &["string.other"]),
+    ("string.special.symbol", &["constant.other.symbol"]),
+    ("tag", &["entity.name.tag"]),
+    (
+        "type",
+        &["entity.name.type", "support.type", "storage.type"],
+    ),
+    (
+        "type.builtin",
+        &["support.type.primitive", "support.type", "storage.type"],
+    ),
+    ("variable", &["variable"]),
+    ("variable.builtin", &["variable.language"]),
+    (
+        "variable.member",
+        &["variable.other.member", "variable.other.property"],
+    ),
+    ("variable.parameter", &["variable.parameter"]),
+];
+
+// One of a theme's rules, which applies a foreground color and font style to
+// the scopes that match any of its selectors.
+struct Rule {
+    selectors: Vec<String>,
+    foreground: Option<Color>,
+    font_style: Option<FontStyle>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct FontStyle {
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+pub(crate) fn theme_from_vscode(json: &Value) -> Result<Theme, Error> {
+    let Some(token_colors) = json["tokenColors"].as_array() else {
+        return Err(Error::InvalidTheme(
+            "`tokenColors` is not an array".to_string(),
+        ));
+    };
+    let rules = token_colors
+        .iter()
+        .filter_map(|entry| {
+            let selectors = match &entry["scope"] {
+                Value::String(scope) => parse_selectors(scope),
+                Value::Array(scopes) => scopes
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .flat_map(parse_selectors)
+                    .collect(),
+                _ => return None,
+            };
+            let settings = &entry["settings"];
+            Some(Rule {
+                selectors,
+                foreground: settings["foreground"].as_str().and_then(parse_color),
+                font_style: settings["fontStyle"].as_str().map(parse_font_style),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(theme_from_rules(&rules))
+}
+
+pub(crate) fn theme_from_tmtheme(xml: &str) -> Result<Theme, Error> {
+    let plist = plist::parse(xml)?;
+    let Some(settings) = plist.get("settings").and_then(plist::Value::as_array) else {
+        return Err(Error::InvalidTheme(
+            "`settings` is not an array".to_string(),
+        ));
+    };
+    let rules = settings
+        .iter()
+        .filter_map(|entry| {
+            // The entry without a scope holds the editor's colors.
+            let selectors = parse_selectors(entry.get("scope")?.as_str()?);
+            let settings = entry.get("settings")?;
+            let setting = |key| settings.get(key).and_then(plist::Value::as_str);
+            Some(Rule {
+                selectors,
+                foreground: setting("foreground").and_then(parse_color),
+                font_style: setting("fontStyle").map(parse_font_style),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(theme_from_rules(&rules))
+}
+
+fn theme_from_rules(rules: &[Rule]) -> Theme {
+    let mut theme = Theme {
+        styles: Vec::new(),
+        highlight_names: Vec::new(),
+    };
+    for (name, _) in SCOPES_BY_HIGHLIGHT_NAME {
+        // Fall back to the scopes of the name's dot-separated prefixes, so that
+        // `function.builtin` uses the style of `function` if the theme has no
+        // rule for `support.function`.
+        let mut prefix = *name;
+        let style = loop {
+            if let Some(style) = style_for_highlight_name(rules, prefix) {
+                break Some(style);
+            }
+            match prefix.rfind('.') {
+                Some(index) => prefix = &prefix[..index],
+                None => break None,
+            }
+        };
+        if let Some(style) = style {
+            theme.highlight_names.push((*name).to_string());
+            theme.styles.push(style);
+        }
+    }
+    theme
+}
+
+fn style_for_highlight_name(rules: &[Rule], name: &str) -> Option<Style> {
+    match SCOPES_BY_HIGHLIGHT_NAME.iter().find(|(n, _)| *n == name) {
+        Some((_, scopes)) => scopes
+            .iter()
+            .find_map(|scope| style_for_scope(rules, scope)),
+        None => style_for_scope(rules, name),
+    }
+}
+
+// Find the most specific rules that set the foreground color and the font
+// style of a scope. Selectors match a scope when they are equal to it or to
+// one of its dot-separated prefixes, and longer selectors are more specific.
+// Among equally specific rules, the last one wins.
+fn style_for_scope(rules: &[Rule], scope: &str) -> Option<Style> {
+    let mut foreground = None;
+    let mut font_style = None;
+    for rule in rules {
+        let Some(score) = rule
+            .selectors
+            .iter()
+            .filter(|selector| {
+                scope == selector.as_str()
+                    || scope
+                        .strip_prefix(selector.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map(String::len)
+            .max()
+        else {
+            continue;
+        };
+        if let Some(color) = rule.foreground {
+            if foreground.map_or(true, |(s, _)| score >= s) {
+                foreground = Some((score, color));
+            }
+        }
+        if let Some(style) = rule.font_style {
+            if font_style.map_or(true, |(s, _)| score >= s) {
+                font_style = Some((score, style));
+            }
+        }
+    }
+    if foreground.is_none() && font_style.is_none() {
+        return None;
+    }
+    let font_style = font_style.map(|(_, style)| style).unwrap_or_default();
+    Some(Style {
+        color: foreground.map(|(_, color)| color),
+        bold: font_style.bold,
+        italic: font_style.italic,
+        underline: font_style.underline,
+    })
+}
+
+// Split a comma-separated list of scope selectors. Descendant selectors, such
+// as `source.js entity.name.function`, only apply to scopes within other
+// scopes, which highlight names don't have, so they are skipped. Exclusions
+// are ignored.
+fn parse_selectors(selectors: &str) -> Vec<String> {
+    selectors
+        .split(',')
+        .filter_map(|selector| {
+            let selector = selector.split(" -").next().unwrap_or_default();
+            let mut scopes = selector.split_whitespace();
+            let scope = scopes.next()?;
+            scopes.next().is_none().then(|| scope.to_string())
+        })
+        .collect()
+}
+
+// Parse a color of the form `#rgb`, `#rrggbb`, or either of those with an
+// alpha component, which is ignored.
+pub(crate) fn parse_color(color: &str) -> Option<Color> {
+    let hex = color.strip_prefix('#')?;
+    let component = |i: usize, len: usize| {
+        let value = u8::from_str_radix(hex.get(i * len..(i + 1) * len)?, 16).ok()?;
+        Some(if len == 1 { value * 17 } else { value })
+    };
+    let len = match hex.len() {
+        3 | 4 => 1,
+        6 | 8 => 2,
+        _ => return None,
+    };
+    Some(Color::Rgb(
+        component(0, len)?,
+        component(1, len)?,
+        component(2, len)?,
+    ))
+}
+
+fn parse_font_style(font_style: &str) -> FontStyle {
+    let mut result = FontStyle::default();
+    for word in font_style.split_whitespace() {
+        match word {
+            "bold" => result.bold = true,
+            "italic" => result.italic = true,
+            "underline" => result.underline = true,
+            _ => {}
+        }
+    }
+    result
+}
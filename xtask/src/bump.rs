@@ -143,6 +143,10 @@ fn tag_next_version(
         "lib/Cargo.toml",
         "highlight/Cargo.toml",
         "tags/Cargo.toml",
+        "format/Cargo.toml",
+        "folds/Cargo.toml",
+        "indent/Cargo.toml",
+        "theme/Cargo.toml",
         "cli/npm/package.json",
         "lib/binding_web/package.json",
         "Makefile",